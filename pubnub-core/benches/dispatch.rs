@@ -0,0 +1,133 @@
+//! Measures the delivery of the messages to many listeners of a channel.
+//!
+//! Run with `cargo bench -p pubnub-core --bench dispatch`.
+
+use futures_channel::mpsc;
+use futures_executor::{LocalPool, LocalSpawner};
+use futures_util::future::pending;
+use futures_util::lock::Mutex;
+use futures_util::stream::StreamExt;
+use futures_util::task::SpawnExt;
+use pubnub_core::data::message::Message;
+use pubnub_core::data::{cursor::Cursor, request, response, timetoken::Timetoken};
+use pubnub_core::json::JsonValue;
+use pubnub_core::mock::runtime::MockRuntime;
+use pubnub_core::mock::transport::{MockTransport, MockTransportError};
+use pubnub_core::Builder;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const LISTENERS: usize = 1000;
+const BATCHES: usize = 10;
+const MESSAGES_PER_BATCH: usize = 10;
+const PAYLOAD_SIZE: usize = 16 * 1024;
+
+type Responses = mpsc::UnboundedSender<Result<response::Subscribe, MockTransportError>>;
+
+/// A transport answering the subscribe requests with the responses sent by
+/// the bench.
+fn transport() -> (MockTransport, Responses) {
+    let (responses_tx, responses_rx) = mpsc::unbounded();
+    let responses_rx = Arc::new(Mutex::new(responses_rx));
+
+    let mut mock = MockTransport::new();
+    mock.expect_clone().times(1).return_once(move || {
+        let mut mock = MockTransport::new();
+        mock.expect_call::<request::Subscribe, response::Subscribe>()
+            .returning(move |_| {
+                let responses_rx = Arc::clone(&responses_rx);
+                Box::pin(async move {
+                    match responses_rx.lock().await.next().await {
+                        Some(response) => response,
+                        None => pending().await,
+                    }
+                })
+            });
+        mock
+    });
+
+    (mock, responses_tx)
+}
+
+/// A runtime spawning the futures on the local pool, with the delays that
+/// never complete.
+fn runtime(spawner: LocalSpawner) -> MockRuntime {
+    let mut mock = MockRuntime::new();
+    let spawner_clone = spawner.clone();
+    mock.expect_mock_workaround_spawn::<()>()
+        .returning_st(move |future| {
+            spawner_clone.spawn(future).unwrap();
+        });
+    mock.expect_mock_delay()
+        .returning(|_| Box::pin(pending::<()>()));
+    mock.expect_clone()
+        .returning_st(move || runtime(spawner.clone()));
+    mock
+}
+
+/// Deliver all the batches to all the listeners, and return the time it took.
+fn run() -> Duration {
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let (transport, responses_tx) = transport();
+    let mut pubnub = Builder::with_components(transport, runtime(spawner.clone())).build();
+    let channel: pubnub_core::data::channel::Name = "bench".parse().unwrap();
+
+    // Subscribe all the listeners, and start consuming.
+    responses_tx
+        .unbounded_send(Ok((vec![], Cursor::new(1, 1))))
+        .unwrap();
+    let expected = BATCHES * MESSAGES_PER_BATCH;
+    let done = Arc::new(AtomicUsize::new(0));
+    for _ in 0..LISTENERS {
+        let mut subscription = pool.run_until(pubnub.subscribe(channel.clone())).unwrap();
+        let done = Arc::clone(&done);
+        spawner
+            .spawn(async move {
+                for _ in 0..expected {
+                    subscription.next().await.unwrap();
+                }
+                done.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+    }
+    pool.run_until_stalled();
+
+    let payload = JsonValue::String("x".repeat(PAYLOAD_SIZE));
+    let start = Instant::now();
+    for batch in 0..BATCHES {
+        let messages = (0..MESSAGES_PER_BATCH)
+            .map(|i| {
+                Message::new(
+                    channel.clone(),
+                    payload.clone(),
+                    Timetoken::from_parts((2 + batch * MESSAGES_PER_BATCH + i) as u64, 1),
+                )
+            })
+            .collect();
+        let cursor = Cursor::new((2 + (batch + 1) * MESSAGES_PER_BATCH) as u64, 1);
+        responses_tx.unbounded_send(Ok((messages, cursor))).unwrap();
+    }
+    pool.run_until_stalled();
+    let elapsed = start.elapsed();
+
+    assert_eq!(done.load(Ordering::SeqCst), LISTENERS);
+    elapsed
+}
+
+fn main() {
+    const RUNS: u32 = 5;
+
+    // Warm up.
+    run();
+
+    let total: Duration = (0..RUNS).map(|_| run()).sum();
+    println!(
+        "{} messages of {} bytes to {} listeners: {:?} per run",
+        BATCHES * MESSAGES_PER_BATCH,
+        PAYLOAD_SIZE,
+        LISTENERS,
+        total / RUNS
+    );
+}
@@ -1,5 +1,8 @@
-use crate::pubnub::PubNub;
+use crate::data::filter::FilterExpression;
+use crate::pubnub::{PubNub, RateLimiter, RetryPolicy};
 use crate::runtime::Runtime;
+use crate::subscription::cursors::CurrentCursors;
+use crate::subscription::status::StatusBroadcaster;
 use crate::subscription::subscribe_loop::ExitTx as SubscribeLoopExitTx;
 use crate::subscription::subscribe_loop_supervisor::{
     SubscribeLoopSupervisor, SubscribeLoopSupervisorParams,
@@ -7,6 +10,19 @@ use crate::subscription::subscribe_loop_supervisor::{
 use crate::transport::Transport;
 use futures_util::lock::Mutex;
 use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "broadcast")]
+use crate::subscription::broadcast::Broadcasts;
+#[cfg(feature = "cipher")]
+use pubnub_util::cipher::Cipher;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Counters;
+
+/// The longest the PubNub network holds an idle subscribe request, with
+/// some slack for the round-trip.
+const SUBSCRIBE_LONG_POLL: Duration = Duration::from_secs(310);
 
 /// # PubNub Client Builder
 ///
@@ -22,6 +38,28 @@ pub struct Builder<TTransport = (), TRuntime = ()> {
     /// Subscription related configuration params.
     /// If set, gets a signal when subscribe loop exits.
     subscribe_loop_exit_tx: Option<SubscribeLoopExitTx>,
+    /// Whether to skip the redelivered messages in the subscribe loop.
+    message_deduplication: bool,
+    /// The expression to filter the received messages with.
+    filter_expr: Option<FilterExpression>,
+    /// The number of messages buffered for each subscription stream.
+    subscription_buffer: usize,
+    /// How long a subscribe request may take before it's considered stuck.
+    watchdog_timeout: Option<Duration>,
+    /// How long `subscribe` waits for the subscribe loop to connect.
+    ready_timeout: Option<Duration>,
+    /// The number of the recent messages replayed to the late subscribers.
+    replay_buffer: usize,
+    /// The size of the messages above which they're published with `POST`.
+    publish_post_threshold: usize,
+    /// The number of the publishes allowed per second.
+    publish_rate_limit: Option<u32>,
+    /// How the failed publishes are retried.
+    publish_retry: RetryPolicy,
+
+    /// Cipher to encrypt and decrypt the message payloads with.
+    #[cfg(feature = "cipher")]
+    cipher: Option<Cipher>,
 }
 
 impl<TTransport, TRuntime> Builder<TTransport, TRuntime>
@@ -48,10 +86,38 @@ where
             transport,
             runtime,
             subscribe_loop_exit_tx,
+            message_deduplication,
+            filter_expr,
+            subscription_buffer,
+            watchdog_timeout,
+            ready_timeout,
+            replay_buffer,
+            publish_post_threshold,
+            publish_rate_limit,
+            publish_retry,
+            #[cfg(feature = "cipher")]
+            cipher,
         } = self;
 
+        let status_broadcaster = StatusBroadcaster::default();
+        let current_cursors = CurrentCursors::default();
+        #[cfg(feature = "metrics")]
+        let metrics = Arc::new(Counters::default());
+
         let subscribe_loop_supervisor_params = SubscribeLoopSupervisorParams {
             exit_tx: subscribe_loop_exit_tx,
+            message_deduplication,
+            filter_expr,
+            subscription_buffer,
+            watchdog_timeout,
+            ready_timeout,
+            replay_buffer,
+            status_broadcaster: status_broadcaster.clone(),
+            current_cursors: current_cursors.clone(),
+            #[cfg(feature = "cipher")]
+            cipher,
+            #[cfg(feature = "metrics")]
+            metrics: Arc::clone(&metrics),
         };
 
         PubNub {
@@ -61,6 +127,18 @@ where
             subscribe_loop_supervisor: Arc::new(Mutex::new(SubscribeLoopSupervisor::new(
                 subscribe_loop_supervisor_params,
             ))),
+            status_broadcaster,
+            current_cursors,
+            #[cfg(feature = "broadcast")]
+            broadcasts: Broadcasts::new(subscription_buffer),
+            publish_post_threshold,
+            publish_rate_limiter: publish_rate_limit
+                .map(|per_sec| Arc::new(RateLimiter::new(per_sec))),
+            publish_retry,
+            #[cfg(feature = "cipher")]
+            cipher,
+            #[cfg(feature = "metrics")]
+            metrics,
         }
     }
 }
@@ -84,6 +162,17 @@ impl<TTransport, TRuntime> Builder<TTransport, TRuntime> {
     pub fn with_components(transport: TTransport, runtime: TRuntime) -> Self {
         Self {
             subscribe_loop_exit_tx: None,
+            message_deduplication: true,
+            filter_expr: None,
+            subscription_buffer: 100,
+            watchdog_timeout: None,
+            ready_timeout: Some(Duration::from_secs(10)),
+            replay_buffer: 0,
+            publish_post_threshold: 16 * 1024,
+            publish_rate_limit: None,
+            publish_retry: RetryPolicy::none(),
+            #[cfg(feature = "cipher")]
+            cipher: None,
 
             transport,
             runtime,
@@ -114,6 +203,329 @@ impl<TTransport, TRuntime> Builder<TTransport, TRuntime> {
         self
     }
 
+    /// Enable or disable the de-duplication of messages in the subscribe
+    /// loop.
+    ///
+    /// The PubNub network can redeliver the last message after a reconnect.
+    /// When enabled, the subscribe loop tracks the timetoken of the last
+    /// delivered message on each channel, and skips the messages that are not
+    /// newer than that.
+    ///
+    /// Enabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .message_deduplication(false)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn message_deduplication(mut self, enabled: bool) -> Self {
+        self.message_deduplication = enabled;
+        self
+    }
+
+    /// Set the expression to filter the received messages with.
+    ///
+    /// The filter is applied by the PubNub network, so the messages that
+    /// don't match it are never delivered to the client. The expression is
+    /// checked when parsed, see [`FilterExpression`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .filter_expression("uuid != 'me'".parse().unwrap())
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn filter_expression(mut self, filter_expr: FilterExpression) -> Self {
+        self.filter_expr = Some(filter_expr);
+        self
+    }
+
+    /// Set the number of messages buffered for each subscription stream.
+    ///
+    /// The subscribe loop doesn't wait for the space in the buffer when
+    /// delivering the messages, so a slow stream doesn't hold up the others.
+    /// Once the buffer is full, the messages for the stream are dropped, and
+    /// counted in its [`dropped`](crate::Subscription::dropped) count. A
+    /// larger buffer lets the bursty consumers fall further behind before
+    /// that happens. Defaults to 100, the most messages a single subscribe
+    /// response carries.
+    ///
+    /// The streams of `PubNub::subscribe_broadcast` keep at least as many
+    /// messages for the consumers that fall behind.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `buffer` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .subscription_buffer(100)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn subscription_buffer(mut self, buffer: usize) -> Self {
+        assert!(buffer > 0, "The subscription buffer must not be zero");
+        self.subscription_buffer = buffer;
+        self
+    }
+
+    /// Keep the last `messages` of each subscribed destination, and replay
+    /// them to the streams subscribing to it later.
+    ///
+    /// The replayed messages are delivered before any of the live ones,
+    /// so a late stream sees them in order and without gaps. The messages
+    /// are kept only while the destination has subscribers. The streams
+    /// buffer the replayed messages on top of the
+    /// [`subscription_buffer`](Self::subscription_buffer). Defaults to 0,
+    /// which disables the replay.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .replay_buffer(50)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn replay_buffer(mut self, messages: usize) -> Self {
+        self.replay_buffer = messages;
+        self
+    }
+
+    /// Enable the subscribe loop watchdog, reconnecting whenever a subscribe
+    /// request doesn't complete within the `timeout`.
+    ///
+    /// The PubNub network holds the subscribe requests for up to 280
+    /// seconds when there are no messages, and then answers with an empty
+    /// response, so a loop with no traffic still completes a request every
+    /// few minutes. A request taking longer than that is stuck, i.e. on a
+    /// half-open connection. The watchdog drops it, reports a
+    /// [`StatusEvent::Stalled`](crate::StatusEvent::Stalled), and sends a
+    /// new one, followed by a
+    /// [`StatusEvent::Reconnected`](crate::StatusEvent::Reconnected) once it
+    /// succeeds. Set the `timeout` to a couple of long-poll intervals, i.e.
+    /// 10 minutes, to leave the slow but healthy requests alone.
+    ///
+    /// Disabled by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `timeout` isn't longer than the long-poll, i.e. 310
+    /// seconds, as the watchdog would then drop the healthy idle requests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    /// use std::time::Duration;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .watchdog_timeout(Duration::from_secs(10 * 60))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn watchdog_timeout(mut self, timeout: Duration) -> Self {
+        assert!(
+            timeout > SUBSCRIBE_LONG_POLL,
+            "The watchdog timeout must be longer than the subscribe long-poll"
+        );
+        self.watchdog_timeout = Some(timeout);
+        self
+    }
+
+    /// Limit how long subscribing waits for the subscribe loop to connect.
+    ///
+    /// Subscribing to the first destination starts a subscribe loop, and
+    /// waits for its first request to succeed. The errors the loop can't
+    /// recover from, such as an invalid subscribe key, end the subscription
+    /// stream right away. The other errors are retried, so with the network
+    /// down the subscribing would wait for as long as it's down. With the
+    /// `timeout` set, the subscription is returned once it passes, while the
+    /// loop keeps reconnecting in the background and reports the failures
+    /// to the [`status_stream`](crate::PubNub::status_stream).
+    ///
+    /// Defaults to 10 seconds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    /// use std::time::Duration;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .ready_timeout(Duration::from_secs(30))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn ready_timeout(mut self, timeout: Duration) -> Self {
+        self.ready_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the size of the messages, in bytes, above which they're published
+    /// with `POST` rather than `GET`.
+    ///
+    /// The messages published with `GET` are sent in the URL, and the URLs
+    /// longer than around 32KB are rejected by the servers and proxies on the
+    /// way. The size compared against the threshold is that of the message
+    /// and its metadata serialized to JSON and URL-encoded, as they'd be
+    /// sent in the URL. Defaults to 16KB.
+    ///
+    /// Pass `usize::MAX` to always publish with `GET`, or `0` to always
+    /// publish with `POST`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .publish_post_threshold(8 * 1024)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn publish_post_threshold(mut self, bytes: usize) -> Self {
+        self.publish_post_threshold = bytes;
+        self
+    }
+
+    /// Limit the publishes to `per_sec` a second, across all the clones of
+    /// the client.
+    ///
+    /// The limit is enforced with a token bucket holding a second worth of
+    /// publishes: the bursts up to `per_sec` go out right away, and the
+    /// publishes over the limit wait for their turn, in order. Applies to
+    /// [`PubNub::publish`] and its variants.
+    ///
+    /// The PubNub network enforces its own limits regardless. When it
+    /// rejects a publish for exceeding them, the publishes that follow are
+    /// held back for the delay it asks for with the `Retry-After` header, or
+    /// for a second, whatever the bucket holds. The rejected publish is
+    /// retried a couple of times too, waiting for its turn like the others.
+    /// Without the limit, the rejection is returned right away.
+    ///
+    /// Unlimited by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_sec` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .publish_rate_limit(100)
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn publish_rate_limit(mut self, per_sec: u32) -> Self {
+        assert!(per_sec > 0, "The publish rate limit must not be zero");
+        self.publish_rate_limit = Some(per_sec);
+        self
+    }
+
+    /// Set the policy for retrying the failed publishes.
+    ///
+    /// Only the failures the transport reports as transient, see
+    /// [`TransportError::is_transient`](crate::TransportError::is_transient),
+    /// are retried, waiting for the backoff of the `policy` in between.
+    /// Applies to [`PubNub::publish`] and its variants.
+    ///
+    /// Publishes are not idempotent: if the connection breaks after the
+    /// request was sent, the retry may produce a duplicate message. Publish
+    /// with a client-generated
+    /// [`timetoken_override`](crate::data::pubsub::PublishOptions::timetoken_override)
+    /// to make the duplicates recognizable.
+    ///
+    /// No retries by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{Builder, RetryPolicy};
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .publish_retry(RetryPolicy::new(3))
+    ///     .build();
+    /// ```
+    #[must_use]
+    pub fn publish_retry(mut self, policy: RetryPolicy) -> Self {
+        self.publish_retry = policy;
+        self
+    }
+
+    /// Set the cipher key to encrypt and decrypt the message payloads with.
+    ///
+    /// When set, the published payloads are encrypted, and the payloads of
+    /// the received messages are decrypted. The encryption is compatible
+    /// with the other PubNub SDKs using the same cipher key.
+    ///
+    /// The received payloads that can't be decrypted are delivered as-is,
+    /// see [`Message::decrypted`](crate::data::message::Message::decrypted).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime)
+    ///     .cipher_key("my-cipher-key")
+    ///     .build();
+    /// ```
+    #[cfg(feature = "cipher")]
+    #[must_use]
+    pub fn cipher_key(mut self, cipher_key: impl AsRef<str>) -> Self {
+        self.cipher = Some(Cipher::new(cipher_key.as_ref()));
+        self
+    }
+
     /// Set the transport to use.
     ///
     /// This allows changing the [`Transport`] type on the builder and,
@@ -126,6 +538,17 @@ impl<TTransport, TRuntime> Builder<TTransport, TRuntime> {
             // Copy the rest of the fields.
             runtime: self.runtime,
             subscribe_loop_exit_tx: self.subscribe_loop_exit_tx,
+            message_deduplication: self.message_deduplication,
+            filter_expr: self.filter_expr,
+            subscription_buffer: self.subscription_buffer,
+            watchdog_timeout: self.watchdog_timeout,
+            ready_timeout: self.ready_timeout,
+            replay_buffer: self.replay_buffer,
+            publish_post_threshold: self.publish_post_threshold,
+            publish_rate_limit: self.publish_rate_limit,
+            publish_retry: self.publish_retry,
+            #[cfg(feature = "cipher")]
+            cipher: self.cipher,
         }
     }
 
@@ -141,6 +564,17 @@ impl<TTransport, TRuntime> Builder<TTransport, TRuntime> {
             // Copy the rest of the fields.
             transport: self.transport,
             subscribe_loop_exit_tx: self.subscribe_loop_exit_tx,
+            message_deduplication: self.message_deduplication,
+            filter_expr: self.filter_expr,
+            subscription_buffer: self.subscription_buffer,
+            watchdog_timeout: self.watchdog_timeout,
+            ready_timeout: self.ready_timeout,
+            replay_buffer: self.replay_buffer,
+            publish_post_threshold: self.publish_post_threshold,
+            publish_rate_limit: self.publish_rate_limit,
+            publish_retry: self.publish_retry,
+            #[cfg(feature = "cipher")]
+            cipher: self.cipher,
         }
     }
 }
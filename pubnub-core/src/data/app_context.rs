@@ -0,0 +1,267 @@
+//! App Context (Objects) related types.
+
+use super::channel;
+use super::object::Object;
+use super::uuid::UUID;
+
+/// Metadata of a UUID, i.e. a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UuidMetadata {
+    /// The UUID the metadata belongs to.
+    pub id: UUID,
+
+    /// Display name.
+    pub name: Option<String>,
+
+    /// Email address.
+    pub email: Option<String>,
+
+    /// Identifier in an external system.
+    pub external_id: Option<String>,
+
+    /// URL of the profile picture.
+    pub profile_url: Option<String>,
+
+    /// Arbitrary custom data.
+    ///
+    /// Must be a JSON object with scalar values.
+    pub custom: Option<Object>,
+
+    /// Date of the last update, as returned by the server.
+    ///
+    /// Ignored when setting the metadata.
+    pub updated: Option<String>,
+
+    /// Entity tag of the metadata, as returned by the server.
+    ///
+    /// Identifies the revision of the metadata. Pass it along with the
+    /// update to only apply the update if the metadata wasn't modified since
+    /// it was fetched.
+    pub etag: Option<String>,
+}
+
+impl UuidMetadata {
+    /// Create empty metadata for the `id`.
+    #[must_use]
+    pub fn new(id: impl Into<UUID>) -> Self {
+        Self {
+            id: id.into(),
+            name: None,
+            email: None,
+            external_id: None,
+            profile_url: None,
+            custom: None,
+            updated: None,
+            etag: None,
+        }
+    }
+}
+
+/// Metadata of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMetadata {
+    /// The channel the metadata belongs to.
+    pub id: channel::Name,
+
+    /// Display name.
+    pub name: Option<String>,
+
+    /// Description of the channel.
+    pub description: Option<String>,
+
+    /// Arbitrary custom data.
+    ///
+    /// Must be a JSON object with scalar values.
+    pub custom: Option<Object>,
+
+    /// Date of the last update, as returned by the server.
+    ///
+    /// Ignored when setting the metadata.
+    pub updated: Option<String>,
+
+    /// Entity tag of the metadata, as returned by the server.
+    ///
+    /// See [`UuidMetadata::etag`].
+    pub etag: Option<String>,
+}
+
+impl ChannelMetadata {
+    /// Create empty metadata for the `id`.
+    #[must_use]
+    pub fn new(id: channel::Name) -> Self {
+        Self {
+            id,
+            name: None,
+            description: None,
+            custom: None,
+            updated: None,
+            etag: None,
+        }
+    }
+}
+
+/// A membership of a UUID in a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMembership {
+    /// The channel. Only the `id` is set, unless the channel metadata was
+    /// requested to be included.
+    pub channel: ChannelMetadata,
+
+    /// Arbitrary custom data of the membership.
+    pub custom: Option<Object>,
+
+    /// Date of the last update, as returned by the server.
+    pub updated: Option<String>,
+
+    /// Entity tag of the membership, as returned by the server.
+    pub etag: Option<String>,
+}
+
+/// A member of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelMember {
+    /// The UUID of the member. Only the `id` is set, unless the UUID metadata
+    /// was requested to be included.
+    pub uuid: UuidMetadata,
+
+    /// Arbitrary custom data of the membership.
+    pub custom: Option<Object>,
+
+    /// Date of the last update, as returned by the server.
+    pub updated: Option<String>,
+
+    /// Entity tag of the membership, as returned by the server.
+    pub etag: Option<String>,
+}
+
+/// Changes to apply to the memberships, all in a single request.
+///
+/// `T` is the type of the other side of the membership: a
+/// [`channel::Name`] when changing the memberships of a UUID, or a [`UUID`]
+/// when changing the members of a channel.
+///
+/// The memberships not mentioned are left intact, so the changes can be
+/// applied partially.
+///
+/// The API has two kinds of changes rather than three: adding a membership
+/// and updating an existing one are the same `set` operation. A membership
+/// that doesn't exist yet is added, and one that does has its custom data
+/// replaced as a whole. Replacing all the memberships at once takes
+/// removing the ones not set explicitly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipChanges<T> {
+    /// Memberships to add, or to replace the custom data of, if they
+    /// already exist.
+    pub set: Vec<MembershipEntry<T>>,
+
+    /// Memberships to remove.
+    pub remove: Vec<T>,
+}
+
+impl<T> Default for MembershipChanges<T> {
+    fn default() -> Self {
+        Self {
+            set: Vec::new(),
+            remove: Vec::new(),
+        }
+    }
+}
+
+/// A membership to set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipEntry<T> {
+    /// The other side of the membership.
+    pub id: T,
+
+    /// Arbitrary custom data of the membership.
+    pub custom: Option<Object>,
+}
+
+/// Which related data to include in the membership responses.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MembershipInclude {
+    /// Include the custom data of the memberships.
+    pub custom: bool,
+
+    /// Include the metadata of the other side of the memberships, i.e. the
+    /// channels for the memberships of a UUID.
+    pub metadata: bool,
+
+    /// Include the custom data within the metadata of the other side of the
+    /// memberships. Only has effect along with `metadata`.
+    pub metadata_custom: bool,
+}
+
+impl MembershipInclude {
+    /// Include all the related data.
+    #[must_use]
+    pub fn all() -> Self {
+        Self {
+            custom: true,
+            metadata: true,
+            metadata_custom: true,
+        }
+    }
+}
+
+/// A page of a paginated list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    /// The items on the page.
+    pub items: Vec<T>,
+
+    /// The total number of items in the list, if requested.
+    pub total_count: Option<usize>,
+
+    /// A cursor pointing to the next page, if there is one.
+    pub next: Option<PageCursor>,
+
+    /// A cursor pointing to the previous page, if there is one.
+    pub prev: Option<PageCursor>,
+}
+
+/// An opaque cursor pointing to a page of a paginated list.
+///
+/// Obtained from a [`Page`], and passed along with the list request to get
+/// the page it points to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageCursor {
+    direction: PageDirection,
+    token: String,
+}
+
+/// The direction a [`PageCursor`] points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PageDirection {
+    /// The cursor points to the next page.
+    Next,
+    /// The cursor points to the previous page.
+    Prev,
+}
+
+impl PageCursor {
+    /// Create a cursor from the token returned by the server.
+    ///
+    /// Intended to be used by the [`Transport`](crate::Transport)
+    /// implementations.
+    #[must_use]
+    pub fn new(direction: PageDirection, token: impl Into<String>) -> Self {
+        Self {
+            direction,
+            token: token.into(),
+        }
+    }
+
+    /// The direction the cursor points to.
+    #[must_use]
+    pub fn direction(&self) -> PageDirection {
+        self.direction
+    }
+
+    /// The token to pass to the server.
+    #[must_use]
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
@@ -4,4 +4,4 @@ mod name;
 mod wildcard_spec;
 
 pub use name::Name;
-pub use wildcard_spec::WildcardSpec;
+pub use wildcard_spec::{InvalidWildcardSpec, WildcardSpec};
@@ -5,10 +5,22 @@ use std::str::FromStr;
 /// A list of the symbols, prohibited for use in the channel name.
 pub const PROHIBITED_SYMBOLS: &[char] = &[','];
 
+/// A list of the symbols the PubNub network rejects in the channel names.
+pub const RESERVED_SYMBOLS: &[char] = &[',', '/', '\\', ':', '*'];
+
+/// The maximum length of a channel name, in characters.
+pub const MAX_LENGTH: usize = 92;
+
 /// A Channel name.
 ///
 /// This type represents an exact channel (or channel group) name.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate"),
+    serde(try_from = "String", into = "String")
+)]
 pub struct Name(String);
 
 impl Name {
@@ -16,6 +28,49 @@ impl Name {
         !s.contains(PROHIBITED_SYMBOLS)
     }
 
+    /// Check the name against the rules of the PubNub network.
+    ///
+    /// The names that pass the type-level check may still be rejected by
+    /// the network: a name must not be empty or longer than 92 characters,
+    /// and must not contain `,`, `/`, `\`, `:`, `*` or the non-printable
+    /// characters. With `wildcard`, the name may end with `.*`, for the
+    /// wildcard subscribes.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the name is rejected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::channel::Name;
+    ///
+    /// let name: Name = "news.*".parse().unwrap();
+    /// assert!(name.check(true).is_ok());
+    /// assert!(name.check(false).is_err());
+    /// ```
+    pub fn check(&self, wildcard: bool) -> Result<(), &'static str> {
+        let name = if wildcard && self.0.ends_with(".*") {
+            &self.0[..self.0.len() - 2]
+        } else {
+            self.0.as_str()
+        };
+
+        if name.is_empty() {
+            return Err("the name is empty");
+        }
+        if self.0.chars().count() > MAX_LENGTH {
+            return Err("the name is too long");
+        }
+        if name.contains(RESERVED_SYMBOLS) {
+            return Err("the name contains one of `,`, `/`, `\\`, `:` or `*`");
+        }
+        if name.chars().any(char::is_control) {
+            return Err("the name contains a non-printable character");
+        }
+        Ok(())
+    }
+
     /// Create a new [`Name`] skipping the validity check.
     #[must_use]
     pub fn from_string_unchecked(s: String) -> Self {
@@ -103,6 +158,31 @@ mod tests {
         assert_eq!(is_valid("channels_*"), true);
     }
 
+    #[test]
+    fn check() {
+        let check = |s: &str, wildcard| Name::from_string_unchecked(s.to_owned()).check(wildcard);
+
+        assert_eq!(check("qwe", false), Ok(()));
+        assert_eq!(check("a.b-c_d=e@f~g", false), Ok(()));
+        assert_eq!(check("чат", false), Ok(()));
+        assert_eq!(check(&"a".repeat(92), false), Ok(()));
+        assert_eq!(check("a.*", true), Ok(()));
+        assert_eq!(check("a.b.*", true), Ok(()));
+
+        assert!(check("", false).is_err());
+        assert!(check(&"a".repeat(93), false).is_err());
+        for reserved in &["a,b", "a/b", "a\\b", "a:b", "a*", "a.*"] {
+            assert!(check(reserved, false).is_err(), "{}", reserved);
+        }
+        assert!(check("a\nb", false).is_err());
+        assert!(check("a\u{7f}", false).is_err());
+
+        // Only the trailing `.*` is a wildcard.
+        assert!(check(".*", true).is_err());
+        assert!(check("a*", true).is_err());
+        assert!(check("*.a", true).is_err());
+    }
+
     #[test]
     fn invalid() {
         // Spec.
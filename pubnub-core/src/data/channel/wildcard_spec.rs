@@ -17,6 +17,12 @@ use std::str::FromStr;
 ///
 /// [wildcard subscribe]: https://support.pubnub.com/support/solutions/folders/14000109563
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate"),
+    serde(try_from = "String", into = "String")
+)]
 pub struct WildcardSpec(String);
 
 impl WildcardSpec {
@@ -66,6 +72,22 @@ impl WildcardSpec {
         true
     }
 
+    /// Whether the messages published to the `channel` are delivered to the
+    /// subscribers of this wildcard.
+    ///
+    /// `a.*` matches `a.b` and `a.b.c`, but not `a` itself. A specifier
+    /// without the wildcard only matches the channel of the same name.
+    #[must_use]
+    pub fn matches(&self, channel: &channel::Name) -> bool {
+        let channel: &str = channel.as_ref();
+        if self.0.ends_with('*') {
+            let prefix = &self.0[..self.0.len() - 1];
+            channel.len() > prefix.len() && channel.starts_with(prefix)
+        } else {
+            channel == self.0
+        }
+    }
+
     /// Create a new [`WildcardSpec`] skipping the validity check.
     #[must_use]
     pub fn from_string_unchecked(s: String) -> Self {
@@ -120,6 +142,19 @@ impl From<channel::Name> for WildcardSpec {
     }
 }
 
+impl From<WildcardSpec> for String {
+    fn from(spec: WildcardSpec) -> String {
+        spec.0
+    }
+}
+
+/// The pattern isn't a valid [`WildcardSpec`].
+///
+/// Returned by [`PubNub::subscribe_wildcard`](crate::PubNub::subscribe_wildcard).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid wildcard pattern {0:?}")]
+pub struct InvalidWildcardSpec(pub String);
+
 #[cfg(test)]
 mod tests {
     use super::WildcardSpec;
@@ -189,4 +224,18 @@ mod tests {
         // segments (three or more . delimiters), then you will receive a 400
         // INVALID error response.
     }
+
+    #[test]
+    fn matches() {
+        let spec: WildcardSpec = "a.*".parse().unwrap();
+        assert!(spec.matches(&"a.b".parse().unwrap()));
+        assert!(spec.matches(&"a.b.c".parse().unwrap()));
+        assert!(!spec.matches(&"a".parse().unwrap()));
+        assert!(!spec.matches(&"ab.c".parse().unwrap()));
+        assert!(!spec.matches(&"b.a".parse().unwrap()));
+
+        let spec: WildcardSpec = "a.b".parse().unwrap();
+        assert!(spec.matches(&"a.b".parse().unwrap()));
+        assert!(!spec.matches(&"a.b.c".parse().unwrap()));
+    }
 }
@@ -0,0 +1,113 @@
+//! Subscribe cursor type.
+
+use super::timetoken::Timetoken;
+use std::fmt;
+
+/// # PubNub Subscribe Cursor
+///
+/// The position the subscribe loop continues from. The PubNub network
+/// returns it along with every batch of messages, and expects it back with
+/// the next subscribe request.
+///
+/// The cursor is made of the time and the region of the subscribe
+/// connection. It's not to be confused with the [`Timetoken`] of a message:
+/// the region of the latter is the one the message was published in, which
+/// has no bearing on the region to resume the subscription in.
+///
+/// Store the cursor, i.e. the one returned by
+/// [`PubNub::current_cursor`](crate::PubNub::current_cursor), to resume
+/// from it with [`PubNub::subscribe_from`](crate::PubNub::subscribe_from)
+/// later on. With the `serde` feature, it's serialized as
+/// `{"timetoken": "15850559815660696", "region": 12}`, with the time as a
+/// numeric string so it doesn't lose precision in the languages that read
+/// all the JSON numbers as doubles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+pub struct Cursor {
+    /// The time, in 100 ns ticks since the Unix epoch.
+    ///
+    /// `0` means the current time.
+    #[cfg_attr(feature = "serde", serde(with = "super::timetoken::numeric_string"))]
+    pub timetoken: u64,
+
+    /// The region of the subscribe connection.
+    ///
+    /// `0` means there's no region hint, and lets the PubNub network pick
+    /// it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub region: u32,
+}
+
+impl Cursor {
+    /// Create a `Cursor` from its parts, i.e. the ones received from the
+    /// PubNub network.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::cursor::Cursor;
+    ///
+    /// let cursor = Cursor::new(15_850_559_815_660_696, 12);
+    /// assert_eq!(cursor.timetoken, 15_850_559_815_660_696);
+    /// assert_eq!(cursor.region, 12);
+    /// ```
+    #[must_use]
+    pub const fn new(timetoken: u64, region: u32) -> Self {
+        Self { timetoken, region }
+    }
+}
+
+/// Resume from the time of the timetoken, i.e. one returned from publish.
+///
+/// The region of the timetoken is kept, as the network knows no better for
+/// the timetokens it returns with no region.
+impl From<Timetoken> for Cursor {
+    fn from(timetoken: Timetoken) -> Self {
+        Self::new(timetoken.t, timetoken.r)
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "{{ timetoken: {}, region: {} }}",
+            self.timetoken, self.region
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cursor;
+    use crate::data::timetoken::Timetoken;
+
+    #[test]
+    fn from_timetoken() {
+        assert_eq!(
+            Cursor::from(Timetoken::from_parts(15_850_559_815_660_696, 12)),
+            Cursor::new(15_850_559_815_660_696, 12)
+        );
+        assert_eq!(Cursor::from(Timetoken::default()), Cursor::default());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let cursor = Cursor::new(15_850_559_815_660_696, 12);
+        let serialized = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"timetoken":"15850559815660696","region":12}"#
+        );
+        assert_eq!(serde_json::from_str::<Cursor>(&serialized).unwrap(), cursor);
+        assert_eq!(
+            serde_json::from_str::<Cursor>(r#"{"timetoken":"1"}"#).unwrap(),
+            Cursor::new(1, 0)
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! File Sharing API types.
+
+use super::timetoken::Timetoken;
+use json::JsonValue;
+
+/// A file stored with the PubNub File Sharing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct File {
+    /// The ID the file is stored under.
+    pub id: String,
+
+    /// The name of the file.
+    pub name: String,
+}
+
+/// The form to upload a file with, returned by the
+/// [`GenerateFileUploadUrl`](super::request::GenerateFileUploadUrl) request.
+///
+/// The form is presigned, and expires after a while, so upload the file
+/// right away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadForm {
+    /// The file the form uploads.
+    pub file: File,
+
+    /// The URL to send the form to. Not a PubNub network URL.
+    pub url: String,
+
+    /// The HTTP method to send the form with, i.e. `POST`.
+    pub method: String,
+
+    /// The form fields to send along with the file, in order.
+    pub fields: Vec<(String, String)>,
+}
+
+/// A file sent with [`PubNub::send_file`](crate::PubNub::send_file).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentFile {
+    /// The uploaded file.
+    pub file: File,
+
+    /// The timetoken of the file message.
+    pub timetoken: Timetoken,
+}
+
+/// A file shared on a channel, as announced by a file message. See
+/// [`Message::as_file`](super::message::Message::as_file).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMessage {
+    /// The shared file.
+    pub file: File,
+
+    /// The message sent along with the file, if any.
+    pub message: Option<JsonValue>,
+}
+
+/// A file stored on a channel, as listed by
+/// [`PubNub::list_files`](crate::PubNub::list_files).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileInfo {
+    /// The ID the file is stored under.
+    pub id: String,
+
+    /// The name of the file.
+    pub name: String,
+
+    /// The size of the file, in bytes.
+    pub size: u64,
+
+    /// When the file was uploaded, in the ISO 8601 format, i.e.
+    /// `2020-07-26T13:42:06Z`.
+    pub created: String,
+}
+
+/// A page of the files stored on a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilesPage {
+    /// The files on the page.
+    pub files: Vec<FileInfo>,
+
+    /// The token of the next page, if there is one.
+    pub next: Option<String>,
+}
@@ -0,0 +1,450 @@
+//! Subscribe filter expressions.
+
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A filter expression, applied by the PubNub network to the messages
+/// delivered to the subscriber.
+///
+/// The expression is checked against an approximation of the PubNub filter
+/// language: comparisons (`==`, `!=`, `<`, `>`, `<=`, `>=`, `like`,
+/// `contains`) of identifiers, strings and numbers, combined with `&&`, `||`
+/// and `!`, and grouped with parentheses. Passing the check doesn't
+/// guarantee the server accepts the expression, but the common mistakes are
+/// caught early.
+///
+/// # Example
+///
+/// ```
+/// use pubnub_core::data::filter::FilterExpression;
+///
+/// let filter: FilterExpression = "uuid != 'me' && (meta.priority > 2 || meta.tag like 'urgent*')"
+///     .parse()
+///     .unwrap();
+///
+/// assert!("meta.priority => 2".parse::<FilterExpression>().is_err());
+/// assert!("(meta.priority > 2".parse::<FilterExpression>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FilterExpression(String);
+
+/// An error in the filter expression syntax.
+///
+/// The positions are byte offsets in the expression.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FilterError {
+    /// The expression is empty.
+    #[error("Filter expression is empty")]
+    Empty,
+
+    /// A parenthesis at the position has no matching pair.
+    #[error("Unbalanced parenthesis at position {0}")]
+    UnbalancedParens(usize),
+
+    /// The operator at the position is not known.
+    #[error("Unknown operator {operator:?} at position {position}")]
+    UnknownOperator {
+        /// The operator as it appears in the expression.
+        operator: String,
+        /// The position of the operator.
+        position: usize,
+    },
+
+    /// The string literal starting at the position is not terminated.
+    #[error("Unterminated string at position {0}")]
+    UnterminatedString(usize),
+
+    /// The token at the position is not expected there, i.e. a comparison
+    /// is missing an operand.
+    #[error("Unexpected {found} at position {position}")]
+    Unexpected {
+        /// The unexpected token, or `end of expression`.
+        found: String,
+        /// The position of the token.
+        position: usize,
+    },
+}
+
+impl FilterExpression {
+    /// Check the expression syntax and wrap it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first syntax error found.
+    pub fn new(expression: impl Into<String>) -> Result<Self, FilterError> {
+        let expression = expression.into();
+        Parser::new(&expression)?.parse()?;
+        Ok(Self(expression))
+    }
+
+    /// Create a new [`FilterExpression`] skipping the syntax check.
+    #[must_use]
+    pub fn from_string_unchecked(expression: String) -> Self {
+        Self(expression)
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for FilterExpression {
+    type Error = FilterError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl AsRef<str> for FilterExpression {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Display for FilterExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<FilterExpression> for String {
+    fn from(expression: FilterExpression) -> String {
+        expression.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Compare(String),
+    Operand(String),
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LParen => f.write_str("'('"),
+            Token::RParen => f.write_str("')'"),
+            Token::Not => f.write_str("'!'"),
+            Token::And => f.write_str("'&&'"),
+            Token::Or => f.write_str("'||'"),
+            Token::Compare(operator) => write!(f, "operator {:?}", operator),
+            Token::Operand(operand) => write!(f, "operand {:?}", operand),
+        }
+    }
+}
+
+/// Split the expression into the tokens, along with their positions.
+fn tokenize(expression: &str) -> Result<Vec<(usize, Token)>, FilterError> {
+    const COMPARISONS: &[&str] = &["==", "!=", "<=", ">=", "<", ">"];
+    const KEYWORDS: &[&str] = &["like", "contains"];
+
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some(&(position, c)) = chars.peek() {
+        let rest = &expression[position..];
+        let token = if c.is_whitespace() {
+            chars.next();
+            continue;
+        } else if c == '(' {
+            chars.next();
+            Token::LParen
+        } else if c == ')' {
+            chars.next();
+            Token::RParen
+        } else if rest.starts_with("&&") {
+            chars.nth(1);
+            Token::And
+        } else if rest.starts_with("||") {
+            chars.nth(1);
+            Token::Or
+        } else if let Some(operator) = COMPARISONS.iter().find(|op| rest.starts_with(*op)) {
+            chars.nth(operator.len() - 1);
+            Token::Compare((*operator).to_owned())
+        } else if c == '!' {
+            chars.next();
+            Token::Not
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut escaped = false;
+            let end = chars.find(|&(_, next)| {
+                let end = !escaped && next == c;
+                escaped = !escaped && next == '\\';
+                end
+            });
+            let end = end.ok_or(FilterError::UnterminatedString(position))?.0;
+            Token::Operand(expression[position..=end].to_owned())
+        } else if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' {
+            let mut end = position;
+            while let Some(&(next_position, next)) = chars.peek() {
+                if !(next.is_alphanumeric() || "_-.[]".contains(next)) {
+                    break;
+                }
+                end = next_position + next.len_utf8();
+                chars.next();
+            }
+            let word = &expression[position..end];
+            if KEYWORDS
+                .iter()
+                .any(|keyword| word.eq_ignore_ascii_case(keyword))
+            {
+                Token::Compare(word.to_lowercase())
+            } else {
+                Token::Operand(word.to_owned())
+            }
+        } else {
+            let operator: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_punctuation() && !"()'\"".contains(*c))
+                .collect();
+            return Err(FilterError::UnknownOperator { operator, position });
+        };
+        tokens.push((position, token));
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser, checking the expression structure.
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn new(expression: &str) -> Result<Self, FilterError> {
+        let tokens = tokenize(expression)?;
+        if tokens.is_empty() {
+            return Err(FilterError::Empty);
+        }
+
+        // Check the parentheses first, to report them precisely.
+        let mut open = Vec::new();
+        for (position, token) in &tokens {
+            match token {
+                Token::LParen => open.push(*position),
+                Token::RParen => {
+                    open.pop().ok_or(FilterError::UnbalancedParens(*position))?;
+                }
+                _ => {}
+            }
+        }
+        if let Some(position) = open.pop() {
+            return Err(FilterError::UnbalancedParens(position));
+        }
+
+        Ok(Self {
+            tokens,
+            next: 0,
+            end: expression.len(),
+        })
+    }
+
+    fn parse(mut self) -> Result<(), FilterError> {
+        self.parse_or()?;
+        match self.tokens.get(self.next) {
+            Some((position, token)) => Err(FilterError::Unexpected {
+                found: token.to_string(),
+                position: *position,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<(), FilterError> {
+        self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next += 1;
+            self.parse_and()?;
+        }
+        Ok(())
+    }
+
+    fn parse_and(&mut self) -> Result<(), FilterError> {
+        self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.next += 1;
+            self.parse_unary()?;
+        }
+        Ok(())
+    }
+
+    fn parse_unary(&mut self) -> Result<(), FilterError> {
+        match self.advance()? {
+            Token::Not => self.parse_unary(),
+            Token::LParen => {
+                self.parse_or()?;
+                // The parentheses are balanced, so this is a `)`.
+                self.expect(|token| token == &Token::RParen)
+            }
+            Token::Operand(_) => {
+                self.expect(is_compare)?;
+                self.expect(is_operand)
+            }
+            token => Err(self.unexpected(&token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn advance(&mut self) -> Result<Token, FilterError> {
+        let token = self.tokens.get(self.next).map(|(_, token)| token.clone());
+        self.next += 1;
+        token.ok_or(FilterError::Unexpected {
+            found: "end of expression".to_owned(),
+            position: self.end,
+        })
+    }
+
+    fn expect(&mut self, predicate: impl Fn(&Token) -> bool) -> Result<(), FilterError> {
+        let token = self.advance()?;
+        if predicate(&token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token))
+        }
+    }
+
+    /// The error for the token just advanced over.
+    fn unexpected(&self, token: &Token) -> FilterError {
+        FilterError::Unexpected {
+            found: token.to_string(),
+            position: self.tokens[self.next - 1].0,
+        }
+    }
+}
+
+#[allow(clippy::match_like_matches_macro)] // `matches!` requires Rust 1.42
+fn is_compare(token: &Token) -> bool {
+    match token {
+        Token::Compare(_) => true,
+        _ => false,
+    }
+}
+
+#[allow(clippy::match_like_matches_macro)] // `matches!` requires Rust 1.42
+fn is_operand(token: &Token) -> bool {
+    match token {
+        Token::Operand(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterError, FilterExpression};
+    use std::convert::TryFrom;
+
+    fn check(expression: &str) -> Result<(), FilterError> {
+        FilterExpression::new(expression).map(|_| ())
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(check("uuid == 'me'"), Ok(()));
+        assert_eq!(check("uuid != \"me\""), Ok(()));
+        assert_eq!(check("meta.priority >= 2"), Ok(()));
+        assert_eq!(check("meta.tags contains 'a'"), Ok(()));
+        assert_eq!(check("meta.name LIKE 'john*'"), Ok(()));
+        assert_eq!(check("a == 1 && b == 2 || c == 3"), Ok(()));
+        assert_eq!(check("!(a == 1 && (b < -2.5 || c > 3))"), Ok(()));
+        assert_eq!(check("meta.items[0] == 'it\\'s'"), Ok(()));
+    }
+
+    #[test]
+    fn from_owned_string() {
+        let expression = "uuid != 'me'".to_owned();
+        assert_eq!(
+            FilterExpression::try_from(expression).map(String::from),
+            Ok("uuid != 'me'".to_owned())
+        );
+        assert_eq!(
+            FilterExpression::try_from(String::new()),
+            Err(FilterError::Empty)
+        );
+    }
+
+    #[test]
+    fn unbalanced_parens() {
+        assert_eq!(check("(a == 1"), Err(FilterError::UnbalancedParens(0)));
+        assert_eq!(check("a == 1)"), Err(FilterError::UnbalancedParens(6)));
+        assert_eq!(
+            check("((a == 1) || (b == 2)"),
+            Err(FilterError::UnbalancedParens(0))
+        );
+    }
+
+    #[test]
+    fn unknown_operators() {
+        assert_eq!(
+            check("a = 1"),
+            Err(FilterError::UnknownOperator {
+                operator: "=".to_owned(),
+                position: 2
+            })
+        );
+        assert_eq!(
+            check("a == 1 & b == 2"),
+            Err(FilterError::UnknownOperator {
+                operator: "&".to_owned(),
+                position: 7
+            })
+        );
+        assert_eq!(
+            check("a =~ 1"),
+            Err(FilterError::UnknownOperator {
+                operator: "=~".to_owned(),
+                position: 2
+            })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(check("  "), Err(FilterError::Empty));
+        assert_eq!(check("a == 'b"), Err(FilterError::UnterminatedString(5)));
+        assert_eq!(
+            check("a =="),
+            Err(FilterError::Unexpected {
+                found: "end of expression".to_owned(),
+                position: 4
+            })
+        );
+        assert_eq!(
+            check("a b"),
+            Err(FilterError::Unexpected {
+                found: "operand \"b\"".to_owned(),
+                position: 2
+            })
+        );
+        assert_eq!(
+            check("a == 1 ||"),
+            Err(FilterError::Unexpected {
+                found: "end of expression".to_owned(),
+                position: 9
+            })
+        );
+        assert_eq!(
+            check("()"),
+            Err(FilterError::Unexpected {
+                found: "')'".to_owned(),
+                position: 1
+            })
+        );
+    }
+}
@@ -17,3 +17,36 @@ pub struct Item {
     /// The message metadata.
     pub metadata: Object,
 }
+
+/// The most messages the history returns per page.
+pub const MAX_PAGE_SIZE: usize = 100;
+
+/// The options of [`PubNub::history_iter`](crate::PubNub::history_iter).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IterOptions {
+    /// The number of the messages to fetch per page, at most
+    /// [`MAX_PAGE_SIZE`]. Defaults to the maximum.
+    pub count: usize,
+
+    /// Only read the messages older than this timetoken. Defaults to the
+    /// current time.
+    pub start: Option<Timetoken>,
+
+    /// Stop at the message with this timetoken, inclusive. Defaults to the
+    /// oldest message stored.
+    pub end: Option<Timetoken>,
+
+    /// Whether to populate the metadata of the messages.
+    pub include_metadata: bool,
+}
+
+impl Default for IterOptions {
+    fn default() -> Self {
+        Self {
+            count: MAX_PAGE_SIZE,
+            start: None,
+            end: None,
+            include_metadata: false,
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Serde support for the [`JsonValue`] fields.
+//!
+//! The values are serialized as the JSON they hold, so the integers stay
+//! integers, and the objects keep the order of the keys.
+
+use json::number::Number;
+use json::object::Object;
+use json::JsonValue;
+use serde_crate::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_crate::ser::{Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// Serialize a [`JsonValue`], for `#[serde(with = "...")]`.
+pub(crate) fn serialize<S: Serializer>(
+    value: &JsonValue,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    Json(value).serialize(serializer)
+}
+
+/// Deserialize a [`JsonValue`], for `#[serde(with = "...")]`.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<JsonValue, D::Error> {
+    deserializer.deserialize_any(JsonVisitor)
+}
+
+/// A [`JsonValue`] serialized as the JSON it holds.
+pub(crate) struct Json<'a>(pub &'a JsonValue);
+
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            JsonValue::Null => serializer.serialize_unit(),
+            JsonValue::Short(value) => serializer.serialize_str(value.as_str()),
+            JsonValue::String(value) => serializer.serialize_str(value),
+            JsonValue::Number(value) => serialize_number(*value, serializer),
+            JsonValue::Boolean(value) => serializer.serialize_bool(*value),
+            JsonValue::Object(object) => {
+                serializer.collect_map(object.iter().map(|(key, value)| (key, Json(value))))
+            }
+            JsonValue::Array(values) => serializer.collect_seq(values.iter().map(Json)),
+        }
+    }
+}
+
+/// Serialize the integers as such, and the rest as the closest `f64`.
+fn serialize_number<S: Serializer>(number: Number, serializer: S) -> Result<S::Ok, S::Error> {
+    if number.is_nan() {
+        // The way the `json` crate dumps it.
+        return serializer.serialize_unit();
+    }
+
+    let (positive, mantissa, exponent) = number.as_parts();
+    if exponent == 0 {
+        if positive {
+            return serializer.serialize_u64(mantissa);
+        }
+        if let Ok(value) = i64::try_from(-i128::from(mantissa)) {
+            return serializer.serialize_i64(value);
+        }
+    }
+
+    // Parsing the decimal form rounds correctly, unlike scaling the mantissa.
+    let float = number
+        .to_string()
+        .parse()
+        .unwrap_or_else(|_| f64::from(number));
+    serializer.serialize_f64(float)
+}
+
+struct JsonVisitor;
+
+impl<'de> Visitor<'de> for JsonVisitor {
+    type Value = JsonValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<JsonValue, E> {
+        Ok(value.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<JsonValue, E> {
+        Ok(value.into())
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<JsonValue, E> {
+        Ok(value.into())
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<JsonValue, E> {
+        Ok(value.into())
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<JsonValue, E> {
+        Ok(value.into())
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<JsonValue, E> {
+        Ok(value.into())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<JsonValue, E> {
+        Ok(JsonValue::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<JsonValue, D::Error> {
+        deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JsonValue, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(Owned(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(JsonValue::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonValue, A::Error> {
+        let mut object = Object::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some((key, Owned(value))) = map.next_entry::<String, _>()? {
+            object.insert(&key, value);
+        }
+        Ok(JsonValue::Object(object))
+    }
+}
+
+/// A deserialized [`JsonValue`] nested in an array or an object.
+struct Owned(JsonValue);
+
+impl<'de> Deserialize<'de> for Owned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Owned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+    use json::{array, object, JsonValue};
+
+    fn round_trip(value: &JsonValue) -> JsonValue {
+        let serialized = serde_json::to_string(&Json(value)).unwrap();
+        super::deserialize(&mut serde_json::Deserializer::from_str(&serialized)).unwrap()
+    }
+
+    #[test]
+    fn nested_values_round_trip() {
+        let value = object! {
+            "text" => "Hello",
+            "short" => "a",
+            "flags" => array![true, false, JsonValue::Null],
+            "nested" => object! { "list" => array![1, -2, object! {}] },
+        };
+        assert_eq!(round_trip(&value), value);
+    }
+
+    #[test]
+    fn numbers_keep_precision() {
+        let value = array![std::u64::MAX, std::i64::MIN + 1, 0.1, 1.5e300, -2.25];
+        assert_eq!(
+            serde_json::to_string(&Json(&value)).unwrap(),
+            "[18446744073709551615,-9223372036854775807,0.1,1.5e+300,-2.25]"
+        );
+        assert_eq!(round_trip(&value), value);
+    }
+}
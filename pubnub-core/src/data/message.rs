@@ -1,25 +1,60 @@
 //! Message and relevant types.
 
 use super::channel;
+use super::file::{File, FileMessage};
 use super::timetoken::Timetoken;
 use json::JsonValue;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+use thiserror::Error;
 
 /// # PubNub Message
 ///
 /// This is the message structure yielded by [`Subscription`].
 ///
+/// The struct may get more fields, so it can't be built with a struct
+/// literal outside of this crate. Use [`Message::new`] instead, i.e. to test
+/// the message handlers.
+///
+/// The payload is decoded into the [`json`](Self::json) as soon as it's
+/// received, and the bytes it was decoded from are kept alongside, see
+/// [`Message::raw`]. The decoding isn't deferred until the payload is read,
+/// as the `json` is a public field the handlers read directly.
+///
+/// With the `serde` feature, the [`json`](Self::json) and the
+/// [`metadata`](Self::metadata) are serialized as the JSON they hold. The
+/// [`raw_payload`](Self::raw_payload) is skipped, and the missing fields
+/// are deserialized as the defaults.
+///
 /// [`Subscription`]: crate::Subscription
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate"),
+    serde(default)
+)]
+#[non_exhaustive]
 pub struct Message {
     /// Enum Type of Message.
     pub message_type: Type,
     /// Wildcard channel or channel group.
     pub route: Option<Route>,
     /// Origin Channel of Message Receipt.
+    ///
+    /// This is the plain channel name, exactly as passed to subscribe,
+    /// regardless of how it was encoded on the wire.
     pub channel: channel::Name,
     /// Decoded JSON Message Payload.
+    #[cfg_attr(feature = "serde", serde(with = "super::json_serde"))]
     pub json: JsonValue,
+    /// The payload exactly as received, if the transport preserves it.
+    ///
+    /// See [`Message::raw`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub raw_payload: Option<RawPayload>,
     /// Metadata of Message.
+    #[cfg_attr(feature = "serde", serde(with = "super::json_serde"))]
     pub metadata: JsonValue,
     /// Message ID Timetoken.
     pub timetoken: Timetoken,
@@ -29,10 +64,275 @@ pub struct Message {
     pub subscribe_key: String,
     /// Message flags.
     pub flags: u32,
+    /// Whether the payload was decrypted with the cipher key.
+    ///
+    /// When the cipher key is set, the published messages that can't be
+    /// decrypted (i.e. the ones published unencrypted) are delivered with
+    /// the payload as-is, and this flag unset.
+    pub decrypted: bool,
+    /// Why the message couldn't be decoded, if it couldn't.
+    ///
+    /// The undecodable messages hold the whole message as received in the
+    /// [`json`](Self::json). They're skipped by the
+    /// [`Subscription`](crate::Subscription), and reported as the
+    /// [`DecodeError`]s by the [`TrySubscription`](crate::TrySubscription).
+    pub decode_error: Option<String>,
+}
+
+impl Message {
+    /// Create a published message, the way the transports decode it.
+    ///
+    /// The [raw payload](Self::raw) is the `json` serialized, as if it was
+    /// received that way. The other fields are left empty, set them as
+    /// needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::{message::Message, timetoken::Timetoken};
+    /// use pubnub_core::json::object;
+    ///
+    /// let mut message = Message::new(
+    ///     "my-channel".parse().unwrap(),
+    ///     object! { "text" => "Hello, world!" },
+    ///     Timetoken::from_parts(15_850_559_815_660_696, 12),
+    /// );
+    /// message.client = Some("JoeBob".to_owned());
+    /// ```
+    #[must_use]
+    pub fn new(channel: channel::Name, json: JsonValue, timetoken: Timetoken) -> Self {
+        let raw: Arc<[u8]> = json.dump().into_bytes().into();
+        let len = raw.len();
+        Self {
+            message_type: Type::Publish,
+            channel,
+            json,
+            raw_payload: Some(RawPayload::new(raw, 0..len)),
+            timetoken,
+            ..Self::default()
+        }
+    }
+
+    /// The payload bytes exactly as received, i.e. to forward them elsewhere
+    /// without serializing the [`json`](Self::json) again.
+    ///
+    /// For the encrypted messages, these are the bytes before decryption.
+    /// The [`json`](Self::json) is decoded regardless, reading the raw bytes
+    /// only spares serializing it again.
+    ///
+    /// Returns `None` when the transport doesn't preserve the payloads, or
+    /// when the message was deserialized with the `serde` feature.
+    #[must_use]
+    pub fn raw(&self) -> Option<&[u8]> {
+        self.raw_payload.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The sequence number the message was published with, see
+    /// [`PubNub::publish_with_seqn`](crate::PubNub::publish_with_seqn).
+    ///
+    /// Returns `None` for the messages published without one.
+    #[must_use]
+    pub fn sequence(&self) -> Option<u64> {
+        self.metadata[SEQUENCE_META_KEY].as_u64()
+    }
+
+    /// Whether the message is a regular published message.
+    #[must_use]
+    pub fn is_publish(&self) -> bool {
+        self.message_type == Type::Publish
+    }
+
+    /// Whether the message is a signal.
+    #[must_use]
+    pub fn is_signal(&self) -> bool {
+        self.message_type == Type::Signal
+    }
+
+    /// Whether the message is a presence event, i.e. one received on a
+    /// presence channel.
+    #[must_use]
+    pub fn is_presence(&self) -> bool {
+        self.message_type == Type::Presence
+    }
+
+    /// Whether the message is a message action event.
+    #[must_use]
+    pub fn is_action(&self) -> bool {
+        self.message_type == Type::Action
+    }
+
+    /// Whether the message is an Objects service event.
+    #[must_use]
+    pub fn is_objects(&self) -> bool {
+        self.message_type == Type::Objects
+    }
+
+    /// Whether the message is a file message, announcing a file shared on
+    /// the channel.
+    #[must_use]
+    pub fn is_file(&self) -> bool {
+        self.message_type == Type::File
+    }
+
+    /// The shared file and the message sent along with it, for the file
+    /// messages.
+    ///
+    /// Returns `None` for the other messages, and for the file messages
+    /// without the file ID or name. The file ID and name can be passed to
+    /// [`PubNub::get_file_url`](crate::PubNub::get_file_url) or
+    /// [`PubNub::download_file`](crate::PubNub::download_file) as-is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::message::{Message, Type};
+    /// use pubnub_core::data::timetoken::Timetoken;
+    /// use pubnub_core::json::object;
+    ///
+    /// let mut message = Message::new(
+    ///     "my-channel".parse().unwrap(),
+    ///     object! {
+    ///         "message" => object! { "text" => "Look!" },
+    ///         "file" => object! { "id" => "5a3e", "name" => "cat.jpg" },
+    ///     },
+    ///     Timetoken::default(),
+    /// );
+    /// assert!(message.as_file().is_none());
+    ///
+    /// message.message_type = Type::File;
+    /// let file_message = message.as_file().unwrap();
+    /// assert_eq!(file_message.file.name, "cat.jpg");
+    /// assert_eq!(file_message.message.unwrap()["text"], "Look!");
+    /// ```
+    #[must_use]
+    pub fn as_file(&self) -> Option<FileMessage> {
+        if !self.is_file() {
+            return None;
+        }
+        let file = &self.json["file"];
+        let file = File {
+            id: file["id"].as_str()?.to_owned(),
+            name: file["name"].as_str()?.to_owned(),
+        };
+        let message = match &self.json["message"] {
+            JsonValue::Null => None,
+            message => Some(message.clone()),
+        };
+        Some(FileMessage { file, message })
+    }
+
+    /// The numeric message type code, as sent by the PubNub service.
+    ///
+    /// Useful for the [`Type::Unknown`] messages, for which it's the only
+    /// way to tell them apart. See [`Type::code`].
+    #[must_use]
+    pub fn raw_type_code(&self) -> u32 {
+        self.message_type.code()
+    }
+
+    /// The message as a [`serde_json::Value`], i.e. to hand it to the code
+    /// built around `serde_json`.
+    ///
+    /// The [`json`](Self::json) and the [`metadata`](Self::metadata) are
+    /// converted to the nested values. The integers are kept exact up to the
+    /// full `u64` and `i64` range, and the other numbers are converted to the
+    /// closest `f64`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::{message::Message, timetoken::Timetoken};
+    /// use pubnub_core::json::object;
+    ///
+    /// let message = Message::new(
+    ///     "my-channel".parse().unwrap(),
+    ///     object! { "text" => "Hello, world!" },
+    ///     Timetoken::from_parts(15_850_559_815_660_696, 12),
+    /// );
+    /// let value = message.as_serde_json();
+    /// assert_eq!(value["json"]["text"], "Hello, world!");
+    /// assert_eq!(value["timetoken"]["t"], "15850559815660696");
+    /// ```
+    #[cfg(feature = "serde")]
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // The messages are always serializable.
+    pub fn as_serde_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("message is always serializable")
+    }
+}
+
+/// A received message that couldn't be decoded, as reported by the
+/// [`TrySubscription`](crate::TrySubscription).
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("Unable to decode the message received on {channel}: {reason}")]
+pub struct DecodeError {
+    /// The channel the message was received on.
+    pub channel: channel::Name,
+    /// The message as received.
+    pub json: JsonValue,
+    /// What's wrong with the message.
+    pub reason: String,
+}
+
+/// The key of the sequence number in the message metadata.
+pub(crate) const SEQUENCE_META_KEY: &str = "seqn";
+
+/// # Raw message payload
+///
+/// A part of the received response holding the payload of a [`Message`].
+/// The messages received in the same response share the buffer, so the
+/// payloads aren't copied.
+#[derive(Debug, Clone)]
+pub struct RawPayload {
+    buffer: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl RawPayload {
+    /// Refer to the `range` of the `buffer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `range` is out of the `buffer` bounds.
+    #[must_use]
+    pub fn new(buffer: Arc<[u8]>, range: Range<usize>) -> Self {
+        assert!(
+            buffer.get(range.clone()).is_some(),
+            "Payload range is out of the buffer bounds"
+        );
+        Self { buffer, range }
+    }
+}
+
+impl Deref for RawPayload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl AsRef<[u8]> for RawPayload {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
 }
 
+impl PartialEq for RawPayload {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for RawPayload {}
+
 /// Message route.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub enum Route {
     /// Message arrived on a wildcard channel.
     ChannelWildcard(channel::WildcardSpec),
@@ -49,6 +349,11 @@ pub enum Route {
 /// applications built on the PubNub Rust client to be forward-compatible without requiring a full
 /// client upgrade.
 #[derive(Debug, Clone, Eq, PartialEq, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
 pub enum Type {
     /// A class message containing arbitrary payload data.
     Publish,
@@ -58,12 +363,49 @@ pub enum Type {
     Objects,
     /// A message action event.
     Action,
+    /// A file message, announcing a file shared on the channel. See
+    /// [`Message::as_file`].
+    File,
     /// Presence event from channel (e.g. another client joined).
     Presence,
     /// Unknown type. The value may have special meaning in some contexts.
     Unknown(u32),
 }
 
+impl Type {
+    /// The numeric code of the type, as sent by the PubNub service.
+    ///
+    /// The service doesn't have a code of its own for the presence events,
+    /// they're sent as the regular messages, so [`Type::Presence`] maps to
+    /// the code of [`Type::Publish`].
+    #[must_use]
+    pub fn code(self) -> u32 {
+        match self {
+            Type::Publish | Type::Presence => 0,
+            Type::Signal => 1,
+            Type::Objects => 2,
+            Type::Action => 3,
+            Type::File => 4,
+            Type::Unknown(code) => code,
+        }
+    }
+
+    /// The type with the numeric `code`, as sent by the PubNub service.
+    ///
+    /// The codes without a known type map to [`Type::Unknown`].
+    #[must_use]
+    pub fn from_code(code: u32) -> Self {
+        match code {
+            0 => Type::Publish,
+            1 => Type::Signal,
+            2 => Type::Objects,
+            3 => Type::Action,
+            4 => Type::File,
+            code => Type::Unknown(code),
+        }
+    }
+}
+
 impl Default for Message {
     #[must_use]
     fn default() -> Self {
@@ -72,11 +414,161 @@ impl Default for Message {
             route: None,
             channel: channel::Name::default(),
             json: JsonValue::Null,
+            raw_payload: None,
             metadata: JsonValue::Null,
             timetoken: Timetoken::default(),
             client: None,
             subscribe_key: String::default(),
             flags: Default::default(),
+            decrypted: false,
+            decode_error: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    use super::Route;
+    use super::{Message, Type};
+    use crate::data::file::{File, FileMessage};
+    use crate::data::timetoken::Timetoken;
+    use json::object;
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let mut message = Message::new(
+            "my-channel".parse().unwrap(),
+            object! { "text" => "Hello", "n" => 9_007_199_254_740_993_u64 },
+            Timetoken::from_parts(15_850_559_815_660_696, 12),
+        );
+        message.message_type = Type::Unknown(7);
+        message.route = Some(Route::ChannelWildcard("my.*".parse().unwrap()));
+        message.metadata = object! { "seqn" => 3 };
+        message.client = Some("JoeBob".to_owned());
+
+        let serialized = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            serialized["message_type"],
+            serde_json::json!({ "Unknown": 7 })
+        );
+        assert_eq!(
+            serialized["timetoken"],
+            serde_json::json!({ "t": "15850559815660696", "r": 12 })
+        );
+
+        let deserialized: Message = serde_json::from_value(serialized).unwrap();
+        // The raw payload isn't serialized.
+        message.raw_payload = None;
+        assert_eq!(deserialized, message);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn as_serde_json_converts_payload_and_metadata() {
+        let mut message = Message::new(
+            "my-channel".parse().unwrap(),
+            json::parse(
+                r#"{
+                    "big": 18446744073709551615,
+                    "negative": -9223372036854775808,
+                    "float": 0.12345678901234568,
+                    "nested": [{ "a": [1, "b"] }, null]
+                }"#,
+            )
+            .unwrap(),
+            Timetoken::default(),
+        );
+        message.metadata = object! { "seqn" => 3 };
+
+        let value = message.as_serde_json();
+        assert_eq!(
+            value["json"],
+            serde_json::json!({
+                "big": std::u64::MAX,
+                "negative": std::i64::MIN,
+                "float": 0.123_456_789_012_345_68,
+                "nested": [{ "a": [1, "b"] }, null],
+            })
+        );
+        assert_eq!(value["metadata"], serde_json::json!({ "seqn": 3 }));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_rejects_invalid_channel() {
+        let result: Result<Message, _> =
+            serde_json::from_value(serde_json::json!({ "channel": "a,b" }));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn type_predicates() {
+        let mut message = Message::new(
+            "my-channel".parse().unwrap(),
+            object! {},
+            Timetoken::default(),
+        );
+        assert!(message.is_publish());
+        assert!(!message.is_signal());
+        assert_eq!(message.raw_type_code(), 0);
+        assert_eq!(message.raw(), Some(&b"{}"[..]));
+
+        message.message_type = Type::Action;
+        assert!(message.is_action());
+        assert!(!message.is_publish());
+        assert_eq!(message.raw_type_code(), 3);
+
+        message.message_type = Type::Unknown(42);
+        assert!(!message.is_publish());
+        assert!(!message.is_signal());
+        assert!(!message.is_presence());
+        assert!(!message.is_action());
+        assert!(!message.is_objects());
+        assert_eq!(message.raw_type_code(), 42);
+    }
+
+    #[test]
+    fn type_codes_round_trip() {
+        for code in 0..8 {
+            assert_eq!(Type::from_code(code).code(), code);
         }
+        assert_eq!(Type::from_code(2), Type::Objects);
+        assert_eq!(Type::from_code(4), Type::File);
+        assert_eq!(Type::from_code(5), Type::Unknown(5));
+    }
+
+    #[test]
+    fn as_file_extracts_the_file_and_the_message() {
+        let mut message = Message::new(
+            "my-channel".parse().unwrap(),
+            object! {
+                "message" => object! { "text" => "Look!" },
+                "file" => object! { "id" => "5a3e", "name" => "cat.jpg" },
+            },
+            Timetoken::default(),
+        );
+        // An ordinary publish, even with the same shape.
+        assert_eq!(message.as_file(), None);
+
+        message.message_type = Type::File;
+        assert!(message.is_file());
+        assert_eq!(
+            message.as_file(),
+            Some(FileMessage {
+                file: File {
+                    id: "5a3e".to_owned(),
+                    name: "cat.jpg".to_owned(),
+                },
+                message: Some(object! { "text" => "Look!" }),
+            })
+        );
+
+        message.json.remove("message");
+        assert_eq!(message.as_file().unwrap().message, None);
+
+        message.json["file"].remove("id");
+        assert_eq!(message.as_file(), None);
     }
 }
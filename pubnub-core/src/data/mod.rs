@@ -1,12 +1,19 @@
 //! Data structs and enums.
 
+pub mod app_context;
 pub mod channel;
+pub mod cursor;
+pub mod file;
+pub mod filter;
 pub mod history;
+#[cfg(feature = "serde")]
+mod json_serde;
 pub mod message;
 pub mod object;
 pub mod pam;
 pub mod presence;
 pub mod pubsub;
+pub mod push;
 pub mod request;
 pub mod response;
 pub mod target;
@@ -2,8 +2,12 @@
 
 use super::object::Object;
 use bitflags::bitflags;
+use json::JsonValue;
+use pubnub_util::pam_token::{self, Value};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt::Debug;
+use thiserror::Error;
 
 bitflags! {
     /// Permissions bitmask. Values can be combined with a bitwise OR operation.
@@ -15,6 +19,9 @@ bitflags! {
     /// |`MANAGE`|`0b0000_0100` |`0x04`     |`4`        |Applies to Channel-Groups, Objects               |
     /// |`DELETE`|`0b0000_1000` |`0x08`     |`8`        |Applies to History                               |
     /// |`CREATE`|`0b0001_0000` |`0x10`     |`16`       |Applies to Objects                               |
+    /// |`GET`   |`0b0010_0000` |`0x20`     |`32`       |Applies to App Context                           |
+    /// |`UPDATE`|`0b0100_0000` |`0x40`     |`64`       |Applies to App Context                           |
+    /// |`JOIN`  |`0b1000_0000` |`0x80`     |`128`      |Applies to App Context                           |
     ///
     /// ## Permissions matrix:
     ///
@@ -35,6 +42,9 @@ bitflags! {
     /// |`groups`     |`MANAGE`  |Groups; Add Channels   |Adding channels to a channel-group                 |
     /// |`groups`     |`MANAGE`  |Groups; Remove Channels|Removing channels from a channel-group             |
     /// |`groups`     |`MANAGE`  |Delete Group           |Deleting a channel-group                           |
+    /// |`uuids`      |`GET`     |UUID Metadata; Get     |Reading the metadata and memberships of a UUID     |
+    /// |`uuids`      |`UPDATE`  |UUID Metadata; Set     |Updating the metadata and memberships of a UUID    |
+    /// |`uuids`      |`DELETE`  |UUID Metadata; Remove  |Removing the metadata of a UUID                    |
     /// |`users`      |`CREATE`  |User; Create           |Creating a user by `UserID`                        |
     /// |`users`      |`DELETE`  |User; Delete           |Deleting a user and all of its space memberships   |
     /// |`users`      |`MANAGE`  |User; Add membership   |Adding space membership for a user                 |
@@ -63,6 +73,12 @@ bitflags! {
         const DELETE = 0b0000_1000;
         /// Applies to Objects
         const CREATE = 0b0001_0000;
+        /// Applies to App Context
+        const GET = 0b0010_0000;
+        /// Applies to App Context
+        const UPDATE = 0b0100_0000;
+        /// Applies to App Context
+        const JOIN = 0b1000_0000;
     }
 }
 
@@ -114,6 +130,9 @@ pub struct Resources {
     /// A shallow mapping of channel groups to permissions.
     pub groups: HashMap<String, BitMask>,
 
+    /// A shallow mapping of UUIDs to permissions.
+    pub uuids: HashMap<String, BitMask>,
+
     /// A shallow mapping of user IDs to permissions.
     pub users: HashMap<String, BitMask>,
 
@@ -132,9 +151,248 @@ pub struct Patterns {
     /// A shallow mapping of channel-group regular expressions to permissions.
     pub groups: HashMap<PatternRegex, BitMask>,
 
+    /// A shallow mapping of UUID regular expressions to permissions.
+    pub uuids: HashMap<PatternRegex, BitMask>,
+
     /// A shallow mapping of user ID regular expressions to permissions.
     pub users: HashMap<PatternRegex, BitMask>,
 
     /// A shallow mapping of space ID regular expressions to permissions.
     pub spaces: HashMap<PatternRegex, BitMask>,
 }
+
+/// The contents of a PAMv3 token, as granted by the
+/// [`Grant`](super::request::Grant).
+///
+/// The contents are readable by anyone holding the token, only the
+/// signature keeps them from being altered. Parse a token with
+/// [`TokenContents::parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenContents {
+    /// The version of the token format.
+    pub version: u32,
+
+    /// When the token was granted, in seconds since the UNIX epoch.
+    pub timestamp: u64,
+
+    /// The duration (in minutes) the token is valid for, since the
+    /// [`timestamp`](Self::timestamp).
+    pub ttl: u32,
+
+    /// The UUID the token is restricted to, if any.
+    pub authorized_uuid: Option<String>,
+
+    /// The permissions to the resources.
+    pub resources: Resources,
+
+    /// The permissions to the resources matching the patterns.
+    pub patterns: Patterns,
+
+    /// The meta mapping the token was granted with.
+    pub meta: Object,
+
+    /// The signature of the token.
+    pub signature: Vec<u8>,
+}
+
+/// An error that can occur while parsing a PAMv3 token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum ParseTokenError {
+    /// The token is not valid base64-encoded CBOR.
+    #[error("malformed token: {0}")]
+    Malformed(#[from] pam_token::Error),
+
+    /// The token is missing a field, or has one of an unexpected type.
+    #[error("invalid token field: {0}")]
+    InvalidField(&'static str),
+}
+
+impl TokenContents {
+    /// Parse the `token`, i.e. to check its permissions or its expiration
+    /// on the client side.
+    ///
+    /// The signature is not verified, that takes the secret key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token is malformed.
+    pub fn parse(token: &str) -> Result<Self, ParseTokenError> {
+        let value = pam_token::decode(token)?;
+        let field = |name: &'static str| value.get(name).ok_or(ParseTokenError::InvalidField(name));
+        let integer = |name: &'static str| {
+            field(name)?
+                .as_integer()
+                .ok_or(ParseTokenError::InvalidField(name))
+        };
+
+        let signature = match field("sig")? {
+            Value::Bytes(bytes) => bytes.clone(),
+            _ => return Err(ParseTokenError::InvalidField("sig")),
+        };
+        let authorized_uuid = match value.get("uuid") {
+            Some(uuid) => Some(
+                uuid.as_str()
+                    .ok_or(ParseTokenError::InvalidField("uuid"))?
+                    .to_owned(),
+            ),
+            None => None,
+        };
+        let meta = match value.get("meta") {
+            Some(meta) => to_json(meta),
+            None => JsonValue::new_object(),
+        };
+
+        Ok(Self {
+            version: u32::try_from(integer("v")?)
+                .map_err(|_| ParseTokenError::InvalidField("v"))?,
+            timestamp: u64::try_from(integer("t")?)
+                .map_err(|_| ParseTokenError::InvalidField("t"))?,
+            ttl: u32::try_from(integer("ttl")?)
+                .map_err(|_| ParseTokenError::InvalidField("ttl"))?,
+            authorized_uuid,
+            resources: parse_resources(field("res")?, "res")?.into(),
+            patterns: parse_resources(field("pat")?, "pat")?.into(),
+            meta,
+            signature,
+        })
+    }
+
+    /// When the token expires, in seconds since the UNIX epoch.
+    #[must_use]
+    pub fn expires_at(&self) -> u64 {
+        self.timestamp + u64::from(self.ttl) * 60
+    }
+}
+
+/// The permissions per resource type, in the order of the [`Resources`]
+/// fields.
+type ResourceMaps = [HashMap<String, BitMask>; 5];
+
+impl From<ResourceMaps> for Resources {
+    fn from([channels, groups, uuids, users, spaces]: ResourceMaps) -> Self {
+        Self {
+            channels,
+            groups,
+            uuids,
+            users,
+            spaces,
+        }
+    }
+}
+
+impl From<ResourceMaps> for Patterns {
+    fn from([channels, groups, uuids, users, spaces]: ResourceMaps) -> Self {
+        Self {
+            channels,
+            groups,
+            uuids,
+            users,
+            spaces,
+        }
+    }
+}
+
+/// Parse the permissions of the `res` or the `pat` token `field`.
+fn parse_resources(value: &Value, field: &'static str) -> Result<ResourceMaps, ParseTokenError> {
+    let mut maps = ResourceMaps::default();
+    for (map, key) in maps.iter_mut().zip(&["chan", "grp", "uuid", "usr", "spc"]) {
+        let entries = match value.get(key) {
+            Some(Value::Map(entries)) => entries,
+            Some(_) => return Err(ParseTokenError::InvalidField(field)),
+            None => continue,
+        };
+        for (name, bits) in entries {
+            let bits = bits
+                .as_integer()
+                .and_then(|bits| u64::try_from(bits).ok())
+                .ok_or(ParseTokenError::InvalidField(field))?;
+            map.insert(name.clone(), BitMask::from_bits_truncate(bits));
+        }
+    }
+    Ok(maps)
+}
+
+/// Convert the decoded CBOR `value` to JSON. The byte strings are converted
+/// lossily to strings.
+fn to_json(value: &Value) -> JsonValue {
+    match value {
+        Value::Integer(value) => match (i64::try_from(*value), u64::try_from(*value)) {
+            (Ok(value), _) => value.into(),
+            (_, Ok(value)) => value.into(),
+            #[allow(clippy::cast_precision_loss)]
+            _ => (*value as f64).into(),
+        },
+        Value::Bytes(bytes) => String::from_utf8_lossy(bytes).into_owned().into(),
+        Value::Text(text) => text.as_str().into(),
+        Value::Array(values) => JsonValue::Array(values.iter().map(to_json).collect()),
+        Value::Map(map) => {
+            let mut object = JsonValue::new_object();
+            for (key, value) in map {
+                object[key.as_str()] = to_json(value);
+            }
+            object
+        }
+        Value::Bool(value) => (*value).into(),
+        Value::Float(value) => (*value).into(),
+        Value::Null => JsonValue::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitMask, ParseTokenError, TokenContents};
+    use pubnub_util::pam_token;
+
+    /// A token granted by the PubNub network, with the byte string keys.
+    const TOKEN: &str = "qEF2AkF0GmEI03xDdHRsGDxDcmVzpURjaGFuoWljaGFubmVsLTEY70NncnChb2NoYW5uZWxfZ3JvdXAtMQVDdXNyoENzcGOgRHV1aWShZnV1aWQtMRhoQ3BhdKVEY2hhbqFtXmNoYW5uZWwtXFMqJBjvQ2dycKF0XjpjaGFubmVsX2dyb3VwLVxTKiQFQ3VzcqBDc3BjoER1dWlkoWpedXVpZC1cUyokGGhEbWV0YaBEdXVpZHR0ZXN0LWF1dGhvcml6ZWQtdXVpZENzaWdYIPpU-vCe9rkpYs87YUrFNWkyNq8CVvmKwEjVinnDrJJc";
+
+    #[test]
+    fn parse_token() {
+        let contents = TokenContents::parse(TOKEN).unwrap();
+
+        assert_eq!(contents.version, 2);
+        assert_eq!(contents.timestamp, 1_627_968_380);
+        assert_eq!(contents.ttl, 60);
+        assert_eq!(contents.expires_at(), 1_627_968_380 + 60 * 60);
+        assert_eq!(
+            contents.authorized_uuid,
+            Some("test-authorized-uuid".to_owned())
+        );
+        assert_eq!(
+            contents.resources.channels["channel-1"],
+            BitMask::from_bits_truncate(239)
+        );
+        assert_eq!(
+            contents.resources.groups["channel_group-1"],
+            BitMask::READ | BitMask::MANAGE
+        );
+        assert_eq!(
+            contents.resources.uuids["uuid-1"],
+            BitMask::DELETE | BitMask::GET | BitMask::UPDATE
+        );
+        assert!(contents.resources.users.is_empty());
+        assert_eq!(
+            contents.patterns.channels[r"^channel-\S*$"],
+            BitMask::from_bits_truncate(239)
+        );
+        assert_eq!(
+            contents.patterns.uuids[r"^uuid-\S*$"],
+            BitMask::DELETE | BitMask::GET | BitMask::UPDATE
+        );
+        assert_eq!(contents.meta, json::object! {});
+        assert_eq!(contents.signature.len(), 32);
+    }
+
+    #[test]
+    fn parse_malformed_token() {
+        assert_eq!(
+            TokenContents::parse("not a token"),
+            Err(ParseTokenError::Malformed(pam_token::Error::Base64))
+        );
+        // `{"v": 2}`
+        assert_eq!(
+            TokenContents::parse("oWF2Ag"),
+            Err(ParseTokenError::InvalidField("sig"))
+        );
+    }
+}
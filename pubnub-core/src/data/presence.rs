@@ -8,13 +8,13 @@ pub mod respond_with {
     //! Type system level flags to specialize the response types.
 
     /// Return occupancy only.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct OccupancyOnly;
     /// Return occupancy and UUIDs of the users.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct OccupancyAndUUIDs;
     /// Return cooupance, UUIDs of the users and the related states.
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Full;
 
     /// A trait that bounds type system level flag to an actual type
@@ -1,6 +1,8 @@
 //! Publish/subscribe API related types.
 
 use super::channel;
+use super::object::Object;
+use super::timetoken::Timetoken;
 
 /// A subscription destination.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -13,7 +15,50 @@ pub enum SubscribeTo {
     ChannelGroup(channel::Name),
 }
 
+impl From<channel::Name> for SubscribeTo {
+    fn from(name: channel::Name) -> Self {
+        SubscribeTo::Channel(name)
+    }
+}
+
+impl From<channel::WildcardSpec> for SubscribeTo {
+    fn from(spec: channel::WildcardSpec) -> Self {
+        SubscribeTo::ChannelWildcard(spec)
+    }
+}
+
+/// The kind of a subscription destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListenerKind {
+    /// A [`SubscribeTo::Channel`].
+    Channel,
+    /// A [`SubscribeTo::ChannelWildcard`].
+    ChannelWildcard,
+    /// A [`SubscribeTo::ChannelGroup`].
+    ChannelGroup,
+}
+
 impl SubscribeTo {
+    /// Returns the name of the channel, the wildcard specifier or the
+    /// channel group.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            SubscribeTo::Channel(ref v) | SubscribeTo::ChannelGroup(ref v) => v.as_ref(),
+            SubscribeTo::ChannelWildcard(ref v) => v.as_ref(),
+        }
+    }
+
+    /// Returns the kind of the destination.
+    #[must_use]
+    pub fn kind(&self) -> ListenerKind {
+        match self {
+            SubscribeTo::Channel(_) => ListenerKind::Channel,
+            SubscribeTo::ChannelWildcard(_) => ListenerKind::ChannelWildcard,
+            SubscribeTo::ChannelGroup(_) => ListenerKind::ChannelGroup,
+        }
+    }
+
     /// Returns a channel name if this is a [`SubscribeTo::Channel`].
     #[must_use]
     pub fn as_channel(&self) -> Option<&channel::Name> {
@@ -41,3 +86,114 @@ impl SubscribeTo {
         }
     }
 }
+
+/// How the message of a publish request is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PublishMethod {
+    /// The message is sent in the URL of a `GET` request.
+    ///
+    /// The URL length is limited, so this only works for the small messages.
+    Get,
+    /// The message is sent in the body of a `POST` request.
+    ///
+    /// Works for the messages of any size, and keeps the message out of the
+    /// URL, i.e. out of the logs of the proxies on the way.
+    Post,
+}
+
+/// The options of [`PubNub::publish_with_options`](crate::PubNub::publish_with_options).
+///
+/// The options combine freely, i.e. a message with metadata can be sent
+/// via `POST` without replicating it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishOptions {
+    /// Additional information associated with the message. Defaults to
+    /// none.
+    pub meta: Option<Object>,
+
+    /// How to send the message. Defaults to `None`, i.e. in the URL, unless
+    /// it's bigger than the
+    /// [`publish_post_threshold`](crate::Builder::publish_post_threshold).
+    pub method: Option<PublishMethod>,
+
+    /// Publish timetoken override (`ptto`), the timetoken to store the
+    /// message with instead of the one assigned by the server. Defaults to
+    /// none.
+    ///
+    /// A client-generated value makes the retries of the publish, see
+    /// [`Builder::publish_retry`](crate::Builder::publish_retry), produce
+    /// the duplicates with the same timetoken, so the subscribers can
+    /// recognize them.
+    pub timetoken_override: Option<u64>,
+
+    /// Whether to replicate the message to all the PubNub data centers.
+    /// Defaults to `true`.
+    ///
+    /// When `false` (`norep`), the message is delivered only to the
+    /// subscribers connected to the same data center as the publisher.
+    /// Replication is unrelated to the echo: the publisher's own
+    /// subscriptions still receive the message. To skip the own messages,
+    /// subscribe with a filter expression on the publisher's UUID instead,
+    /// i.e. `uuid != 'my-uuid'`, see
+    /// [`subscribe_with_filter`](crate::PubNub::subscribe_with_filter).
+    pub replicate: bool,
+}
+
+impl Default for PublishOptions {
+    fn default() -> Self {
+        Self {
+            meta: None,
+            method: None,
+            timetoken_override: None,
+            replicate: true,
+        }
+    }
+}
+
+/// The response of the PubNub network to a publish request.
+///
+/// Successful publishes are answered with `[1, "Sent", "<timetoken>"]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PublishResponse {
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The status message, i.e. `Sent`.
+    pub message: String,
+    /// The timetoken of the published message.
+    ///
+    /// Publish responses carry no origin region, so the region is always
+    /// `0`.
+    pub timetoken: Timetoken,
+}
+
+impl PublishResponse {
+    /// Create a `PublishResponse`.
+    #[must_use]
+    pub fn new(status: u16, message: String, timetoken: Timetoken) -> Self {
+        Self {
+            status,
+            message,
+            timetoken,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListenerKind, SubscribeTo};
+
+    #[test]
+    fn name_and_kind() {
+        let channel = SubscribeTo::Channel("a".parse().unwrap());
+        let wildcard = SubscribeTo::ChannelWildcard("a.*".parse().unwrap());
+        let group = SubscribeTo::ChannelGroup("a".parse().unwrap());
+
+        assert_eq!(channel.name(), "a");
+        assert_eq!(channel.kind(), ListenerKind::Channel);
+        assert_eq!(wildcard.name(), "a.*");
+        assert_eq!(wildcard.kind(), ListenerKind::ChannelWildcard);
+        assert_eq!(group.name(), "a");
+        assert_eq!(group.kind(), ListenerKind::ChannelGroup);
+    }
+}
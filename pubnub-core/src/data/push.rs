@@ -0,0 +1,44 @@
+//! Mobile push notifications related types.
+
+/// The push notification service a device is registered with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PushType {
+    /// Apple Push Notification service, via the legacy binary protocol.
+    ///
+    /// Deprecated by Apple, use [`PushType::Apns2`] instead.
+    Apns,
+
+    /// Apple Push Notification service, via the HTTP/2-based protocol.
+    Apns2 {
+        /// The APNs environment the device token belongs to.
+        environment: ApnsEnvironment,
+        /// The bundle ID of the app, used as the notification topic.
+        topic: String,
+    },
+
+    /// Firebase Cloud Messaging (formerly Google Cloud Messaging).
+    Gcm,
+
+    /// Microsoft Push Notification Service.
+    Mpns,
+}
+
+/// The APNs environment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApnsEnvironment {
+    /// The development (sandbox) environment.
+    Development,
+    /// The production environment.
+    Production,
+}
+
+impl ApnsEnvironment {
+    /// The value the PubNub API uses for the environment.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApnsEnvironment::Development => "development",
+            ApnsEnvironment::Production => "production",
+        }
+    }
+}
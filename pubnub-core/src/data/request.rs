@@ -1,12 +1,16 @@
 //! Types used by [`crate::Transport`].
 
+use super::app_context;
+use super::file;
 use super::history;
 use crate::data::channel;
+use crate::data::cursor::Cursor;
+use crate::data::filter::FilterExpression;
 use crate::data::object::Object;
 use crate::data::pam;
 use crate::data::presence;
 use crate::data::pubsub;
-use crate::data::timetoken::Timetoken;
+use crate::data::push;
 use crate::data::uuid::UUID;
 use std::{collections::HashMap, marker::PhantomData};
 
@@ -21,6 +25,25 @@ pub struct Publish {
 
     /// Additional information associated with the message.
     pub meta: Option<Object>,
+
+    /// Publish timetoken override (`ptto`).
+    ///
+    /// When set, the message is stored with this timetoken instead of the one
+    /// assigned by the server. Passing a client-generated value allows
+    /// retrying the publish without producing a message with a different
+    /// timetoken, so the subscribers can recognize the duplicates.
+    pub timetoken_override: Option<u64>,
+
+    /// Whether the message is replicated to all the PubNub data centers
+    /// (`norep` when not).
+    ///
+    /// When `false`, the message is delivered only to the subscribers
+    /// connected to the data center it was published to. It doesn't stop
+    /// the publisher's own subscriptions from receiving the message.
+    pub replicate: bool,
+
+    /// Whether the message is sent in the URL or in the request body.
+    pub method: pubsub::PublishMethod,
 }
 
 /// Subscribe to messages on channels and/or channel groups.
@@ -29,14 +52,17 @@ pub struct Subscribe {
     /// The destinations to subscribe to.
     pub to: Vec<pubsub::SubscribeTo>,
 
-    /// A timetoken to use.
-    /// tt: 0 (zero) for the initial subscribe, or a valid timetoken if
-    /// resuming / continuing / fast-forwarding from a previous subscribe flow.
-    /// tr: Region as returned from the initial call with tt=0.
-    pub timetoken: Timetoken,
+    /// The cursor to continue from.
+    /// The default cursor for the initial subscribe, or the one returned
+    /// from the previous subscribe if resuming / continuing / fast-forwarding
+    /// the subscribe flow.
+    pub cursor: Cursor,
 
     /// The heartbeat value to send to the PubNub network.
     pub heartbeat: Option<presence::HeartbeatValue>,
+
+    /// The expression to filter the messages with.
+    pub filter_expr: Option<FilterExpression>,
 }
 
 /// Set state for a user for channels and/or channel groups.
@@ -104,6 +130,17 @@ pub struct WhereNow {
     pub uuid: UUID,
 }
 
+/// Announce leaving the channels and/or channel groups.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leave {
+    /// The subscription destinations to leave.
+    pub to: Vec<pubsub::SubscribeTo>,
+
+    /// The User UUID to leave as. If `None`, the UUID of the transport is
+    /// used.
+    pub uuid: Option<UUID>,
+}
+
 /// Announce a heartbeat.
 #[allow(missing_copy_implementations)]
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -124,6 +161,13 @@ pub struct Heartbeat {
 /// PAMv3 Grant.
 pub type Grant = pam::GrantBody;
 
+/// PAMv3 token revocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevokeToken {
+    /// The token to revoke, as returned by the [`Grant`].
+    pub token: String,
+}
+
 /// Fetch history.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct GetHistory {
@@ -204,3 +248,242 @@ pub struct MessageCountsWithChannelTimetokens {
     /// Timetoken value must be non-zero.
     pub channels: HashMap<channel::Name, history::Timetoken>,
 }
+
+/// Get the metadata of a UUID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetUuidMetadata {
+    /// The UUID to get the metadata of.
+    pub uuid: UUID,
+}
+
+/// Set the metadata of a UUID.
+///
+/// Only the fields that are set are updated, the rest are kept as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetUuidMetadata {
+    /// The metadata to set.
+    pub metadata: app_context::UuidMetadata,
+
+    /// If set, the update is only applied if the entity tag of the stored
+    /// metadata matches this value (`If-Match`), i.e. if the metadata wasn't
+    /// modified since it was fetched.
+    pub if_match: Option<String>,
+}
+
+/// Remove the metadata of a UUID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoveUuidMetadata {
+    /// The UUID to remove the metadata of.
+    pub uuid: UUID,
+}
+
+/// Get the metadata of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetChannelMetadata {
+    /// The channel to get the metadata of.
+    pub channel: channel::Name,
+}
+
+/// Get the metadata of all the channels, one page at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetAllChannelMetadata {
+    /// The maximum number of items on the page. If `None`, the server
+    /// default is used.
+    pub limit: Option<usize>,
+
+    /// The page to get. If `None`, the first page is returned.
+    pub page: Option<app_context::PageCursor>,
+
+    /// Whether to include the total number of items in the response.
+    pub include_total_count: bool,
+}
+
+/// Set the metadata of a channel.
+///
+/// Only the fields that are set are updated, the rest are kept as-is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetChannelMetadata {
+    /// The metadata to set.
+    pub metadata: app_context::ChannelMetadata,
+
+    /// If set, the update is only applied if the entity tag of the stored
+    /// metadata matches this value (`If-Match`).
+    pub if_match: Option<String>,
+}
+
+/// Remove the metadata of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoveChannelMetadata {
+    /// The channel to remove the metadata of.
+    pub channel: channel::Name,
+}
+
+/// Get the channel memberships of a UUID, one page at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetMemberships {
+    /// The UUID to get the memberships of.
+    pub uuid: UUID,
+
+    /// Which related data to include.
+    pub include: app_context::MembershipInclude,
+
+    /// The maximum number of items on the page. If `None`, the server
+    /// default is used.
+    pub limit: Option<usize>,
+
+    /// The page to get. If `None`, the first page is returned.
+    pub page: Option<app_context::PageCursor>,
+
+    /// Whether to include the total number of items in the response.
+    pub include_total_count: bool,
+}
+
+/// Change the channel memberships of a UUID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetMemberships {
+    /// The UUID to change the memberships of.
+    pub uuid: UUID,
+
+    /// The changes to apply.
+    pub changes: app_context::MembershipChanges<channel::Name>,
+
+    /// Which related data to include in the response.
+    pub include: app_context::MembershipInclude,
+}
+
+/// Get the members of a channel, one page at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetChannelMembers {
+    /// The channel to get the members of.
+    pub channel: channel::Name,
+
+    /// Which related data to include.
+    pub include: app_context::MembershipInclude,
+
+    /// The maximum number of items on the page. If `None`, the server
+    /// default is used.
+    pub limit: Option<usize>,
+
+    /// The page to get. If `None`, the first page is returned.
+    pub page: Option<app_context::PageCursor>,
+
+    /// Whether to include the total number of items in the response.
+    pub include_total_count: bool,
+}
+
+/// Change the members of a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetChannelMembers {
+    /// The channel to change the members of.
+    pub channel: channel::Name,
+
+    /// The changes to apply.
+    pub changes: app_context::MembershipChanges<UUID>,
+
+    /// Which related data to include in the response.
+    pub include: app_context::MembershipInclude,
+}
+
+/// Register a device for push notifications on channels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddPushChannels {
+    /// The device token issued by the push notification service.
+    pub device_token: String,
+
+    /// The push notification service the device token belongs to.
+    pub push_type: push::PushType,
+
+    /// The channels to receive push notifications on.
+    pub channels: Vec<channel::Name>,
+}
+
+/// Unregister a device from push notifications on channels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovePushChannels {
+    /// The device token issued by the push notification service.
+    pub device_token: String,
+
+    /// The push notification service the device token belongs to.
+    pub push_type: push::PushType,
+
+    /// The channels to stop receiving push notifications on.
+    pub channels: Vec<channel::Name>,
+}
+
+/// List the channels a device is registered for push notifications on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListPushChannels {
+    /// The device token issued by the push notification service.
+    pub device_token: String,
+
+    /// The push notification service the device token belongs to.
+    pub push_type: push::PushType,
+}
+
+/// Fetch the current time of the PubNub network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Time;
+
+/// Request the form to upload a file to the channel with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerateFileUploadUrl {
+    /// The channel to share the file on.
+    pub channel: channel::Name,
+
+    /// The name of the file.
+    pub file_name: String,
+}
+
+/// Upload a file with the form returned by the [`GenerateFileUploadUrl`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadFile {
+    /// The form to upload the file with.
+    pub form: file::UploadForm,
+
+    /// The contents of the file.
+    pub data: Vec<u8>,
+}
+
+/// Publish the message announcing an uploaded file on the channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublishFileMessage {
+    /// The channel the file was uploaded to.
+    pub channel: channel::Name,
+
+    /// The uploaded file.
+    pub file: file::File,
+
+    /// The message to send along with the file.
+    pub message: Option<Object>,
+}
+
+/// List the files stored on the channel, a page at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListFiles {
+    /// The channel to list the files of.
+    pub channel: channel::Name,
+
+    /// The token of the page to get, from the previous page. The first page
+    /// if `None`.
+    pub next: Option<String>,
+}
+
+/// Get the URL to download a file from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetFileUrl {
+    /// The channel the file was shared on.
+    pub channel: channel::Name,
+
+    /// The file to download.
+    pub file: file::File,
+}
+
+/// Download a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadFile {
+    /// The channel the file was shared on.
+    pub channel: channel::Name,
+
+    /// The file to download.
+    pub file: file::File,
+}
@@ -1,18 +1,23 @@
 //! Types used by [`crate::Transport`].
 
+use crate::data::app_context;
 use crate::data::channel;
+use crate::data::cursor::Cursor;
+use crate::data::file;
 use crate::data::history;
 use crate::data::message::Message;
 use crate::data::object::Object;
 use crate::data::presence;
+use crate::data::pubsub;
 use crate::data::timetoken::Timetoken;
+use futures_core::stream::BoxStream;
 use std::collections::HashMap;
 
 /// A response to a publish request.
-pub type Publish = Timetoken;
+pub type Publish = pubsub::PublishResponse;
 
 /// A response to a subscribe request.
-pub type Subscribe = (Vec<Message>, Timetoken);
+pub type Subscribe = (Vec<Message>, Cursor);
 
 /// A response to a set state request.
 pub type SetState = ();
@@ -32,9 +37,15 @@ pub type WhereNow = Vec<channel::Name>;
 /// A response to a heartbeat request.
 pub type Heartbeat = ();
 
+/// A response to a leave request.
+pub type Leave = ();
+
 /// A response to a PAMv3 grant request.
 pub type Grant = String;
 
+/// A response to a PAMv3 token revocation request.
+pub type RevokeToken = ();
+
 /// A response to a get history request.
 pub type GetHistory = HashMap<channel::Name, Vec<history::Item>>;
 
@@ -49,3 +60,69 @@ pub type MessageCountsWithTimetoken = HashMap<channel::Name, usize>;
 
 /// A response to a message counts with channel timetokens request.
 pub type MessageCountsWithChannelTimetokens = HashMap<channel::Name, usize>;
+
+/// A response to a get UUID metadata request.
+pub type GetUuidMetadata = app_context::UuidMetadata;
+
+/// A response to a set UUID metadata request. The updated metadata.
+pub type SetUuidMetadata = app_context::UuidMetadata;
+
+/// A response to a remove UUID metadata request.
+pub type RemoveUuidMetadata = ();
+
+/// A response to a get channel metadata request.
+pub type GetChannelMetadata = app_context::ChannelMetadata;
+
+/// A response to a get all channel metadata request.
+pub type GetAllChannelMetadata = app_context::Page<app_context::ChannelMetadata>;
+
+/// A response to a set channel metadata request. The updated metadata.
+pub type SetChannelMetadata = app_context::ChannelMetadata;
+
+/// A response to a remove channel metadata request.
+pub type RemoveChannelMetadata = ();
+
+/// A response to a get memberships request.
+pub type GetMemberships = app_context::Page<app_context::ChannelMembership>;
+
+/// A response to a set memberships request. The first page of the updated
+/// memberships.
+pub type SetMemberships = app_context::Page<app_context::ChannelMembership>;
+
+/// A response to a get channel members request.
+pub type GetChannelMembers = app_context::Page<app_context::ChannelMember>;
+
+/// A response to a set channel members request. The first page of the
+/// updated members.
+pub type SetChannelMembers = app_context::Page<app_context::ChannelMember>;
+
+/// A response to an add push channels request.
+pub type AddPushChannels = ();
+
+/// A response to a remove push channels request.
+pub type RemovePushChannels = ();
+
+/// A response to a list push channels request. List of channels.
+pub type ListPushChannels = Vec<channel::Name>;
+
+/// A response to a time request, with no region.
+pub type Time = Timetoken;
+
+/// A response to a generate file upload URL request.
+pub type GenerateFileUploadUrl = file::UploadForm;
+
+/// A response to an upload file request.
+pub type UploadFile = ();
+
+/// A response to a publish file message request.
+pub type PublishFileMessage = Timetoken;
+
+/// A response to a list files request.
+pub type ListFiles = file::FilesPage;
+
+/// A response to a get file URL request.
+pub type GetFileUrl = String;
+
+/// A response to a download file request. The contents of the file, a chunk
+/// at a time, or the `TError` the download failed with midway.
+pub type DownloadFile<TError> = BoxStream<'static, Result<Vec<u8>, TError>>;
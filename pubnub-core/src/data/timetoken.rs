@@ -1,21 +1,63 @@
 //! Timetoken type.
 
-use std::time::{SystemTime, SystemTimeError};
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, SystemTimeError};
+
+/// The number of the timetoken ticks in a second, i.e. a tick is 100 ns.
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// The number of nanoseconds in a timetoken tick.
+const NANOS_PER_TICK: u32 = 100;
 
 /// # PubNub Timetoken
 ///
 /// This is the timetoken structure that PubNub uses as a stream index.
 /// It allows clients to resume streaming from where they left off for added
 /// resiliency.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+///
+/// Timetokens are ordered by time first, and by region second.
+///
+/// With the `serde` feature, a timetoken is serialized the way the PubNub
+/// network sends it, i.e. `{"t": "15850559815660696", "r": 12}`, with the
+/// time as a numeric string so it doesn't lose precision in the languages
+/// that read all the JSON numbers as doubles.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Copy)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde_crate::Serialize, serde_crate::Deserialize),
+    serde(crate = "serde_crate")
+)]
+#[non_exhaustive]
 pub struct Timetoken {
     /// Timetoken
+    #[cfg_attr(feature = "serde", serde(with = "numeric_string"))]
     pub t: u64,
     /// Origin region
+    ///
+    /// `0` means there's no region hint, i.e. for the timetokens returned
+    /// from publish.
+    #[cfg_attr(feature = "serde", serde(default))]
     pub r: u32,
 }
 
 impl Timetoken {
+    /// Create a `Timetoken` from its parts, i.e. the ones received from the
+    /// PubNub network.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::timetoken::Timetoken;
+    ///
+    /// let timetoken = Timetoken::from_parts(15_850_559_815_660_696, 12);
+    /// assert_eq!(timetoken.t, 15_850_559_815_660_696);
+    /// assert_eq!(timetoken.r, 12);
+    /// ```
+    #[must_use]
+    pub const fn from_parts(t: u64, r: u32) -> Self {
+        Self { t, r }
+    }
+
     /// Create a `Timetoken`.
     ///
     /// # Arguments
@@ -51,10 +93,80 @@ impl Timetoken {
         let nanos = time.subsec_nanos();
 
         // Format the timetoken with the appropriate resolution
-        let t = (secs * 10_000_000) | (u64::from(nanos) / 100);
+        let t = secs * TICKS_PER_SECOND + u64::from(nanos / NANOS_PER_TICK);
 
         Ok(Self { t, r: region })
     }
+
+    /// Create a `Timetoken` for the `time`, without a region.
+    ///
+    /// The `time` is truncated to the timetoken resolution, i.e. 100 ns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the `time` is before the Unix epoch.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::timetoken::Timetoken;
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let time = SystemTime::UNIX_EPOCH + Duration::from_nanos(1_585_055_981_566_069_600);
+    /// let timetoken = Timetoken::from_system_time(time)?;
+    /// assert_eq!(timetoken.t, 15_850_559_815_660_696);
+    /// assert_eq!(timetoken.to_system_time(), time);
+    /// # Ok::<(), std::time::SystemTimeError>(())
+    /// ```
+    pub fn from_system_time(time: SystemTime) -> Result<Self, SystemTimeError> {
+        Self::new(time, 0)
+    }
+
+    /// The time of the timetoken.
+    ///
+    /// The region doesn't affect the time, the timetokens that only differ
+    /// in it convert to the same time.
+    #[must_use]
+    pub fn to_system_time(&self) -> SystemTime {
+        let secs = self.t / TICKS_PER_SECOND;
+        // The remainder is below the ticks per second, so it always fits.
+        let ticks = u32::try_from(self.t % TICKS_PER_SECOND).unwrap_or_default();
+        SystemTime::UNIX_EPOCH + Duration::new(secs, ticks * NANOS_PER_TICK)
+    }
+
+    /// Create a `Timetoken` for the `time`, without a region.
+    ///
+    /// Same as [`from_system_time`](Self::from_system_time), for the
+    /// [`chrono`] times.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the `time` is before the Unix epoch.
+    #[cfg(feature = "chrono")]
+    pub fn from_date_time<Tz: chrono::TimeZone>(
+        time: &chrono::DateTime<Tz>,
+    ) -> Result<Self, SystemTimeError> {
+        Self::from_system_time(SystemTime::from(time.clone()))
+    }
+
+    /// The time of the timetoken, as a [`chrono`] time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::data::timetoken::Timetoken;
+    ///
+    /// let timetoken = Timetoken::from_parts(15_850_559_815_660_696, 12);
+    /// assert_eq!(
+    ///     timetoken.to_date_time().to_rfc3339(),
+    ///     "2020-03-24T13:19:41.566069600+00:00"
+    /// );
+    /// ```
+    #[cfg(feature = "chrono")]
+    #[must_use]
+    pub fn to_date_time(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from(self.to_system_time())
+    }
 }
 
 impl Default for Timetoken {
@@ -72,3 +184,69 @@ impl std::fmt::Display for Timetoken {
         write!(fmt, "{{ t: {}, r: {} }}", self.t, self.r)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Timetoken;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn system_time_round_trip() {
+        // Past the point where the bitwise-or of the parts still happens to
+        // produce the sum.
+        let time = SystemTime::UNIX_EPOCH + Duration::new(1_585_055_981, 999_999_900);
+        let timetoken = Timetoken::from_system_time(time).unwrap();
+        assert_eq!(timetoken.t, 15_850_559_819_999_999);
+        assert_eq!(timetoken.r, 0);
+        assert_eq!(timetoken.to_system_time(), time);
+
+        // The region doesn't matter.
+        assert_eq!(Timetoken { r: 12, ..timetoken }.to_system_time(), time);
+    }
+
+    #[test]
+    fn system_time_is_truncated_to_ticks() {
+        let time = SystemTime::UNIX_EPOCH + Duration::new(1, 199);
+        let timetoken = Timetoken::from_system_time(time).unwrap();
+        assert_eq!(timetoken.t, 10_000_001);
+        assert_eq!(
+            timetoken.to_system_time(),
+            SystemTime::UNIX_EPOCH + Duration::new(1, 100)
+        );
+    }
+
+    #[test]
+    fn system_time_before_epoch_is_an_error() {
+        let time = SystemTime::UNIX_EPOCH - Duration::from_secs(1);
+        assert!(Timetoken::from_system_time(time).is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn date_time_round_trip() {
+        let timetoken = Timetoken::from_parts(15_850_559_815_660_696, 12);
+        let time = timetoken.to_date_time();
+        assert_eq!(time.timestamp_nanos_opt(), Some(1_585_055_981_566_069_600));
+        assert_eq!(
+            Timetoken::from_date_time(&time).unwrap(),
+            Timetoken::from_parts(15_850_559_815_660_696, 0)
+        );
+    }
+}
+
+/// Serde support for the numbers represented as strings.
+#[cfg(feature = "serde")]
+pub(crate) mod numeric_string {
+    use serde_crate::{de, Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::trivially_copy_pass_by_ref)] // Required by `serde(with)`.
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
@@ -21,16 +21,27 @@
 #![forbid(unsafe_code)]
 
 pub use crate::builder::Builder;
-pub use crate::pubnub::PubNub;
-pub use crate::runtime::Runtime;
-pub use crate::subscription::Subscription;
-pub use crate::transport::{Service as TransportService, Transport};
+pub use crate::pubnub::{Aborted, PresenceOnly, PubNub, RetryPolicy, SendFileError};
+pub use crate::runtime::{JoinError, JoinHandle, Runtime};
+pub use crate::subscription::{
+    DroppedCounter, FilteredSubscription, StatusEvent, Subscription, TrySubscription,
+};
+pub use crate::transport::{
+    AccessManagerTransport, AppContextTransport, Capabilities, Endpoint, Error as TransportError,
+    FilesTransport, HistoryTransport, PresenceTransport, PushTransport,
+    Service as TransportService, Transport, Unsupported,
+};
 pub use json;
 
+#[cfg(feature = "broadcast")]
+pub use crate::subscription::{BroadcastSubscription, Lagged};
+
 pub use async_trait::async_trait;
 
 mod builder;
 pub mod data;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 mod pubnub;
 mod runtime;
 mod subscription;
@@ -0,0 +1,110 @@
+//! Client metrics.
+
+use crate::data::pubsub::PublishMethod;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "metrics-prometheus")]
+mod prometheus;
+
+#[cfg(feature = "metrics-prometheus")]
+pub(crate) use self::prometheus::PrometheusCollector;
+
+/// A snapshot of the client metrics.
+///
+/// Obtained via [`PubNub::metrics`](crate::PubNub::metrics). The counters
+/// are shared by all the clones of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Metrics {
+    /// The number of messages delivered by the subscribe loop.
+    pub messages_received: u64,
+
+    /// The number of messages published successfully.
+    pub messages_published: u64,
+
+    /// The number of the published messages that were sent with `POST`,
+    /// i.e. were too big for the URL.
+    pub messages_published_via_post: u64,
+
+    /// The number of times the subscribe loop recovered after a failed
+    /// request.
+    pub subscribe_reconnects: u64,
+
+    /// The number of subscription streams currently alive.
+    pub active_subscriptions: u64,
+
+    /// The network traffic, as reported by the transport.
+    pub traffic: Traffic,
+}
+
+/// Network traffic counters, reported by the
+/// [`Transport`](crate::Transport).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Traffic {
+    /// The number of bytes sent.
+    pub bytes_sent: u64,
+
+    /// The number of bytes received.
+    pub bytes_received: u64,
+}
+
+impl Traffic {
+    /// Create a `Traffic` snapshot.
+    #[must_use]
+    pub fn new(bytes_sent: u64, bytes_received: u64) -> Self {
+        Self {
+            bytes_sent,
+            bytes_received,
+        }
+    }
+}
+
+/// The live counters, updated by the client and the subscribe loop.
+///
+/// The counters are independent, so the relaxed ordering is enough.
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    messages_received: AtomicU64,
+    messages_published: AtomicU64,
+    messages_published_via_post: AtomicU64,
+    subscribe_reconnects: AtomicU64,
+    active_subscriptions: AtomicU64,
+}
+
+impl Counters {
+    pub fn message_received(&self) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn message_published(&self, method: PublishMethod) {
+        self.messages_published.fetch_add(1, Ordering::Relaxed);
+        if method == PublishMethod::Post {
+            self.messages_published_via_post
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn subscribe_reconnected(&self) {
+        self.subscribe_reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn subscription_opened(&self) {
+        self.active_subscriptions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn subscription_closed(&self) {
+        self.active_subscriptions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self, traffic: Traffic) -> Metrics {
+        Metrics {
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            messages_published: self.messages_published.load(Ordering::Relaxed),
+            messages_published_via_post: self.messages_published_via_post.load(Ordering::Relaxed),
+            subscribe_reconnects: self.subscribe_reconnects.load(Ordering::Relaxed),
+            active_subscriptions: self.active_subscriptions.load(Ordering::Relaxed),
+            traffic,
+        }
+    }
+}
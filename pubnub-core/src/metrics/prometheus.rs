@@ -0,0 +1,128 @@
+//! Prometheus exporter for the client metrics.
+
+use super::Counters;
+use crate::transport::Transport;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{IntCounter, IntGauge, Opts};
+use std::convert::TryFrom;
+use std::sync::{Arc, Mutex};
+
+/// Exposes the client metrics to Prometheus.
+///
+/// The values are taken from the client counters every time the metrics are
+/// gathered.
+pub(crate) struct PrometheusCollector<TTransport> {
+    counters: Arc<Counters>,
+    transport: TTransport,
+
+    messages_received: IntCounter,
+    messages_published: IntCounter,
+    messages_published_via_post: IntCounter,
+    subscribe_reconnects: IntCounter,
+    active_subscriptions: IntGauge,
+    bytes_sent: IntCounter,
+    bytes_received: IntCounter,
+
+    /// Serializes the updates of the Prometheus counters, which can only be
+    /// incremented.
+    update_lock: Mutex<()>,
+}
+
+impl<TTransport: Transport> PrometheusCollector<TTransport> {
+    /// Create a collector, labelling the metrics with the `client` name.
+    pub fn new(
+        counters: Arc<Counters>,
+        transport: TTransport,
+        client: &str,
+    ) -> prometheus::Result<Self> {
+        let opts = |name: &str, help: &str| Opts::new(name, help).const_label("client", client);
+        Ok(Self {
+            counters,
+            transport,
+
+            messages_received: IntCounter::with_opts(opts(
+                "pubnub_messages_received_total",
+                "Messages delivered by the subscribe loop.",
+            ))?,
+            messages_published: IntCounter::with_opts(opts(
+                "pubnub_messages_published_total",
+                "Messages published successfully.",
+            ))?,
+            messages_published_via_post: IntCounter::with_opts(opts(
+                "pubnub_messages_published_via_post_total",
+                "Messages published successfully with POST.",
+            ))?,
+            subscribe_reconnects: IntCounter::with_opts(opts(
+                "pubnub_subscribe_reconnects_total",
+                "Subscribe loop recoveries after a failed request.",
+            ))?,
+            active_subscriptions: IntGauge::with_opts(opts(
+                "pubnub_active_subscriptions",
+                "Subscription streams currently alive.",
+            ))?,
+            bytes_sent: IntCounter::with_opts(opts(
+                "pubnub_sent_bytes_total",
+                "Bytes sent by the transport.",
+            ))?,
+            bytes_received: IntCounter::with_opts(opts(
+                "pubnub_received_bytes_total",
+                "Bytes received by the transport.",
+            ))?,
+
+            update_lock: Mutex::new(()),
+        })
+    }
+
+    fn counters(&self) -> [&IntCounter; 6] {
+        [
+            &self.messages_received,
+            &self.messages_published,
+            &self.messages_published_via_post,
+            &self.subscribe_reconnects,
+            &self.bytes_sent,
+            &self.bytes_received,
+        ]
+    }
+}
+
+impl<TTransport: Transport + 'static> Collector for PrometheusCollector<TTransport> {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut descs: Vec<_> = self.counters().iter().flat_map(|c| c.desc()).collect();
+        descs.extend(self.active_subscriptions.desc());
+        descs
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let metrics = self.counters.snapshot(self.transport.traffic());
+        let values = [
+            metrics.messages_received,
+            metrics.messages_published,
+            metrics.messages_published_via_post,
+            metrics.subscribe_reconnects,
+            metrics.traffic.bytes_sent,
+            metrics.traffic.bytes_received,
+        ];
+
+        let _guard = self
+            .update_lock
+            .lock()
+            .expect("metrics update lock is poisoned");
+        for (counter, &value) in self.counters().iter().zip(values.iter()) {
+            let delta = to_i64(value) - counter.get();
+            if delta > 0 {
+                counter.inc_by(delta);
+            }
+        }
+        self.active_subscriptions
+            .set(to_i64(metrics.active_subscriptions));
+
+        let mut families: Vec<_> = self.counters().iter().flat_map(|c| c.collect()).collect();
+        families.extend(self.active_subscriptions.collect());
+        families
+    }
+}
+
+fn to_i64(value: u64) -> i64 {
+    i64::try_from(value).unwrap_or(std::i64::MAX)
+}
@@ -1,8 +1,9 @@
 //! [`Runtime`] mocks.
 
-use crate::Runtime;
+use crate::{JoinHandle, Runtime};
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
 use mockall::mock;
 
@@ -12,6 +13,8 @@ mock! {
         /// A function to expect to catch a `spawn` call.
         /// Workaround for `async_trait` integration.
         fn mock_workaround_spawn<O: 'static>(&self, future: Pin<Box<dyn Future<Output = O> + Send + 'static>>) {}
+        /// A function to expect to catch a `delay` call.
+        fn mock_delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {}
     }
     trait Clone {
         fn clone(&self) -> Self;
@@ -25,10 +28,16 @@ impl std::fmt::Debug for MockRuntime {
 }
 
 impl Runtime for MockRuntime {
-    fn spawn<F>(&self, future: F)
+    fn spawn<F>(&self, future: F) -> JoinHandle
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        self.mock_workaround_spawn(Box::pin(future))
+        let (future, handle) = JoinHandle::catching(future);
+        self.mock_workaround_spawn(Box::pin(future));
+        handle
+    }
+
+    fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+        self.mock_delay(duration)
     }
 }
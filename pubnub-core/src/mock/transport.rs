@@ -1,19 +1,35 @@
 //! [`Transport`] mocks.
 
-use crate::data::{presence, request, response};
-use crate::{transport::Service, Transport};
+use crate::data::{channel, presence, pubsub, request, response, uuid::UUID};
+use crate::{transport::Service, Capabilities, Endpoint, Transport, TransportError, Unsupported};
 use futures_core::future::BoxFuture;
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 use thiserror::Error;
 
 use mockall::mock;
 
 /// A dummy error used by the [`MockTransport`].
 #[allow(missing_copy_implementations)]
-#[derive(Debug, Error)]
+#[derive(Debug, Default, Error)]
 #[error("mock tranport error")]
-pub struct MockTransportError;
+pub struct MockTransportError {
+    /// Whether the error is reported as fatal.
+    pub fatal: bool,
+    /// The endpoint the error was reported as unsupported for, if any.
+    pub unsupported: Option<Endpoint>,
+    /// Whether the error is reported as rate limited.
+    pub rate_limited: bool,
+    /// Whether the error is reported as transient.
+    pub transient: bool,
+    /// The delay the error asks to retry after, if any.
+    pub retry_after: Option<Duration>,
+    /// The channel name the error was reported as invalid for, if any.
+    pub invalid_name: Option<String>,
+    /// The destinations the error reports the access was denied to.
+    pub denied: Vec<pubsub::SubscribeTo>,
+}
 
 mock! {
     /// Mock Transport.
@@ -25,6 +41,15 @@ mock! {
             &self,
             request: TReq,
         ) -> BoxFuture<'static, Result<TRes, MockTransportError>> {}
+
+        /// Report the capabilities of the transport.
+        fn mock_capabilities(&self) -> Capabilities {}
+
+        /// Set the access token.
+        fn mock_set_auth_token(&self, token: Option<String>) {}
+
+        /// Report the UUID of the client.
+        fn mock_uuid(&self) -> Option<UUID> {}
     }
     trait Clone {
         fn clone(&self) -> Self {}
@@ -90,7 +115,9 @@ impl_mock_service![
 ];
 impl_mock_service![request::WhereNow, response::WhereNow];
 impl_mock_service![request::Heartbeat, response::Heartbeat];
+impl_mock_service![request::Leave, response::Leave];
 impl_mock_service![request::Grant, response::Grant];
+impl_mock_service![request::RevokeToken, response::RevokeToken];
 
 impl_mock_service![request::GetHistory, response::GetHistory];
 impl_mock_service![request::DeleteHistory, response::DeleteHistory];
@@ -103,6 +130,94 @@ impl_mock_service![
     response::MessageCountsWithChannelTimetokens
 ];
 
+impl_mock_service![request::GetUuidMetadata, response::GetUuidMetadata];
+impl_mock_service![request::SetUuidMetadata, response::SetUuidMetadata];
+impl_mock_service![request::RemoveUuidMetadata, response::RemoveUuidMetadata];
+impl_mock_service![request::GetChannelMetadata, response::GetChannelMetadata];
+impl_mock_service![
+    request::GetAllChannelMetadata,
+    response::GetAllChannelMetadata
+];
+impl_mock_service![request::SetChannelMetadata, response::SetChannelMetadata];
+impl_mock_service![
+    request::RemoveChannelMetadata,
+    response::RemoveChannelMetadata
+];
+impl_mock_service![request::GetMemberships, response::GetMemberships];
+impl_mock_service![request::SetMemberships, response::SetMemberships];
+impl_mock_service![request::GetChannelMembers, response::GetChannelMembers];
+impl_mock_service![request::SetChannelMembers, response::SetChannelMembers];
+
+impl_mock_service![request::AddPushChannels, response::AddPushChannels];
+impl_mock_service![request::RemovePushChannels, response::RemovePushChannels];
+impl_mock_service![request::ListPushChannels, response::ListPushChannels];
+
+impl_mock_service![request::Time, response::Time];
+
+impl_mock_service![
+    request::GenerateFileUploadUrl,
+    response::GenerateFileUploadUrl
+];
+impl_mock_service![request::UploadFile, response::UploadFile];
+impl_mock_service![request::PublishFileMessage, response::PublishFileMessage];
+impl_mock_service![request::ListFiles, response::ListFiles];
+impl_mock_service![request::GetFileUrl, response::GetFileUrl];
+impl_mock_service![
+    request::DownloadFile,
+    response::DownloadFile<MockTransportError>
+];
+
+impl TransportError for MockTransportError {
+    fn is_fatal(&self) -> bool {
+        self.fatal
+    }
+
+    fn denied_destinations(&self) -> Vec<pubsub::SubscribeTo> {
+        self.denied.clone()
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        self.rate_limited
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    fn is_transient(&self) -> bool {
+        self.transient
+    }
+}
+
+impl From<Unsupported> for MockTransportError {
+    fn from(Unsupported(endpoint): Unsupported) -> Self {
+        Self {
+            unsupported: Some(endpoint),
+            ..Self::default()
+        }
+    }
+}
+
 impl Transport for MockTransport {
     type Error = MockTransportError;
+
+    fn capabilities(&self) -> Capabilities {
+        self.mock_capabilities()
+    }
+
+    fn set_auth_token(&self, token: Option<String>) {
+        self.mock_set_auth_token(token);
+    }
+
+    fn uuid(&self) -> Option<UUID> {
+        self.mock_uuid()
+    }
+
+    /// Checks the names for real, with [`Name::check`](channel::Name::check).
+    fn check_name(&self, name: &channel::Name, wildcard: bool) -> Result<(), MockTransportError> {
+        name.check(wildcard).map_err(|_| MockTransportError {
+            invalid_name: Some(name.to_string()),
+            ..MockTransportError::default()
+        })
+    }
 }
@@ -0,0 +1,313 @@
+use super::PubNub;
+use crate::data::app_context::{
+    ChannelMember, ChannelMembership, ChannelMetadata, MembershipChanges, MembershipInclude, Page,
+    PageCursor, UuidMetadata,
+};
+use crate::data::channel;
+use crate::data::request;
+use crate::data::uuid::UUID;
+use crate::runtime::Runtime;
+use crate::transport::{AppContextTransport, Endpoint, Transport};
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: AppContextTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Get the metadata of a UUID.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn get_uuid_metadata(
+        &self,
+        uuid: UUID,
+    ) -> Result<UuidMetadata, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport.call(request::GetUuidMetadata { uuid }).await
+    }
+
+    /// Set the metadata of a UUID.
+    ///
+    /// Only the fields that are set are updated. The `custom` data, if set,
+    /// replaces the stored one as a whole.
+    ///
+    /// If the [`etag`](UuidMetadata::etag) of the `metadata` is set, the
+    /// update is only applied if the stored metadata wasn't modified since it
+    /// was fetched, otherwise the transport reports an error. Clear the
+    /// `etag` to update the metadata unconditionally.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let mut metadata = pubnub.get_uuid_metadata("my-uuid".into()).await?;
+    /// metadata.custom = Some(object! {
+    ///     "visits" => 10,
+    /// });
+    /// let metadata = pubnub.set_uuid_metadata(metadata).await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn set_uuid_metadata(
+        &self,
+        metadata: UuidMetadata,
+    ) -> Result<UuidMetadata, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        let if_match = metadata.etag.clone();
+        self.transport
+            .call(request::SetUuidMetadata { metadata, if_match })
+            .await
+    }
+
+    /// Remove the metadata of a UUID.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn remove_uuid_metadata(
+        &self,
+        uuid: UUID,
+    ) -> Result<(), <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::RemoveUuidMetadata { uuid })
+            .await
+    }
+
+    /// Get the metadata of a channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn get_channel_metadata(
+        &self,
+        channel: channel::Name,
+    ) -> Result<ChannelMetadata, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::GetChannelMetadata { channel })
+            .await
+    }
+
+    /// Get the metadata of all the channels, one page at a time.
+    ///
+    /// Pass `None` as the `page` to get the first page, and the cursors
+    /// from the returned [`Page`] to get the following ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let mut page = None;
+    /// loop {
+    ///     let result = pubnub.get_all_channel_metadata(Some(100), page).await?;
+    ///     for metadata in result.items {
+    ///         println!("{}: {:?}", metadata.id, metadata.name);
+    ///     }
+    ///     match result.next {
+    ///         Some(next) => page = Some(next),
+    ///         None => break,
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn get_all_channel_metadata(
+        &self,
+        limit: Option<usize>,
+        page: Option<PageCursor>,
+    ) -> Result<Page<ChannelMetadata>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::GetAllChannelMetadata {
+                limit,
+                page,
+                include_total_count: true,
+            })
+            .await
+    }
+
+    /// Set the metadata of a channel.
+    ///
+    /// Works the same way as [`PubNub::set_uuid_metadata`], including the
+    /// handling of the [`etag`](ChannelMetadata::etag).
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn set_channel_metadata(
+        &self,
+        metadata: ChannelMetadata,
+    ) -> Result<ChannelMetadata, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        let if_match = metadata.etag.clone();
+        self.transport
+            .call(request::SetChannelMetadata { metadata, if_match })
+            .await
+    }
+
+    /// Remove the metadata of a channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn remove_channel_metadata(
+        &self,
+        channel: channel::Name,
+    ) -> Result<(), <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::RemoveChannelMetadata { channel })
+            .await
+    }
+
+    /// Get the channel memberships of a UUID, one page at a time.
+    ///
+    /// The custom data of the memberships and the channel metadata are
+    /// included. Use [`PubNub::call`] with a [`request::GetMemberships`] for
+    /// a finer control.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn get_memberships(
+        &self,
+        uuid: UUID,
+        page: Option<PageCursor>,
+    ) -> Result<Page<ChannelMembership>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::GetMemberships {
+                uuid,
+                include: MembershipInclude::all(),
+                limit: None,
+                page,
+                include_total_count: true,
+            })
+            .await
+    }
+
+    /// Change the channel memberships of a UUID.
+    ///
+    /// The memberships to set and to remove are applied in a single
+    /// request, the rest of the memberships are left intact. Setting adds
+    /// the new memberships and updates the existing ones alike, see
+    /// [`MembershipChanges`].
+    ///
+    /// Returns the first page of the updated memberships.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::data::app_context::{MembershipChanges, MembershipEntry};
+    /// use pubnub_core::{json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let changes = MembershipChanges {
+    ///     set: vec![MembershipEntry {
+    ///         id: "my-channel".parse().unwrap(),
+    ///         custom: Some(object! {
+    ///             "role" => "admin",
+    ///         }),
+    ///     }],
+    ///     remove: vec!["my-old-channel".parse().unwrap()],
+    /// };
+    /// let memberships = pubnub.set_memberships("my-uuid".into(), changes).await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn set_memberships(
+        &self,
+        uuid: UUID,
+        changes: MembershipChanges<channel::Name>,
+    ) -> Result<Page<ChannelMembership>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::SetMemberships {
+                uuid,
+                changes,
+                include: MembershipInclude::all(),
+            })
+            .await
+    }
+
+    /// Get the members of a channel, one page at a time.
+    ///
+    /// The custom data of the memberships and the UUID metadata are
+    /// included. Use [`PubNub::call`] with a [`request::GetChannelMembers`]
+    /// for a finer control.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn get_channel_members(
+        &self,
+        channel: channel::Name,
+        page: Option<PageCursor>,
+    ) -> Result<Page<ChannelMember>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::GetChannelMembers {
+                channel,
+                include: MembershipInclude::all(),
+                limit: None,
+                page,
+                include_total_count: true,
+            })
+            .await
+    }
+
+    /// Change the members of a channel.
+    ///
+    /// Works the same way as [`PubNub::set_memberships`].
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn set_channel_members(
+        &self,
+        channel: channel::Name,
+        changes: MembershipChanges<UUID>,
+    ) -> Result<Page<ChannelMember>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AppContext)?;
+        self.transport
+            .call(request::SetChannelMembers {
+                channel,
+                changes,
+                include: MembershipInclude::all(),
+            })
+            .await
+    }
+}
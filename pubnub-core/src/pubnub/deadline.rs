@@ -0,0 +1,115 @@
+use super::PubNub;
+use crate::runtime::Runtime;
+use crate::transport::Transport;
+use futures_util::future::{select, Either};
+use std::future::Future;
+use std::time::Duration;
+use thiserror::Error;
+
+/// The call was aborted before it completed, see
+/// [`PubNub::with_timeout`] and [`PubNub::with_cancellation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum Aborted {
+    /// The call didn't complete in time.
+    #[error("Deadline exceeded")]
+    DeadlineExceeded,
+    /// The call was cancelled.
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: Transport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Run the `call`, i.e. a [`publish`](Self::publish) or a
+    /// [`call`](Self::call), aborting it if it doesn't complete within the
+    /// `timeout`.
+    ///
+    /// The call is aborted by dropping it, and the transports abort the
+    /// requests in flight when dropped, so the connection isn't left behind
+    /// waiting for the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Aborted::DeadlineExceeded`] if the `timeout` elapses first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{json::object, Builder};
+    /// use std::time::Duration;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let publish = pubnub.publish("my-channel".parse().unwrap(), object! { "text" => "Hi" });
+    /// let timetoken = pubnub.with_timeout(Duration::from_secs(5), publish).await??;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn with_timeout<F>(&self, timeout: Duration, call: F) -> Result<F::Output, Aborted>
+    where
+        F: Future,
+    {
+        race(call, self.runtime.delay(timeout), Aborted::DeadlineExceeded).await
+    }
+
+    /// Run the `call`, aborting it once the `cancel` future completes, i.e.
+    /// when a [`oneshot`](futures_channel::oneshot) cancellation signal is
+    /// sent or dropped.
+    ///
+    /// The call is aborted the same way as with the
+    /// [`with_timeout`](Self::with_timeout).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Aborted::Cancelled`] if the `cancel` future completes first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_channel::oneshot;
+    /// use pubnub_core::{json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    /// let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+    ///
+    /// // Call `cancel_tx.send(())` elsewhere to abort.
+    /// let publish = pubnub.publish("my-channel".parse().unwrap(), object! { "text" => "Hi" });
+    /// let timetoken = pubnub.with_cancellation(publish, cancel_rx).await??;
+    /// # drop(cancel_tx);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn with_cancellation<F, C>(&self, call: F, cancel: C) -> Result<F::Output, Aborted>
+    where
+        F: Future,
+        C: Future,
+    {
+        race(call, cancel, Aborted::Cancelled).await
+    }
+}
+
+/// Run the `call` until the `abort` future completes, dropping the call
+/// then.
+async fn race<F, A>(call: F, abort: A, aborted: Aborted) -> Result<F::Output, Aborted>
+where
+    F: Future,
+    A: Future,
+{
+    futures_util::pin_mut!(call);
+    futures_util::pin_mut!(abort);
+    match select(call, abort).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(aborted),
+    }
+}
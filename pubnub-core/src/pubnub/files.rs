@@ -0,0 +1,306 @@
+use super::PubNub;
+use crate::data::channel;
+use crate::data::file::{File, FileInfo, SentFile};
+use crate::data::object::Object;
+use crate::data::request;
+use crate::data::timetoken::Timetoken;
+use crate::runtime::Runtime;
+use crate::transport::{Endpoint, FilesTransport, Transport};
+use futures_util::stream::{Stream, StreamExt};
+use thiserror::Error;
+
+/// The error of [`PubNub::send_file`], telling the stage that failed.
+#[derive(Debug, Error)]
+pub enum SendFileError<E: std::error::Error + 'static> {
+    /// Requesting the form to upload the file with failed. Nothing was
+    /// uploaded.
+    #[error("Generating the file upload URL failed")]
+    GenerateUploadUrl(#[source] E),
+
+    /// Uploading the file failed.
+    #[error("Uploading the file failed")]
+    Upload(#[source] E),
+
+    /// The file was uploaded, but publishing the file message failed.
+    ///
+    /// Retry with [`PubNub::publish_file_message`], rather than uploading
+    /// the file again.
+    #[error("Publishing the file message failed")]
+    Publish {
+        /// The uploaded file.
+        file: File,
+        /// The error of the publish.
+        #[source]
+        source: E,
+    },
+}
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: FilesTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Share a file on the channel with the PubNub File Sharing.
+    ///
+    /// Uploads the `data` as a file named `file_name`, then publishes a
+    /// file message referencing it, along with the optional `message`. The
+    /// subscribers receive the file message, and download the file from
+    /// the PubNub network.
+    ///
+    /// The file and the message are sent as is, even with a cipher key
+    /// configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`SendFileError`] of the stage that failed, wrapping the
+    /// transport-specific error. The file is kept when only the publish of
+    /// the file message fails, so it can be retried.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let sent = pubnub
+    ///     .send_file(
+    ///         "my-channel".parse().unwrap(),
+    ///         "cat.jpg".to_owned(),
+    ///         std::fs::read("cat.jpg")?,
+    ///         Some(object! { "caption" => "My cat" }),
+    ///     )
+    ///     .await?;
+    /// println!("Sent file {} at {}", sent.file.id, sent.timetoken);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn send_file(
+        &self,
+        channel: channel::Name,
+        file_name: String,
+        data: Vec<u8>,
+        message: Option<Object>,
+    ) -> Result<SentFile, SendFileError<<TTransport as Transport>::Error>> {
+        self.ensure_supported(Endpoint::Files)
+            .map_err(SendFileError::GenerateUploadUrl)?;
+
+        let form = self
+            .transport
+            .call(request::GenerateFileUploadUrl {
+                channel: channel.clone(),
+                file_name,
+            })
+            .await
+            .map_err(SendFileError::GenerateUploadUrl)?;
+        let file = form.file.clone();
+
+        self.transport
+            .call(request::UploadFile { form, data })
+            .await
+            .map_err(SendFileError::Upload)?;
+
+        match self
+            .publish_file_message(channel, file.clone(), message)
+            .await
+        {
+            Ok(timetoken) => Ok(SentFile { file, timetoken }),
+            Err(source) => Err(SendFileError::Publish { file, source }),
+        }
+    }
+
+    /// Publish the message announcing an uploaded `file` on the channel,
+    /// i.e. to retry after [`SendFileError::Publish`].
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn publish_file_message(
+        &self,
+        channel: channel::Name,
+        file: File,
+        message: Option<Object>,
+    ) -> Result<Timetoken, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Files)?;
+        self.transport
+            .call(request::PublishFileMessage {
+                channel,
+                file,
+                message,
+            })
+            .await
+    }
+
+    /// List all the files stored on the channel.
+    ///
+    /// Reads the list a page at a time, until the last one.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let channel = "my-channel".parse().unwrap();
+    /// for file in pubnub.list_files(&channel).await? {
+    ///     println!("{} ({} bytes)", file.name, file.size);
+    ///     let data = pubnub
+    ///         .download_file(channel.clone(), file.id, file.name.clone())
+    ///         .await?;
+    ///     std::fs::write(file.name, data)?;
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn list_files(
+        &self,
+        channel: &channel::Name,
+    ) -> Result<Vec<FileInfo>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Files)?;
+        let mut files = Vec::new();
+        let mut next = None;
+        loop {
+            let page = self
+                .transport
+                .call(request::ListFiles {
+                    channel: channel.clone(),
+                    next,
+                })
+                .await?;
+            // An empty page ends the list too, in case the token doesn't.
+            let empty = page.files.is_empty();
+            files.extend(page.files);
+            next = page.next;
+            if next.is_none() || empty {
+                return Ok(files);
+            }
+        }
+    }
+
+    /// The URL to download the file with the `file_id` and the `file_name`
+    /// from, i.e. to hand over to another HTTP client.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn get_file_url(
+        &self,
+        channel: channel::Name,
+        file_id: String,
+        file_name: String,
+    ) -> Result<String, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Files)?;
+        self.transport
+            .call(request::GetFileUrl {
+                channel,
+                file: File {
+                    id: file_id,
+                    name: file_name,
+                },
+            })
+            .await
+    }
+
+    /// Download the file with the `file_id` and the `file_name`.
+    ///
+    /// The file is returned whole, so it's held in memory at once. Use
+    /// [`download_file_stream`](Self::download_file_stream) to process the
+    /// big files a chunk at a time instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors. The transports may limit the
+    /// size of the file, i.e. the `Hyper` transport fails as soon as it
+    /// receives more than its response size limit.
+    pub async fn download_file(
+        &self,
+        channel: channel::Name,
+        file_id: String,
+        file_name: String,
+    ) -> Result<Vec<u8>, <TTransport as Transport>::Error> {
+        let stream = self
+            .download_file_stream(channel, file_id, file_name)
+            .await?;
+        futures_util::pin_mut!(stream);
+
+        let mut bytes = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend(chunk?);
+        }
+        Ok(bytes)
+    }
+
+    /// Download the file with the `file_id` and the `file_name`, a chunk at
+    /// a time.
+    ///
+    /// The chunks are yielded as they're received, so only the chunk being
+    /// processed is held in memory. A failure midway is yielded as an
+    /// error, and ends the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors. The transports may limit the
+    /// size of the file, i.e. the stream of the `Hyper` transport fails as
+    /// soon as it receives more than its response size limit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let stream = pubnub
+    ///     .download_file_stream(
+    ///         "my-channel".parse().unwrap(),
+    ///         "0-1".to_owned(),
+    ///         "cat.jpg".to_owned(),
+    ///     )
+    ///     .await?;
+    /// futures_util::pin_mut!(stream);
+    ///
+    /// while let Some(chunk) = stream.next().await {
+    ///     println!("Received {} bytes", chunk?.len());
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn download_file_stream(
+        &self,
+        channel: channel::Name,
+        file_id: String,
+        file_name: String,
+    ) -> Result<
+        impl Stream<Item = Result<Vec<u8>, <TTransport as Transport>::Error>>,
+        <TTransport as Transport>::Error,
+    > {
+        self.ensure_supported(Endpoint::Files)?;
+        self.transport
+            .call(request::DownloadFile {
+                channel,
+                file: File {
+                    id: file_id,
+                    name: file_name,
+                },
+            })
+            .await
+    }
+}
@@ -0,0 +1,160 @@
+use super::PubNub;
+use crate::data::message::{self, Message};
+use crate::data::timetoken::Timetoken;
+use crate::data::{channel, history, request};
+use crate::runtime::Runtime;
+use crate::transport::{Endpoint, HistoryTransport, Transport};
+use futures_util::stream::{self, Stream};
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+#[cfg(feature = "cipher")]
+use crate::subscription::subscribe_loop::decrypt_message;
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: HistoryTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Read the whole history of the `channel`, newest first.
+    ///
+    /// The history is returned by the PubNub network in pages of up to
+    /// [`MAX_PAGE_SIZE`](history::MAX_PAGE_SIZE) messages. The stream reads
+    /// them one after another going back in time, each page starting before
+    /// the oldest message of the previous one, and yields the messages of
+    /// all of them. The pages are only read as the stream is polled.
+    ///
+    /// The stream ends after a page with fewer messages than requested.
+    /// The messages of a page that aren't older than the previous page are
+    /// skipped, so a misbehaving server can't make it loop forever. A
+    /// failed request is yielded as an error, and ends the stream.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::{data::history::IterOptions, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let stream = pubnub.history_iter("my-channel".parse().unwrap(), IterOptions::default());
+    /// futures_util::pin_mut!(stream);
+    ///
+    /// while let Some(message) = stream.next().await {
+    ///     println!("Stored message: {:?}", message?);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub fn history_iter(
+        &self,
+        channel: channel::Name,
+        options: history::IterOptions,
+    ) -> impl Stream<Item = Result<Message, <TTransport as Transport>::Error>> {
+        let count = options.count.max(1).min(history::MAX_PAGE_SIZE);
+        let state = HistoryState {
+            pubnub: self.clone(),
+            channel,
+            options,
+            count,
+            page: VecDeque::new(),
+            start: options.start,
+            done: false,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(message) = state.page.pop_front() {
+                    return Some((Ok(message), state));
+                }
+                if state.done {
+                    return None;
+                }
+
+                let mut items = match state.next_page().await {
+                    Ok(items) => items,
+                    Err(err) => {
+                        state.done = true;
+                        return Some((Err(err), state));
+                    }
+                };
+
+                // Guard against a server not moving back in time, which would
+                // loop forever otherwise.
+                if let Some(start) = state.start {
+                    items.retain(|item| item.timetoken < start);
+                }
+                items.sort_by_key(|item| Reverse(item.timetoken));
+                state.done = items.len() < state.count;
+                state.start = items.last().map(|item| item.timetoken);
+
+                let pubnub = &state.pubnub;
+                let channel = &state.channel;
+                let messages = items
+                    .into_iter()
+                    .map(|item| pubnub.history_message(channel, item))
+                    .collect::<Vec<_>>();
+                state.page.extend(messages);
+            }
+        })
+    }
+
+    /// Turn the history `item` of the `channel` into a message.
+    pub(super) fn history_message(&self, channel: &channel::Name, item: history::Item) -> Message {
+        let message = Message {
+            message_type: message::Type::Publish,
+            channel: channel.clone(),
+            json: item.message,
+            metadata: item.metadata,
+            timetoken: Timetoken {
+                t: item.timetoken,
+                r: 0,
+            },
+            ..Message::default()
+        };
+        #[cfg(feature = "cipher")]
+        let message = match self.cipher {
+            Some(ref cipher) => decrypt_message(cipher, message),
+            None => message,
+        };
+        message
+    }
+}
+
+/// The state of a [`PubNub::history_iter`] stream.
+struct HistoryState<TTransport: Transport, TRuntime: Runtime> {
+    pubnub: PubNub<TTransport, TRuntime>,
+    channel: channel::Name,
+    options: history::IterOptions,
+    /// The page size.
+    count: usize,
+    /// The messages of the current page yet to be yielded.
+    page: VecDeque<Message>,
+    /// The timetoken to read the next page before.
+    start: Option<history::Timetoken>,
+    done: bool,
+}
+
+impl<TTransport, TRuntime> HistoryState<TTransport, TRuntime>
+where
+    TTransport: HistoryTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    async fn next_page(&self) -> Result<Vec<history::Item>, <TTransport as Transport>::Error> {
+        self.pubnub.ensure_supported(Endpoint::History)?;
+        let request = request::GetHistory {
+            channels: vec![self.channel.clone()],
+            max: Some(self.count),
+            reverse: None,
+            start: self.start,
+            end: self.options.end,
+            include_metadata: Some(self.options.include_metadata),
+        };
+        let mut response = self.pubnub.transport.call(request).await?;
+        Ok(response.remove(&self.channel).unwrap_or_default())
+    }
+}
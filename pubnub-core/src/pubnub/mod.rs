@@ -1,20 +1,60 @@
+use crate::data::pubsub;
 use crate::runtime::Runtime;
+use crate::subscription::cursors::CurrentCursors;
+use crate::subscription::status::StatusBroadcaster;
 use crate::subscription::subscribe_loop_supervisor::SubscribeLoopSupervisor;
-use crate::transport::{Service, Transport};
+use crate::transport::{Endpoint, Service, Transport, Unsupported};
 use futures_util::lock::Mutex;
 use std::sync::Arc;
 
+#[cfg(feature = "broadcast")]
+use crate::subscription::broadcast::Broadcasts;
+#[cfg(feature = "cipher")]
+use pubnub_util::cipher::Cipher;
+
+#[cfg(feature = "metrics-prometheus")]
+use crate::metrics::PrometheusCollector;
+#[cfg(feature = "metrics")]
+use crate::metrics::{Counters, Metrics};
+
+mod app_context;
+mod deadline;
+mod files;
+mod history;
+mod pam;
 mod presence;
 mod publish;
+mod push;
+mod rate_limit;
+mod retry;
 mod subscribe;
+mod time;
 
 #[cfg(test)]
 mod tests;
 
+pub use deadline::Aborted;
+pub use files::SendFileError;
+pub use presence::PresenceOnly;
+pub use retry::RetryPolicy;
+
+pub(crate) use rate_limit::RateLimiter;
+
 /// # PubNub Client
 ///
 /// The PubNub lib implements socket pools to relay data requests as a client
 /// connection to the PubNub Network.
+///
+/// # Cloning
+///
+/// Cloning the client is cheap, and the clones share the subscribe loop:
+/// the subscriptions made with any of the clones are served by a single
+/// loop, over a single connection. The loop is kept alive by the
+/// subscriptions rather than by the clients, so dropping a clone doesn't
+/// end the subscriptions made with it, nor those of the other clones.
+///
+/// The clones share the metrics, the status events and
+/// the [publish rate limit](crate::Builder::publish_rate_limit) too.
 #[derive(Clone, Debug)]
 pub struct PubNub<TTransport, TRuntime>
 where
@@ -28,6 +68,33 @@ where
 
     /// Subscribe loop lifecycle management.
     pub(crate) subscribe_loop_supervisor: Arc<Mutex<SubscribeLoopSupervisor>>,
+
+    /// Delivers the subscribe loop status events.
+    pub(crate) status_broadcaster: StatusBroadcaster,
+
+    /// The latest cursors of the subscribe loops.
+    pub(crate) current_cursors: CurrentCursors,
+
+    /// The channels subscribed to with the shared streams.
+    #[cfg(feature = "broadcast")]
+    pub(crate) broadcasts: Broadcasts,
+
+    /// The size of the messages above which they're published with `POST`.
+    pub(crate) publish_post_threshold: usize,
+
+    /// Paces the publishes, if the rate limit is set.
+    pub(crate) publish_rate_limiter: Option<Arc<RateLimiter>>,
+
+    /// How the failed publishes are retried.
+    pub(crate) publish_retry: RetryPolicy,
+
+    /// Cipher to encrypt the published payloads with.
+    #[cfg(feature = "cipher")]
+    pub(crate) cipher: Option<Cipher>,
+
+    /// The metrics counters.
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Arc<Counters>,
 }
 
 impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
@@ -44,6 +111,51 @@ where
     pub fn runtime(&self) -> &TRuntime {
         &self.runtime
     }
+
+    /// Get a snapshot of the client metrics.
+    ///
+    /// The counters are maintained with relaxed atomics, so the snapshot
+    /// isn't guaranteed to be consistent across the individual counters.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.snapshot(self.transport.traffic())
+    }
+
+    /// Register the client metrics with a Prometheus `registry`, i.e. the
+    /// [`prometheus::default_registry`].
+    ///
+    /// The metrics are labelled with the `client` name, so that multiple
+    /// clients can be registered with the same registry. The values are
+    /// taken from the client every time the registry is gathered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the metrics of a client with the same name are
+    /// already registered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let mut transport = MockTransport::new();
+    /// # transport.expect_clone().returning(MockTransport::new);
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    /// pubnub.register_prometheus(prometheus::default_registry(), "main")?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn register_prometheus(
+        &self,
+        registry: &prometheus::Registry,
+        client: &str,
+    ) -> prometheus::Result<()> {
+        let collector =
+            PrometheusCollector::new(Arc::clone(&self.metrics), self.transport.clone(), client)?;
+        registry.register(Box::new(collector))
+    }
 }
 
 impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
@@ -51,8 +163,38 @@ where
     TTransport: Transport + 'static,
     TRuntime: Runtime + 'static,
 {
+    /// Fail with the [`Unsupported`] error if the transport doesn't support
+    /// the `endpoint`.
+    pub(crate) fn ensure_supported(
+        &self,
+        endpoint: Endpoint,
+    ) -> Result<(), <TTransport as Transport>::Error> {
+        if self.transport.capabilities().contains(endpoint.into()) {
+            Ok(())
+        } else {
+            Err(Unsupported(endpoint).into())
+        }
+    }
+
+    /// Check the names of the `to` destination with the transport, see
+    /// [`Transport::check_name`].
+    pub(crate) fn check_destination(
+        &self,
+        to: &pubsub::SubscribeTo,
+    ) -> Result<(), <TTransport as Transport>::Error> {
+        match to {
+            pubsub::SubscribeTo::Channel(name) => self.transport.check_name(name, true),
+            pubsub::SubscribeTo::ChannelGroup(name) => self.transport.check_name(name, false),
+            // Checked by the type already.
+            pubsub::SubscribeTo::ChannelWildcard(_) => Ok(()),
+        }
+    }
+
     /// Perform a transport call.
     ///
+    /// The call is passed to the transport as is, without checking its
+    /// [`capabilities`](Transport::capabilities).
+    ///
     /// # Errors
     ///
     /// Returns transport-specific errors.
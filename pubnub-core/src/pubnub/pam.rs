@@ -0,0 +1,125 @@
+use super::PubNub;
+use crate::data::pam::{ParseTokenError, TokenContents};
+use crate::data::request;
+use crate::runtime::Runtime;
+use crate::transport::{AccessManagerTransport, Endpoint, Transport};
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: AccessManagerTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Grant a PAMv3 access token with the permissions of the `request`.
+    ///
+    /// Requires the secret key, so only use this on the servers. Hand the
+    /// returned token to the clients, to use as their auth key.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::data::{pam, request};
+    /// use pubnub_core::json::object;
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let mut resources = pam::Resources::default();
+    /// resources.channels.insert(
+    ///     "my-channel".to_owned(),
+    ///     pam::BitMask::READ | pam::BitMask::WRITE,
+    /// );
+    /// let mut patterns = pam::Patterns::default();
+    /// patterns.uuids.insert("^user-.*$".to_owned(), pam::BitMask::GET);
+    ///
+    /// let token = pubnub
+    ///     .grant_token(request::Grant {
+    ///         ttl: 60,
+    ///         permissions: pam::Permissions {
+    ///             resources,
+    ///             patterns,
+    ///             meta: object! {},
+    ///         },
+    ///     })
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn grant_token(
+        &self,
+        request: request::Grant,
+    ) -> Result<String, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AccessManager)?;
+        self.transport.call(request).await
+    }
+
+    /// Revoke a PAMv3 access token granted with
+    /// [`grant_token`](Self::grant_token).
+    ///
+    /// Requires the secret key, like the grant. The revoked token is
+    /// rejected from then on, though it may take a while to propagate.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn revoke_token(&self, token: &str) -> Result<(), <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::AccessManager)?;
+        self.transport
+            .call(request::RevokeToken {
+                token: token.to_owned(),
+            })
+            .await
+    }
+}
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: Transport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Attach the PAMv3 access `token` to all the subsequent requests, as
+    /// the `auth` parameter.
+    ///
+    /// Applies to the running subscriptions too, from their next request
+    /// on. Takes precedence over the auth key the transport is configured
+    /// with. The requests made with an expired or a revoked token are
+    /// denied by the PubNub network, i.e. with the
+    /// `Error::AccessDenied` of the `hyper` transport, so set a fresh one
+    /// before the current one expires, see [`parse_token`](Self::parse_token).
+    ///
+    /// The transports that don't support the tokens drop it with a warning,
+    /// see [`Transport::set_auth_token`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let mut transport = MockTransport::new();
+    /// # transport.expect_mock_set_auth_token().return_const(());
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// pubnub.set_token("my-token");
+    /// ```
+    pub fn set_token(&mut self, token: &str) {
+        self.transport.set_auth_token(Some(token.to_owned()));
+    }
+
+    /// Parse a PAMv3 access `token`, i.e. to check its permissions or when
+    /// it expires, see [`TokenContents::parse`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token is malformed.
+    pub fn parse_token(&self, token: &str) -> Result<TokenContents, ParseTokenError> {
+        TokenContents::parse(token)
+    }
+}
@@ -1,8 +1,24 @@
 use super::PubNub;
-use crate::data::channel;
+use crate::data::object::Object;
+use crate::data::presence::{respond_with, HeartbeatValue};
+use crate::data::uuid::UUID;
+use crate::data::{channel, message, pubsub, request, response};
 use crate::runtime::Runtime;
-use crate::subscription::Subscription;
-use crate::transport::Transport;
+use crate::subscription::{FilteredSubscription, Subscription};
+use crate::transport::{Endpoint, PresenceTransport, Service, Transport};
+use futures_channel::oneshot;
+use futures_util::future::{select, Either};
+use log::error;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The presence timeout announced with the heartbeats, in seconds.
+const PRESENCE_TIMEOUT: HeartbeatValue = 300;
+
+/// The time between the heartbeats, comfortably within the presence timeout
+/// so a single late heartbeat doesn't time the client out.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(PRESENCE_TIMEOUT as u64 / 2 - 1);
 
 impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
 where
@@ -13,11 +29,231 @@ where
     ///
     /// This is just a tiny wrapper that calls [`PubNub::subscribe`]
     /// internally with the specified channel name with a `-pnpres` suffix.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`PubNub::subscribe`].
     pub async fn subscribe_to_presence(
         &mut self,
         channel: channel::Name,
-    ) -> Subscription<TRuntime> {
+    ) -> Result<Subscription<TRuntime>, <TTransport as Transport>::Error> {
         let channel = channel::Name::from_string_unchecked(format!("{}-pnpres", channel));
         self.subscribe(channel).await
     }
+
+    /// Subscribe to the presence events of the specified channel, without
+    /// its data messages.
+    ///
+    /// Works like [`subscribe_to_presence`](Self::subscribe_to_presence),
+    /// only yielding the [presence events](message::Type::Presence). The data
+    /// traffic of the channel is never received for it, only the `-pnpres`
+    /// channel is subscribed to.
+    ///
+    /// Composes with [`subscribe`](Self::subscribe) to the same channel: the
+    /// two share the single subscribe loop, the data subscription yields the
+    /// data messages, and this one the presence events.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`PubNub::subscribe`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let mut presence = pubnub
+    ///     .subscribe_presence_only("my-channel".parse().unwrap())
+    ///     .await?;
+    ///
+    /// while let Some(event) = presence.next().await {
+    ///     println!("Presence event: {}", event.json);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn subscribe_presence_only(
+        &mut self,
+        channel: channel::Name,
+    ) -> Result<FilteredSubscription<TRuntime>, <TTransport as Transport>::Error> {
+        let subscription = self.subscribe_to_presence(channel).await?;
+        Ok(subscription.only(message::Type::Presence))
+    }
+}
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: PresenceTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Announce the presence on the `channels` with the heartbeats, without
+    /// subscribing to them.
+    ///
+    /// For the clients that are only there to be seen, i.e. the backends
+    /// tracking the presence, that never read the messages. No subscribe
+    /// long-poll is opened, a background task just sends a heartbeat right
+    /// away and then every couple of minutes, well within the presence
+    /// timeout. The client counts towards the occupancy of the channels, as
+    /// reported by `HereNow`, under the [`uuid`](Transport::uuid) of the
+    /// transport, or a random one if the transport doesn't report any.
+    ///
+    /// The heartbeats go on until the returned [`PresenceOnly`] is dropped,
+    /// and then the client leaves the channels, except the ones it's still
+    /// subscribed to with [`subscribe`](Self::subscribe).
+    ///
+    /// The failed heartbeats are logged, and retried with the next one.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the [`Unsupported`](crate::Unsupported) error if the
+    /// transport doesn't support the presence.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channels = vec!["lobby".parse().unwrap(), "room-1".parse().unwrap()];
+    /// let presence = pubnub.presence_only(channels)?;
+    ///
+    /// // ...
+    ///
+    /// // Leave the channels.
+    /// drop(presence);
+    /// # Ok::<(), pubnub_core::mock::transport::MockTransportError>(())
+    /// # };
+    /// ```
+    pub fn presence_only(
+        &mut self,
+        channels: impl IntoIterator<Item = channel::Name>,
+    ) -> Result<PresenceOnly, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Presence)?;
+
+        let to: Vec<_> = channels
+            .into_iter()
+            .map(pubsub::SubscribeTo::Channel)
+            .collect();
+        // The same for the heartbeats and the leave, so the client leaves
+        // as whom it announced. The subscriptions are announced under the
+        // UUID of the transport, a random one is on its own.
+        let (uuid, transport_uuid) = match self.transport.uuid() {
+            Some(uuid) => (uuid, true),
+            None => (UUID::random(), false),
+        };
+        let heartbeat = request::Heartbeat {
+            heartbeat: Some(PRESENCE_TIMEOUT),
+            to: to.clone(),
+            uuid: uuid.clone(),
+            state: Object::new_object(),
+        };
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let transport = self.transport.clone();
+        let runtime = self.runtime.clone();
+        let subscribe_loop_supervisor = Arc::clone(&self.subscribe_loop_supervisor);
+
+        // Not awaited, the task ends once the handle is dropped.
+        self.runtime.spawn(async move {
+            loop {
+                if let Err(err) = transport.call(heartbeat.clone()).await {
+                    error!("Unable to announce the presence: {:?}", err);
+                }
+                let delay = runtime.delay(HEARTBEAT_INTERVAL);
+                if let Either::Right(_) = select(delay, &mut stop_rx).await {
+                    break;
+                }
+            }
+
+            // Stay on the channels the client is still subscribed to.
+            let to: Vec<_> = if transport_uuid {
+                let subscribed = subscribe_loop_supervisor.lock().await.destinations().await;
+                to.into_iter()
+                    .filter(|to| !subscribed.contains(to))
+                    .collect()
+            } else {
+                to
+            };
+            if to.is_empty() {
+                return;
+            }
+
+            let leave = request::Leave {
+                to,
+                uuid: Some(uuid),
+            };
+            if let Err(err) = transport.call(leave).await {
+                error!("Unable to leave the channels: {:?}", err);
+            }
+        });
+
+        Ok(PresenceOnly { _stop_tx: stop_tx })
+    }
+
+    /// Get the occupancy of all the channels of the subscribe key at once.
+    ///
+    /// What's reported per channel is picked with the `TRespondWith`: just
+    /// the occupancy with [`OccupancyOnly`](respond_with::OccupancyOnly),
+    /// the UUIDs as well with [`OccupancyAndUUIDs`](respond_with::OccupancyAndUUIDs),
+    /// and their state too with [`Full`](respond_with::Full). The channels
+    /// nobody is present on aren't listed.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::data::presence::respond_with::OccupancyOnly;
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let here_now = pubnub.global_here_now::<OccupancyOnly>().await?;
+    /// for (channel, info) in here_now.channels {
+    ///     println!("{}: {}", channel, info.occupancy);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn global_here_now<TRespondWith>(
+        &self,
+    ) -> Result<response::GlobalHereNow<TRespondWith>, <TTransport as Transport>::Error>
+    where
+        TRespondWith: respond_with::RespondWith,
+        TTransport: Service<
+            request::GlobalHereNow<TRespondWith>,
+            Response = response::GlobalHereNow<TRespondWith>,
+            Error = <TTransport as Transport>::Error,
+        >,
+    {
+        self.ensure_supported(Endpoint::Presence)?;
+        let request = request::GlobalHereNow {
+            respond_with: PhantomData,
+        };
+        Service::<request::GlobalHereNow<TRespondWith>>::call(&self.transport, request).await
+    }
+}
+
+/// The presence announced with [`PubNub::presence_only`].
+///
+/// Dropping it stops the heartbeats and leaves the channels.
+#[derive(Debug)]
+pub struct PresenceOnly {
+    /// Only there to be dropped, stopping the heartbeats.
+    _stop_tx: oneshot::Sender<()>,
 }
@@ -1,10 +1,21 @@
 use super::PubNub;
 use crate::data::channel;
+use crate::data::message::SEQUENCE_META_KEY;
 use crate::data::object::Object;
+use crate::data::pubsub::{PublishMethod, PublishOptions, PublishResponse};
 use crate::data::request;
 use crate::data::timetoken::Timetoken;
 use crate::runtime::Runtime;
-use crate::transport::Transport;
+use crate::transport::{Error as TransportError, Transport};
+use log::debug;
+use std::time::Duration;
+
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+/// The number of the times a publish rejected for exceeding the rate limit
+/// of the PubNub network is retried, when the publishes are rate limited.
+const MAX_RATE_LIMITED_RETRIES: u32 = 2;
 
 impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
 where
@@ -13,6 +24,11 @@ where
 {
     /// Publish a message over the PubNub network.
     ///
+    /// The message is sent in the URL of a `GET` request, unless it's bigger
+    /// than the
+    /// [`publish_post_threshold`](crate::Builder::publish_post_threshold),
+    /// in which case it's sent in the body of a `POST` request instead.
+    ///
     /// # Errors
     ///
     /// Returns transport-specific errors.
@@ -48,12 +64,49 @@ where
         channel: channel::Name,
         message: Object,
     ) -> Result<Timetoken, <TTransport as Transport>::Error> {
-        let request = request::Publish {
-            channel,
-            meta: None,
-            payload: message,
-        };
-        self.transport.call(request).await
+        let response = self.publish_raw(channel, message).await?;
+        Ok(response.timetoken)
+    }
+
+    /// Publish a message over the PubNub network, and return the response of
+    /// the server as is.
+    ///
+    /// Same as [`publish`](Self::publish), but gives access to the HTTP
+    /// status and the status message along with the timetoken.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors. Responses other than
+    /// `[1, "Sent", ...]` are errors too, carrying the status and the message
+    /// returned by the server.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{data::channel, json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let response = pubnub
+    ///     .publish_raw(channel_name, object! { "content" => "Hello, world!" })
+    ///     .await?;
+    ///
+    /// println!("{} {}: {}", response.status, response.message, response.timetoken);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn publish_raw(
+        &self,
+        channel: channel::Name,
+        message: Object,
+    ) -> Result<PublishResponse, <TTransport as Transport>::Error> {
+        self.publish_with_options_raw(channel, message, PublishOptions::default())
+            .await
     }
 
     /// Publish a message over the PubNub network with an extra metadata payload.
@@ -96,11 +149,348 @@ where
         message: Object,
         metadata: Object,
     ) -> Result<Timetoken, <TTransport as Transport>::Error> {
+        let options = PublishOptions {
+            meta: Some(metadata),
+            ..PublishOptions::default()
+        };
+        self.publish_with_options(channel, message, options).await
+    }
+
+    /// Publish a message over the PubNub network along with a sequence
+    /// number, so that the consumers can detect the gaps.
+    ///
+    /// The sequence number is sent in the metadata rather than the payload,
+    /// so the consumers not interested in it get the message as is. Read it
+    /// back with [`Message::sequence`](crate::data::message::Message::sequence).
+    /// Keeping the numbers monotonic is up to the publisher.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{data::channel, json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// for seqn in 1..=3 {
+    ///     pubnub
+    ///         .publish_with_seqn(channel_name.clone(), object! { "seqn" => seqn }, seqn)
+    ///         .await?;
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn publish_with_seqn(
+        &self,
+        channel: channel::Name,
+        message: Object,
+        seqn: u64,
+    ) -> Result<Timetoken, <TTransport as Transport>::Error> {
+        let mut metadata = Object::new_object();
+        metadata[SEQUENCE_META_KEY] = seqn.into();
+        self.publish_with_metadata(channel, message, metadata).await
+    }
+
+    /// Publish a message over the PubNub network, sending it in the body of
+    /// a `POST` request.
+    ///
+    /// The regular [`publish`](Self::publish) puts the small messages in the
+    /// URL, exposing them in the logs of the proxies on the way. This variant
+    /// always keeps the message out of the URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{data::channel, json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let timetoken = pubnub
+    ///     .publish_via_post(
+    ///         channel_name,
+    ///         object! {
+    ///             "content" => "x".repeat(64 * 1024),
+    ///         },
+    ///     )
+    ///     .await?;
+    ///
+    /// println!("Timetoken: {}", timetoken);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn publish_via_post(
+        &self,
+        channel: channel::Name,
+        message: Object,
+    ) -> Result<Timetoken, <TTransport as Transport>::Error> {
+        let options = PublishOptions {
+            method: Some(PublishMethod::Post),
+            ..PublishOptions::default()
+        };
+        self.publish_with_options(channel, message, options).await
+    }
+
+    /// Publish a message over the PubNub network with the `options`.
+    ///
+    /// Unlike the other `publish_*` variants, which each set a single
+    /// option, this one combines any of them, i.e. sends a message with
+    /// metadata via `POST`, or without replicating it to the other PubNub
+    /// data centers, see [`PublishOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::data::pubsub::{PublishMethod, PublishOptions};
+    /// use pubnub_core::{data::channel, json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let options = PublishOptions {
+    ///     meta: Some(object! { "uuid" => "JoeBob" }),
+    ///     method: Some(PublishMethod::Post),
+    ///     replicate: false,
+    ///     ..PublishOptions::default()
+    /// };
+    /// let timetoken = pubnub
+    ///     .publish_with_options(channel_name, object! { "local" => true }, options)
+    ///     .await?;
+    ///
+    /// println!("Timetoken: {}", timetoken);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn publish_with_options(
+        &self,
+        channel: channel::Name,
+        message: Object,
+        options: PublishOptions,
+    ) -> Result<Timetoken, <TTransport as Transport>::Error> {
+        let response = self
+            .publish_with_options_raw(channel, message, options)
+            .await?;
+        Ok(response.timetoken)
+    }
+
+    /// Publish multiple messages over the PubNub network, in order.
+    ///
+    /// Each message is sent only after the previous one was acknowledged, so
+    /// the messages targeting the same channel get timetokens in the same
+    /// order they are passed in. Sending the requests one after another also
+    /// lets the transport reuse a single keep-alive connection for the whole
+    /// batch, instead of opening a socket per message.
+    ///
+    /// Stops at the first failure to preserve the ordering: the returned
+    /// list has a result for every message that was attempted, with the
+    /// failure being the last one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{data::channel, json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let results = pubnub
+    ///     .publish_many(vec![
+    ///         (channel_name.clone(), object! { "content" => "first" }),
+    ///         (channel_name, object! { "content" => "second" }),
+    ///     ])
+    ///     .await;
+    ///
+    /// for result in results {
+    ///     println!("Timetoken: {}", result?);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn publish_many(
+        &self,
+        items: impl IntoIterator<Item = (channel::Name, Object)>,
+    ) -> Vec<Result<Timetoken, <TTransport as Transport>::Error>> {
+        let mut results = Vec::new();
+        for (channel, message) in items {
+            let result = self.publish(channel, message).await;
+            let failed = result.is_err();
+            results.push(result);
+            if failed {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Build the publish request from the `options`, and send it.
+    async fn publish_with_options_raw(
+        &self,
+        channel: channel::Name,
+        message: Object,
+        options: PublishOptions,
+    ) -> Result<PublishResponse, <TTransport as Transport>::Error> {
+        let PublishOptions {
+            meta,
+            method,
+            timetoken_override,
+            replicate,
+        } = options;
+        let payload = self.prepare_payload(message);
+        let method = method.unwrap_or_else(|| self.publish_method(&payload, meta.as_ref()));
         let request = request::Publish {
             channel,
-            meta: Some(metadata),
-            payload: message,
+            payload,
+            meta,
+            timetoken_override,
+            replicate,
+            method,
         };
-        self.transport.call(request).await
+        self.publish_request(request).await
     }
+
+    /// Send the publish request, counting and tracing the published message.
+    async fn publish_request(
+        &self,
+        request: request::Publish,
+    ) -> Result<PublishResponse, <TTransport as Transport>::Error> {
+        self.transport.check_name(&request.channel, false)?;
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "publish",
+            channel = %request.channel,
+            method = ?request.method,
+            timetoken = tracing::field::Empty,
+        );
+        #[cfg(feature = "metrics")]
+        let method = request.method;
+
+        let mut retry = 0;
+        let mut transient_retry = 0;
+        let result = loop {
+            if let Some(ref limiter) = self.publish_rate_limiter {
+                let wait = limiter.reserve();
+                if wait > Duration::from_secs(0) {
+                    debug!("Publish rate limit reached, waiting for {:?}", wait);
+                    self.runtime.delay(wait).await;
+                }
+            }
+
+            let call = self.transport.call(request.clone());
+            #[cfg(feature = "tracing")]
+            let call = call.instrument(span.clone());
+            let result = call.await;
+
+            // Hold all the publishes back, and retry this one when its turn
+            // comes.
+            if let (Some(limiter), Err(err)) = (&self.publish_rate_limiter, &result) {
+                if err.is_rate_limited() {
+                    limiter.back_off(err.retry_after());
+                    if retry < MAX_RATE_LIMITED_RETRIES {
+                        debug!("Publish rate limited, retrying: {}", err);
+                        retry += 1;
+                        continue;
+                    }
+                }
+            }
+
+            if let Err(ref err) = result {
+                if let Some(delay) = self.publish_retry.retry_delay(err, transient_retry) {
+                    debug!("Publish failed, retrying in {:?}: {}", delay, err);
+                    self.runtime.delay(delay).await;
+                    transient_retry += 1;
+                    continue;
+                }
+            }
+            break result;
+        };
+
+        #[cfg(feature = "tracing")]
+        match result {
+            Ok(ref response) => {
+                span.record("timetoken", tracing::field::display(response.timetoken));
+            }
+            Err(ref err) => tracing::debug!(parent: &span, error = %err, "Publish failed"),
+        }
+        #[cfg(feature = "metrics")]
+        {
+            if result.is_ok() {
+                self.metrics.message_published(method);
+            }
+        }
+        result
+    }
+
+    /// Pick the method to publish the message with, switching to `POST` when
+    /// the message would make the URL too long.
+    ///
+    /// The size is the one the message and the metadata take in the URL,
+    /// i.e. percent-encoded.
+    fn publish_method(&self, payload: &Object, meta: Option<&Object>) -> PublishMethod {
+        let size = encoded_len(payload) + meta.map_or(0, encoded_len);
+        if size > self.publish_post_threshold {
+            PublishMethod::Post
+        } else {
+            PublishMethod::Get
+        }
+    }
+
+    /// Prepare the payload for publishing, encrypting it if the cipher key
+    /// is set.
+    fn prepare_payload(&self, payload: Object) -> Object {
+        #[cfg(feature = "cipher")]
+        {
+            if let Some(ref cipher) = self.cipher {
+                let encrypted = cipher
+                    .encrypt(payload.dump().as_bytes())
+                    .expect("Unable to generate the initialization vector");
+                return Object::String(encrypted);
+            }
+        }
+        payload
+    }
+}
+
+/// The length of the `object` serialized and percent-encoded into the URL,
+/// measured over a single serialization.
+///
+/// Only the unreserved characters are sent as-is, each byte of the others
+/// takes three, as with the URI template expansion.
+fn encoded_len(object: &Object) -> usize {
+    object
+        .dump()
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => 1,
+            _ => 3,
+        })
+        .sum()
 }
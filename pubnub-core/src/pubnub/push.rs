@@ -0,0 +1,100 @@
+use super::PubNub;
+use crate::data::channel;
+use crate::data::push::PushType;
+use crate::data::request;
+use crate::runtime::Runtime;
+use crate::transport::{Endpoint, PushTransport, Transport};
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: PushTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Register a device for push notifications on the channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::data::push::{ApnsEnvironment, PushType};
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    ///
+    /// let push_type = PushType::Apns2 {
+    ///     environment: ApnsEnvironment::Production,
+    ///     topic: "com.example.app".to_owned(),
+    /// };
+    /// pubnub
+    ///     .add_push_channels(
+    ///         "my-device-token".to_owned(),
+    ///         push_type,
+    ///         vec!["my-channel".parse().unwrap()],
+    ///     )
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn add_push_channels(
+        &self,
+        device_token: String,
+        push_type: PushType,
+        channels: Vec<channel::Name>,
+    ) -> Result<(), <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Push)?;
+        self.transport
+            .call(request::AddPushChannels {
+                device_token,
+                push_type,
+                channels,
+            })
+            .await
+    }
+
+    /// Unregister a device from push notifications on the channels.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn remove_push_channels(
+        &self,
+        device_token: String,
+        push_type: PushType,
+        channels: Vec<channel::Name>,
+    ) -> Result<(), <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Push)?;
+        self.transport
+            .call(request::RemovePushChannels {
+                device_token,
+                push_type,
+                channels,
+            })
+            .await
+    }
+
+    /// List the channels a device is registered for push notifications on.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn list_push_channels(
+        &self,
+        device_token: String,
+        push_type: PushType,
+    ) -> Result<Vec<channel::Name>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::Push)?;
+        self.transport
+            .call(request::ListPushChannels {
+                device_token,
+                push_type,
+            })
+            .await
+    }
+}
@@ -0,0 +1,114 @@
+//! Client-side rate limiting of the publishes.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long the publishes are held back once the PubNub network reports the
+/// rate limit is exceeded, unless it asks for a different delay.
+const RATE_LIMITED_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A token bucket holding a second worth of publishes.
+///
+/// Rather than counting the tokens, it tracks when the next publish is due
+/// at the sustained rate, and lets the publishes run ahead of that by up to
+/// the burst. The publishes reserve their slots in order, so the waiting
+/// ones don't race each other for the tokens.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    /// The time between the publishes at the sustained rate.
+    interval: Duration,
+
+    /// How far the publishes can run ahead of the sustained rate.
+    burst: Duration,
+
+    /// When the next publish is due at the sustained rate, if any was made.
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `per_sec` publishes per second.
+    pub fn new(per_sec: u32) -> Self {
+        let interval = Duration::from_secs(1) / per_sec;
+        Self {
+            interval,
+            burst: interval * (per_sec - 1),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Reserve the slot for a publish, returning how long to wait for it.
+    pub fn reserve(&self) -> Duration {
+        self.reserve_at(Instant::now())
+    }
+
+    /// Hold the publishes back after the PubNub network rejected one for
+    /// exceeding the rate limit, whatever the bucket holds.
+    ///
+    /// Waits for the `retry_after` the network asked for, if any. The delay
+    /// isn't added to the slots already reserved, the publishes due later
+    /// than that keep their turn.
+    pub fn back_off(&self, retry_after: Option<Duration>) {
+        self.back_off_at(Instant::now(), retry_after);
+    }
+
+    fn reserve_at(&self, now: Instant) -> Duration {
+        let mut next = self.next.lock().expect("rate limiter lock is poisoned");
+        let due = next.map_or(now, |next| next.max(now));
+        *next = Some(due + self.interval);
+        due.duration_since(now)
+            .checked_sub(self.burst)
+            .unwrap_or_default()
+    }
+
+    fn back_off_at(&self, now: Instant, retry_after: Option<Duration>) {
+        let mut next = self.next.lock().expect("rate limiter lock is poisoned");
+        let due = now + self.burst + retry_after.unwrap_or(RATE_LIMITED_BACKOFF);
+        *next = Some(next.map_or(due, |next| next.max(due)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn burst_then_sustained_rate() {
+        let limiter = RateLimiter::new(4);
+        let start = Instant::now();
+
+        let waits: Vec<_> = (0..6).map(|_| limiter.reserve_at(start)).collect();
+        assert_eq!(
+            waits,
+            vec![
+                Duration::from_millis(0),
+                Duration::from_millis(0),
+                Duration::from_millis(0),
+                Duration::from_millis(0),
+                Duration::from_millis(250),
+                Duration::from_millis(500),
+            ]
+        );
+
+        // The bucket refills over time.
+        let later = start + Duration::from_secs(3);
+        assert_eq!(limiter.reserve_at(later), Duration::from_millis(0));
+    }
+
+    #[test]
+    fn back_off_holds_publishes_back() {
+        let limiter = RateLimiter::new(4);
+        let start = Instant::now();
+
+        limiter.back_off_at(start, None);
+        assert_eq!(limiter.reserve_at(start), Duration::from_secs(1));
+        assert_eq!(limiter.reserve_at(start), Duration::from_millis(1250));
+
+        // The delay asked for by the server replaces the default one, and
+        // isn't added to the slots already reserved.
+        limiter.back_off_at(start, Some(Duration::from_secs(3)));
+        assert_eq!(limiter.reserve_at(start), Duration::from_secs(3));
+        limiter.back_off_at(start, Some(Duration::from_millis(100)));
+        assert_eq!(limiter.reserve_at(start), Duration::from_millis(3250));
+    }
+}
@@ -0,0 +1,128 @@
+//! Retries of the failed publishes.
+
+use crate::transport::Error as TransportError;
+use std::time::Duration;
+
+/// Controls how the failed publishes are retried, see
+/// [`Builder::publish_retry`](crate::Builder::publish_retry).
+///
+/// Only the transient failures are retried, as told by the transport with
+/// [`TransportError::is_transient`], i.e. network errors and `5xx`
+/// responses. Requests rejected by the server as invalid (`4xx` responses)
+/// are never retried.
+///
+/// Retries use exponential backoff: the first retry waits for
+/// `initial_backoff`, and every following one doubles the delay, up to
+/// `max_backoff`.
+///
+/// The requests rejected for exceeding the rate limit (`429` responses) are
+/// not retried by the policy either. They're held back along with all the
+/// other publishes for the delay the server asks for with the `Retry-After`
+/// header instead, see
+/// [`Builder::publish_rate_limit`](crate::Builder::publish_rate_limit).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of retries after the initial attempt.
+    pub max_retries: u32,
+
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+
+    /// The upper bound of the delay between retries.
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    #[must_use]
+    pub fn none() -> Self {
+        Self::new(0)
+    }
+
+    /// A policy that retries up to `max_retries` times with the default
+    /// backoff settings.
+    #[must_use]
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+
+    /// The delay to wait before the retry number `retry` (zero-based).
+    #[must_use]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        1_u32
+            .checked_shl(retry)
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+impl RetryPolicy {
+    /// The delay to wait before the retry number `retry` (zero-based) of the
+    /// request that failed with the `error`, or `None` if it shouldn't be
+    /// retried.
+    pub(crate) fn retry_delay<E: TransportError>(&self, error: &E, retry: u32) -> Option<Duration> {
+        if retry >= self.max_retries {
+            return None;
+        }
+        if error.is_transient() && !error.is_rate_limited() {
+            Some(self.backoff(retry))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// No retries by default.
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use crate::mock::transport::MockTransportError;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(4), Duration::from_secs(1));
+        assert_eq!(policy.backoff(100), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let policy = RetryPolicy::new(2);
+        let transient = MockTransportError {
+            transient: true,
+            ..MockTransportError::default()
+        };
+
+        assert_eq!(policy.retry_delay(&transient, 0), Some(policy.backoff(0)));
+        assert_eq!(policy.retry_delay(&transient, 2), None);
+        assert_eq!(policy.retry_delay(&MockTransportError::default(), 0), None);
+
+        // Left to the client's rate limiter.
+        let rate_limited = MockTransportError {
+            transient: true,
+            rate_limited: true,
+            ..MockTransportError::default()
+        };
+        assert_eq!(policy.retry_delay(&rate_limited, 0), None);
+    }
+}
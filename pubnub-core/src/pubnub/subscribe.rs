@@ -1,8 +1,29 @@
 use super::PubNub;
-use crate::data::{channel, pubsub};
+use crate::data::filter::FilterExpression;
+use crate::data::message::Message;
+use crate::data::{channel, cursor::Cursor, history, pubsub, request, timetoken::Timetoken};
 use crate::runtime::Runtime;
-use crate::subscription::Subscription;
-use crate::transport::Transport;
+use crate::subscription::cursors::CurrentCursors;
+use crate::subscription::subscribe_loop_supervisor::SubscribeLoopSupervisor;
+use crate::subscription::{StatusEvent, Subscription, TrySubscription};
+use crate::transport::{Capabilities, Endpoint, HistoryTransport, Transport};
+use futures_channel::oneshot;
+use futures_util::future::{join_all, select, Either, FutureExt};
+use futures_util::lock::Mutex;
+use futures_util::stream::{self, FusedStream, SelectAll, Stream, StreamExt};
+use futures_util::task::{Context, Poll};
+use log::error;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::sync::Arc;
+
+#[cfg(feature = "broadcast")]
+use crate::subscription::{broadcast, BroadcastSubscription};
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
 
 impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
 where
@@ -11,15 +32,32 @@ where
 {
     /// Subscribe to a message stream over the PubNub network.
     ///
-    /// The PubNub client only maintains a single subscribe loop for all subscription streams. This
-    /// has a benefit that it optimizes for a low number of sockets to the PubNub network. It has a
-    /// downside that requires _all_ streams to consume faster than the subscribe loop produces.
-    /// A slow consumer will create a head-of-line blocking bottleneck in the processing of
-    /// received messages. All streams can only consume as fast as the slowest.
+    /// The PubNub client only maintains a single subscribe loop for all subscription streams
+    /// (except for the ones with their own filter, see
+    /// [`subscribe_with_filter`](Self::subscribe_with_filter)). This has a benefit that it
+    /// optimizes for a low number of sockets to the PubNub network.
+    ///
+    /// The subscribe loop doesn't wait for a slow consumer. Each stream buffers the number of
+    /// messages set with the [`Builder::subscription_buffer`](crate::Builder::subscription_buffer),
+    /// and once the buffer is full, the messages for the stream are dropped and counted in its
+    /// [`dropped`](Subscription::dropped) count. For example, with 3 total subscription streams
+    /// and 1 that takes 30 seconds to process each message, the other 2 streams carry on as usual,
+    /// and the slow one misses the messages that don't fit its buffer.
+    ///
+    /// The returned future resolves once the subscribe loop is ready to
+    /// deliver the messages for the channel. It never blocks the thread, so
+    /// it's safe to subscribe from within the tasks of any runtime. If the
+    /// loop fails to connect for good, e.g. with an invalid subscribe key,
+    /// the returned stream ends right away, and the error is reported to the
+    /// [`status_stream`](Self::status_stream). The wait for the errors that
+    /// are retried is bounded by the
+    /// [`Builder::ready_timeout`](crate::Builder::ready_timeout).
     ///
-    /// For example, with 3 total subscription streams and 1 that takes 30 seconds to process each
-    /// message; the other 2 streams will be blocked waiting for that 30-second duration on the
-    /// slow consumer.
+    /// # Errors
+    ///
+    /// Fails if the transport rejects the channel name, see
+    /// [`Transport::check_name`], without subscribing. The other
+    /// subscriptions carry on.
     ///
     /// # Example
     ///
@@ -33,18 +71,1055 @@ where
     /// # async {
     /// let mut pubnub = Builder::with_components(transport, runtime).build();
     /// let channel_name: channel::Name = "my-channel".parse().unwrap();
-    /// let mut stream = pubnub.subscribe(channel_name).await;
+    /// let mut stream = pubnub.subscribe(channel_name).await?;
+    ///
+    /// while let Some(message) = stream.next().await {
+    ///     println!("Received message: {:?}", message);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn subscribe(
+        &mut self,
+        channel: channel::Name,
+    ) -> Result<Subscription<TRuntime>, <TTransport as Transport>::Error> {
+        self.subscribe_from(channel, Cursor::default()).await
+    }
+
+    /// Subscribe to a message stream over a specified channel, reporting
+    /// the messages that couldn't be decoded.
+    ///
+    /// Works like [`subscribe`](Self::subscribe), but the stream yields the
+    /// results: the undecodable messages come as the [`DecodeError`]s, where
+    /// `subscribe` skips them. An error doesn't end the stream.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`subscribe`](Self::subscribe).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::{data::channel, Builder};
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let mut stream = pubnub.try_subscribe(channel_name).await?;
+    ///
+    /// while let Some(result) = stream.next().await {
+    ///     match result {
+    ///         Ok(message) => println!("Received message: {:?}", message),
+    ///         Err(error) => println!("Received garbage: {}", error),
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    ///
+    /// [`DecodeError`]: crate::data::message::DecodeError
+    pub async fn try_subscribe(
+        &mut self,
+        channel: channel::Name,
+    ) -> Result<TrySubscription<TRuntime>, <TTransport as Transport>::Error> {
+        Ok(TrySubscription::new(self.subscribe(channel).await?))
+    }
+
+    /// Subscribe to a message stream from a channel, a set of channels
+    /// matching a wildcard, or a channel group.
+    ///
+    /// Works like [`subscribe`](Self::subscribe), for any kind of the
+    /// [`SubscribeTo`](pubsub::SubscribeTo) destination. The channel names
+    /// and the wildcard specifiers convert into the destinations, the
+    /// channel groups have to be spelled out.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the transport rejects the channel or the channel group name,
+    /// see [`Transport::check_name`], without subscribing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::data::{channel, pubsub::SubscribeTo};
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let wildcard: channel::WildcardSpec = "sports.*".parse().unwrap();
+    /// let sports = pubnub.subscribe_to(wildcard).await?;
+    /// let group = pubnub
+    ///     .subscribe_to(SubscribeTo::ChannelGroup("my-group".parse().unwrap()))
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn subscribe_to(
+        &mut self,
+        to: impl Into<pubsub::SubscribeTo>,
+    ) -> Result<Subscription<TRuntime>, <TTransport as Transport>::Error> {
+        let to = to.into();
+        self.check_destination(&to)?;
+        Ok(self.subscribe_to_unchecked(to).await)
+    }
+
+    /// Subscribe to the `to` destination, the names of which were checked
+    /// already.
+    async fn subscribe_to_unchecked(&mut self, to: pubsub::SubscribeTo) -> Subscription<TRuntime> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("subscribe", destination = ?to);
+
+        let subscribe = async {
+            let supervisor_arc_clone = self.subscribe_loop_supervisor.clone();
+            let mut supervisor_guard = supervisor_arc_clone.lock().await;
+            let filter_expr = supervisor_guard.filter_expr().cloned();
+            supervisor_guard
+                .subscribe(self, to, Cursor::default(), filter_expr)
+                .await
+        };
+        #[cfg(feature = "tracing")]
+        let subscribe = subscribe.instrument(span);
+        subscribe.await
+    }
+
+    /// Subscribe to the channels matching a wildcard `pattern`, like
+    /// `news.*`.
+    ///
+    /// The stream yields the messages published to any channel matching the
+    /// pattern, see [`WildcardSpec::matches`](channel::WildcardSpec::matches).
+    /// Tell them apart by their [`channel`](Message::channel), the
+    /// [`route`](Message::route) holds the wildcard.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the `pattern` isn't a valid
+    /// [`WildcardSpec`](channel::WildcardSpec), without subscribing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let mut news = pubnub.subscribe_wildcard("news.*").await.unwrap();
+    ///
+    /// while let Some(message) = news.next().await {
+    ///     println!("Received news on {}: {}", message.channel, message.json);
+    /// }
+    /// # };
+    /// ```
+    pub async fn subscribe_wildcard(
+        &mut self,
+        pattern: &str,
+    ) -> Result<Subscription<TRuntime>, channel::InvalidWildcardSpec> {
+        let spec: channel::WildcardSpec = pattern
+            .parse()
+            .map_err(|()| channel::InvalidWildcardSpec(pattern.to_owned()))?;
+        Ok(self.subscribe_to_unchecked(spec.into()).await)
+    }
+
+    /// Subscribe to many channels at once, with a single message stream.
+    ///
+    /// Works like [`subscribe`](Self::subscribe) for each of the `channels`,
+    /// and merges the streams. Tell the messages apart by their
+    /// [`channel`](Message::channel). The streams are polled in turns, so a
+    /// busy channel can't starve the others.
+    ///
+    /// Dropping the stream drops the subscriptions to all the channels, and
+    /// leaves the ones no other stream is subscribed to, if the transport
+    /// supports presence.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the transport rejects any of the channel names, see
+    /// [`Transport::check_name`], without subscribing to any of them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channels = vec!["news".parse().unwrap(), "sports".parse().unwrap()];
+    /// let mut stream = pubnub.subscribe_all(channels).await?;
+    ///
+    /// while let Some(message) = stream.next().await {
+    ///     println!("Received message on {}: {}", message.channel, message.json);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn subscribe_all(
+        &mut self,
+        channels: impl IntoIterator<Item = channel::Name>,
+    ) -> Result<impl Stream<Item = Arc<Message>>, <TTransport as Transport>::Error> {
+        let channels: Vec<_> = channels.into_iter().collect();
+        for channel in &channels {
+            self.transport.check_name(channel, true)?;
+        }
+
+        let mut subscriptions = Vec::new();
+        let mut left = Vec::new();
+        for channel in channels {
+            let mut subscription = self
+                .subscribe_to_unchecked(pubsub::SubscribeTo::Channel(channel))
+                .await;
+            let (leave_tx, leave_rx) = oneshot::channel();
+            subscription.leave_tx = Some(leave_tx);
+            subscriptions.push(subscription);
+            left.push(leave_rx);
+        }
+
+        Ok(MergedSubscription {
+            subscriptions: stream::select_all(subscriptions),
+            _leave: LeaveOnDrop {
+                transport: Some(self.transport.clone()),
+                runtime: self.runtime.clone(),
+                left,
+            },
+        })
+    }
+
+    /// Subscribe to a message stream, starting from the `cursor`.
+    ///
+    /// Delivers the messages published after the `cursor`, as far as the
+    /// PubNub network still has them, followed by the live ones.
+    ///
+    /// The region of the `cursor` is sent along, so the PubNub network
+    /// resumes from the position in the same region, which matters with the
+    /// keysets spanning many regions. Prefer the cursors of the subscribe
+    /// loop, see [`current_cursor`](Self::current_cursor). A [`Timetoken`]
+    /// converts to a cursor too, i.e. the one returned from publish, but
+    /// mind the region of a message timetoken is the one it was published
+    /// in, and not the one to resume in.
+    ///
+    /// If the subscribe loop is running behind the `cursor` already, the
+    /// channel is added to it, and the stream starts from the position the
+    /// loop is at. If the loop is ahead, it rewinds to the `cursor`, and the
+    /// [de-duplication](crate::Builder::message_deduplication) keeps the
+    /// other streams from getting their messages again. Subscribing to a
+    /// channel the loop is subscribed to already starts from the position
+    /// the loop is at, whatever the `cursor`.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`subscribe`](Self::subscribe).
+    pub async fn subscribe_from(
+        &mut self,
+        channel: channel::Name,
+        cursor: impl Into<Cursor>,
+    ) -> Result<Subscription<TRuntime>, <TTransport as Transport>::Error> {
+        self.transport.check_name(&channel, true)?;
+        Ok(self.subscribe_from_unchecked(channel, cursor.into()).await)
+    }
+
+    /// Subscribe to the `channel` from the `cursor`, the name of which was
+    /// checked already.
+    async fn subscribe_from_unchecked(
+        &mut self,
+        channel: channel::Name,
+        cursor: Cursor,
+    ) -> Subscription<TRuntime> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("subscribe", channel = %channel, cursor = %cursor);
+
+        let subscribe = async {
+            let supervisor_arc_clone = self.subscribe_loop_supervisor.clone();
+            let mut supervisor_guard = supervisor_arc_clone.lock().await;
+            let filter_expr = supervisor_guard.filter_expr().cloned();
+            supervisor_guard
+                .subscribe(
+                    self,
+                    pubsub::SubscribeTo::Channel(channel),
+                    cursor,
+                    filter_expr,
+                )
+                .await
+        };
+        #[cfg(feature = "tracing")]
+        let subscribe = subscribe.instrument(span);
+        subscribe.await
+    }
+
+    /// Subscribe to a message stream, filtered with the `filter_expr`
+    /// instead of the client-wide filter.
+    ///
+    /// The PubNub network applies the filter to the whole subscribe request,
+    /// so the subscriptions with different filters can't share a subscribe
+    /// loop. A separate loop, with a connection of its own, is maintained
+    /// for every distinct filter, and the subscriptions with the same filter
+    /// share it.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`subscribe`](Self::subscribe).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{data::channel, Builder};
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let urgent = pubnub
+    ///     .subscribe_with_filter(channel_name, "meta.priority > 2".parse().unwrap())
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn subscribe_with_filter(
+        &mut self,
+        channel: channel::Name,
+        filter_expr: FilterExpression,
+    ) -> Result<Subscription<TRuntime>, <TTransport as Transport>::Error> {
+        self.transport.check_name(&channel, true)?;
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("subscribe", channel = %channel, filter = %filter_expr);
+
+        let subscribe = async {
+            let supervisor_arc_clone = self.subscribe_loop_supervisor.clone();
+            let mut supervisor_guard = supervisor_arc_clone.lock().await;
+            supervisor_guard
+                .subscribe(
+                    self,
+                    pubsub::SubscribeTo::Channel(channel),
+                    Cursor::default(),
+                    Some(filter_expr),
+                )
+                .await
+        };
+        #[cfg(feature = "tracing")]
+        let subscribe = subscribe.instrument(span);
+        Ok(subscribe.await)
+    }
+
+    /// Subscribe to a message stream shared by many consumers.
+    ///
+    /// All the broadcast streams for the channel share a single
+    /// [`Subscription`], so adding a stream is cheap. The messages are shared
+    /// via [`Arc`](std::sync::Arc) rather than cloned for every stream.
+    ///
+    /// Like with the [`subscribe`](Self::subscribe) streams, a slow consumer
+    /// doesn't hold up the others. Each stream buffers the number of
+    /// messages set with the [`Builder::subscription_buffer`], rounded up to
+    /// a power of two, and a stream that falls further behind yields a
+    /// [`Lagged`](crate::Lagged) error with the number of the missed messages
+    /// before carrying on with the oldest message it still has. The total
+    /// is kept in the stream's [`dropped`](BroadcastSubscription::dropped)
+    /// count.
+    ///
+    /// The streams end when the channel is unsubscribed from.
+    ///
+    /// [`Builder::subscription_buffer`]: crate::Builder::subscription_buffer
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`subscribe`](Self::subscribe).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::{data::channel, Builder};
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let mut stream = pubnub.subscribe_broadcast(channel_name).await?;
+    ///
+    /// while let Some(message) = stream.next().await {
+    ///     match message {
+    ///         Ok(message) => println!("Received message: {:?}", message),
+    ///         Err(lagged) => println!("Fell behind: {}", lagged),
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    #[cfg(feature = "broadcast")]
+    pub async fn subscribe_broadcast(
+        &mut self,
+        channel: channel::Name,
+    ) -> Result<BroadcastSubscription, <TTransport as Transport>::Error> {
+        self.transport.check_name(&channel, true)?;
+
+        let broadcasts = self.broadcasts.clone();
+        let mut senders = broadcasts.lock().await;
+        if let Some(sender) = senders.get(&channel) {
+            return Ok(broadcast::join(channel, sender));
+        }
+
+        let subscription = self
+            .subscribe_from_unchecked(channel.clone(), Cursor::default())
+            .await;
+        Ok(broadcasts.start(&mut senders, channel, subscription, &self.runtime))
+    }
+
+    /// Subscribe to a message stream that survives the end of the
+    /// subscription.
+    ///
+    /// When the underlying subscription ends on its own, i.e. on a fatal
+    /// subscribe loop error, `reconnect` is called to obtain a `PubNub`
+    /// client to carry on with. It may return a fresh client, for instance
+    /// with a renewed auth key, or a clone of this one. Returning `None` ends
+    /// the stream. The stream ends as well when the subscription is ended on
+    /// request, by an [`unsubscribe`](Self::unsubscribe) from the channel or
+    /// a [`shutdown`](Self::shutdown) of the client.
+    ///
+    /// The new subscription starts from the time of the last delivered
+    /// message, in the region the subscribe loop was in, so the messages
+    /// published in the gap aren't lost, and the messages that were already
+    /// delivered are skipped. If no message was delivered yet, the new
+    /// subscription starts from the current time. As with the
+    /// [`subscribe_from`](Self::subscribe_from), a subscribe loop the client
+    /// runs for the other channels already rewinds to that time, so the gap
+    /// is recovered either way.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`subscribe`](Self::subscribe).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::{data::channel, Builder};
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    ///
+    /// let reconnect_pubnub = pubnub.clone();
+    /// let stream = pubnub
+    ///     .resilient_subscribe(channel_name, move || {
+    ///         let pubnub = reconnect_pubnub.clone();
+    ///         async move { Some(pubnub) }
+    ///     })
+    ///     .await?;
+    /// futures_util::pin_mut!(stream);
     ///
     /// while let Some(message) = stream.next().await {
     ///     println!("Received message: {:?}", message);
     /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// # };
     /// ```
-    pub async fn subscribe(&mut self, channel: channel::Name) -> Subscription<TRuntime> {
+    pub async fn resilient_subscribe<F, Fut>(
+        &mut self,
+        channel: channel::Name,
+        reconnect: F,
+    ) -> Result<impl Stream<Item = Arc<Message>>, <TTransport as Transport>::Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Option<Self>>,
+    {
+        let explicit_ends = self.explicit_ends(&channel).await;
+        let subscription = self.subscribe(channel.clone()).await?;
+        let state = ResilientState {
+            channel,
+            reconnect,
+            subscription: Some(subscription),
+            subscribe_loop_supervisor: Arc::clone(&self.subscribe_loop_supervisor),
+            explicit_ends,
+            current_cursors: self.current_cursors.clone(),
+            last: None,
+        };
+
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                let subscription = if let Some(ref mut subscription) = state.subscription {
+                    subscription
+                } else {
+                    let mut pubnub = (state.reconnect)().await?;
+                    let cursor = state.last.unwrap_or_default();
+                    state.explicit_ends = pubnub.explicit_ends(&state.channel).await;
+                    let subscription = pubnub
+                        .subscribe_from_unchecked(state.channel.clone(), cursor)
+                        .await;
+                    state.subscription = Some(subscription);
+                    state.subscribe_loop_supervisor = Arc::clone(&pubnub.subscribe_loop_supervisor);
+                    state.current_cursors = pubnub.current_cursors.clone();
+                    continue;
+                };
+
+                let message = if let Some(message) = subscription.next().await {
+                    message
+                } else {
+                    // Ended on request, rather than by the loop.
+                    let explicit_ends = state
+                        .subscribe_loop_supervisor
+                        .lock()
+                        .await
+                        .explicit_ends(&pubsub::SubscribeTo::Channel(state.channel.clone()));
+                    if explicit_ends != state.explicit_ends {
+                        return None;
+                    }
+                    state.subscription = None;
+                    continue;
+                };
+
+                // Skip the messages that were delivered before reconnecting.
+                // The regions of the message timetokens are the ones they
+                // were published in, so they don't take part.
+                if let Some(last) = state.last {
+                    if message.timetoken.t <= last.timetoken {
+                        continue;
+                    }
+                }
+                // Resume in the region of the loop, not the publisher's.
+                let region = state
+                    .current_cursors
+                    .get(state.channel.as_ref())
+                    .map_or(0, |cursor| cursor.region);
+                state.last = Some(Cursor::new(message.timetoken.t, region));
+                return Some((message, state));
+            }
+        }))
+    }
+
+    /// The number of the times the subscriptions to the `channel` were
+    /// ended on request.
+    async fn explicit_ends(&self, channel: &channel::Name) -> usize {
+        self.subscribe_loop_supervisor
+            .lock()
+            .await
+            .explicit_ends(&pubsub::SubscribeTo::Channel(channel.clone()))
+    }
+}
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: HistoryTransport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Replay the messages published to the `channel` after the `since`
+    /// timetoken from the history, then carry on with the live ones.
+    ///
+    /// The channel is subscribed to before the history is read, so the
+    /// messages published in the meantime aren't lost. The live messages that
+    /// were already replayed from the history, i.e. with the same timetoken
+    /// and payload, are skipped, so every message is delivered once. The
+    /// live messages missing from the history, i.e. the ones published
+    /// without storing them, are delivered as they come.
+    ///
+    /// The history is read a page at a time, as the stream is consumed. A
+    /// failed history request is yielded as an error, and ends the stream.
+    ///
+    /// # Memory
+    ///
+    /// The live messages that come while replaying are taken off the
+    /// subscription right away, so they aren't dropped once its buffer is
+    /// full, and are kept by the stream until
+    /// the replay is done. Along with them, the stream keeps the keys of the
+    /// replayed messages published since subscribing, to skip their live
+    /// copies. Both grow with the number of the messages published to the
+    /// channel while replaying, not with the size of the replayed history.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`subscribe`](Self::subscribe).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::{data::channel, data::timetoken::Timetoken, Builder};
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let since = Timetoken::from_parts(15_000_000_000_000_000, 0);
+    ///
+    /// let stream = pubnub.replay_then_subscribe(channel_name, since).await?;
+    /// futures_util::pin_mut!(stream);
+    ///
+    /// while let Some(message) = stream.next().await {
+    ///     println!("Received message: {:?}", message?);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn replay_then_subscribe(
+        &mut self,
+        channel: channel::Name,
+        since: Timetoken,
+    ) -> Result<
+        impl Stream<Item = Result<Arc<Message>, <TTransport as Transport>::Error>>,
+        <TTransport as Transport>::Error,
+    > {
+        let subscription = self.subscribe(channel.clone()).await?;
+        // The live messages come from the cursor the loop polls the channel
+        // with, once subscribed.
+        let subscribed_at = self
+            .current_cursors
+            .get(channel.as_ref())
+            .map_or(0, |cursor| cursor.timetoken);
+        let state = ReplayState {
+            pubnub: self.clone(),
+            channel,
+            subscription,
+            subscribed_at,
+            live: VecDeque::new(),
+            page: VecDeque::new(),
+            next_page: since.t.saturating_add(1),
+            replayed: VecDeque::new(),
+            replaying: true,
+            failed: false,
+        };
+
+        Ok(stream::unfold(state, |mut state| async move {
+            loop {
+                if state.failed {
+                    return None;
+                }
+
+                if state.replaying || !state.page.is_empty() {
+                    state.drain_live();
+                }
+
+                if let Some(message) = state.page.pop_front() {
+                    state.next_page = message.timetoken.t;
+                    state.replayed.push_back(replay_key(&message));
+                    state.prune_replayed();
+                    return Some((Ok(message), state));
+                }
+
+                if state.replaying {
+                    let page = match state.read_page().await {
+                        Ok(page) => page,
+                        Err(err) => {
+                            state.failed = true;
+                            return Some((Err(err), state));
+                        }
+                    };
+                    state.replaying = page.len() == REPLAY_PAGE_SIZE;
+                    // The page starts with the last replayed messages.
+                    let fresh: Vec<_> = page
+                        .into_iter()
+                        .filter(|message| !state.was_replayed(replay_key(message)))
+                        .map(Arc::new)
+                        .collect();
+                    state.page.extend(fresh);
+                    continue;
+                }
+
+                let message = match state.live.pop_front() {
+                    Some(message) => message,
+                    None => state.subscription.next().await?,
+                };
+
+                // Skip the messages that were replayed from the history. The
+                // live messages come in order, so the replayed ones older
+                // than this one can't come anymore.
+                let key = replay_key(&message);
+                while state.replayed.front().map_or(false, |&(t, _)| t < key.0) {
+                    state.replayed.pop_front();
+                }
+                if let Some(index) = state.replayed.iter().position(|&replayed| replayed == key) {
+                    state.replayed.remove(index);
+                    continue;
+                }
+                return Some((Ok(message), state));
+            }
+        }))
+    }
+
+    /// Read a page of the history of the `channel`, oldest first, starting
+    /// at the `from` timetoken, inclusive.
+    async fn history_page(
+        &self,
+        channel: channel::Name,
+        from: history::Timetoken,
+    ) -> Result<Vec<Message>, <TTransport as Transport>::Error> {
+        self.ensure_supported(Endpoint::History)?;
+        // The `start` is exclusive and reads the older messages, the `end`
+        // is inclusive and reads the newer ones, the oldest first with the
+        // `reverse`.
+        let request = request::GetHistory {
+            channels: vec![channel.clone()],
+            max: Some(REPLAY_PAGE_SIZE),
+            reverse: Some(true),
+            start: None,
+            end: Some(from),
+            include_metadata: Some(true),
+        };
+        let mut response = self.transport.call(request).await?;
+
+        let items = response.remove(&channel).unwrap_or_default();
+        let messages = items
+            .into_iter()
+            .map(|item| self.history_message(&channel, item));
+        Ok(messages.collect())
+    }
+}
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: Transport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Unsubscribe from a channel.
+    ///
+    /// Stops the delivery of messages from the channel to _all_ the
+    /// subscription streams for it: the streams end, yielding `None`. When
+    /// the last subscribed channel is removed, the subscribe loop is torn
+    /// down.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{data::channel, Builder};
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let channel_name: channel::Name = "my-channel".parse().unwrap();
+    /// let stream = pubnub.subscribe(channel_name.clone()).await?;
+    ///
+    /// pubnub.unsubscribe(channel_name).await;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn unsubscribe(&mut self, channel: channel::Name) {
         let supervisor_arc_clone = self.subscribe_loop_supervisor.clone();
         let mut supervisor_guard = supervisor_arc_clone.lock().await;
         supervisor_guard
-            .subscribe(self, pubsub::SubscribeTo::Channel(channel))
+            .unsubscribe(pubsub::SubscribeTo::Channel(channel))
+            .await;
+    }
+
+    /// Shut the client down gracefully.
+    ///
+    /// Stops all the subscribe loops, ending all the subscription streams,
+    /// and announces leaving the channels to the PubNub network. Resolves
+    /// once the loops have stopped and the leave request is done.
+    ///
+    /// The publishes and the other calls in flight are not affected: they
+    /// are driven by their own futures, and complete on their own. The idle
+    /// connections are closed when the last clone of the transport is
+    /// dropped, which is this client if it's the last clone around.
+    ///
+    /// The clones of the client share the subscribe loops, so shutting down
+    /// another clone afterwards is a no-op. A subscribe on another clone
+    /// starts a new subscribe loop, though.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::Builder;
+    ///
+    /// # async {
+    /// let mut pubnub = Builder::with_components(transport, runtime).build();
+    /// let stream = pubnub.subscribe("my-channel".parse().unwrap()).await?;
+    ///
+    /// pubnub.shutdown().await;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn shutdown(self) {
+        let destinations = self.subscribe_loop_supervisor.lock().await.shutdown().await;
+        // Nothing to leave, or no way to.
+        if destinations.is_empty()
+            || !self
+                .transport
+                .capabilities()
+                .contains(Capabilities::PRESENCE)
+        {
+            return;
+        }
+
+        if let Err(err) = self
+            .transport
+            .call(request::Leave {
+                to: destinations,
+                uuid: None,
+            })
             .await
+        {
+            error!("Unable to leave the channels on shutdown: {:?}", err);
+        }
+    }
+
+    /// Get a stream of the subscribe loop status events.
+    ///
+    /// The stream reports when the subscribe loop connects, fails, recovers
+    /// after a failure, and stops. The events are buffered until consumed,
+    /// up to a limit: a stream that isn't read drops its oldest events once
+    /// it's full, so the latest ones are still delivered. Dropping the stream
+    /// doesn't affect the delivery of messages.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::{Builder, StatusEvent};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    /// let mut status_stream = pubnub.status_stream();
+    ///
+    /// while let Some(event) = status_stream.next().await {
+    ///     if let StatusEvent::Error(err) = event {
+    ///         println!("Subscribe failed: {}", err);
+    ///     }
+    /// }
+    /// # };
+    /// ```
+    pub fn status_stream(&self) -> impl Stream<Item = StatusEvent> {
+        self.status_broadcaster.listen()
+    }
+
+    /// The cursor the subscribe loop serving the `channel` continues from,
+    /// i.e. to resume from with [`subscribe_from`](Self::subscribe_from)
+    /// later on.
+    ///
+    /// Returns `None` until the loop polls successfully, and once it stops.
+    /// Channel groups and wildcard subscriptions are looked up by their
+    /// name. If the `channel` is polled by several loops, i.e. for the
+    /// subscriptions with different filters, the most recent of their
+    /// cursors is returned. Reading the cursor doesn't wait for the loop.
+    #[must_use]
+    pub fn current_cursor(&self, channel: &str) -> Option<Cursor> {
+        self.current_cursors.get(channel)
+    }
+
+    /// The [`current_cursor`](Self::current_cursor) of the `channel`, as a
+    /// [`Timetoken`] with the region of the cursor.
+    ///
+    /// It resumes from the same position when passed to
+    /// [`subscribe_from`](Self::subscribe_from).
+    #[must_use]
+    pub fn current_timetoken(&self, channel: &str) -> Option<Timetoken> {
+        self.current_cursor(channel).map(|cursor| Timetoken {
+            t: cursor.timetoken,
+            r: cursor.region,
+        })
+    }
+}
+
+/// The number of the messages to read from the history at a time.
+const REPLAY_PAGE_SIZE: usize = 100;
+
+/// The state of a [`PubNub::replay_then_subscribe`] stream.
+struct ReplayState<TTransport: Transport, TRuntime: Runtime> {
+    pubnub: PubNub<TTransport, TRuntime>,
+    channel: channel::Name,
+    subscription: Subscription<TRuntime>,
+    /// The timetoken the live messages are newer than.
+    subscribed_at: history::Timetoken,
+    /// The live messages that came while replaying, yet to be delivered.
+    live: VecDeque<Arc<Message>>,
+    /// The messages read from the history, yet to be delivered.
+    page: VecDeque<Arc<Message>>,
+    /// Where the next page of the history starts, inclusive.
+    next_page: history::Timetoken,
+    /// The [keys](replay_key) of the replayed messages, oldest first, to
+    /// skip them when they come live too.
+    replayed: VecDeque<(history::Timetoken, u64)>,
+    /// Whether there may be more messages in the history.
+    replaying: bool,
+    /// Whether reading the history failed, ending the stream.
+    failed: bool,
+}
+
+impl<TTransport: HistoryTransport + 'static, TRuntime: Runtime + 'static>
+    ReplayState<TTransport, TRuntime>
+{
+    /// Read the next page of the history, taking the live messages off the
+    /// subscription in the meantime.
+    async fn read_page(&mut self) -> Result<Vec<Message>, <TTransport as Transport>::Error> {
+        let page = {
+            let Self {
+                pubnub,
+                channel,
+                subscription,
+                live,
+                next_page,
+                ..
+            } = self;
+            let page = pubnub.history_page(channel.clone(), *next_page);
+            futures_util::pin_mut!(page);
+            loop {
+                if subscription.is_terminated() {
+                    break page.await;
+                }
+                match select(page.as_mut(), subscription.next()).await {
+                    Either::Left((page, _)) => break page,
+                    Either::Right((Some(message), _)) => live.push_back(message),
+                    Either::Right((None, _)) => {}
+                }
+            }
+        };
+        self.prune_replayed();
+        page
+    }
+
+    /// Take the live messages the subscription has ready.
+    fn drain_live(&mut self) {
+        while let Some(Some(message)) = self.subscription.next().now_or_never() {
+            self.push_live(message);
+        }
+    }
+
+    fn push_live(&mut self, message: Arc<Message>) {
+        self.live.push_back(message);
+        self.prune_replayed();
+    }
+
+    /// Forget the replayed messages that can't come live anymore, i.e. the
+    /// ones older than the live messages, which come in order. The ones the
+    /// next page starts with are kept to skip them there.
+    fn prune_replayed(&mut self) {
+        let live_from = self
+            .live
+            .front()
+            .map_or(self.subscribed_at.saturating_add(1), |message| {
+                message.timetoken.t
+            });
+        let keep_from = live_from.min(self.next_page);
+        while self.replayed.front().map_or(false, |&(t, _)| t < keep_from) {
+            self.replayed.pop_front();
+        }
+    }
+
+    /// Whether the message with the `key` was replayed already.
+    fn was_replayed(&self, key: (history::Timetoken, u64)) -> bool {
+        self.replayed
+            .iter()
+            .rev()
+            .take_while(|&&(t, _)| t >= key.0)
+            .any(|&replayed| replayed == key)
+    }
+}
+
+/// Tell the replayed messages apart by the timetoken and the payload, as
+/// the history timetokens carry no region. The channel is the same for all
+/// of them.
+fn replay_key(message: &Message) -> (history::Timetoken, u64) {
+    let mut hasher = DefaultHasher::new();
+    message.json.dump().hash(&mut hasher);
+    (message.timetoken.t, hasher.finish())
+}
+
+/// The state of a [`PubNub::resilient_subscribe`] stream.
+struct ResilientState<TRuntime: Runtime, F> {
+    channel: channel::Name,
+    reconnect: F,
+    subscription: Option<Subscription<TRuntime>>,
+    /// The subscribe loops of the client subscribed last.
+    subscribe_loop_supervisor: Arc<Mutex<SubscribeLoopSupervisor>>,
+    /// The number of the times the subscriptions to the channel were ended
+    /// on request, as of subscribing to it last.
+    explicit_ends: usize,
+    /// The cursors of the loops of the client subscribed last.
+    current_cursors: CurrentCursors,
+    /// The time of the last delivered message, in the region of the loop.
+    last: Option<Cursor>,
+}
+
+/// The stream of a [`PubNub::subscribe_all`].
+///
+/// The fields are dropped in order, so the subscriptions are gone by the
+/// time the leave is issued.
+struct MergedSubscription<TTransport: Transport + 'static, TRuntime: Runtime> {
+    subscriptions: SelectAll<Subscription<TRuntime>>,
+    /// Only there to be dropped.
+    _leave: LeaveOnDrop<TTransport, TRuntime>,
+}
+
+// None of the fields are pinned.
+impl<TTransport: Transport + 'static, TRuntime: Runtime> Unpin
+    for MergedSubscription<TTransport, TRuntime>
+{
+}
+
+impl<TTransport: Transport + 'static, TRuntime: Runtime> Stream
+    for MergedSubscription<TTransport, TRuntime>
+{
+    type Item = Arc<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Stream::poll_next(Pin::new(&mut self.get_mut().subscriptions), cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.subscriptions.size_hint()
+    }
+}
+
+/// Leaves the destinations the dropped subscriptions were the last
+/// listeners of.
+struct LeaveOnDrop<TTransport: Transport + 'static, TRuntime: Runtime> {
+    transport: Option<TTransport>,
+    runtime: TRuntime,
+    /// Resolve to the destinations to leave. The ones that still have
+    /// listeners, or whose subscribe loop is gone, are cancelled instead.
+    left: Vec<oneshot::Receiver<pubsub::SubscribeTo>>,
+}
+
+impl<TTransport: Transport + 'static, TRuntime: Runtime> Drop
+    for LeaveOnDrop<TTransport, TRuntime>
+{
+    fn drop(&mut self) {
+        let transport = match self.transport.take() {
+            Some(transport) => transport,
+            None => return,
+        };
+        let left = std::mem::take(&mut self.left);
+
+        self.runtime.spawn(async move {
+            let to: Vec<_> = join_all(left)
+                .await
+                .into_iter()
+                .filter_map(Result::ok)
+                .collect();
+            // Nothing to leave, or no way to.
+            if to.is_empty() || !transport.capabilities().contains(Capabilities::PRESENCE) {
+                return;
+            }
+
+            if let Err(err) = transport.call(request::Leave { to, uuid: None }).await {
+                error!("Unable to leave the channels: {:?}", err);
+            }
+        });
     }
 }
@@ -1,25 +1,50 @@
 #![cfg(feature = "mock")]
 
 use crate::builder::Builder;
+use crate::data::app_context::UuidMetadata;
+use crate::data::cursor::Cursor;
+use crate::data::file::{File, FileInfo, FilesPage, SentFile, UploadForm};
+use crate::data::filter::FilterExpression;
+use crate::data::push::PushType;
 use crate::data::timetoken::Timetoken;
 use crate::mock::runtime::MockRuntime;
 use crate::mock::transport::{MockTransport, MockTransportError};
+use crate::pubnub::{Aborted, PubNub, RetryPolicy, SendFileError};
+#[cfg(feature = "broadcast")]
+use crate::subscription::Lagged;
+use crate::subscription::StatusEvent;
+use crate::transport::{Capabilities, Endpoint};
 use futures_channel::{mpsc, oneshot};
-use futures_executor::{block_on, LocalPool};
-use futures_util::stream::StreamExt;
+use futures_executor::{block_on, LocalPool, LocalSpawner};
+use futures_util::future::{pending, ready, select, Either, FutureExt};
+use futures_util::lock::Mutex;
+use futures_util::stream::{FusedStream, StreamExt};
 use futures_util::task::{LocalSpawnExt, SpawnExt};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 use mockall::predicate::eq;
 use mockall::Sequence;
 
-use crate::data::message::{self, Message};
-use crate::data::{channel, pubsub, request, response};
+use crate::data::message::{self, DecodeError, Message};
+use crate::data::{channel, history, pam, presence, pubsub, request, response};
 use crate::json::object;
 
 fn init() {
     pubnub_test_util::init_log();
 }
 
+/// A successful publish response.
+fn sent(timetoken: Timetoken) -> response::Publish {
+    pubsub::PublishResponse {
+        status: 200,
+        message: "Sent".to_owned(),
+        timetoken,
+    }
+}
+
 #[test]
 fn mocked_pubnub_publish_ok() {
     init();
@@ -37,8 +62,11 @@ fn mocked_pubnub_publish_ok() {
                 channel: "test_channel".parse().unwrap(),
                 payload: message.clone(),
                 meta: None,
+                timetoken_override: None,
+                replicate: true,
+                method: pubsub::PublishMethod::Get,
             }))
-            .returning(|_| Box::pin(async { Ok(Timetoken { t: 123, r: 456 }) }));
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 456 })) }));
 
         let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
@@ -52,286 +80,4948 @@ fn mocked_pubnub_publish_ok() {
 }
 
 #[test]
-fn mocked_pubnub_subscribe_ok() {
+fn mocked_pubnub_connect_fetches_time() {
     init();
-    let mut pool = LocalPool::new();
-    let spawner = pool.spawner();
-    let spawner1 = spawner.clone();
-    let spawner2 = spawner.clone();
-    spawner
-        .spawn_local(async {
-            // Setup.
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
 
-            let test_channel: channel::Name = "test_channel".parse().unwrap();
+        mock_transport
+            .expect_call::<request::Time, response::Time>()
+            .with(eq(request::Time))
+            .times(3)
+            .returning(|_| Box::pin(async { Ok(Timetoken { t: 123, r: 0 }) }));
 
-            let (sub_drop_req_tx, sub_drop_req_rx) = oneshot::channel::<()>();
-            let (sub_drop_done_tx, sub_drop_done_rx) = oneshot::channel::<()>();
-            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
-            let messages = vec![Message {
-                message_type: message::Type::Publish,
-                route: None,
-                channel: test_channel.clone(),
-                json: object! {
-                    "test" => "value",
-                },
-                timetoken: Timetoken { t: 100, r: 12 },
-                client: None,
-                subscribe_key: "test_subscribe_key".to_owned(),
-                flags: 514,
-                ..Message::default()
-            }];
+        // Idempotent.
+        pubnub.connect().await.expect("unexpected failure");
+        pubnub.connect().await.expect("unexpected failure");
+        assert_eq!(
+            pubnub.time().await.expect("unexpected failure"),
+            Timetoken { t: 123, r: 0 }
+        );
+    })
+}
 
-            let mut seq = Sequence::new();
+fn upload_form() -> UploadForm {
+    UploadForm {
+        file: File {
+            id: "file-id".to_owned(),
+            name: "cat.jpg".to_owned(),
+        },
+        url: "https://files.example.com/upload".to_owned(),
+        method: "POST".to_owned(),
+        fields: vec![("key".to_owned(), "file-id/cat.jpg".to_owned())],
+    }
+}
 
-            let mock_transport = {
-                let mut mock = MockTransport::new();
+#[test]
+fn mocked_pubnub_send_file() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let channel: channel::Name = "test_channel".parse().unwrap();
+        let mut seq = Sequence::new();
 
-                let test_channel = test_channel.clone();
-                mock.expect_clone()
-                    .times(1)
-                    .in_sequence(&mut seq)
-                    .return_once(move || {
-                        let mut mock = MockTransport::new();
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        mock_transport
+            .expect_call::<request::GenerateFileUploadUrl, response::GenerateFileUploadUrl>()
+            .with(eq(request::GenerateFileUploadUrl {
+                channel: channel.clone(),
+                file_name: "cat.jpg".to_owned(),
+            }))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(upload_form()) }));
+        mock_transport
+            .expect_call::<request::UploadFile, response::UploadFile>()
+            .with(eq(request::UploadFile {
+                form: upload_form(),
+                data: b"meow".to_vec(),
+            }))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        mock_transport
+            .expect_call::<request::PublishFileMessage, response::PublishFileMessage>()
+            .with(eq(request::PublishFileMessage {
+                channel: channel.clone(),
+                file: upload_form().file,
+                message: Some(object! { "caption" => "My cat" }),
+            }))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(Timetoken { t: 123, r: 0 }) }));
 
-                        mock.expect_call::<request::Subscribe, response::Subscribe>()
-                            .times(1)
-                            .in_sequence(&mut seq)
-                            .with(eq(request::Subscribe {
-                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                                timetoken: Timetoken::default(),
-                                heartbeat: None,
-                            }))
-                            .return_once(move |_| {
-                                Box::pin(async move {
-                                    Ok((messages.clone(), Timetoken { t: 150, r: 1 }))
-                                })
-                            });
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
-                        mock.expect_call::<request::Subscribe, response::Subscribe>()
-                            .times(1)
-                            .in_sequence(&mut seq)
-                            .with(eq(request::Subscribe {
-                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                                timetoken: Timetoken { t: 150, r: 1 },
-                                heartbeat: None,
-                            }))
-                            .return_once(move |_| {
-                                Box::pin(async move {
-                                    // Request drop.
-                                    sub_drop_req_tx.send(()).unwrap();
+        let sent = pubnub
+            .send_file(
+                channel,
+                "cat.jpg".to_owned(),
+                b"meow".to_vec(),
+                Some(object! { "caption" => "My cat" }),
+            )
+            .await
+            .expect("unexpected failure");
+        assert_eq!(
+            sent,
+            SentFile {
+                file: upload_form().file,
+                timetoken: Timetoken { t: 123, r: 0 },
+            }
+        );
+    })
+}
 
-                                    // Wait for the drop to complete.
-                                    sub_drop_done_rx.await.unwrap();
-                                    unreachable!();
-                                })
-                            });
+#[test]
+fn mocked_pubnub_send_file_reports_the_failed_stage() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let channel: channel::Name = "test_channel".parse().unwrap();
 
-                        mock
-                    });
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        let mut generated = 0;
+        mock_transport
+            .expect_call::<request::GenerateFileUploadUrl, response::GenerateFileUploadUrl>()
+            .times(3)
+            .returning(move |_| {
+                generated += 1;
+                if generated == 1 {
+                    Box::pin(async { Err(MockTransportError::default()) })
+                } else {
+                    Box::pin(async { Ok(upload_form()) })
+                }
+            });
+        let mut uploaded = 0;
+        mock_transport
+            .expect_call::<request::UploadFile, response::UploadFile>()
+            .times(2)
+            .returning(move |_| {
+                uploaded += 1;
+                if uploaded == 1 {
+                    Box::pin(async { Err(MockTransportError::default()) })
+                } else {
+                    Box::pin(async { Ok(()) })
+                }
+            });
+        mock_transport
+            .expect_call::<request::PublishFileMessage, response::PublishFileMessage>()
+            .times(1)
+            .returning(|_| Box::pin(async { Err(MockTransportError::default()) }));
 
-                mock
-            };
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+        let send = || pubnub.send_file(channel.clone(), "cat.jpg".to_owned(), vec![], None);
 
-            let mock_runtime = {
-                let mut mock = MockRuntime::new();
-                mock.expect_mock_workaround_spawn::<()>()
-                    .returning_st(move |future| {
-                        spawner1.spawn(future).unwrap();
-                    });
-                mock.expect_clone().times(1).return_once_st(move || {
-                    // We got cloned, that has to be subscription's runtime
-                    // clone.
-                    let mut mock = MockRuntime::new();
+        match send().await {
+            Err(SendFileError::GenerateUploadUrl(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match send().await {
+            Err(SendFileError::Upload(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // The uploaded file is reported, for the publish to be retried.
+        match send().await {
+            Err(SendFileError::Publish { file, .. }) => assert_eq!(file, upload_form().file),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    })
+}
 
-                    mock.expect_mock_workaround_spawn::<()>()
-                        .returning_st(move |future| {
-                            spawner2.spawn(future).unwrap();
-                        });
+#[test]
+fn mocked_pubnub_list_files_follows_pages() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let channel: channel::Name = "test_channel".parse().unwrap();
+        let file = |id: &str| FileInfo {
+            id: id.to_owned(),
+            name: "cat.jpg".to_owned(),
+            size: 4,
+            created: "2020-07-26T13:42:06Z".to_owned(),
+        };
+        let mut seq = Sequence::new();
 
-                    mock
-                });
-                mock
-            };
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        mock_transport
+            .expect_call::<request::ListFiles, response::ListFiles>()
+            .with(eq(request::ListFiles {
+                channel: channel.clone(),
+                next: None,
+            }))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_| {
+                Box::pin(async move {
+                    Ok(FilesPage {
+                        files: vec![file("1"), file("2")],
+                        next: Some("page-2".to_owned()),
+                    })
+                })
+            });
+        mock_transport
+            .expect_call::<request::ListFiles, response::ListFiles>()
+            .with(eq(request::ListFiles {
+                channel: channel.clone(),
+                next: Some("page-2".to_owned()),
+            }))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_| {
+                Box::pin(async move {
+                    Ok(FilesPage {
+                        files: vec![file("3")],
+                        next: None,
+                    })
+                })
+            });
 
-            // Invocations.
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
-            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
-                .subscribe_loop_exit_tx(sub_loop_exit_tx)
-                .build();
+        let files = pubnub
+            .list_files(&channel)
+            .await
+            .expect("unexpected failure");
+        assert_eq!(files, vec![file("1"), file("2"), file("3")]);
+    })
+}
 
-            let mut subscription = pubnub.subscribe(test_channel.clone()).await;
+#[test]
+fn mocked_pubnub_download_file_collects_the_chunks() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
 
-            let message = subscription.next().await;
-            // We got the message we expected to get.
-            assert!(message.is_some());
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        mock_transport
+            .expect_call::<request::DownloadFile, response::DownloadFile<MockTransportError>>()
+            .with(eq(request::DownloadFile {
+                channel: "test_channel".parse().unwrap(),
+                file: File {
+                    id: "0-1".to_owned(),
+                    name: "cat.jpg".to_owned(),
+                },
+            }))
+            .returning(|_| {
+                let chunks = vec![Ok(b"me".to_vec()), Ok(b"ow".to_vec())];
+                Box::pin(async move { Ok(futures_util::stream::iter(chunks).boxed()) })
+            });
 
-            // Wait for the drop request.
-            sub_drop_req_rx.await.unwrap();
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
-            // Drop the subscription, which will cause loop termination.
-            drop(subscription);
+        let data = pubnub
+            .download_file(
+                "test_channel".parse().unwrap(),
+                "0-1".to_owned(),
+                "cat.jpg".to_owned(),
+            )
+            .await
+            .expect("unexpected failure");
+        assert_eq!(data, b"meow");
+    })
+}
 
-            // Wait for the loop termination.
-            sub_loop_exit_rx.next().await.unwrap();
+#[test]
+fn mocked_pubnub_global_here_now() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let channel: channel::Name = "test_channel".parse().unwrap();
 
-            // Notify that we've completed with the drop request.
-            // Since the loop is now dead, and we were locked on `sub_drop_done_rx`
-            // in the response future, this send *has to fail* send error, cause
-            // loop termination dropped the response future and the
-            // `sub_drop_done_rx` with it (cuase response future owned
-            // `sub_drop_done_rx` afetr we moved it).
-            sub_drop_done_tx.send(()).unwrap_err();
-        })
-        .unwrap();
+        let mut channels = HashMap::new();
+        channels.insert(
+            channel.clone(),
+            presence::ChannelInfoWithOccupants {
+                occupancy: 1,
+                occupants: vec!["test_uuid".into()],
+            },
+        );
+        let info = presence::GlobalInfo {
+            total_channels: 1,
+            total_occupancy: 1,
+            channels,
+        };
 
-    pool.run()
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        mock_transport
+            .expect_call::<
+                request::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>,
+                response::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>,
+            >()
+            .times(1)
+            .returning({
+                let info = info.clone();
+                move |_| {
+                    let info = info.clone();
+                    Box::pin(async move { Ok(info) })
+                }
+            });
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        let here_now = pubnub
+            .global_here_now::<presence::respond_with::OccupancyAndUUIDs>()
+            .await
+            .expect("unexpected failure");
+        assert_eq!(here_now, info);
+    })
 }
 
-#[allow(clippy::too_many_lines)]
 #[test]
-fn mocked_pubnub_subscribe_trasport_error_does_not_stall_loop() {
+fn mocked_pubnub_grant_and_revoke_token() {
     init();
-    let mut pool = LocalPool::new();
-    let spawner = pool.spawner();
-    let spawner1 = spawner.clone();
-    let spawner2 = spawner.clone();
-    spawner
-        .spawn_local(async {
-            // Setup.
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let mut resources = pam::Resources::default();
+        resources.uuids.insert(
+            "my-uuid".to_owned(),
+            pam::BitMask::GET | pam::BitMask::UPDATE,
+        );
+        let grant = request::Grant {
+            ttl: 60,
+            permissions: pam::Permissions {
+                resources,
+                patterns: pam::Patterns::default(),
+                meta: object! {},
+            },
+        };
+        let mut seq = Sequence::new();
 
-            let test_channel: channel::Name = "test_channel".parse().unwrap();
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        mock_transport
+            .expect_call::<request::Grant, response::Grant>()
+            .with(eq(grant.clone()))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok("my-token".to_owned()) }));
+        mock_transport
+            .expect_call::<request::RevokeToken, response::RevokeToken>()
+            .with(eq(request::RevokeToken {
+                token: "my-token".to_owned(),
+            }))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(()) }));
 
-            let (sub_drop_req_tx, sub_drop_req_rx) = oneshot::channel::<()>();
-            let (sub_drop_done_tx, sub_drop_done_rx) = oneshot::channel::<()>();
-            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
-            let messages = vec![Message {
-                message_type: message::Type::Publish,
-                route: None,
-                channel: test_channel.clone(),
-                json: object! {
-                    "test" => "value",
-                },
-                timetoken: Timetoken { t: 100, r: 12 },
-                client: None,
-                subscribe_key: "test_subscribe_key".to_owned(),
-                flags: 514,
-                ..Message::default()
-            }];
+        let token = pubnub.grant_token(grant).await.unwrap();
+        assert_eq!(token, "my-token");
+        pubnub.revoke_token(&token).await.unwrap();
+    })
+}
 
-            let mut seq = Sequence::new();
+#[test]
+fn mocked_pubnub_with_timeout_aborts_call() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mut mock_runtime = MockRuntime::new();
 
-            let mock_transport = {
-                let mut mock = MockTransport::new();
+        // Dropping the call future drops the sender.
+        let (dropped_tx, dropped_rx) = oneshot::channel::<()>();
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .times(1)
+            .return_once(move |_| {
+                Box::pin(async move {
+                    let _dropped_tx = dropped_tx;
+                    pending().await
+                })
+            });
+        mock_runtime
+            .expect_mock_delay()
+            .with(eq(Duration::from_secs(5)))
+            .times(1)
+            .returning(|_| Box::pin(ready(())));
 
-                let test_channel = test_channel.clone();
-                mock.expect_clone()
-                    .times(1)
-                    .in_sequence(&mut seq)
-                    .return_once(move || {
-                        let mut mock = MockTransport::new();
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
 
-                        mock.expect_call::<request::Subscribe, response::Subscribe>()
-                            .times(1)
-                            .in_sequence(&mut seq)
-                            .with(eq(request::Subscribe {
-                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                                timetoken: Timetoken::default(),
-                                heartbeat: None,
-                            }))
-                            .return_once(move |_| {
-                                Box::pin(async move {
-                                    Ok((messages.clone(), Timetoken { t: 150, r: 1 }))
-                                })
-                            });
+        let publish = pubnub.publish("test_channel".parse().unwrap(), object! {});
+        let result = pubnub.with_timeout(Duration::from_secs(5), publish).await;
+        assert_eq!(result.unwrap_err(), Aborted::DeadlineExceeded);
+        assert!(dropped_rx.await.is_err());
+    })
+}
 
-                        mock.expect_call::<request::Subscribe, response::Subscribe>()
-                            .times(1)
-                            .in_sequence(&mut seq)
+#[test]
+fn mocked_pubnub_with_cancellation() {
+    init();
+    block_on(async {
+        let pubnub = Builder::with_components(MockTransport::new(), MockRuntime::new()).build();
+
+        let result = pubnub.with_cancellation(ready(42), pending::<()>()).await;
+        assert_eq!(result, Ok(42));
+
+        let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+        cancel_tx.send(()).unwrap();
+        let result = pubnub.with_cancellation(pending::<()>(), cancel_rx).await;
+        assert_eq!(result, Err(Aborted::Cancelled));
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_raw_ok() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 0 })) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        let response = pubnub
+            .publish_raw(
+                "test_channel".parse().unwrap(),
+                object! { "test" => "value" },
+            )
+            .await
+            .expect("unexpected failure");
+        assert_eq!(response, sent(Timetoken { t: 123, r: 0 }));
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_via_post() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        let message = object! {
+            "test" => "value",
+        };
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .with(eq(request::Publish {
+                channel: "test_channel".parse().unwrap(),
+                payload: message.clone(),
+                meta: None,
+                timetoken_override: None,
+                replicate: true,
+                method: pubsub::PublishMethod::Post,
+            }))
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 0 })) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        let timetoken = pubnub
+            .publish_via_post("test_channel".parse().unwrap(), message)
+            .await
+            .expect("unexpected failure");
+        assert_eq!(timetoken.t, 123);
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_with_options() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        let message = object! {
+            "test" => "value",
+        };
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .with(eq(request::Publish {
+                channel: "test_channel".parse().unwrap(),
+                payload: message.clone(),
+                meta: Some(object! { "uuid" => "JoeBob" }),
+                timetoken_override: Some(15_000_000_000_000_000),
+                replicate: false,
+                method: pubsub::PublishMethod::Post,
+            }))
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 0 })) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        // The options combine, rather than each picking a variant.
+        let options = pubsub::PublishOptions {
+            meta: Some(object! { "uuid" => "JoeBob" }),
+            method: Some(pubsub::PublishMethod::Post),
+            timetoken_override: Some(15_000_000_000_000_000),
+            replicate: false,
+        };
+        let timetoken = pubnub
+            .publish_with_options("test_channel".parse().unwrap(), message, options)
+            .await
+            .expect("unexpected failure");
+        assert_eq!(timetoken.t, 123);
+    })
+}
+
+/// A runtime recording the delays, which complete right away, and so do the
+/// delays of its clones.
+fn recording_delays_runtime(delays: Arc<std::sync::Mutex<Vec<Duration>>>) -> MockRuntime {
+    let mut mock_runtime = MockRuntime::new();
+    let recorded = Arc::clone(&delays);
+    mock_runtime.expect_mock_delay().returning(move |duration| {
+        recorded.lock().unwrap().push(duration);
+        Box::pin(ready(()))
+    });
+    mock_runtime
+        .expect_clone()
+        .returning(move || recording_delays_runtime(Arc::clone(&delays)));
+    mock_runtime
+}
+
+#[test]
+fn mocked_pubnub_publish_rate_limit_paces_clones() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let delays = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mock_runtime = recording_delays_runtime(Arc::clone(&delays));
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .times(2)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 0 })) }));
+        mock_transport.expect_clone().times(1).return_once(|| {
+            let mut mock = MockTransport::new();
+            mock.expect_call::<request::Publish, response::Publish>()
+                .times(1)
+                .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 456, r: 0 })) }));
+            mock
+        });
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime)
+            .publish_rate_limit(2)
+            .build();
+        let clone = pubnub.clone();
+
+        // The burst goes out right away.
+        pubnub
+            .publish("test_channel".parse().unwrap(), object! {})
+            .await
+            .unwrap();
+        pubnub
+            .publish("test_channel".parse().unwrap(), object! {})
+            .await
+            .unwrap();
+        assert!(delays.lock().unwrap().is_empty());
+
+        // The clone shares the limit.
+        clone
+            .publish("test_channel".parse().unwrap(), object! {})
+            .await
+            .unwrap();
+        let delays = delays.lock().unwrap();
+        assert_eq!(delays.len(), 1);
+        assert!(delays[0] > Duration::from_millis(400) && delays[0] <= Duration::from_millis(500));
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_rate_limited_backs_off() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let delays = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mock_runtime = recording_delays_runtime(Arc::clone(&delays));
+        let mut seq = Sequence::new();
+        let rate_limited = || {
+            Box::pin(async {
+                Err(MockTransportError {
+                    rate_limited: true,
+                    retry_after: Some(Duration::from_secs(3)),
+                    ..MockTransportError::default()
+                })
+            })
+        };
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(move |_| rate_limited());
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 0 })) }));
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .times(3)
+            .in_sequence(&mut seq)
+            .returning(move |_| rate_limited());
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime)
+            .publish_rate_limit(100)
+            .build();
+
+        // Well within the limit, but the server rejects the publish anyway,
+        // asking to retry after a while.
+        pubnub
+            .publish("test_channel".parse().unwrap(), object! {})
+            .await
+            .unwrap();
+        {
+            let delays = delays.lock().unwrap();
+            assert_eq!(delays.len(), 1);
+            assert!(delays[0] > Duration::from_millis(2900) && delays[0] <= Duration::from_secs(3));
+        }
+
+        // Still held back, and given up after a couple of retries. The
+        // delays are recorded without passing, so every attempt waits.
+        let err = pubnub
+            .publish("test_channel".parse().unwrap(), object! {})
+            .await
+            .unwrap_err();
+        assert!(err.rate_limited);
+        assert_eq!(delays.lock().unwrap().len(), 4);
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_retries_transient_errors() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let delays = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mock_runtime = recording_delays_runtime(Arc::clone(&delays));
+        let mut seq = Sequence::new();
+        let transient = || {
+            Box::pin(async {
+                Err(MockTransportError {
+                    transient: true,
+                    ..MockTransportError::default()
+                })
+            })
+        };
+
+        // The retries resend the same timetoken override.
+        let ptto = |request: &request::Publish| request.timetoken_override == Some(15_000);
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(ptto)
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(move |_| transient());
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(ptto)
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 15_000, r: 0 })) }));
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Err(MockTransportError::default()) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime)
+            .publish_retry(RetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(1),
+            })
+            .build();
+
+        let options = pubsub::PublishOptions {
+            timetoken_override: Some(15_000),
+            ..pubsub::PublishOptions::default()
+        };
+        let timetoken = pubnub
+            .publish_with_options("test_channel".parse().unwrap(), object! {}, options)
+            .await
+            .unwrap();
+        assert_eq!(timetoken.t, 15_000);
+        assert_eq!(
+            *delays.lock().unwrap(),
+            [Duration::from_millis(100), Duration::from_millis(200)]
+        );
+
+        // The other errors aren't retried.
+        pubnub
+            .publish("test_channel".parse().unwrap(), object! {})
+            .await
+            .unwrap_err();
+        assert_eq!(delays.lock().unwrap().len(), 2);
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_switches_to_post_for_large_messages() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let mut seq = Sequence::new();
+
+        let small = object! { "text" => "hi" };
+        let large = object! { "text" => "x".repeat(64) };
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(|request| request.method == pubsub::PublishMethod::Get)
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 100, r: 0 })) }));
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(|request| request.method == pubsub::PublishMethod::Post)
+            .times(3)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 200, r: 0 })) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime)
+            .publish_post_threshold(32)
+            .build();
+
+        let channel: channel::Name = "test_channel".parse().unwrap();
+        pubnub
+            .publish(channel.clone(), small.clone())
+            .await
+            .unwrap();
+        pubnub.publish(channel.clone(), large).await.unwrap();
+        // Only fits unencoded, the spaces take three bytes each in the URL.
+        pubnub
+            .publish(channel.clone(), object! { "t" => " ".repeat(8) })
+            .await
+            .unwrap();
+        // The metadata counts towards the size too.
+        pubnub
+            .publish_with_metadata(channel, small, object! { "pad" => "x".repeat(32) })
+            .await
+            .unwrap();
+
+        #[cfg(feature = "metrics")]
+        {
+            let metrics = pubnub.metrics();
+            assert_eq!(metrics.messages_published, 4);
+            assert_eq!(metrics.messages_published_via_post, 3);
+        }
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_with_seqn_sends_it_in_metadata() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        let message = object! { "text" => "hi" };
+        let expected_payload = message.clone();
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(move |request| {
+                request.payload == expected_payload && request.meta == Some(object! { "seqn" => 7 })
+            })
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 100, r: 0 })) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+        pubnub
+            .publish_with_seqn("test_channel".parse().unwrap(), message.clone(), 7)
+            .await
+            .unwrap();
+
+        // The consumers read it back from the metadata.
+        let received = Message {
+            json: message,
+            metadata: object! { "seqn" => 7 },
+            ..Message::default()
+        };
+        assert_eq!(received.sequence(), Some(7));
+        assert_eq!(Message::default().sequence(), None);
+    })
+}
+
+#[test]
+fn mocked_pubnub_publish_many_stops_at_first_failure() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+        let mut seq = Sequence::new();
+
+        let channel: channel::Name = "test_channel".parse().unwrap();
+        let message = |n: u64| {
+            object! {
+                "n" => n,
+            }
+        };
+
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(move |request| request.payload == message(1))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 100, r: 1 })) }));
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(move |request| request.payload == message(2))
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_| Box::pin(async { Err(MockTransportError::default()) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        let results = pubnub
+            .publish_many(vec![
+                (channel.clone(), message(1)),
+                (channel.clone(), message(2)),
+                (channel, message(3)),
+            ])
+            .await;
+        assert_eq!(results.len(), 2);
+        assert_eq!(*results[0].as_ref().unwrap(), Timetoken { t: 100, r: 1 });
+        assert!(results[1].is_err());
+    })
+}
+
+#[test]
+fn mocked_pubnub_set_uuid_metadata_sends_etag() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        let mut metadata = UuidMetadata::new("test_uuid");
+        metadata.custom = Some(object! {
+            "visits" => 10,
+        });
+        metadata.etag = Some("AbCdEf".to_owned());
+
+        let updated = UuidMetadata {
+            etag: Some("GhIjKl".to_owned()),
+            ..metadata.clone()
+        };
+
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(Capabilities::all);
+        mock_transport
+            .expect_call::<request::SetUuidMetadata, response::SetUuidMetadata>()
+            .with(eq(request::SetUuidMetadata {
+                metadata: metadata.clone(),
+                if_match: Some("AbCdEf".to_owned()),
+            }))
+            .return_once(move |_| Box::pin(async move { Ok(updated) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        let result = pubnub
+            .set_uuid_metadata(metadata)
+            .await
+            .expect("unexpected failure");
+        assert_eq!(result.etag, Some("GhIjKl".to_owned()));
+    })
+}
+
+#[test]
+fn mocked_pubnub_unsupported_endpoint_is_an_error() {
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        // The transport implements the push services, but doesn't support
+        // them, so they're never called.
+        mock_transport
+            .expect_mock_capabilities()
+            .returning(|| Capabilities::all() - Capabilities::PUSH);
+        mock_transport
+            .expect_call::<request::ListPushChannels, response::ListPushChannels>()
+            .never();
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime).build();
+
+        let error = pubnub
+            .list_push_channels("token".to_owned(), PushType::Gcm)
+            .await
+            .unwrap_err();
+        assert_eq!(error.unsupported, Some(Endpoint::Push));
+    })
+}
+
+#[test]
+fn mocked_pubnub_subscribe_ok() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let spawner1 = spawner.clone();
+    let spawner2 = spawner.clone();
+    spawner
+        .spawn_local(async {
+            // Setup.
+
+            let test_channel: channel::Name = "test_channel".parse().unwrap();
+
+            let (sub_drop_req_tx, sub_drop_req_rx) = oneshot::channel::<()>();
+            let (sub_drop_done_tx, sub_drop_done_rx) = oneshot::channel::<()>();
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            let messages = vec![Message {
+                message_type: message::Type::Publish,
+                route: None,
+                channel: test_channel.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t: 100, r: 12 },
+                client: None,
+                subscribe_key: "test_subscribe_key".to_owned(),
+                flags: 514,
+                ..Message::default()
+            }];
+
+            let mut seq = Sequence::new();
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                let test_channel = test_channel.clone();
+                mock.expect_clone()
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_once(move || {
+                        let mut mock = MockTransport::new();
+
+                        mock.expect_call::<request::Subscribe, response::Subscribe>()
+                            .times(1)
+                            .in_sequence(&mut seq)
+                            .with(eq(request::Subscribe {
+                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
+                                cursor: Cursor::default(),
+                                heartbeat: None,
+                                filter_expr: None,
+                            }))
+                            .return_once(move |_| {
+                                Box::pin(async move { Ok((messages.clone(), Cursor::new(150, 1))) })
+                            });
+
+                        mock.expect_call::<request::Subscribe, response::Subscribe>()
+                            .times(1)
+                            .in_sequence(&mut seq)
                             .with(eq(request::Subscribe {
                                 to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                                timetoken: Timetoken { t: 150, r: 1 },
+                                cursor: Cursor::new(150, 1),
                                 heartbeat: None,
+                                filter_expr: None,
                             }))
-                            .return_once(move |_| Box::pin(async move { Err(MockTransportError) }));
+                            .return_once(move |_| {
+                                Box::pin(async move {
+                                    // Request drop.
+                                    sub_drop_req_tx.send(()).unwrap();
+
+                                    // Wait for the drop to complete.
+                                    sub_drop_done_rx.await.unwrap();
+                                    unreachable!();
+                                })
+                            });
+
+                        mock
+                    });
+
+                mock
+            };
+
+            let mock_runtime = {
+                let mut mock = MockRuntime::new();
+                mock.expect_mock_workaround_spawn::<()>()
+                    .returning_st(move |future| {
+                        spawner1.spawn(future).unwrap();
+                    });
+                mock.expect_mock_delay()
+                    .returning(|_| Box::pin(pending::<()>()));
+                // Cloned for the subscribe loop and for the subscription.
+                mock.expect_clone().times(2).returning_st(move || {
+                    let mut mock = MockRuntime::new();
+
+                    let spawner2 = spawner2.clone();
+                    mock.expect_mock_workaround_spawn::<()>()
+                        .returning_st(move |future| {
+                            spawner2.spawn(future).unwrap();
+                        });
+
+                    mock
+                });
+                mock
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut subscription = pubnub.subscribe(test_channel.clone()).await.unwrap();
+
+            let message = subscription.next().await;
+            // We got the message we expected to get.
+            assert!(message.is_some());
+
+            // Wait for the drop request.
+            sub_drop_req_rx.await.unwrap();
+
+            // Drop the subscription, which will cause loop termination.
+            drop(subscription);
+
+            // Wait for the loop termination.
+            sub_loop_exit_rx.next().await.unwrap();
+
+            // Notify that we've completed with the drop request.
+            // Since the loop is now dead, and we were locked on `sub_drop_done_rx`
+            // in the response future, this send *has to fail* send error, cause
+            // loop termination dropped the response future and the
+            // `sub_drop_done_rx` with it (cuase response future owned
+            // `sub_drop_done_rx` afetr we moved it).
+            sub_drop_done_tx.send(()).unwrap_err();
+        })
+        .unwrap();
+
+    pool.run()
+}
+
+#[allow(clippy::too_many_lines)]
+#[test]
+fn mocked_pubnub_subscribe_trasport_error_does_not_stall_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let spawner1 = spawner.clone();
+    let spawner2 = spawner.clone();
+    spawner
+        .spawn_local(async {
+            // Setup.
+
+            let test_channel: channel::Name = "test_channel".parse().unwrap();
+
+            let (sub_drop_req_tx, sub_drop_req_rx) = oneshot::channel::<()>();
+            let (sub_drop_done_tx, sub_drop_done_rx) = oneshot::channel::<()>();
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            let messages = vec![Message {
+                message_type: message::Type::Publish,
+                route: None,
+                channel: test_channel.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t: 100, r: 12 },
+                client: None,
+                subscribe_key: "test_subscribe_key".to_owned(),
+                flags: 514,
+                ..Message::default()
+            }];
+
+            let mut seq = Sequence::new();
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                let test_channel = test_channel.clone();
+                mock.expect_clone()
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_once(move || {
+                        let mut mock = MockTransport::new();
+
+                        mock.expect_call::<request::Subscribe, response::Subscribe>()
+                            .times(1)
+                            .in_sequence(&mut seq)
+                            .with(eq(request::Subscribe {
+                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
+                                cursor: Cursor::default(),
+                                heartbeat: None,
+                                filter_expr: None,
+                            }))
+                            .return_once(move |_| {
+                                Box::pin(async move { Ok((messages.clone(), Cursor::new(150, 1))) })
+                            });
+
+                        mock.expect_call::<request::Subscribe, response::Subscribe>()
+                            .times(1)
+                            .in_sequence(&mut seq)
+                            .with(eq(request::Subscribe {
+                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
+                                cursor: Cursor::new(150, 1),
+                                heartbeat: None,
+                                filter_expr: None,
+                            }))
+                            .return_once(move |_| {
+                                Box::pin(async move { Err(MockTransportError::default()) })
+                            });
+
+                        mock.expect_call::<request::Subscribe, response::Subscribe>()
+                            .times(1)
+                            .in_sequence(&mut seq)
+                            .with(eq(request::Subscribe {
+                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
+                                cursor: Cursor::new(150, 1),
+                                heartbeat: None,
+                                filter_expr: None,
+                            }))
+                            .return_once(move |_| {
+                                Box::pin(async move {
+                                    // Request drop.
+                                    sub_drop_req_tx.send(()).unwrap();
+
+                                    // Wait for the drop to complete.
+                                    sub_drop_done_rx.await.unwrap();
+                                    unreachable!();
+                                })
+                            });
+
+                        mock
+                    });
+
+                mock
+            };
+
+            let mock_runtime = {
+                let mut mock = MockRuntime::new();
+                mock.expect_mock_workaround_spawn::<()>()
+                    .returning_st(move |future| {
+                        spawner1.spawn(future).unwrap();
+                    });
+                mock.expect_mock_delay()
+                    .returning(|_| Box::pin(pending::<()>()));
+                // Cloned for the subscribe loop and for the subscription.
+                mock.expect_clone().times(2).returning_st(move || {
+                    let mut mock = MockRuntime::new();
+
+                    let spawner2 = spawner2.clone();
+                    mock.expect_mock_workaround_spawn::<()>()
+                        .returning_st(move |future| {
+                            spawner2.spawn(future).unwrap();
+                        });
+
+                    mock
+                });
+                mock
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut subscription = pubnub.subscribe(test_channel.clone()).await.unwrap();
+
+            let message = subscription.next().await;
+            // We got the message we expected to get.
+            assert!(message.is_some());
+
+            // Wait for the drop request.
+            sub_drop_req_rx.await.unwrap();
+
+            // Drop the subscription, which will cause loop termination.
+            drop(subscription);
+
+            // Wait for the loop termination.
+            sub_loop_exit_rx.next().await.unwrap();
+
+            // Notify that we've completed with the drop request.
+            // Since the loop is now dead, and we were locked on `sub_drop_done_rx`
+            // in the response future, this send *has to fail* send error, cause
+            // loop termination dropped the response future and the
+            // `sub_drop_done_rx` with it (cuase response future owned
+            // `sub_drop_done_rx` afetr we moved it).
+            sub_drop_done_tx.send(()).unwrap_err();
+        })
+        .unwrap();
+
+    pool.run()
+}
+
+/// Tracks the amount of the in-flight requests, and the peak value of it.
+#[derive(Debug, Clone, Default)]
+struct InFlightCounter {
+    current: Arc<AtomicUsize>,
+    peak: Arc<AtomicUsize>,
+}
+
+/// Counts as an in-flight request until dropped.
+#[derive(Debug)]
+struct InFlightGuard(InFlightCounter);
+
+impl InFlightCounter {
+    fn enter(&self) -> InFlightGuard {
+        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        // The test runs on a single thread, so there's no race here.
+        if current > self.peak() {
+            self.peak.store(current, Ordering::SeqCst);
+        }
+        InFlightGuard(self.clone())
+    }
+
+    fn current(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn mocked_pubnub_subscribe_churn_does_not_leak_requests() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let spawner1 = spawner.clone();
+    let spawner2 = spawner.clone();
+
+    let counter = InFlightCounter::default();
+    let counter_clone = counter.clone();
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                mock.expect_clone().times(1).return_once(move || {
+                    let mut mock = MockTransport::new();
+
+                    let mut is_first = true;
+                    mock.expect_call::<request::Subscribe, response::Subscribe>()
+                        .returning(move |_| {
+                            let guard = counter_clone.enter();
+
+                            if is_first {
+                                // Let the initial request succeed to get the
+                                // loop to the ready state.
+                                is_first = false;
+                                return Box::pin(async move {
+                                    drop(guard);
+                                    Ok((vec![], Cursor::new(150, 1)))
+                                });
+                            }
+
+                            // All the other requests hang until cancelled.
+                            Box::pin(async move {
+                                let _guard = guard;
+                                pending().await
+                            })
+                        });
+
+                    mock
+                });
+
+                mock
+            };
+
+            let mock_runtime = {
+                let mut mock = MockRuntime::new();
+                mock.expect_mock_workaround_spawn::<()>()
+                    .returning_st(move |future| {
+                        spawner1.spawn(future).unwrap();
+                    });
+                mock.expect_mock_delay()
+                    .returning(|_| Box::pin(pending::<()>()));
+                mock.expect_clone().returning_st(move || {
+                    // Subscriptions clone the runtime.
+                    let spawner = spawner2.clone();
+                    let mut mock = MockRuntime::new();
+
+                    mock.expect_mock_workaround_spawn::<()>()
+                        .returning_st(move |future| {
+                            spawner.spawn(future).unwrap();
+                        });
+
+                    mock
+                });
+                mock
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            // Add 100 channels, each addition restarts the in-flight request.
+            let mut subscriptions = Vec::new();
+            for i in 0..100 {
+                let channel = format!("test_channel_{}", i).parse().unwrap();
+                subscriptions.push(pubnub.subscribe(channel).await.unwrap());
+            }
+
+            // Drop all of them, which will cause loop termination.
+            drop(subscriptions);
+
+            // Wait for the loop termination.
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+
+    // The cancelled requests must not linger around.
+    assert_eq!(counter.current(), 0);
+    assert_eq!(counter.peak(), 1);
+}
+
+/// Subscribe to a channel where the network redelivers a message, and collect
+/// the timetokens of the received messages.
+fn subscribe_with_redelivery(message_deduplication: bool) -> Vec<u64> {
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let spawner1 = spawner.clone();
+    let spawner2 = spawner.clone();
+
+    let test_channel: channel::Name = "test_channel".parse().unwrap();
+    let message = move |t| Message {
+        message_type: message::Type::Publish,
+        channel: test_channel.clone(),
+        json: object! {
+            "test" => "value",
+        },
+        timetoken: Timetoken { t, r: 1 },
+        ..Message::default()
+    };
+    let mut responses = vec![
+        (vec![], 150),
+        (vec![message(200), message(300)], 300),
+        // The last message is redelivered after a reconnect.
+        (vec![message(300), message(400)], 400),
+    ]
+    .into_iter();
+
+    let (result_tx, result_rx) = oneshot::channel();
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                mock.expect_clone().times(1).return_once(move || {
+                    let mut mock = MockTransport::new();
+
+                    mock.expect_call::<request::Subscribe, response::Subscribe>()
+                        .returning(move |_| match responses.next() {
+                            Some((messages, t)) => {
+                                Box::pin(async move { Ok((messages, Cursor::new(t, 1))) })
+                            }
+                            // Hang after all the responses are delivered.
+                            None => Box::pin(pending()),
+                        });
+
+                    mock
+                });
+
+                mock
+            };
+
+            let mock_runtime = {
+                let mut mock = MockRuntime::new();
+                mock.expect_mock_workaround_spawn::<()>()
+                    .returning_st(move |future| {
+                        spawner1.spawn(future).unwrap();
+                    });
+                mock.expect_mock_delay()
+                    .returning(|_| Box::pin(pending::<()>()));
+                // Cloned for the subscribe loop and for the subscription.
+                mock.expect_clone().times(2).returning_st(move || {
+                    let mut mock = MockRuntime::new();
+
+                    let spawner2 = spawner2.clone();
+                    mock.expect_mock_workaround_spawn::<()>()
+                        .returning_st(move |future| {
+                            spawner2.spawn(future).unwrap();
+                        });
+
+                    mock
+                });
+                mock
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .message_deduplication(message_deduplication)
+                .build();
+
+            let subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+
+            let expected_count = if message_deduplication { 3 } else { 4 };
+            let timetokens = subscription
+                .take(expected_count)
+                .map(|message| message.timetoken.t)
+                .collect()
+                .await;
+
+            // Wait for the loop termination.
+            sub_loop_exit_rx.next().await.unwrap();
+
+            result_tx.send(timetokens).unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+    block_on(result_rx).unwrap()
+}
+
+#[test]
+fn mocked_pubnub_subscribe_skips_redelivered_messages() {
+    init();
+    assert_eq!(subscribe_with_redelivery(true), vec![200, 300, 400]);
+}
+
+#[test]
+fn mocked_pubnub_subscribe_can_keep_redelivered_messages() {
+    init();
+    assert_eq!(subscribe_with_redelivery(false), vec![200, 300, 300, 400]);
+}
+
+#[test]
+fn mocked_pubnub_subscribe_again_forgets_delivered_timetokens() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+            let message = |t| Message {
+                message_type: message::Type::Publish,
+                channel: channel_a.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription_b = pubnub.subscribe(channel_b).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            let mut subscription_a = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![message(200)], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(subscription_a.next().await, Some(Arc::new(message(200))));
+            requests_rx.next().await.unwrap();
+
+            // Subscribing again starts afresh, the older messages aren't
+            // taken for the redelivered ones.
+            drop(subscription_a);
+            requests_rx.next().await.unwrap();
+            let mut subscription_a = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![message(180)], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(subscription_a.next().await, Some(Arc::new(message(180))));
+
+            drop(subscription_a);
+            drop(subscription_b);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_unsubscribe_ends_all_streams() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let spawner1 = spawner.clone();
+    let spawner2 = spawner.clone();
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                mock.expect_clone().times(1).return_once(move || {
+                    let mut mock = MockTransport::new();
+
+                    let mut is_first = true;
+                    mock.expect_call::<request::Subscribe, response::Subscribe>()
+                        .returning(move |_| {
+                            if is_first {
+                                is_first = false;
+                                return Box::pin(async { Ok((vec![], Cursor::new(150, 1))) });
+                            }
+                            Box::pin(pending())
+                        });
+
+                    mock
+                });
+
+                mock
+            };
+
+            let mock_runtime = {
+                let mut mock = MockRuntime::new();
+                mock.expect_mock_workaround_spawn::<()>()
+                    .returning_st(move |future| {
+                        spawner1.spawn(future).unwrap();
+                    });
+                mock.expect_mock_delay()
+                    .returning(|_| Box::pin(pending::<()>()));
+                mock.expect_clone().returning_st(move || {
+                    let spawner = spawner2.clone();
+                    let mut mock = MockRuntime::new();
+
+                    mock.expect_mock_workaround_spawn::<()>()
+                        .returning_st(move |future| {
+                            spawner.spawn(future).unwrap();
+                        });
+
+                    mock
+                });
+                mock
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+
+            let mut subscription_a1 = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            let mut subscription_a2 = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            let mut subscription_b = pubnub.subscribe(channel_b.clone()).await.unwrap();
+
+            assert_eq!(subscription_a1.channel_name(), "test_channel_a");
+            assert_eq!(
+                subscription_a1.listener_kind(),
+                pubsub::ListenerKind::Channel
+            );
+            assert_eq!(subscription_b.channel_name(), "test_channel_b");
+
+            // All the streams for the channel end.
+            assert!(!subscription_a1.is_terminated());
+            pubnub.unsubscribe(channel_a).await;
+            assert!(subscription_a1.next().await.is_none());
+            assert!(subscription_a2.next().await.is_none());
+            assert!(subscription_a1.is_terminated());
+            assert!(subscription_a2.is_terminated());
+            assert!(!subscription_b.is_terminated());
+
+            // Removing the last channel tears down the loop.
+            pubnub.unsubscribe(channel_b).await;
+            assert!(subscription_b.next().await.is_none());
+            sub_loop_exit_rx.next().await.unwrap();
+            assert!(subscription_b.is_terminated());
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_clones_share_subscribe_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (requests_tx, mut requests_rx) = mpsc::unbounded();
+            let (responses_tx, responses_rx) = mpsc::unbounded();
+            let responses_rx = Arc::new(Mutex::new(responses_rx));
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                // Serves the clone of the client and the subscribe loop. The
+                // clones can't be cloned again, so a second loop would fail.
+                mock.expect_clone().times(2).returning(move || {
+                    let mut mock = MockTransport::new();
+
+                    let requests_tx = requests_tx.clone();
+                    let responses_rx = Arc::clone(&responses_rx);
+                    mock.expect_call::<request::Subscribe, response::Subscribe>()
+                        .returning(move |request| {
+                            let _ = requests_tx.unbounded_send(request);
+                            let responses_rx = Arc::clone(&responses_rx);
+                            Box::pin(async move {
+                                match responses_rx.lock().await.next().await {
+                                    Some(response) => response,
+                                    None => pending().await,
+                                }
+                            })
+                        });
+                    mock.expect_call::<request::Publish, response::Publish>()
+                        .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 200, r: 0 })) }));
+
+                    mock
+                });
+
+                mock
+            };
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+            let message = |channel: &channel::Name, t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+            let mut pubnub_clone = pubnub.clone();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription_a = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            assert_eq!(requests_rx.next().await.unwrap().to.len(), 1);
+            assert_eq!(requests_rx.next().await.unwrap().to.len(), 1);
+
+            // The clone joins the running loop.
+            let mut subscription_b = pubnub_clone.subscribe(channel_b.clone()).await.unwrap();
+            let request = requests_rx.next().await.unwrap();
+            assert_eq!(request.to.len(), 2);
+
+            // Dropping the original client keeps the loop going.
+            drop(pubnub);
+
+            let timetoken = pubnub_clone
+                .publish(channel_a.clone(), object! { "test" => "value" })
+                .await
+                .unwrap();
+            assert_eq!(timetoken.t, 200);
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![message(&channel_a, 200), message(&channel_b, 201)],
+                    Cursor::new(201, 1),
+                )))
+                .unwrap();
+            assert_eq!(
+                subscription_a.next().await,
+                Some(Arc::new(message(&channel_a, 200)))
+            );
+            assert_eq!(
+                subscription_b.next().await,
+                Some(Arc::new(message(&channel_b, 201)))
+            );
+
+            // The loop ends with the last subscription.
+            drop(subscription_a);
+            drop(subscription_b);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_shutdown_stops_loop_and_leaves() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+
+            let mock_transport = {
+                let mut mock = MockTransport::new();
+
+                // Serves both the subscribe loop and the clone of the
+                // client, neither of which is expected to leave.
+                mock.expect_clone().times(2).returning(move || {
+                    let mut mock = MockTransport::new();
+
+                    mock.expect_call::<request::Subscribe, response::Subscribe>()
+                        .returning(move |request| {
+                            if request.cursor.timetoken == 0 {
+                                return Box::pin(async { Ok((vec![], Cursor::new(150, 1))) });
+                            }
+                            Box::pin(pending())
+                        });
+
+                    mock
+                });
+
+                mock.expect_mock_capabilities().returning(Capabilities::all);
+                let expected_to = vec![pubsub::SubscribeTo::Channel(channel.clone())];
+                mock.expect_call::<request::Leave, response::Leave>()
+                    .times(1)
+                    .returning(move |request| {
+                        assert_eq!(request.to, expected_to);
+                        Box::pin(async { Ok(()) })
+                    });
+
+                mock
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+            let pubnub_clone = pubnub.clone();
+
+            let mut subscription1 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let mut subscription2 = pubnub.subscribe(channel).await.unwrap();
+
+            pubnub.shutdown().await;
+
+            // The streams end, and the loop is gone.
+            assert!(subscription1.next().await.is_none());
+            assert!(subscription2.next().await.is_none());
+            sub_loop_exit_rx.next().await.unwrap();
+
+            // There's nothing left to shut down.
+            pubnub_clone.shutdown().await;
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+/// A transport mock driven by the test: the subscribe requests are reported
+/// via the returned receiver, and the responses are taken from the returned
+/// sender. Requests hang until a response is sent.
+fn scripted_subscribe_transport() -> (
+    MockTransport,
+    mpsc::UnboundedReceiver<request::Subscribe>,
+    mpsc::UnboundedSender<Result<response::Subscribe, MockTransportError>>,
+) {
+    let (requests_tx, requests_rx) = mpsc::unbounded();
+    let (responses_tx, responses_rx) = mpsc::unbounded();
+    let responses_rx = Arc::new(Mutex::new(responses_rx));
+
+    let mut mock = MockTransport::new();
+    mock.expect_clone().times(1).return_once(move || {
+        let mut mock = MockTransport::new();
+
+        mock.expect_call::<request::Subscribe, response::Subscribe>()
+            .returning(move |request| {
+                let _ = requests_tx.unbounded_send(request);
+                let responses_rx = Arc::clone(&responses_rx);
+                Box::pin(async move {
+                    match responses_rx.lock().await.next().await {
+                        Some(response) => response,
+                        None => pending().await,
+                    }
+                })
+            });
+
+        mock
+    });
+
+    (mock, requests_rx, responses_tx)
+}
+
+/// A runtime mock that spawns the futures on the local pool, with the delays
+/// that never complete, and can be cloned any number of times.
+fn local_pool_runtime(spawner: LocalSpawner) -> MockRuntime {
+    let mut mock = MockRuntime::new();
+    let spawner_clone = spawner.clone();
+    mock.expect_mock_workaround_spawn::<()>()
+        .returning_st(move |future| {
+            spawner_clone.spawn(future).unwrap();
+        });
+    // The delays never complete, i.e. the ready timeout never passes.
+    mock.expect_mock_delay()
+        .returning(|_| Box::pin(pending::<()>()));
+    mock.expect_clone()
+        .returning_st(move || local_pool_runtime(spawner.clone()));
+    mock
+}
+
+/// A runtime spawning onto the local pool, the delays of which complete
+/// right away, recorded to the `delays`.
+fn recording_local_pool_runtime(
+    spawner: LocalSpawner,
+    delays: Arc<std::sync::Mutex<Vec<Duration>>>,
+) -> MockRuntime {
+    let mut mock = MockRuntime::new();
+    let spawner_clone = spawner.clone();
+    mock.expect_mock_workaround_spawn::<()>()
+        .returning_st(move |future| {
+            spawner_clone.spawn(future).unwrap();
+        });
+    let recorded = Arc::clone(&delays);
+    mock.expect_mock_delay().returning(move |duration| {
+        recorded.lock().unwrap().push(duration);
+        Box::pin(ready(()))
+    });
+    mock.expect_clone()
+        .returning_st(move || recording_local_pool_runtime(spawner.clone(), Arc::clone(&delays)));
+    mock
+}
+
+#[test]
+fn mocked_pubnub_subscribe_drop_keeps_other_channels() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+            let to_a = pubsub::SubscribeTo::Channel(channel_a.clone());
+            let to_b = pubsub::SubscribeTo::Channel(channel_b.clone());
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription_a = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![to_a.clone()]);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![to_a.clone()]);
+
+            // Adding a new channel restarts the request.
+            let mut subscription_b = pubnub.subscribe(channel_b.clone()).await.unwrap();
+            let mut to = requests_rx.next().await.unwrap().to;
+            to.sort_by_key(|to| to.as_channel().unwrap().to_string());
+            assert_eq!(to, vec![to_a.clone(), to_b.clone()]);
+
+            // Adding another listener to the same channel doesn't.
+            let subscription_b2 = pubnub.subscribe(channel_b.clone()).await.unwrap();
+            drop(subscription_b2);
+
+            // Dropping a channel restarts the request without the channel.
+            drop(subscription_a);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![to_b.clone()]);
+
+            // The other channel still gets the messages.
+            let message = Message {
+                message_type: message::Type::Publish,
+                channel: channel_b.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(subscription_b.next().await, Some(Arc::new(message)));
+
+            drop(subscription_b);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_invalid_name_keeps_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let invalid = channel::Name::from_string_unchecked("a,b".to_owned());
+            let to = pubsub::SubscribeTo::Channel(channel.clone());
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime).build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription = pubnub.subscribe(channel.clone()).await.unwrap();
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![to.clone()]);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![to.clone()]);
+
+            // The invalid name is rejected to the caller only, without
+            // reaching the transport.
+            let error = pubnub.subscribe(invalid.clone()).await.unwrap_err();
+            assert_eq!(error.invalid_name.as_deref(), Some("a,b"));
+            let error = pubnub
+                .publish(invalid, object! { "test" => "value" })
+                .await
+                .unwrap_err();
+            assert_eq!(error.invalid_name.as_deref(), Some("a,b"));
+
+            // The loop keeps going for the other subscriptions.
+            let message = Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(subscription.next().await, Some(Arc::new(message)));
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![to]);
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_mass_drop_does_not_block() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscriptions = Vec::new();
+            for i in 0..64 {
+                let channel: channel::Name = format!("test_channel_{}", i % 8).parse().unwrap();
+                subscriptions.push(pubnub.subscribe(channel).await.unwrap());
+            }
+
+            // Drop them all at once from within the task, way more than the
+            // control channel buffers. The drops must not wait for the loop,
+            // that runs on this very executor.
+            drop(subscriptions);
+
+            // The loop gets all the drops, and exits.
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_with_single_message_buffer() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+    let done = Arc::new(AtomicUsize::new(0));
+    let done_clone = Arc::clone(&done);
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let messages: Vec<_> = (1..=3)
+                .map(|i| Message {
+                    message_type: message::Type::Publish,
+                    channel: channel.clone(),
+                    json: object! {
+                        "i" => i,
+                    },
+                    timetoken: Timetoken { t: 150 + i, r: 1 },
+                    ..Message::default()
+                })
+                .collect();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscription_buffer(1)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription = pubnub.subscribe(channel).await.unwrap();
+
+            // A stream that keeps up gets every message.
+            for (message, t) in messages.into_iter().zip(151..) {
+                responses_tx
+                    .unbounded_send(Ok((vec![message.clone()], Cursor::new(t, 1))))
+                    .unwrap();
+                assert_eq!(subscription.next().await, Some(Arc::new(message)));
+            }
+            assert_eq!(subscription.dropped(), 0);
+            done_clone.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    // The loop keeps waiting for the next response.
+    pool.run_until_stalled();
+    assert_eq!(done.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn mocked_pubnub_subscribe_unread_stream_stays_within_buffer() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+    let done = Arc::new(AtomicUsize::new(0));
+    let done_clone = Arc::clone(&done);
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let messages: Vec<_> = (1..=6)
+                .map(|i| Message {
+                    message_type: message::Type::Publish,
+                    channel: channel.clone(),
+                    json: object! {
+                        "i" => i,
+                    },
+                    timetoken: Timetoken { t: 150 + i, r: 1 },
+                    ..Message::default()
+                })
+                .collect();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscription_buffer(3)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut unread = pubnub.subscribe(channel.clone()).await.unwrap();
+            let unread_dropped = unread.dropped_counter();
+            let mut subscription = pubnub.subscribe(channel).await.unwrap();
+
+            // The read stream gets every message, while the unread one
+            // falls behind.
+            for (chunk, t) in messages.chunks(3).zip(&[153, 156]) {
+                responses_tx
+                    .unbounded_send(Ok((chunk.to_vec(), Cursor::new(*t, 1))))
+                    .unwrap();
+                for message in chunk {
+                    assert_eq!(subscription.next().await, Some(Arc::new(message.clone())));
+                }
+            }
+            assert_eq!(subscription.dropped(), 0);
+
+            // The unread stream only kept what fits its buffer, the rest
+            // was dropped and counted.
+            assert_eq!(unread_dropped.get(), 3);
+            for message in &messages[..3] {
+                assert_eq!(
+                    unread.next().now_or_never(),
+                    Some(Some(Arc::new(message.clone())))
+                );
+            }
+            assert_eq!(unread.next().now_or_never(), None);
+            assert_eq!(unread.dropped(), 3);
+            done_clone.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    // The loop keeps waiting for the next response.
+    pool.run_until_stalled();
+    assert_eq!(done.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+#[should_panic(expected = "The subscription buffer must not be zero")]
+fn subscription_buffer_must_not_be_zero() {
+    let _ =
+        Builder::with_components(MockTransport::new(), MockRuntime::new()).subscription_buffer(0);
+}
+
+#[test]
+fn mocked_pubnub_subscribe_to_channel_group() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let group = pubsub::SubscribeTo::ChannelGroup("test_group".parse().unwrap());
+            let channel: channel::Name = "test_channel".parse().unwrap();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut group_subscription = pubnub.subscribe_to(group.clone()).await.unwrap();
+            assert_eq!(group_subscription.destination(), &group);
+            assert_eq!(
+                group_subscription.listener_kind(),
+                pubsub::ListenerKind::ChannelGroup
+            );
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![group.clone()]);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![group.clone()]);
+
+            let channel_subscription = pubnub.subscribe_to(channel.clone()).await.unwrap();
+            assert_eq!(
+                channel_subscription.destination(),
+                &pubsub::SubscribeTo::Channel(channel)
+            );
+
+            // The messages from the channels of the group are routed to it.
+            let message = Message {
+                message_type: message::Type::Publish,
+                route: Some(message::Route::ChannelGroup("test_group".parse().unwrap())),
+                channel: "group_member".parse().unwrap(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(group_subscription.next().await, Some(Arc::new(message)));
+
+            drop(group_subscription);
+            drop(channel_subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_filtered_by_message_type() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let group = pubsub::SubscribeTo::ChannelGroup("test_group".parse().unwrap());
+            let message = |message_type, channel: &str, t| Message {
+                message_type,
+                route: Some(message::Route::ChannelGroup("test_group".parse().unwrap())),
+                channel: channel.parse().unwrap(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+            let publish = message(message::Type::Publish, "member", 200);
+            let presence = message(message::Type::Presence, "member-pnpres", 201);
+            let signal = message(message::Type::Signal, "member", 202);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut signals = pubnub
+                .subscribe_to(group.clone())
+                .await
+                .unwrap()
+                .only(message::Type::Signal);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![group.clone()]);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![group.clone()]);
+            let mut messages = pubnub
+                .subscribe_to(group.clone())
+                .await
+                .unwrap()
+                .without_presence();
+            assert_eq!(messages.subscription().destination(), &group);
+
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![publish.clone(), presence, signal.clone()],
+                    Cursor::new(202, 1),
+                )))
+                .unwrap();
+            assert_eq!(signals.next().await, Some(Arc::new(signal.clone())));
+            assert_eq!(messages.next().await, Some(Arc::new(publish)));
+            assert_eq!(messages.next().await, Some(Arc::new(signal)));
+
+            // The filter can be removed later on.
+            let mut all = signals.into_inner();
+            let presence = message(message::Type::Presence, "member-pnpres", 203);
+            responses_tx
+                .unbounded_send(Ok((vec![presence.clone()], Cursor::new(203, 1))))
+                .unwrap();
+            assert_eq!(all.next().await, Some(Arc::new(presence)));
+
+            drop(all);
+            drop(messages);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_presence_only() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let presence_channel: channel::Name = "test_channel-pnpres".parse().unwrap();
+            let message = |message_type, channel: &channel::Name, t| Message {
+                message_type,
+                channel: channel.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+            let data = message(message::Type::Publish, &channel, 200);
+            let presence = message(message::Type::Presence, &presence_channel, 201);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut messages = pubnub.subscribe(channel.clone()).await.unwrap();
+            assert_eq!(
+                requests_rx.next().await.unwrap().to,
+                vec![pubsub::SubscribeTo::Channel(channel.clone())]
+            );
+            assert_eq!(
+                requests_rx.next().await.unwrap().to,
+                vec![pubsub::SubscribeTo::Channel(channel.clone())]
+            );
+
+            // The same loop subscribes to the presence channel too.
+            let mut presence_events = pubnub
+                .subscribe_presence_only(channel.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                presence_events.subscription().destination(),
+                &pubsub::SubscribeTo::Channel(presence_channel.clone())
+            );
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut to = requests_rx.next().await.unwrap().to;
+            to.sort_by_key(|to| to.as_channel().unwrap().to_string());
+            assert_eq!(
+                to,
+                vec![
+                    pubsub::SubscribeTo::Channel(channel.clone()),
+                    pubsub::SubscribeTo::Channel(presence_channel.clone()),
+                ]
+            );
+
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![data.clone(), presence.clone()],
+                    Cursor::new(201, 1),
+                )))
+                .unwrap();
+            assert_eq!(presence_events.next().await, Some(Arc::new(presence)));
+            assert_eq!(messages.next().await, Some(Arc::new(data)));
+
+            drop(presence_events);
+            drop(messages);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_replays_to_late_subscribers() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let message = |t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .replay_buffer(2)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut early = pubnub.subscribe(channel.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![message(200), message(201), message(202)],
+                    Cursor::new(202, 1),
+                )))
+                .unwrap();
+            for t in 200..=202 {
+                assert_eq!(early.next().await, Some(Arc::new(message(t))));
+            }
+            requests_rx.next().await.unwrap();
+
+            // The late stream gets the last messages, followed by the live
+            // ones.
+            let mut late = pubnub.subscribe(channel.clone()).await.unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![message(203)], Cursor::new(203, 1))))
+                .unwrap();
+            for t in 201..=203 {
+                assert_eq!(late.next().await, Some(Arc::new(message(t))));
+            }
+            assert_eq!(early.next().await, Some(Arc::new(message(203))));
+
+            drop(early);
+            drop(late);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_replay_over_subscription_buffer() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let message = |t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .subscription_buffer(1)
+                .replay_buffer(5)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut early = pubnub.subscribe(channel.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            responses_tx
+                .unbounded_send(Ok(((200..205).map(message).collect(), Cursor::new(204, 1))))
+                .unwrap();
+            for t in 200..205 {
+                assert_eq!(early.next().await, Some(Arc::new(message(t))));
+            }
+            requests_rx.next().await.unwrap();
+
+            // The replay doesn't wait for the late streams to be read, so
+            // the loop keeps taking the subscriptions.
+            let mut late = pubnub.subscribe(channel.clone()).await.unwrap();
+            let mut later = pubnub.subscribe(channel.clone()).await.unwrap();
+            for t in 200..205 {
+                assert_eq!(late.next().await, Some(Arc::new(message(t))));
+                assert_eq!(later.next().await, Some(Arc::new(message(t))));
+            }
+
+            drop(early);
+            drop(late);
+            drop(later);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_try_subscribe_reports_undecodable_messages() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let message = |t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+            let undecodable = Message {
+                channel: channel.clone(),
+                json: object! { "c" => "test_channel" },
+                decode_error: Some("missing or invalid timetoken".to_owned()),
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut messages = pubnub.subscribe(channel.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+            let mut results = pubnub.try_subscribe(channel.clone()).await.unwrap();
+
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![message(200), undecodable, message(201)],
+                    Cursor::new(201, 1),
+                )))
+                .unwrap();
+
+            // The error doesn't end the stream.
+            assert_eq!(results.next().await, Some(Ok(Arc::new(message(200)))));
+            assert_eq!(
+                results.next().await,
+                Some(Err(DecodeError {
+                    channel: channel.clone(),
+                    json: object! { "c" => "test_channel" },
+                    reason: "missing or invalid timetoken".to_owned(),
+                }))
+            );
+            assert_eq!(results.next().await, Some(Ok(Arc::new(message(201)))));
+
+            // The plain subscription skips the undecodable message.
+            assert_eq!(messages.next().await, Some(Arc::new(message(200))));
+            assert_eq!(messages.next().await, Some(Arc::new(message(201))));
+
+            drop(messages);
+            drop(results);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_wildcard() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let wildcard = pubsub::SubscribeTo::ChannelWildcard("a.*".parse().unwrap());
+            let message = |route: Option<&str>, channel: &str, t| Message {
+                route: route.map(|route| message::Route::ChannelWildcard(route.parse().unwrap())),
+                channel: channel.parse().unwrap(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+            let routed = message(Some("a.*"), "a.b", 200);
+            // Routed by the other wildcard only.
+            let nested = message(Some("a.b.*"), "a.b.c", 201);
+            let unrelated = message(None, "b.a", 202);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            assert_eq!(
+                pubnub.subscribe_wildcard("a*").await.unwrap_err(),
+                channel::InvalidWildcardSpec("a*".to_owned())
+            );
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription = pubnub.subscribe_wildcard("a.*").await.unwrap();
+            assert_eq!(subscription.destination(), &wildcard);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![wildcard.clone()]);
+            assert_eq!(requests_rx.next().await.unwrap().to, vec![wildcard.clone()]);
+            let nested_wildcard = pubsub::SubscribeTo::ChannelWildcard("a.b.*".parse().unwrap());
+            let mut nested_subscription = pubnub.subscribe_to(nested_wildcard).await.unwrap();
+
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![routed.clone(), nested.clone(), unrelated],
+                    Cursor::new(202, 1),
+                )))
+                .unwrap();
+            assert_eq!(subscription.next().await, Some(Arc::new(routed)));
+            assert_eq!(subscription.next().await, Some(Arc::new(nested.clone())));
+            assert_eq!(nested_subscription.next().await, Some(Arc::new(nested)));
+
+            drop(subscription);
+            drop(nested_subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_all_merges_and_leaves() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (leaves_tx, mut leaves_rx) = mpsc::unbounded();
+            let (responses_tx, responses_rx) = mpsc::unbounded();
+            let responses_rx = Arc::new(Mutex::new(responses_rx));
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+
+            // Serves both the subscribe loop and the merged stream.
+            let mut mock_transport = MockTransport::new();
+            mock_transport.expect_clone().times(2).returning(move || {
+                let mut mock = MockTransport::new();
+
+                let responses_rx = Arc::clone(&responses_rx);
+                mock.expect_call::<request::Subscribe, response::Subscribe>()
+                    .returning(move |_| {
+                        let responses_rx = Arc::clone(&responses_rx);
+                        Box::pin(async move {
+                            match responses_rx.lock().await.next().await {
+                                Some(response) => response,
+                                None => pending().await,
+                            }
+                        })
+                    });
+
+                mock.expect_mock_capabilities().returning(Capabilities::all);
+                let leaves_tx = leaves_tx.clone();
+                mock.expect_call::<request::Leave, response::Leave>()
+                    .returning(move |request| {
+                        leaves_tx.unbounded_send(request.to).unwrap();
+                        Box::pin(async { Ok(()) })
+                    });
+
+                mock
+            });
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(mock_transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut merged = pubnub
+                .subscribe_all(vec![channel_a.clone(), channel_b.clone()])
+                .await
+                .unwrap();
+            let mut subscription_b = pubnub.subscribe(channel_b.clone()).await.unwrap();
+
+            let message = |channel: &channel::Name, t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+            let message_a = message(&channel_a, 200);
+            let message_b = message(&channel_b, 201);
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![message_a.clone(), message_b.clone()],
+                    Cursor::new(201, 1),
+                )))
+                .unwrap();
+
+            let mut received = vec![merged.next().await.unwrap(), merged.next().await.unwrap()];
+            received.sort_by_key(|message| message.timetoken.t);
+            assert_eq!(
+                received,
+                vec![Arc::new(message_a), Arc::new(message_b.clone())]
+            );
+            assert_eq!(subscription_b.next().await, Some(Arc::new(message_b)));
+
+            // Only the channel nobody else listens to is left.
+            drop(merged);
+            assert_eq!(
+                leaves_rx.next().await.unwrap(),
+                vec![pubsub::SubscribeTo::Channel(channel_a)]
+            );
+
+            drop(subscription_b);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_add_preserves_timetoken() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription_a = pubnub.subscribe(channel_a).await.unwrap();
+            assert_eq!(requests_rx.next().await.unwrap().cursor, Cursor::default());
+            assert_eq!(
+                requests_rx.next().await.unwrap().cursor,
+                Cursor::new(150, 1)
+            );
+
+            // The restarted request continues from the current timetoken
+            // instead of starting over.
+            let subscription_b = pubnub.subscribe(channel_b).await.unwrap();
+            let request = requests_rx.next().await.unwrap();
+            assert_eq!(request.to.len(), 2);
+            assert_eq!(request.cursor, Cursor::new(150, 1));
+
+            drop(subscription_a);
+            drop(subscription_b);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+/// A [`local_pool_runtime`] whose delays complete one at a time, as the
+/// `ticks` are sent.
+fn ticking_runtime(
+    spawner: LocalSpawner,
+    ticks: Arc<Mutex<mpsc::UnboundedReceiver<()>>>,
+) -> MockRuntime {
+    let mut mock = MockRuntime::new();
+    let spawner_clone = spawner.clone();
+    mock.expect_mock_workaround_spawn::<()>()
+        .returning_st(move |future| {
+            spawner_clone.spawn(future).unwrap();
+        });
+    let delay_ticks = Arc::clone(&ticks);
+    mock.expect_mock_delay().returning_st(move |_| {
+        let ticks = Arc::clone(&delay_ticks);
+        Box::pin(async move {
+            if ticks.lock().await.next().await.is_none() {
+                pending::<()>().await;
+            }
+        })
+    });
+    mock.expect_clone()
+        .returning_st(move || ticking_runtime(spawner.clone(), Arc::clone(&ticks)));
+    mock
+}
+
+#[test]
+fn mocked_pubnub_presence_only() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let (ticks_tx, ticks_rx) = mpsc::unbounded();
+    let runtime = ticking_runtime(spawner.clone(), Arc::new(Mutex::new(ticks_rx)));
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (heartbeats_tx, mut heartbeats_rx) = mpsc::unbounded();
+            let (leaves_tx, mut leaves_rx) = mpsc::unbounded();
+
+            let mut transport = MockTransport::new();
+            transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::all);
+            transport
+                .expect_mock_uuid()
+                .returning(|| Some("my-uuid".into()));
+            transport.expect_clone().times(1).return_once(move || {
+                let mut mock = MockTransport::new();
+                mock.expect_call::<request::Heartbeat, response::Heartbeat>()
+                    .times(2)
+                    .returning(move |request| {
+                        heartbeats_tx.unbounded_send(request).unwrap();
+                        Box::pin(ready(Ok(())))
+                    });
+                mock.expect_call::<request::Leave, response::Leave>()
+                    .times(1)
+                    .return_once(move |request| {
+                        leaves_tx.unbounded_send(request).unwrap();
+                        Box::pin(ready(Ok(())))
+                    });
+                mock
+            });
+
+            let to = vec![
+                pubsub::SubscribeTo::Channel("test_channel_a".parse().unwrap()),
+                pubsub::SubscribeTo::Channel("test_channel_b".parse().unwrap()),
+            ];
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime).build();
+            let presence = pubnub
+                .presence_only(vec![
+                    "test_channel_a".parse().unwrap(),
+                    "test_channel_b".parse().unwrap(),
+                ])
+                .unwrap();
+
+            // Announced right away, and again with every tick.
+            let heartbeat = heartbeats_rx.next().await.unwrap();
+            assert_eq!(heartbeat.to, to);
+            assert_eq!(heartbeat.uuid, "my-uuid".into());
+            assert_eq!(heartbeat.heartbeat, Some(300));
+            ticks_tx.unbounded_send(()).unwrap();
+            assert_eq!(heartbeats_rx.next().await.unwrap(), heartbeat);
+
+            // Dropping the handle leaves the channels, and stops the
+            // heartbeats.
+            drop(presence);
+            let leave = leaves_rx.next().await.unwrap();
+            assert_eq!(leave.to, to);
+            assert_eq!(leave.uuid, Some("my-uuid".into()));
+            assert_eq!(heartbeats_rx.next().await, None);
+            drop(ticks_tx);
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_presence_only_leaves_as_announced() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (heartbeats_tx, mut heartbeats_rx) = mpsc::unbounded();
+            let (leaves_tx, mut leaves_rx) = mpsc::unbounded();
+
+            let mut transport = MockTransport::new();
+            transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::all);
+            // No UUID of its own, so a random one is announced.
+            transport.expect_mock_uuid().returning(|| None);
+            transport.expect_clone().times(1).return_once(move || {
+                let mut mock = MockTransport::new();
+                mock.expect_call::<request::Heartbeat, response::Heartbeat>()
+                    .times(1)
+                    .return_once(move |request| {
+                        heartbeats_tx.unbounded_send(request).unwrap();
+                        Box::pin(ready(Ok(())))
+                    });
+                mock.expect_call::<request::Leave, response::Leave>()
+                    .times(1)
+                    .return_once(move |request| {
+                        leaves_tx.unbounded_send(request).unwrap();
+                        Box::pin(ready(Ok(())))
+                    });
+                mock
+            });
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime).build();
+            let presence = pubnub
+                .presence_only(vec!["test_channel".parse().unwrap()])
+                .unwrap();
+            let heartbeat = heartbeats_rx.next().await.unwrap();
+            drop(presence);
+
+            // The leave is sent as the same UUID as the heartbeats.
+            let leave = leaves_rx.next().await.unwrap();
+            assert_eq!(leave.to, heartbeat.to);
+            assert_eq!(leave.uuid, Some(heartbeat.uuid));
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_presence_only_stays_on_subscribed_channels() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+    let done = Arc::new(AtomicUsize::new(0));
+    let done_clone = Arc::clone(&done);
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (leaves_tx, mut leaves_rx) = mpsc::unbounded();
+
+            let mut transport = MockTransport::new();
+            transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::all);
+            transport
+                .expect_mock_uuid()
+                .returning(|| Some("my-uuid".into()));
+            // One clone for the subscribe loop, one for the heartbeats.
+            transport.expect_clone().times(2).returning(move || {
+                let mut mock = MockTransport::new();
+                let mut responded = false;
+                mock.expect_call::<request::Subscribe, response::Subscribe>()
+                    .returning_st(move |_| {
+                        if std::mem::replace(&mut responded, true) {
+                            Box::pin(pending())
+                        } else {
+                            Box::pin(ready(Ok((vec![], Cursor::new(150, 1)))))
+                        }
+                    });
+                mock.expect_call::<request::Heartbeat, response::Heartbeat>()
+                    .returning(|_| Box::pin(ready(Ok(()))));
+                let leaves_tx = leaves_tx.clone();
+                mock.expect_call::<request::Leave, response::Leave>()
+                    .returning(move |request| {
+                        leaves_tx.unbounded_send(request).unwrap();
+                        Box::pin(ready(Ok(())))
+                    });
+                mock
+            });
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime).build();
+            let _subscription = pubnub
+                .subscribe("test_channel_a".parse().unwrap())
+                .await
+                .unwrap();
+            let presence = pubnub
+                .presence_only(vec![
+                    "test_channel_a".parse().unwrap(),
+                    "test_channel_b".parse().unwrap(),
+                ])
+                .unwrap();
+            drop(presence);
+
+            // Only the channel that isn't subscribed to is left.
+            let leave = leaves_rx.next().await.unwrap();
+            assert_eq!(
+                leave.to,
+                vec![pubsub::SubscribeTo::Channel(
+                    "test_channel_b".parse().unwrap()
+                )]
+            );
+            assert_eq!(leave.uuid, Some("my-uuid".into()));
+            done_clone.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    // The subscribe loop keeps polling.
+    pool.run_until_stalled();
+    assert_eq!(done.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn mocked_pubnub_watchdog_reconnects_stalled_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let (ticks_tx, ticks_rx) = mpsc::unbounded();
+    let runtime = ticking_runtime(spawner.clone(), Arc::new(Mutex::new(ticks_rx)));
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .watchdog_timeout(Duration::from_secs(10 * 60))
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            // An empty response is a healthy idle poll.
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Connected));
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            // The next request never completes.
+            ticks_tx.unbounded_send(()).unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Stalled));
+
+            // The loop reconnects from where it was.
+            assert_eq!(
+                requests_rx.next().await.unwrap().cursor,
+                Cursor::new(150, 1)
+            );
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Reconnected));
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+/// A virtual clock for the delays of the [`clock_runtime`], advanced by the
+/// test.
+#[derive(Debug, Default)]
+struct Clock {
+    now: Duration,
+    wakers: Vec<std::task::Waker>,
+}
+
+impl Clock {
+    fn advance(clock: &std::sync::Mutex<Clock>, by: Duration) {
+        let wakers = {
+            let mut clock = clock.lock().unwrap();
+            clock.now += by;
+            std::mem::take(&mut clock.wakers)
+        };
+        wakers.into_iter().for_each(std::task::Waker::wake);
+    }
+}
+
+/// A runtime mock that spawns the futures on the local pool, with the delays
+/// that complete once the `clock` passes their deadline.
+fn clock_runtime(spawner: LocalSpawner, clock: Arc<std::sync::Mutex<Clock>>) -> MockRuntime {
+    let mut mock = MockRuntime::new();
+    let spawner_clone = spawner.clone();
+    mock.expect_mock_workaround_spawn::<()>()
+        .returning_st(move |future| {
+            spawner_clone.spawn(future).unwrap();
+        });
+    let delay_clock = Arc::clone(&clock);
+    mock.expect_mock_delay().returning_st(move |duration| {
+        let clock = Arc::clone(&delay_clock);
+        let deadline = clock.lock().unwrap().now + duration;
+        Box::pin(futures_util::future::poll_fn(move |cx| {
+            let mut clock = clock.lock().unwrap();
+            if clock.now >= deadline {
+                return std::task::Poll::Ready(());
+            }
+            clock.wakers.push(cx.waker().clone());
+            std::task::Poll::Pending
+        }))
+    });
+    mock.expect_clone()
+        .returning_st(move || clock_runtime(spawner.clone(), Arc::clone(&clock)));
+    mock
+}
+
+#[test]
+fn mocked_pubnub_watchdog_ignores_idle_polls() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let clock = Arc::new(std::sync::Mutex::new(Clock::default()));
+    let runtime = clock_runtime(spawner.clone(), Arc::clone(&clock));
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .watchdog_timeout(Duration::from_secs(10 * 60))
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(100, 1))))
+                .unwrap();
+            let subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Connected));
+            requests_rx.next().await.unwrap();
+
+            // Assertions.
+
+            // The idle polls are answered empty at the end of the long-poll,
+            // well past the watchdog timeout in total, but each within it.
+            for t in 101..106 {
+                requests_rx.next().await.unwrap();
+                Clock::advance(&clock, Duration::from_secs(280));
+                responses_tx
+                    .unbounded_send(Ok((vec![], Cursor::new(t, 1))))
+                    .unwrap();
+            }
+            assert_eq!(
+                requests_rx.next().await.unwrap().cursor,
+                Cursor::new(105, 1)
+            );
+            assert_eq!(status_stream.next().now_or_never(), None);
+
+            // A request stuck for the whole timeout is stalled.
+            Clock::advance(&clock, Duration::from_secs(10 * 60));
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Stalled));
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+#[should_panic(expected = "The watchdog timeout must be longer than the subscribe long-poll")]
+fn watchdog_timeout_must_be_longer_than_long_poll() {
+    let _ = Builder::with_components(MockTransport::new(), MockRuntime::new())
+        .watchdog_timeout(Duration::from_secs(280));
+}
+
+#[test]
+fn mocked_pubnub_subscribe_loop_restarts_after_panic() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let delays = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let runtime = recording_local_pool_runtime(spawner.clone(), Arc::clone(&delays));
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let message = Message {
+                message_type: message::Type::Publish,
+                channel: "test_channel".parse().unwrap(),
+                json: object! { "test" => "value" },
+                timetoken: Timetoken { t: 160, r: 1 },
+                ..Message::default()
+            };
+
+            let (cursors_tx, cursors_rx) = mpsc::unbounded();
+            let mut transport = MockTransport::new();
+            transport.expect_clone().times(1).return_once(move || {
+                let mut mock = MockTransport::new();
+
+                let calls = AtomicUsize::new(0);
+                mock.expect_call::<request::Subscribe, response::Subscribe>()
+                    .returning(move |request| {
+                        let _ = cursors_tx.unbounded_send(request.cursor);
+                        let call = calls.fetch_add(1, Ordering::SeqCst);
+                        let message = message.clone();
+                        Box::pin(async move {
+                            match call {
+                                0 => Ok((vec![], Cursor::new(150, 1))),
+                                1 => panic!("bug in the subscribe loop"),
+                                2 => Ok((vec![message], Cursor::new(200, 1))),
+                                _ => pending().await,
+                            }
+                        })
+                    });
+
+                mock
+            });
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            let mut subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+
+            // Assertions.
+
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Connected));
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Crashed));
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Reconnected));
+
+            // The subscription is kept, and the restarted loop resumes from
+            // the cursor of the crashed one.
+            assert_eq!(
+                subscription.next().await.unwrap().timetoken,
+                Timetoken { t: 160, r: 1 }
+            );
+            let cursors: Vec<_> = cursors_rx.take(3).collect().await;
+            assert_eq!(
+                cursors,
+                vec![Cursor::default(), Cursor::new(150, 1), Cursor::new(150, 1)]
+            );
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+
+            // Restarted after a backoff, past the ready timeout.
+            assert_eq!(
+                *delays.lock().unwrap(),
+                vec![Duration::from_secs(10), Duration::from_secs(1)]
+            );
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_loop_stops_panicking_in_a_row() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let delays = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let runtime = recording_local_pool_runtime(spawner.clone(), Arc::clone(&delays));
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let mut transport = MockTransport::new();
+            transport.expect_clone().times(1).return_once(move || {
+                let mut mock = MockTransport::new();
+                mock.expect_call::<request::Subscribe, response::Subscribe>()
+                    .times(6)
+                    .returning(|_| Box::pin(async { panic!("bug in the subscribe loop") }));
+                mock
+            });
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            let mut subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+
+            // Assertions.
+
+            // Restarted with a growing backoff, then given up on.
+            for _ in 0..5 {
+                assert_eq!(status_stream.next().await, Some(StatusEvent::Crashed));
+            }
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Abandoned));
+            assert!(subscription.next().await.is_none());
+            sub_loop_exit_rx.next().await.unwrap();
+
+            // Past the ready timeout.
+            let delays = delays.lock().unwrap();
+            let expected: Vec<_> = [1, 2, 4, 8, 16]
+                .iter()
+                .map(|&secs| Duration::from_secs(secs))
+                .collect();
+            assert_eq!(delays[1..], expected[..]);
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_status_stream_reports_loop_lifecycle() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            // A dropped status stream doesn't affect the others.
+            drop(pubnub.status_stream());
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription = pubnub.subscribe("test_channel".parse().unwrap()).await.unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Connected));
+
+            responses_tx
+                .unbounded_send(Err(MockTransportError::default()))
+                .unwrap();
+            assert_eq!(
+                status_stream.next().await,
+                Some(StatusEvent::Error(
+                    "MockTransportError { fatal: false, unsupported: None, rate_limited: false, transient: false, retry_after: None, invalid_name: None, denied: [] }"
+                        .to_owned()
+                ))
+            );
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Reconnected));
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Disconnected));
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_current_cursor_follows_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+            assert_eq!(pubnub.current_cursor("test_channel"), None);
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+
+            // The next request is issued once the timetoken is recorded.
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+            assert_eq!(
+                pubnub.current_cursor("test_channel"),
+                Some(Cursor::new(150, 1))
+            );
+            assert_eq!(
+                pubnub.current_timetoken("test_channel"),
+                Some(Timetoken { t: 150, r: 1 })
+            );
+            assert_eq!(pubnub.current_cursor("other_channel"), None);
+            assert_eq!(pubnub.current_timetoken("other_channel"), None);
+
+            // Failures keep the timetoken.
+            responses_tx
+                .unbounded_send(Err(MockTransportError::default()))
+                .unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(200, 1))))
+                .unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+            assert_eq!(
+                pubnub.current_cursor("test_channel"),
+                Some(Cursor::new(200, 1))
+            );
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+            assert_eq!(pubnub.current_cursor("test_channel"), None);
+            assert_eq!(pubnub.current_timetoken("test_channel"), None);
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_fatal_error_stops_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            // The access is denied right away.
+            responses_tx
+                .unbounded_send(Err(MockTransportError {
+                    fatal: true,
+                    ..MockTransportError::default()
+                }))
+                .unwrap();
+            let mut subscription = pubnub.subscribe("test_channel".parse().unwrap()).await.unwrap();
+
+            // The error is reported and the loop stops instead of retrying.
+            assert_eq!(
+                status_stream.next().await,
+                Some(StatusEvent::Error(
+                    "MockTransportError { fatal: true, unsupported: None, rate_limited: false, transient: false, retry_after: None, invalid_name: None, denied: [] }"
+                        .to_owned()
+                ))
+            );
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Disconnected));
+            assert!(subscription.next().await.is_none());
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_access_denied_drops_only_denied_destinations() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+            let message = Message {
+                message_type: message::Type::Publish,
+                channel: channel_b.clone(),
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription_b = pubnub.subscribe(channel_b.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Connected));
+
+            let mut subscription_a = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            // The access to one of the channels is denied.
+            let denied = vec![pubsub::SubscribeTo::Channel(channel_a)];
+            responses_tx
+                .unbounded_send(Err(MockTransportError {
+                    fatal: true,
+                    denied: denied.clone(),
+                    ..MockTransportError::default()
+                }))
+                .unwrap();
+
+            // Only the denied channel is dropped, the loop keeps going for
+            // the rest.
+            assert_eq!(
+                status_stream.next().await,
+                Some(StatusEvent::AccessDenied {
+                    destinations: denied,
+                    message: "mock tranport error".to_owned(),
+                })
+            );
+            assert!(subscription_a.next().await.is_none());
+            let request = requests_rx.next().await.unwrap();
+            assert_eq!(request.to, vec![pubsub::SubscribeTo::Channel(channel_b)]);
+            assert_eq!(request.cursor, Cursor::new(150, 1));
+
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(subscription_b.next().await, Some(Arc::new(message)));
+
+            drop(subscription_b);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_ready_timeout() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let (ticks_tx, ticks_rx) = mpsc::unbounded();
+    let runtime = ticking_runtime(spawner.clone(), Arc::new(Mutex::new(ticks_rx)));
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let message = Message {
+                channel: channel.clone(),
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            // The ready timeout is on by default.
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let mut status_stream = pubnub.status_stream();
+
+            // The first request fails, and the retry doesn't complete before
+            // the timeout.
+            responses_tx
+                .unbounded_send(Err(MockTransportError::default()))
+                .unwrap();
+            ticks_tx.unbounded_send(()).unwrap();
+            let mut subscription = pubnub.subscribe(channel.clone()).await.unwrap();
+            assert_eq!(
+                status_stream.next().await,
+                Some(StatusEvent::Error(
+                    "MockTransportError { fatal: false, unsupported: None, rate_limited: false, transient: false, retry_after: None, invalid_name: None, denied: [] }"
+                        .to_owned()
+                ))
+            );
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            // The loop keeps going, and delivers once it connects.
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(status_stream.next().await, Some(StatusEvent::Connected));
+            assert_eq!(subscription.next().await, Some(Arc::new(message)));
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_replay_then_subscribe_hands_off_without_gaps() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (loop_transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let item = |t: u64| history::Item {
+                message: object! { "t" => t },
+                timetoken: t,
+                metadata: object! {},
+            };
+            let message = |t: u64, r| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: object! { "t" => t },
+                metadata: object! {},
+                timetoken: Timetoken { t, r },
+                ..Message::default()
+            };
+
+            // The history is read a page at a time, oldest first.
+            let mut history_transport = MockTransport::new();
+            history_transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::all);
+            let first_page_channel = channel.clone();
+            history_transport
+                .expect_call::<request::GetHistory, response::GetHistory>()
+                .withf(|request| {
+                    request.start.is_none()
+                        && request.end == Some(101)
+                        && request.reverse == Some(true)
+                })
+                .times(1)
+                .return_once(move |_| {
+                    let items = (101..=200).map(item).collect();
+                    let response = vec![(first_page_channel, items)].into_iter().collect();
+                    Box::pin(async { Ok(response) })
+                });
+            let last_page_channel = channel.clone();
+            history_transport
+                .expect_call::<request::GetHistory, response::GetHistory>()
+                .withf(|request| request.end == Some(200))
+                .times(1)
+                .return_once(move |_| {
+                    // Starting with the last replayed message.
+                    let response = vec![(last_page_channel, vec![item(200), item(201)])]
+                        .into_iter()
+                        .collect();
+                    Box::pin(async { Ok(response) })
+                });
+
+            // One clone for the subscribe loop, and one for the stream.
+            let mut transport = MockTransport::new();
+            let mut seq = Sequence::new();
+            transport
+                .expect_clone()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(move || loop_transport.clone());
+            transport
+                .expect_clone()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(move || history_transport);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime).build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let stream = pubnub
+                .replay_then_subscribe(channel.clone(), Timetoken { t: 100, r: 0 })
+                .await
+                .unwrap();
+            futures_util::pin_mut!(stream);
+
+            // Published while the history is being read, so it arrives both
+            // ways, along with one published without storing it.
+            let mut unstored = message(199, 1);
+            unstored.json = object! { "t" => 199, "stored" => false };
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![unstored.clone(), message(201, 1), message(202, 1)],
+                    Cursor::new(202, 1),
+                )))
+                .unwrap();
+
+            for t in 101..=201 {
+                let received = stream.next().await.unwrap().unwrap();
+                assert_eq!(*received, message(t, 0));
+            }
+            let received = stream.next().await.unwrap().unwrap();
+            assert_eq!(*received, unstored);
+            let received = stream.next().await.unwrap().unwrap();
+            assert_eq!(*received, message(202, 1));
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_replay_then_subscribe_does_not_hold_up_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (loop_transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let other_channel: channel::Name = "other_channel".parse().unwrap();
+            let message = |channel: &channel::Name, t: u64| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: object! { "t" => t },
+                metadata: object! {},
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // The history is read until the test lets it go.
+            let (page_tx, page_rx) = oneshot::channel::<()>();
+            let mut history_transport = MockTransport::new();
+            history_transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::all);
+            let page_channel = channel.clone();
+            history_transport
+                .expect_call::<request::GetHistory, response::GetHistory>()
+                .times(1)
+                .return_once(move |_| {
+                    let items = vec![history::Item {
+                        message: object! { "t" => 101 },
+                        timetoken: 101,
+                        metadata: object! {},
+                    }];
+                    let response = vec![(page_channel, items)].into_iter().collect();
+                    Box::pin(async move {
+                        page_rx.await.unwrap();
+                        Ok(response)
+                    })
+                });
+
+            // One clone for the subscribe loop, and one for the stream.
+            let mut transport = MockTransport::new();
+            let mut seq = Sequence::new();
+            transport
+                .expect_clone()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(move || loop_transport.clone());
+            transport
+                .expect_clone()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(move || history_transport);
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscription_buffer(1)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut other_subscription = pubnub.subscribe(other_channel.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            let stream = pubnub
+                .replay_then_subscribe(channel.clone(), Timetoken { t: 100, r: 0 })
+                .await
+                .unwrap();
+            futures_util::pin_mut!(stream);
+            let first = stream.next();
+            futures_util::pin_mut!(first);
+
+            // More live messages than the subscription buffers come while
+            // the history is read, and the other subscriptions still get
+            // theirs.
+            let live: Vec<_> = (200..210).map(|t| message(&channel, t)).collect();
+            for message in &live {
+                responses_tx
+                    .unbounded_send(Ok((vec![message.clone()], message.timetoken.into())))
+                    .unwrap();
+                // Wait for the loop to poll past the message, while the
+                // replay takes it off the subscription.
+                loop {
+                    match select(first.as_mut(), requests_rx.next()).await {
+                        Either::Right((Some(request), _))
+                            if request.cursor.timetoken == message.timetoken.t =>
+                        {
+                            break
+                        }
+                        Either::Right(_) => {}
+                        Either::Left(_) => panic!("the replay should wait for the history"),
+                    }
+                }
+            }
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![message(&other_channel, 210)],
+                    Cursor::new(210, 1),
+                )))
+                .unwrap();
+            let received = select(first.as_mut(), other_subscription.next()).await;
+            let first = match received {
+                Either::Right((received, first)) => {
+                    assert_eq!(received, Some(Arc::new(message(&other_channel, 210))));
+                    first
+                }
+                Either::Left(_) => panic!("the replay should wait for the history"),
+            };
+
+            page_tx.send(()).unwrap();
+            let received = first.await.unwrap().unwrap();
+            assert_eq!(received.timetoken.t, 101);
+            for expected in live {
+                assert_eq!(stream.next().await.unwrap().unwrap(), Arc::new(expected));
+            }
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+/// A transport answering the history requests with the `pages` of the
+/// timetokens, in order, checking the `start` of each request.
+fn history_pages_transport(
+    channel: &channel::Name,
+    pages: Vec<(Option<u64>, Vec<u64>)>,
+) -> MockTransport {
+    let mut transport = MockTransport::new();
+    transport
+        .expect_mock_capabilities()
+        .returning(Capabilities::all);
+    let mut seq = Sequence::new();
+    for (start, timetokens) in pages {
+        let channel = channel.clone();
+        transport
+            .expect_call::<request::GetHistory, response::GetHistory>()
+            .withf(move |request| request.start == start && request.max == Some(2))
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_once(move |_| {
+                let items = timetokens
+                    .into_iter()
+                    .map(|t| history::Item {
+                        message: object! { "t" => t },
+                        timetoken: t,
+                        metadata: object! {},
+                    })
+                    .collect();
+                let response = vec![(channel, items)].into_iter().collect();
+                Box::pin(async { Ok(response) })
+            });
+    }
+    transport
+}
+
+/// A runtime that is only cloned, never used.
+fn cloneable_runtime() -> MockRuntime {
+    let mut runtime = MockRuntime::new();
+    runtime.expect_clone().returning(MockRuntime::new);
+    runtime
+}
+
+/// Collect the timetokens of the messages of the history of the `channel`.
+async fn history_timetokens(
+    pubnub: &PubNub<MockTransport, MockRuntime>,
+    channel: &channel::Name,
+) -> Vec<u64> {
+    let options = history::IterOptions {
+        count: 2,
+        ..history::IterOptions::default()
+    };
+    pubnub
+        .history_iter(channel.clone(), options)
+        .map(|message| message.unwrap().timetoken.t)
+        .collect()
+        .await
+}
+
+#[test]
+fn mocked_pubnub_history_iter_pages_backward() {
+    init();
+    block_on(async {
+        let channel: channel::Name = "test_channel".parse().unwrap();
+        // The last page is short, so there's no request after it.
+        let history_transport = history_pages_transport(
+            &channel,
+            vec![
+                (None, vec![40, 50]),
+                (Some(40), vec![30, 20]),
+                (Some(20), vec![10]),
+            ],
+        );
+
+        let mut transport = MockTransport::new();
+        transport
+            .expect_clone()
+            .return_once(move || history_transport);
+        let pubnub = Builder::with_components(transport, cloneable_runtime()).build();
+
+        assert_eq!(
+            history_timetokens(&pubnub, &channel).await,
+            vec![50, 40, 30, 20, 10]
+        );
+    })
+}
+
+#[test]
+fn mocked_pubnub_history_iter_stops_on_unexpected_pages() {
+    init();
+    block_on(async {
+        let channel: channel::Name = "test_channel".parse().unwrap();
+        let empty_transport =
+            history_pages_transport(&channel, vec![(None, vec![40, 50]), (Some(40), vec![])]);
+        // A full page, but with the same messages.
+        let stale_transport = history_pages_transport(
+            &channel,
+            vec![(None, vec![40, 50]), (Some(40), vec![40, 50])],
+        );
+
+        let mut transport = MockTransport::new();
+        let mut seq = Sequence::new();
+        transport
+            .expect_clone()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_once(move || empty_transport);
+        transport
+            .expect_clone()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_once(move || stale_transport);
+        let pubnub = Builder::with_components(transport, cloneable_runtime()).build();
+
+        assert_eq!(history_timetokens(&pubnub, &channel).await, vec![50, 40]);
+        assert_eq!(history_timetokens(&pubnub, &channel).await, vec![50, 40]);
+    })
+}
+
+#[test]
+fn mocked_pubnub_subscribe_from_sends_region() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 7))))
+                .unwrap();
+            let subscription = pubnub
+                .subscribe_from(channel.clone(), Cursor::new(100, 4))
+                .await
+                .unwrap();
+
+            // The initial poll carries the region, and the next ones carry
+            // the region of the network.
+            assert_eq!(
+                requests_rx.next().await.unwrap().cursor,
+                Cursor::new(100, 4)
+            );
+            assert_eq!(
+                requests_rx.next().await.unwrap().cursor,
+                Cursor::new(150, 7)
+            );
+            assert_eq!(
+                pubnub.current_cursor("test_channel"),
+                Some(Cursor::new(150, 7))
+            );
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_resilient_subscribe_resumes_from_last_timetoken() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+    let next_runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+            let (next_sub_loop_exit_tx, mut next_sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (next_transport, mut next_requests_rx, next_responses_tx) =
+                scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let message = |t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+            let mut next_pubnub = Some(
+                Builder::with_components(next_transport, next_runtime)
+                    .subscribe_loop_exit_tx(next_sub_loop_exit_tx)
+                    .build(),
+            );
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 3))))
+                .unwrap();
+            let stream = pubnub
+                .resilient_subscribe(channel.clone(), move || {
+                    let next_pubnub = next_pubnub.take();
+                    async move { next_pubnub }
+                })
+                .await
+                .unwrap();
+            futures_util::pin_mut!(stream);
+
+            responses_tx
+                .unbounded_send(Ok((vec![message(200)], Cursor::new(200, 3))))
+                .unwrap();
+            assert_eq!(stream.next().await, Some(Arc::new(message(200))));
+
+            // The subscription ends, and the stream carries on with the
+            // reconnected client from the last delivered message.
+            responses_tx
+                .unbounded_send(Err(MockTransportError {
+                    fatal: true,
+                    ..MockTransportError::default()
+                }))
+                .unwrap();
+            next_responses_tx
+                .unbounded_send(Ok((vec![message(200), message(250)], Cursor::new(250, 3))))
+                .unwrap();
+            assert_eq!(stream.next().await, Some(Arc::new(message(250))));
+            assert_eq!(
+                next_requests_rx.next().await.unwrap().cursor,
+                // In the region of the loop, not the one of the message.
+                Cursor::new(200, 3)
+            );
+            sub_loop_exit_rx.next().await.unwrap();
+
+            // No more clients to reconnect with, the stream ends.
+            next_responses_tx
+                .unbounded_send(Err(MockTransportError {
+                    fatal: true,
+                    ..MockTransportError::default()
+                }))
+                .unwrap();
+            assert_eq!(stream.next().await, None);
+            next_sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_resilient_subscribe_rewinds_running_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (mut transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+            transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::empty);
+            // The client the stream reconnects with adds the channel to the
+            // running loop, so its transport isn't used.
+            transport.expect_clone().returning(MockTransport::new);
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+            let message = |channel: &channel::Name, t| Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                timetoken: Timetoken { t, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            // The other channel keeps the loop running all along.
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription_b = pubnub.subscribe(channel_b.clone()).await.unwrap();
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+
+            let mut reconnect_pubnub = Some(pubnub.clone());
+            let stream_a = pubnub
+                .resilient_subscribe(channel_a.clone(), move || {
+                    let reconnect_pubnub = reconnect_pubnub.take();
+                    async move { reconnect_pubnub }
+                })
+                .await
+                .unwrap();
+            futures_util::pin_mut!(stream_a);
+            requests_rx.next().await.unwrap();
+
+            responses_tx
+                .unbounded_send(Ok((vec![message(&channel_a, 200)], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(
+                stream_a.next().await,
+                Some(Arc::new(message(&channel_a, 200)))
+            );
+            requests_rx.next().await.unwrap();
+
+            // The subscription to the channel ends, and the loop carries on
+            // with the other one before the stream reconnects.
+            responses_tx
+                .unbounded_send(Err(MockTransportError {
+                    fatal: true,
+                    denied: vec![pubsub::SubscribeTo::Channel(channel_a.clone())],
+                    ..MockTransportError::default()
+                }))
+                .unwrap();
+            assert_eq!(requests_rx.next().await.unwrap().to.len(), 1);
+            responses_tx
+                .unbounded_send(Ok((vec![message(&channel_b, 300)], Cursor::new(300, 1))))
+                .unwrap();
+            assert_eq!(
+                subscription_b.next().await,
+                Some(Arc::new(message(&channel_b, 300)))
+            );
+            assert_eq!(
+                requests_rx.next().await.unwrap().cursor,
+                Cursor::new(300, 1)
+            );
+
+            // The loop rewinds to the last delivered message, so the one
+            // published in the gap isn't lost, and the other channel doesn't
+            // get its message again.
+            let next = stream_a.next();
+            futures_util::pin_mut!(next);
+            let request = match select(next, requests_rx.next()).await {
+                Either::Right((request, next)) => {
+                    responses_tx
+                        .unbounded_send(Ok((
+                            vec![message(&channel_a, 250), message(&channel_b, 300)],
+                            Cursor::new(300, 1),
+                        )))
+                        .unwrap();
+                    assert_eq!(next.await, Some(Arc::new(message(&channel_a, 250))));
+                    request.unwrap()
+                }
+                Either::Left(_) => panic!("the stream didn't reconnect"),
+            };
+            assert_eq!(request.to.len(), 2);
+            assert_eq!(request.cursor, Cursor::new(200, 1));
+            requests_rx.next().await.unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![message(&channel_b, 350)], Cursor::new(350, 1))))
+                .unwrap();
+            assert_eq!(
+                subscription_b.next().await,
+                Some(Arc::new(message(&channel_b, 350)))
+            );
+
+            pubnub.shutdown().await;
+            assert_eq!(stream_a.next().await, None);
+            assert_eq!(subscription_b.next().await, None);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_resilient_subscribe_ends_on_request() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (mut transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+            transport
+                .expect_mock_capabilities()
+                .returning(Capabilities::empty);
+
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+            let message = Message {
+                message_type: message::Type::Publish,
+                channel: channel_b.clone(),
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
 
-                        mock.expect_call::<request::Subscribe, response::Subscribe>()
-                            .times(1)
-                            .in_sequence(&mut seq)
-                            .with(eq(request::Subscribe {
-                                to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                                timetoken: Timetoken { t: 150, r: 1 },
-                                heartbeat: None,
-                            }))
-                            .return_once(move |_| {
-                                Box::pin(async move {
-                                    // Request drop.
-                                    sub_drop_req_tx.send(()).unwrap();
+            let reconnects = Arc::new(AtomicUsize::new(0));
+            let reconnect = || {
+                let reconnects = Arc::clone(&reconnects);
+                move || {
+                    reconnects.fetch_add(1, Ordering::SeqCst);
+                    async { None }
+                }
+            };
 
-                                    // Wait for the drop to complete.
-                                    sub_drop_done_rx.await.unwrap();
-                                    unreachable!();
-                                })
-                            });
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let stream_a = pubnub
+                .resilient_subscribe(channel_a.clone(), reconnect())
+                .await
+                .unwrap();
+            futures_util::pin_mut!(stream_a);
+            requests_rx.next().await.unwrap();
+            requests_rx.next().await.unwrap();
+            let stream_b = pubnub
+                .resilient_subscribe(channel_b, reconnect())
+                .await
+                .unwrap();
+            futures_util::pin_mut!(stream_b);
+            requests_rx.next().await.unwrap();
 
-                        mock
-                    });
+            // Unsubscribing ends the stream, instead of subscribing again.
+            pubnub.unsubscribe(channel_a).await;
+            assert_eq!(stream_a.next().await, None);
+            requests_rx.next().await.unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(stream_b.next().await, Some(Arc::new(message)));
 
-                mock
+            // So does shutting down.
+            pubnub.shutdown().await;
+            assert_eq!(stream_b.next().await, None);
+            sub_loop_exit_rx.next().await.unwrap();
+
+            assert_eq!(reconnects.load(Ordering::SeqCst), 0);
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn mocked_pubnub_metrics_count_activity() {
+    use crate::metrics::{Metrics, Traffic};
+
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (mut transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+            transport
+                .expect_call::<request::Publish, response::Publish>()
+                .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 100, r: 1 })) }));
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let message = Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: object! {
+                    "test" => "value",
+                },
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
             };
 
-            let mock_runtime = {
-                let mut mock = MockRuntime::new();
-                mock.expect_mock_workaround_spawn::<()>()
-                    .returning_st(move |future| {
-                        spawner1.spawn(future).unwrap();
-                    });
-                mock.expect_clone().times(1).return_once_st(move || {
-                    // We got cloned, that has to be subscription's runtime
-                    // clone.
-                    let mut mock = MockRuntime::new();
+            // Invocations.
 
-                    mock.expect_mock_workaround_spawn::<()>()
-                        .returning_st(move |future| {
-                            spawner2.spawn(future).unwrap();
-                        });
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
 
-                    mock
-                });
-                mock
+            pubnub
+                .publish(channel.clone(), message.json.clone())
+                .await
+                .unwrap();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut subscription = pubnub.subscribe(channel).await.unwrap();
+
+            responses_tx
+                .unbounded_send(Err(MockTransportError::default()))
+                .unwrap();
+            responses_tx
+                .unbounded_send(Ok((vec![message.clone()], Cursor::new(200, 1))))
+                .unwrap();
+            assert_eq!(subscription.next().await, Some(Arc::new(message)));
+
+            assert_eq!(
+                pubnub.metrics(),
+                Metrics {
+                    messages_received: 1,
+                    messages_published: 1,
+                    messages_published_via_post: 0,
+                    subscribe_reconnects: 1,
+                    active_subscriptions: 1,
+                    traffic: Traffic::default(),
+                }
+            );
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+            assert_eq!(pubnub.metrics().active_subscriptions, 0);
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[cfg(feature = "metrics-prometheus")]
+#[test]
+fn mocked_pubnub_register_prometheus_namespaces_clients() {
+    init();
+    let registry = prometheus::Registry::new();
+    let transport = || {
+        let mut mock = MockTransport::new();
+        mock.expect_clone().returning(MockTransport::new);
+        mock
+    };
+
+    let pubnub_a = Builder::with_components(transport(), MockRuntime::new()).build();
+    let pubnub_b = Builder::with_components(transport(), MockRuntime::new()).build();
+
+    pubnub_a.register_prometheus(&registry, "a").unwrap();
+    pubnub_b.register_prometheus(&registry, "b").unwrap();
+
+    // The same name can't be registered twice.
+    assert!(pubnub_b.register_prometheus(&registry, "a").is_err());
+
+    let families = registry.gather();
+    let published = families
+        .iter()
+        .find(|family| family.get_name() == "pubnub_messages_published_total")
+        .unwrap();
+    assert_eq!(published.get_metric().len(), 2);
+}
+
+#[cfg(feature = "cipher")]
+#[test]
+fn mocked_pubnub_publish_encrypts_payload() {
+    use pubnub_util::cipher::Cipher;
+
+    init();
+    block_on(async {
+        let mut mock_transport = MockTransport::new();
+        let mock_runtime = MockRuntime::new();
+
+        let message = object! {
+            "text" => "Hello, world!",
+        };
+
+        let expected = message.dump();
+        mock_transport
+            .expect_call::<request::Publish, response::Publish>()
+            .withf(move |request| {
+                let encrypted = request.payload.as_str().unwrap();
+                let decrypted = Cipher::new("enigma").decrypt(encrypted).unwrap();
+                request.meta.is_none() && decrypted == expected.as_bytes()
+            })
+            .returning(|_| Box::pin(async { Ok(sent(Timetoken { t: 123, r: 456 })) }));
+
+        let pubnub = Builder::with_components(mock_transport, mock_runtime)
+            .cipher_key("enigma")
+            .build();
+
+        let timetoken = pubnub
+            .publish("test_channel".parse().unwrap(), message)
+            .await
+            .expect("unexpected failure");
+        assert_eq!(timetoken.t, 123);
+    })
+}
+
+#[cfg(feature = "cipher")]
+#[test]
+fn mocked_pubnub_subscribe_decrypts_payload() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+
+            let encrypted = Message {
+                message_type: message::Type::Publish,
+                channel: channel.clone(),
+                json: "AAECAwQFBgcICQoLDA0ODzVe9wtjezP3sRj23aJOhPHDif0Pq+mvL7ow74sLTPyX".into(),
+                timetoken: Timetoken { t: 200, r: 1 },
+                ..Message::default()
+            };
+            let undecryptable = Message {
+                json: "not encrypted".into(),
+                timetoken: Timetoken { t: 100, r: 1 },
+                ..encrypted.clone()
             };
 
             // Invocations.
 
-            let mut pubnub = Builder::with_components(mock_transport, mock_runtime)
+            let mut pubnub = Builder::with_components(transport, runtime)
                 .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .cipher_key(String::from("enigma"))
                 .build();
 
-            let mut subscription = pubnub.subscribe(test_channel.clone()).await;
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(50, 1))))
+                .unwrap();
+            let mut subscription = pubnub.subscribe(channel).await.unwrap();
 
-            let message = subscription.next().await;
-            // We got the message we expected to get.
-            assert!(message.is_some());
+            responses_tx
+                .unbounded_send(Ok((
+                    vec![undecryptable.clone(), encrypted.clone()],
+                    Cursor::new(200, 1),
+                )))
+                .unwrap();
 
-            // Wait for the drop request.
-            sub_drop_req_rx.await.unwrap();
+            // The undecryptable message is delivered as-is.
+            assert_eq!(subscription.next().await, Some(Arc::new(undecryptable)));
+            assert_eq!(
+                subscription.next().await,
+                Some(Arc::new(Message {
+                    json: object! {
+                        "text" => "Hello, world!",
+                    },
+                    decrypted: true,
+                    ..encrypted
+                }))
+            );
+
+            drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
+
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_sends_filter_expression() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let (transport, mut requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let filter_expr: FilterExpression = "uuid != 'me'".parse().unwrap();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .filter_expression(filter_expr.clone())
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let subscription = pubnub
+                .subscribe("test_channel".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(
+                requests_rx.next().await.unwrap().filter_expr,
+                Some(filter_expr)
+            );
 
-            // Drop the subscription, which will cause loop termination.
             drop(subscription);
+            sub_loop_exit_rx.next().await.unwrap();
+        })
+        .unwrap();
 
-            // Wait for the loop termination.
+    pool.run();
+}
+
+#[test]
+fn mocked_pubnub_subscribe_with_filter_uses_separate_loops() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+    let done = Arc::new(AtomicUsize::new(0));
+    let done_clone = Arc::clone(&done);
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            // Every loop gets its own transport clone, which acknowledges the
+            // initial subscribe and keeps the following requests hanging.
+            let (requests_tx, mut requests_rx) = mpsc::unbounded::<request::Subscribe>();
+            let mut transport = MockTransport::new();
+            transport.expect_clone().times(2).returning(move || {
+                let requests_tx = requests_tx.clone();
+                let mut mock = MockTransport::new();
+                mock.expect_call::<request::Subscribe, response::Subscribe>()
+                    .returning(move |request| {
+                        let initial = request.cursor.timetoken == 0;
+                        let _ = requests_tx.unbounded_send(request);
+                        Box::pin(async move {
+                            if initial {
+                                Ok((vec![], Cursor::new(100, 1)))
+                            } else {
+                                pending().await
+                            }
+                        })
+                    });
+                mock
+            });
+
+            let default_filter: FilterExpression = "uuid != 'me'".parse().unwrap();
+            let urgent_filter: FilterExpression = "meta.priority > 2".parse().unwrap();
+            let channel_a: channel::Name = "test_channel_a".parse().unwrap();
+            let channel_b: channel::Name = "test_channel_b".parse().unwrap();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .filter_expression(default_filter.clone())
+                .build();
+
+            let _subscription_a = pubnub.subscribe(channel_a.clone()).await.unwrap();
+            let _subscription_b = pubnub
+                .subscribe_with_filter(channel_b.clone(), urgent_filter.clone())
+                .await
+                .unwrap();
+            // Subscribing with the default filter reuses its loop.
+            let _subscription_a2 = pubnub
+                .subscribe_with_filter(channel_a.clone(), default_filter.clone())
+                .await
+                .unwrap();
+
+            // Each loop keeps its own filter and channels.
+            let mut requests = Vec::new();
+            for _ in 0..4 {
+                let request = requests_rx.next().await.unwrap();
+                requests.push((request.filter_expr, request.to));
+            }
+            let to_a = vec![pubsub::SubscribeTo::Channel(channel_a)];
+            let to_b = vec![pubsub::SubscribeTo::Channel(channel_b)];
+            for (filter_expr, to) in requests {
+                if filter_expr == Some(default_filter.clone()) {
+                    assert_eq!(to, to_a);
+                } else {
+                    assert_eq!(filter_expr, Some(urgent_filter.clone()));
+                    assert_eq!(to, to_b);
+                }
+            }
+            done_clone.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+    // The loops never exit, so run until they wait on the hanging requests.
+    pool.run_until_stalled();
+    assert_eq!(done.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn mocked_pubnub_current_cursor_outlives_other_filter_loop() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+    let done = Arc::new(AtomicUsize::new(0));
+    let done_clone = Arc::clone(&done);
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let default_filter: FilterExpression = "uuid != 'me'".parse().unwrap();
+            let urgent_filter: FilterExpression = "meta.priority > 2".parse().unwrap();
+
+            // Every loop gets its own transport clone, which acknowledges the
+            // initial subscribe with a cursor of its own, and keeps the
+            // following requests hanging.
+            let (requests_tx, mut requests_rx) = mpsc::unbounded::<request::Subscribe>();
+            let mut transport = MockTransport::new();
+            let urgent = urgent_filter.clone();
+            transport.expect_clone().times(2).returning(move || {
+                let requests_tx = requests_tx.clone();
+                let urgent = urgent.clone();
+                let mut mock = MockTransport::new();
+                mock.expect_call::<request::Subscribe, response::Subscribe>()
+                    .returning(move |request| {
+                        let initial = request.cursor.timetoken == 0;
+                        let timetoken = if request.filter_expr.as_ref() == Some(&urgent) {
+                            200
+                        } else {
+                            100
+                        };
+                        let _ = requests_tx.unbounded_send(request);
+                        Box::pin(async move {
+                            if initial {
+                                Ok((vec![], Cursor::new(timetoken, 1)))
+                            } else {
+                                pending().await
+                            }
+                        })
+                    });
+                mock
+            });
+
+            let (sub_loop_exit_tx, mut sub_loop_exit_rx) = mpsc::channel::<()>(1);
+            let channel: channel::Name = "test_channel".parse().unwrap();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .filter_expression(default_filter)
+                .subscribe_loop_exit_tx(sub_loop_exit_tx)
+                .build();
+
+            let _subscription = pubnub.subscribe(channel.clone()).await.unwrap();
+            let urgent_subscription = pubnub
+                .subscribe_with_filter(channel, urgent_filter)
+                .await
+                .unwrap();
+
+            // The next requests are issued once the cursors are recorded.
+            for _ in 0..4 {
+                requests_rx.next().await.unwrap();
+            }
+            assert_eq!(
+                pubnub.current_cursor("test_channel"),
+                Some(Cursor::new(200, 1))
+            );
+
+            // The loop of the other filter still polls the channel.
+            drop(urgent_subscription);
             sub_loop_exit_rx.next().await.unwrap();
+            assert_eq!(
+                pubnub.current_cursor("test_channel"),
+                Some(Cursor::new(100, 1))
+            );
+            done_clone.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
 
-            // Notify that we've completed with the drop request.
-            // Since the loop is now dead, and we were locked on `sub_drop_done_rx`
-            // in the response future, this send *has to fail* send error, cause
-            // loop termination dropped the response future and the
-            // `sub_drop_done_rx` with it (cuase response future owned
-            // `sub_drop_done_rx` afetr we moved it).
-            sub_drop_done_tx.send(()).unwrap_err();
+    // The remaining loop never exits, so run until it waits on the hanging
+    // request.
+    pool.run_until_stalled();
+    assert_eq!(done.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "broadcast")]
+#[test]
+fn mocked_pubnub_subscribe_broadcast_shares_messages() {
+    init();
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+    let runtime = local_pool_runtime(spawner.clone());
+
+    spawner
+        .spawn_local(async move {
+            // Setup.
+
+            let (transport, _requests_rx, responses_tx) = scripted_subscribe_transport();
+
+            let channel: channel::Name = "test_channel".parse().unwrap();
+            let messages: Vec<_> = (1..=3)
+                .map(|i| Message {
+                    message_type: message::Type::Publish,
+                    channel: channel.clone(),
+                    json: object! {
+                        "i" => i,
+                    },
+                    timetoken: Timetoken { t: 150 + i, r: 1 },
+                    ..Message::default()
+                })
+                .collect();
+
+            // Invocations.
+
+            let mut pubnub = Builder::with_components(transport, runtime)
+                .subscription_buffer(1)
+                .build();
+
+            responses_tx
+                .unbounded_send(Ok((vec![], Cursor::new(150, 1))))
+                .unwrap();
+            let mut fast = pubnub.subscribe_broadcast(channel.clone()).await.unwrap();
+            let mut slow = pubnub.subscribe_broadcast(channel.clone()).await.unwrap();
+            let slow_dropped = slow.dropped_counter();
+
+            // One message at a time, so that the fast stream keeps up.
+            let mut received = Vec::new();
+            for message in &messages {
+                responses_tx
+                    .unbounded_send(Ok((vec![message.clone()], Cursor::from(message.timetoken))))
+                    .unwrap();
+                let shared = fast.next().await.unwrap().unwrap();
+                assert_eq!(&*shared, message);
+                received.push(shared);
+            }
+
+            // The slow stream only has room for the last message.
+            assert_eq!(slow.next().await, Some(Err(Lagged(2))));
+            let shared = slow.next().await.unwrap().unwrap();
+            assert!(Arc::ptr_eq(&shared, &received[2]));
+            assert_eq!(slow.dropped(), 2);
+            assert_eq!(slow_dropped.get(), 2);
+            assert_eq!(fast.dropped(), 0);
+
+            // Unsubscribing ends all the streams.
+            pubnub.unsubscribe(channel).await;
+            assert_eq!(fast.next().await, None);
+            assert_eq!(slow.next().await, None);
         })
         .unwrap();
 
-    pool.run()
+    pool.run();
+}
+
+/// A transport with only the services [`Transport`](crate::Transport)
+/// requires, i.e. without any of the optional endpoint groups.
+#[derive(Debug, Clone)]
+struct PartialTransport;
+
+#[async_trait::async_trait]
+impl crate::transport::Service<request::Publish> for PartialTransport {
+    type Response = response::Publish;
+    type Error = MockTransportError;
+
+    async fn call(&self, _req: request::Publish) -> Result<Self::Response, Self::Error> {
+        Ok(sent(Timetoken { t: 123, r: 0 }))
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::transport::Service<request::Subscribe> for PartialTransport {
+    type Response = response::Subscribe;
+    type Error = MockTransportError;
+
+    async fn call(&self, _req: request::Subscribe) -> Result<Self::Response, Self::Error> {
+        Err(MockTransportError::default())
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::transport::Service<request::Leave> for PartialTransport {
+    type Response = response::Leave;
+    type Error = MockTransportError;
+
+    async fn call(&self, _req: request::Leave) -> Result<Self::Response, Self::Error> {
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::transport::Service<request::Time> for PartialTransport {
+    type Response = response::Time;
+    type Error = MockTransportError;
+
+    async fn call(&self, _req: request::Time) -> Result<Self::Response, Self::Error> {
+        Ok(Timetoken { t: 123, r: 0 })
+    }
+}
+
+impl crate::Transport for PartialTransport {
+    type Error = MockTransportError;
+}
+
+#[test]
+fn partial_transport_publishes_without_optional_endpoints() {
+    init();
+    block_on(async {
+        let mut pubnub = Builder::with_components(PartialTransport, MockRuntime::new()).build();
+        // Dropped by the default of the transport.
+        pubnub.set_token("my-token");
+
+        let timetoken = pubnub
+            .publish(
+                "test_channel".parse().unwrap(),
+                object! { "test" => "value" },
+            )
+            .await
+            .expect("unexpected failure");
+        assert_eq!(timetoken.t, 123);
+
+        let time = pubnub.time().await.expect("unexpected failure");
+        assert_eq!(time.t, 123);
+    })
 }
@@ -0,0 +1,58 @@
+use super::PubNub;
+use crate::data::request;
+use crate::data::timetoken::Timetoken;
+use crate::runtime::Runtime;
+use crate::transport::Transport;
+
+impl<TTransport, TRuntime> PubNub<TTransport, TRuntime>
+where
+    TTransport: Transport + 'static,
+    TRuntime: Runtime + 'static,
+{
+    /// Fetch the current time of the PubNub network.
+    ///
+    /// The timetoken carries no region.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors.
+    pub async fn time(&self) -> Result<Timetoken, <TTransport as Transport>::Error> {
+        self.transport.call(request::Time).await
+    }
+
+    /// Open a connection to the PubNub network ahead of the first request.
+    ///
+    /// The first request otherwise pays for the TCP and the TLS handshakes.
+    /// This makes a cheap [`time`](Self::time) call instead, and the
+    /// transports that pool their connections keep it open for the requests
+    /// that follow. Safe to call any number of times, the calls after the
+    /// first one reuse the pooled connection too.
+    ///
+    /// # Errors
+    ///
+    /// Returns transport-specific errors, i.e. when the network is
+    /// unreachable.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use pubnub_core::mock::{transport::MockTransport, runtime::MockRuntime};
+    /// # let transport = MockTransport::new();
+    /// # let runtime = MockRuntime::new();
+    /// use pubnub_core::{json::object, Builder};
+    ///
+    /// # async {
+    /// let pubnub = Builder::with_components(transport, runtime).build();
+    /// pubnub.connect().await?;
+    ///
+    /// // Sent over the connection opened above.
+    /// pubnub
+    ///     .publish("my-channel".parse().unwrap(), object! { "ready" => true })
+    ///     .await?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// # };
+    /// ```
+    pub async fn connect(&self) -> Result<(), <TTransport as Transport>::Error> {
+        self.time().await.map(|_| ())
+    }
+}
@@ -1,10 +1,94 @@
-use std::fmt::Debug;
+use futures_channel::oneshot;
+use futures_util::future::FutureExt;
+use std::fmt::{self, Debug};
 use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use thiserror::Error;
 
 /// Runtime abstracts away the underlying runtime we use for task scheduling.
 pub trait Runtime: Clone + Send + Sync + Unpin + Debug {
     /// Spawn a [`Future`] to run as a task in some executor.
-    fn spawn<F>(&self, future: F)
+    ///
+    /// The returned handle resolves once the task ends, and reports whether
+    /// it panicked. Dropping the handle detaches the task, it keeps running.
+    fn spawn<F>(&self, future: F) -> JoinHandle
     where
         F: Future<Output = ()> + Send + 'static;
+
+    /// A [`Future`] that completes after the `duration`.
+    ///
+    /// Used to enforce the deadlines, see
+    /// [`PubNub::with_timeout`](crate::PubNub::with_timeout), and to pace
+    /// the retries and the heartbeats.
+    fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+}
+
+/// A handle to a task spawned with [`Runtime::spawn`], resolving once the
+/// task ends.
+pub struct JoinHandle {
+    inner: Pin<Box<dyn Future<Output = Result<(), JoinError>> + Send + 'static>>,
+}
+
+impl JoinHandle {
+    /// Wrap the `future` resolving once the task ends, i.e. the join handle
+    /// of the underlying executor.
+    pub fn new<F>(future: F) -> Self
+    where
+        F: Future<Output = Result<(), JoinError>> + Send + 'static,
+    {
+        Self {
+            inner: Box::pin(future),
+        }
+    }
+
+    /// Catch the panics of the `future`, for the executors that don't.
+    ///
+    /// Returns the future to spawn instead of the original one, and the
+    /// handle to it.
+    pub fn catching<F>(future: F) -> (impl Future<Output = ()> + Send + 'static, Self)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (result_tx, result_rx) = oneshot::channel();
+        let task = async move {
+            let result = AssertUnwindSafe(future)
+                .catch_unwind()
+                .await
+                .map_err(|_| JoinError::Panicked);
+            // The handle may be dropped already.
+            let _ = result_tx.send(result);
+        };
+        // The executor dropped the task before it ended.
+        let handle = Self::new(result_rx.map(|result| result.unwrap_or(Err(JoinError::Cancelled))));
+        (task, handle)
+    }
+}
+
+impl Future for JoinHandle {
+    type Output = Result<(), JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+impl Debug for JoinHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinHandle").finish()
+    }
+}
+
+/// The reason a task spawned with [`Runtime::spawn`] ended abnormally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum JoinError {
+    /// The task panicked.
+    #[error("task panicked")]
+    Panicked,
+
+    /// The task was dropped before it ended, i.e. with the executor.
+    #[error("task was cancelled")]
+    Cancelled,
 }
@@ -0,0 +1,158 @@
+//! Subscriptions shared by many consumers.
+
+use super::channel::DroppedCounter;
+use super::subscription::Subscription;
+use crate::data::{channel, message::Message};
+use crate::runtime::Runtime;
+use futures_util::lock::{Mutex, MutexGuard};
+use futures_util::stream::{Stream, StreamExt};
+use futures_util::task::{Context, Poll};
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::broadcast::{self, RecvError};
+
+type Sender = broadcast::Sender<Arc<Message>>;
+
+/// # Shared PubNub message stream
+///
+/// This is the message stream returned by [`PubNub::subscribe_broadcast`].
+/// All the streams for a channel share a single [`Subscription`], and the
+/// messages are shared between the streams rather than cloned for each of
+/// them.
+///
+/// A stream that falls behind by more than the buffer size misses the
+/// oldest messages, and yields a [`Lagged`] error in their place. The total
+/// number of the missed messages is kept in a [`DroppedCounter`].
+///
+/// [`PubNub::subscribe_broadcast`]: crate::pubnub::PubNub::subscribe_broadcast
+#[derive(Debug)]
+pub struct BroadcastSubscription {
+    channel: channel::Name,
+    receiver: broadcast::Receiver<Arc<Message>>,
+    dropped: DroppedCounter,
+}
+
+impl BroadcastSubscription {
+    fn new(channel: channel::Name, receiver: broadcast::Receiver<Arc<Message>>) -> Self {
+        Self {
+            channel,
+            receiver,
+            dropped: DroppedCounter::default(),
+        }
+    }
+
+    /// The total number of the messages the stream missed so far.
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    /// A handle to the number of the missed messages, to keep an eye on it
+    /// while the stream is consumed elsewhere.
+    #[must_use]
+    pub fn dropped_counter(&self) -> DroppedCounter {
+        self.dropped.clone()
+    }
+}
+
+/// The [`BroadcastSubscription`] fell behind, and missed the given number
+/// of messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Missed {0} messages")]
+pub struct Lagged(pub u64);
+
+/// `BroadcastSubscription` is a stream.
+impl Stream for BroadcastSubscription {
+    type Item = Result<Arc<Message>, Lagged>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        Stream::poll_next(Pin::new(&mut this.receiver), cx).map(|item| match item {
+            Some(Ok(message)) => Some(Ok(message)),
+            Some(Err(RecvError::Lagged(missed))) => {
+                warn!(
+                    "Broadcast stream for {} missed {} messages",
+                    this.channel, missed
+                );
+                #[cfg(feature = "tracing")]
+                tracing::warn!(channel = %this.channel, missed, "Dropped messages");
+                this.dropped.add(missed);
+                Some(Err(Lagged(missed)))
+            }
+            Some(Err(RecvError::Closed)) | None => None,
+        })
+    }
+}
+
+/// The channels subscribed to with the [`BroadcastSubscription`]s.
+///
+/// Clones share the set of the channels.
+#[derive(Debug, Clone)]
+pub(crate) struct Broadcasts {
+    capacity: usize,
+    senders: Arc<Mutex<HashMap<channel::Name, Sender>>>,
+}
+
+impl Broadcasts {
+    /// Create an empty set, buffering `capacity` messages for each stream.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            senders: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Lock the set of the channels.
+    pub async fn lock(&self) -> MutexGuard<'_, HashMap<channel::Name, Sender>> {
+        self.senders.lock().await
+    }
+
+    /// Start a new broadcast for the `channel`, forwarding the messages from
+    /// the `subscription` on the `runtime`.
+    ///
+    /// The broadcast ends when the subscription ends, or when all its streams
+    /// are dropped.
+    pub fn start<TRuntime: Runtime + 'static>(
+        &self,
+        senders: &mut HashMap<channel::Name, Sender>,
+        channel: channel::Name,
+        mut subscription: Subscription<TRuntime>,
+        runtime: &TRuntime,
+    ) -> BroadcastSubscription {
+        let (sender, receiver) = broadcast::channel(self.capacity);
+        senders.insert(channel.clone(), sender.clone());
+        let stream = BroadcastSubscription::new(channel.clone(), receiver);
+
+        let broadcasts = self.clone();
+        runtime.spawn(async move {
+            while let Some(message) = subscription.next().await {
+                if sender.send(message).is_ok() {
+                    continue;
+                }
+
+                // All the streams are gone, unless a new one was added in the
+                // meantime. The set is locked when adding the streams.
+                let mut senders = broadcasts.lock().await;
+                if sender.receiver_count() == 0 {
+                    debug!("Stopping the broadcast for {}", channel);
+                    senders.remove(&channel);
+                    return;
+                }
+            }
+
+            // The subscription ended, so end the streams too.
+            debug!("Broadcast subscription for {} ended", channel);
+            broadcasts.lock().await.remove(&channel);
+        });
+
+        stream
+    }
+}
+
+/// Add a stream for the `channel` to a running broadcast.
+pub(crate) fn join(channel: channel::Name, sender: &Sender) -> BroadcastSubscription {
+    BroadcastSubscription::new(channel, sender.subscribe())
+}
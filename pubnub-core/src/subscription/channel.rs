@@ -1,5 +1,70 @@
 use crate::data::message::Message;
 use futures_channel::mpsc;
+use log::{error, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
-pub(crate) type Tx = mpsc::Sender<Message>;
-pub(crate) type Rx = mpsc::Receiver<Message>;
+// The listeners of a channel share the messages.
+pub(crate) type Tx = mpsc::Sender<Arc<Message>>;
+pub(crate) type Rx = mpsc::Receiver<Arc<Message>>;
+
+/// Create the channel of a subscription stream, fitting `buffer` messages.
+pub(crate) fn channel(buffer: usize) -> (Tx, Rx) {
+    // The channel fits a message more than its buffer for each sender, and
+    // the subscribe loop is the only one.
+    mpsc::channel(buffer - 1)
+}
+
+/// A subscription stream, as the subscribe loop delivers to it.
+///
+/// The loop never waits for the stream: once its buffer is full, the
+/// messages for it are dropped, and counted in its [`DroppedCounter`], so a
+/// slow stream doesn't hold up the others.
+#[derive(Debug)]
+pub(crate) struct Listener {
+    tx: Tx,
+    dropped: DroppedCounter,
+}
+
+impl Listener {
+    pub fn new(tx: Tx, dropped: DroppedCounter) -> Self {
+        Self { tx, dropped }
+    }
+
+    /// Deliver the `message`, unless the buffer of the stream is full.
+    pub fn deliver(&mut self, message: Arc<Message>) {
+        match self.tx.try_send(message) {
+            Ok(()) => {}
+            Err(error) if error.is_full() => {
+                let message = error.into_inner();
+                warn!(
+                    "Subscription stream for {} is full, dropping message {}",
+                    message.channel, message.timetoken
+                );
+                #[cfg(feature = "tracing")]
+                tracing::warn!(channel = %message.channel, missed = 1, "Dropped messages");
+                self.dropped.add(1);
+            }
+            Err(error) => error!("Delivery error: {:?}", error),
+        }
+    }
+}
+
+/// The number of the messages a subscription stream missed, since it fell
+/// behind.
+///
+/// Clones share the count, and reading it doesn't hold up the stream.
+#[derive(Debug, Clone, Default)]
+pub struct DroppedCounter(Arc<AtomicU64>);
+
+impl DroppedCounter {
+    /// The total number of the missed messages so far.
+    #[must_use]
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn add(&self, missed: u64) {
+        self.0.fetch_add(missed, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,69 @@
+//! The latest cursors of the subscribe loops.
+
+use crate::data::{cursor::Cursor, filter::FilterExpression, pubsub};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// The key of a cursor: the filter of the loop, and the destination it's
+/// subscribed to.
+///
+/// There's a loop per filter, and several of them may poll the same
+/// destination, each from a cursor of its own.
+type Key = (Option<FilterExpression>, pubsub::SubscribeTo);
+
+/// The cursors the subscribe loops continue from, by the filter of the loop
+/// and the destination.
+///
+/// The loops write once per poll, and the readers only take the read lock
+/// for a single lookup. Clones share the cursors.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CurrentCursors {
+    cursors: Arc<RwLock<HashMap<Key, Cursor>>>,
+}
+
+impl CurrentCursors {
+    /// The cursor of a loop subscribed to a destination with the `name`, if
+    /// it polled successfully.
+    ///
+    /// If several loops are subscribed to it, i.e. with different filters,
+    /// the most recent of their cursors is returned.
+    pub fn get(&self, name: &str) -> Option<Cursor> {
+        self.cursors
+            .read()
+            .expect("cursors lock is poisoned")
+            .iter()
+            .filter(|((_, destination), _)| destination.name() == name)
+            .map(|(_, cursor)| *cursor)
+            .max_by_key(|cursor| cursor.timetoken)
+    }
+
+    /// Record the `cursor` of the loop with the `filter_expr` subscribed to
+    /// the destinations `to`, forgetting the ones it was subscribed to
+    /// `before`.
+    pub fn update(
+        &self,
+        filter_expr: Option<&FilterExpression>,
+        before: &[pubsub::SubscribeTo],
+        to: &[pubsub::SubscribeTo],
+        cursor: Cursor,
+    ) {
+        let mut cursors = self.cursors.write().expect("cursors lock is poisoned");
+        for destination in before {
+            if !to.contains(destination) {
+                cursors.remove(&(filter_expr.cloned(), destination.clone()));
+            }
+        }
+        for destination in to {
+            cursors.insert((filter_expr.cloned(), destination.clone()), cursor);
+        }
+    }
+
+    /// Forget the cursor of the stopped loop with the `filter_expr`
+    /// subscribed to the destinations `to`.
+    pub fn remove(&self, filter_expr: Option<&FilterExpression>, to: &[pubsub::SubscribeTo]) {
+        let mut cursors = self.cursors.write().expect("cursors lock is poisoned");
+        for destination in to {
+            cursors.remove(&(filter_expr.cloned(), destination.clone()));
+        }
+    }
+}
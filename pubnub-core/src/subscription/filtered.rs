@@ -0,0 +1,121 @@
+//! Subscriptions filtered by the message type.
+
+use super::subscription::Subscription;
+use crate::data::message::{self, Message};
+use crate::runtime::Runtime;
+use futures_util::stream::{FusedStream, Stream};
+use futures_util::task::{Context, Poll};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// # Filtered PubNub message stream
+///
+/// This is the message stream returned by [`Subscription::only`] and
+/// [`Subscription::without_presence`]. The stream yields the messages of the
+/// underlying [`Subscription`] that pass the filter, and skips the rest.
+///
+/// The unfiltered stream is still available via
+/// [`into_inner`](Self::into_inner), i.e. to pick up the presence events
+/// again. Dropping the stream drops the underlying [`Subscription`].
+#[derive(Debug)]
+pub struct FilteredSubscription<TRuntime: Runtime> {
+    subscription: Subscription<TRuntime>,
+    filter: Filter,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Filter {
+    Only(message::Type),
+    WithoutPresence,
+}
+
+impl Filter {
+    fn accepts(self, message: &Message) -> bool {
+        match self {
+            Filter::Only(message_type) => message.message_type == message_type,
+            Filter::WithoutPresence => !message.is_presence(),
+        }
+    }
+}
+
+impl<TRuntime: Runtime> Subscription<TRuntime> {
+    /// Only yield the messages of the `message_type`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(
+    /// #     subscription: pubnub_core::Subscription<impl pubnub_core::Runtime>,
+    /// # ) {
+    /// use futures_util::stream::StreamExt;
+    /// use pubnub_core::data::message::Type;
+    ///
+    /// let mut signals = subscription.only(Type::Signal);
+    /// while let Some(signal) = signals.next().await {
+    ///     println!("signal: {}", signal.json);
+    /// }
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn only(self, message_type: message::Type) -> FilteredSubscription<TRuntime> {
+        FilteredSubscription {
+            subscription: self,
+            filter: Filter::Only(message_type),
+        }
+    }
+
+    /// Skip the presence events.
+    ///
+    /// The presence events are only delivered to the wildcard and the
+    /// channel group subscriptions, the ones matching the presence channels
+    /// of their members. Use [`PubNub::subscribe_to_presence`] to follow the
+    /// presence of a channel separately.
+    ///
+    /// [`PubNub::subscribe_to_presence`]: crate::pubnub::PubNub::subscribe_to_presence
+    #[must_use]
+    pub fn without_presence(self) -> FilteredSubscription<TRuntime> {
+        FilteredSubscription {
+            subscription: self,
+            filter: Filter::WithoutPresence,
+        }
+    }
+}
+
+impl<TRuntime: Runtime> FilteredSubscription<TRuntime> {
+    /// The underlying subscription.
+    #[must_use]
+    pub fn subscription(&self) -> &Subscription<TRuntime> {
+        &self.subscription
+    }
+
+    /// Remove the filter, and return the underlying subscription.
+    #[must_use]
+    pub fn into_inner(self) -> Subscription<TRuntime> {
+        self.subscription
+    }
+}
+
+impl<TRuntime: Runtime> Stream for FilteredSubscription<TRuntime> {
+    type Item = Arc<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match Stream::poll_next(Pin::new(&mut this.subscription), cx) {
+                Poll::Ready(Some(message)) if !this.filter.accepts(&message) => {}
+                poll => return poll,
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (_, upper) = self.subscription.size_hint();
+        (0, upper)
+    }
+}
+
+impl<TRuntime: Runtime> FusedStream for FilteredSubscription<TRuntime> {
+    fn is_terminated(&self) -> bool {
+        self.subscription.is_terminated()
+    }
+}
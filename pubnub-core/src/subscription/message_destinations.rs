@@ -16,17 +16,18 @@ impl<'a> MessageDestinations<'a> {
             channel: false,
         }
     }
+}
 
-    fn route_destination(&self) -> Option<pubsub::SubscribeTo> {
-        match self.message.route {
-            Some(message::Route::ChannelGroup(ref val)) => {
-                Some(pubsub::SubscribeTo::ChannelGroup(val.clone()))
-            }
-            Some(message::Route::ChannelWildcard(ref val)) => {
-                Some(pubsub::SubscribeTo::ChannelWildcard(val.clone()))
-            }
-            None => None,
+/// The channel group or the wildcard the `message` was routed by, if any.
+pub(super) fn route_destination(message: &Message) -> Option<pubsub::SubscribeTo> {
+    match message.route {
+        Some(message::Route::ChannelGroup(ref val)) => {
+            Some(pubsub::SubscribeTo::ChannelGroup(val.clone()))
         }
+        Some(message::Route::ChannelWildcard(ref val)) => {
+            Some(pubsub::SubscribeTo::ChannelWildcard(val.clone()))
+        }
+        None => None,
     }
 }
 
@@ -36,7 +37,7 @@ impl<'a> Iterator for MessageDestinations<'a> {
     fn next(&mut self) -> Option<Self::Item> {
         if !self.route {
             self.route = true;
-            let destination = self.route_destination();
+            let destination = route_destination(self.message);
             if destination.is_some() {
                 return destination;
             }
@@ -49,9 +50,36 @@ impl<'a> Iterator for MessageDestinations<'a> {
     }
 }
 
+/// The `subscribed` wildcards matching the channel of the `message`, other
+/// than the one it's routed by.
+///
+/// The PubNub network routes the message by one of the matching wildcards
+/// at most, so the listeners of the rest would miss it otherwise. Only the
+/// messages routed by a wildcard are fanned out: the ones sent to a channel
+/// or a group were subscribed to explicitly, and the presence events of the
+/// `-pnpres` channels don't belong to the data wildcards.
+pub(super) fn matching_wildcards<'b>(
+    message: &Message,
+    subscribed: impl Iterator<Item = &'b pubsub::SubscribeTo>,
+) -> Vec<pubsub::SubscribeTo> {
+    let route = match message.route {
+        Some(message::Route::ChannelWildcard(ref route)) => route,
+        _ => return Vec::new(),
+    };
+    let channel: &str = message.channel.as_ref();
+    if channel.ends_with("-pnpres") {
+        return Vec::new();
+    }
+    subscribed
+        .filter_map(pubsub::SubscribeTo::as_channel_wildcard)
+        .filter(|spec| *spec != route && spec.matches(&message.channel))
+        .map(|spec| pubsub::SubscribeTo::ChannelWildcard(spec.clone()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::MessageDestinations;
+    use super::{matching_wildcards, MessageDestinations};
     use crate::data::message::{self, Message};
     use crate::data::pubsub;
 
@@ -103,4 +131,67 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn test_matching_wildcards() {
+        let subscribed: Vec<pubsub::SubscribeTo> = vec![
+            pubsub::SubscribeTo::ChannelWildcard("a.*".parse().unwrap()),
+            pubsub::SubscribeTo::ChannelWildcard("a.b.*".parse().unwrap()),
+            pubsub::SubscribeTo::ChannelWildcard("b.*".parse().unwrap()),
+            pubsub::SubscribeTo::Channel("a.b.c".parse().unwrap()),
+        ];
+
+        assert_eq!(
+            matching_wildcards(
+                &message(route_channel_wildcard("a.*"), "a.b.c"),
+                subscribed.iter()
+            ),
+            vec![pubsub::SubscribeTo::ChannelWildcard(
+                "a.b.*".parse().unwrap()
+            )]
+        );
+        assert_eq!(
+            matching_wildcards(
+                &message(route_channel_wildcard("c.*"), "c.d"),
+                subscribed.iter()
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_matching_wildcards_only_for_wildcard_routes() {
+        let subscribed: Vec<pubsub::SubscribeTo> = vec![
+            pubsub::SubscribeTo::ChannelWildcard("a.*".parse().unwrap()),
+            pubsub::SubscribeTo::ChannelWildcard("a.b.*".parse().unwrap()),
+        ];
+
+        assert_eq!(
+            matching_wildcards(&message(None, "a.b.c"), subscribed.iter()),
+            vec![]
+        );
+        assert_eq!(
+            matching_wildcards(
+                &message(route_channel_group("group"), "a.b.c"),
+                subscribed.iter()
+            ),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_matching_wildcards_skips_presence_channels() {
+        let subscribed: Vec<pubsub::SubscribeTo> = vec![
+            pubsub::SubscribeTo::ChannelWildcard("a.*".parse().unwrap()),
+            pubsub::SubscribeTo::ChannelWildcard("a.b.*".parse().unwrap()),
+        ];
+
+        assert_eq!(
+            matching_wildcards(
+                &message(route_channel_wildcard("a.*"), "a.b.c-pnpres"),
+                subscribed.iter()
+            ),
+            vec![]
+        );
+    }
 }
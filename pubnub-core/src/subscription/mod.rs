@@ -1,8 +1,16 @@
+mod filtered;
 mod message_destinations;
 mod mvec;
+mod redelivery;
 mod registry;
+mod replay;
+mod try_subscription;
 
+#[cfg(feature = "broadcast")]
+pub(crate) mod broadcast;
 pub(crate) mod channel;
+pub(crate) mod cursors;
+pub(crate) mod status;
 pub(crate) mod subscribe_loop;
 pub(crate) mod subscribe_loop_supervisor;
 
@@ -11,4 +19,11 @@ pub(crate) mod subscribe_loop_supervisor;
 // This nesting never appears in the API.
 #[allow(clippy::module_inception)]
 mod subscription;
+pub use channel::DroppedCounter;
+pub use filtered::FilteredSubscription;
+pub use status::StatusEvent;
 pub use subscription::*;
+pub use try_subscription::TrySubscription;
+
+#[cfg(feature = "broadcast")]
+pub use broadcast::{BroadcastSubscription, Lagged};
@@ -21,6 +21,15 @@ pub(crate) struct MVecIterMut<'a, T> {
 }
 
 impl<T> MVec<T> {
+    /// Create an empty `MVec` that will start indexing elements from
+    /// `counter`.
+    pub(crate) fn starting_at(counter: usize) -> Self {
+        Self {
+            counter,
+            inner: HashMap::default(),
+        }
+    }
+
     pub(crate) fn counter(&self) -> usize {
         self.counter
     }
@@ -47,10 +56,7 @@ impl<T> MVec<T> {
 
 impl<T> Default for MVec<T> {
     fn default() -> Self {
-        Self {
-            counter: Default::default(),
-            inner: HashMap::default(),
-        }
+        Self::starting_at(Default::default())
     }
 }
 
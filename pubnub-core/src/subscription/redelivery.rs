@@ -0,0 +1,96 @@
+//! The timetokens of the delivered messages, to skip the ones the PubNub
+//! network redelivers.
+
+use super::message_destinations::route_destination;
+use crate::data::message::Message;
+use crate::data::timetoken::Timetoken;
+use crate::data::{channel, pubsub};
+use std::collections::HashMap;
+
+/// The timetoken of the last message delivered per channel, along with the
+/// destination the channel was subscribed by.
+#[derive(Debug, Default)]
+pub(crate) struct LastTimetokens {
+    channels: HashMap<channel::Name, (Timetoken, pubsub::SubscribeTo)>,
+}
+
+impl LastTimetokens {
+    /// Check whether the `message` was already delivered, and remember its
+    /// timetoken otherwise.
+    pub fn is_redelivery(&mut self, message: &Message) -> bool {
+        match self.channels.get_mut(&message.channel) {
+            Some((last, _)) if message.timetoken <= *last => true,
+            Some((last, _)) => {
+                *last = message.timetoken;
+                false
+            }
+            None => {
+                let destination = route_destination(message)
+                    .unwrap_or_else(|| pubsub::SubscribeTo::Channel(message.channel.clone()));
+                self.channels
+                    .insert(message.channel.clone(), (message.timetoken, destination));
+                false
+            }
+        }
+    }
+
+    /// Drop the timetokens of the channels subscribed by a `destination`
+    /// that has no listeners left, i.e. all the channels a wildcard matched.
+    ///
+    /// Subscribing again starts afresh, so the messages older than the last
+    /// delivered ones aren't skipped, i.e. when subscribing from an earlier
+    /// cursor.
+    pub fn forget(&mut self, destination: &pubsub::SubscribeTo) {
+        self.channels
+            .retain(|_, (_, subscribed_by)| subscribed_by != destination);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LastTimetokens;
+    use crate::data::message::{self, Message};
+    use crate::data::pubsub;
+    use crate::data::timetoken::Timetoken;
+
+    fn message(route: Option<message::Route>, channel: &str, t: u64) -> Message {
+        Message {
+            route,
+            channel: channel.parse().unwrap(),
+            timetoken: Timetoken { t, r: 0 },
+            ..Message::default()
+        }
+    }
+
+    #[test]
+    fn skips_the_redelivered_messages() {
+        let mut last_timetokens = LastTimetokens::default();
+
+        assert!(!last_timetokens.is_redelivery(&message(None, "a", 2)));
+        assert!(last_timetokens.is_redelivery(&message(None, "a", 2)));
+        assert!(last_timetokens.is_redelivery(&message(None, "a", 1)));
+        assert!(!last_timetokens.is_redelivery(&message(None, "b", 1)));
+        assert!(!last_timetokens.is_redelivery(&message(None, "a", 3)));
+    }
+
+    #[test]
+    fn forgets_the_channels_of_the_destination() {
+        let wildcard = Some(message::Route::ChannelWildcard("w.*".parse().unwrap()));
+        let mut last_timetokens = LastTimetokens::default();
+
+        assert!(!last_timetokens.is_redelivery(&message(None, "a", 2)));
+        for channel in &["w.a", "w.b"] {
+            assert!(!last_timetokens.is_redelivery(&message(wildcard.clone(), channel, 2)));
+        }
+
+        last_timetokens.forget(&pubsub::SubscribeTo::ChannelWildcard(
+            "w.*".parse().unwrap(),
+        ));
+        assert_eq!(last_timetokens.channels.len(), 1);
+        assert!(!last_timetokens.is_redelivery(&message(wildcard, "w.a", 1)));
+        assert!(last_timetokens.is_redelivery(&message(None, "a", 1)));
+
+        last_timetokens.forget(&pubsub::SubscribeTo::Channel("a".parse().unwrap()));
+        assert!(!last_timetokens.is_redelivery(&message(None, "a", 1)));
+    }
+}
@@ -10,6 +10,11 @@ where
     K: Eq + Hash,
 {
     pub(super) map: HashMap<K, MVec<V>>,
+
+    /// The lower bound for the IDs of the newly added names. Ensures the IDs
+    /// are not reused when a name is erased and then registered again, so
+    /// that a stale ID can't unregister an unrelated value.
+    next_id: usize,
 }
 
 /// Newtype to protect access to the registry ID.
@@ -35,6 +40,7 @@ where
     pub fn new() -> Self {
         Self {
             map: HashMap::new(),
+            next_id: 0,
         }
     }
 
@@ -46,9 +52,11 @@ where
             Entry::Occupied(_) => RegistrationEffect::ExistingName,
         };
 
-        let mvec = entry.or_default();
+        let next_id = self.next_id;
+        let mvec = entry.or_insert_with(|| MVec::starting_at(next_id));
         let id = mvec.counter();
         mvec.push(value);
+        self.next_id = self.next_id.max(mvec.counter());
 
         (ID(id), effect)
     }
@@ -73,6 +81,15 @@ where
         Some((removed, effect))
     }
 
+    /// Unregister all the values registered under the `name`.
+    pub fn unregister_all<Q: ?Sized>(&mut self, name: &Q) -> Option<MVec<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq,
+    {
+        self.map.remove(name)
+    }
+
     pub fn get_iter_mut<'a, Q: ?Sized>(&'a mut self, name: &Q) -> Option<MVecIterMut<'a, V>>
     where
         K: Borrow<Q>,
@@ -89,3 +106,27 @@ where
         self.map.keys()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Registry, UnregistrationEffect};
+
+    #[test]
+    fn ids_are_not_reused_after_name_is_erased() {
+        let mut registry = Registry::new();
+
+        let (stale_id, _) = registry.register("a", 1);
+        registry.register("b", 2);
+        assert!(registry.unregister_all("a").is_some());
+
+        let (id, _) = registry.register("a", 3);
+        assert_ne!(id, stale_id);
+
+        // The stale ID doesn't affect the newly registered value.
+        assert!(registry.unregister("a", stale_id).is_none());
+        match registry.unregister("a", id) {
+            Some((3, UnregistrationEffect::NameErased)) => {}
+            other => panic!("unexpected unregistration result: {:?}", other),
+        }
+    }
+}
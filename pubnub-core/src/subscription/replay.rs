@@ -0,0 +1,91 @@
+//! The recent messages of the subscribe loop, replayed to the late
+//! listeners.
+
+use crate::data::message::Message;
+use crate::data::pubsub;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+/// The last messages delivered to each destination, at most `capacity` per
+/// destination.
+#[derive(Debug)]
+pub(crate) struct ReplayBuffers {
+    capacity: usize,
+    buffers: HashMap<pubsub::SubscribeTo, VecDeque<Arc<Message>>>,
+}
+
+impl ReplayBuffers {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            buffers: HashMap::new(),
+        }
+    }
+
+    /// Remember the `message` delivered to the `destination`, evicting the
+    /// oldest one if the buffer is full.
+    pub fn record(&mut self, destination: &pubsub::SubscribeTo, message: &Arc<Message>) {
+        let capacity = self.capacity;
+        let buffer = self
+            .buffers
+            .entry(destination.clone())
+            .or_insert_with(|| VecDeque::with_capacity(capacity));
+        if buffer.len() == capacity {
+            buffer.pop_front();
+        }
+        buffer.push_back(Arc::clone(message));
+    }
+
+    /// The messages to replay to a new listener of the `destination`,
+    /// oldest first.
+    pub fn messages(&self, destination: &pubsub::SubscribeTo) -> Vec<Arc<Message>> {
+        self.buffers
+            .get(destination)
+            .map(|buffer| buffer.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Drop the messages of a `destination` that has no listeners left.
+    pub fn forget(&mut self, destination: &pubsub::SubscribeTo) {
+        self.buffers.remove(destination);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReplayBuffers;
+    use crate::data::message::Message;
+    use crate::data::pubsub;
+    use crate::data::timetoken::Timetoken;
+    use std::sync::Arc;
+
+    fn message(t: u64) -> Arc<Message> {
+        Arc::new(Message {
+            timetoken: Timetoken { t, r: 0 },
+            ..Message::default()
+        })
+    }
+
+    fn timetokens(messages: &[Arc<Message>]) -> Vec<u64> {
+        messages.iter().map(|message| message.timetoken.t).collect()
+    }
+
+    #[test]
+    fn keeps_the_last_messages_per_destination() {
+        let a = pubsub::SubscribeTo::Channel("a".parse().unwrap());
+        let b = pubsub::SubscribeTo::Channel("b".parse().unwrap());
+        let mut buffers = ReplayBuffers::new(2);
+
+        for t in 1..=3 {
+            buffers.record(&a, &message(t));
+        }
+        buffers.record(&b, &message(4));
+
+        assert_eq!(timetokens(&buffers.messages(&a)), vec![2, 3]);
+        assert_eq!(timetokens(&buffers.messages(&b)), vec![4]);
+
+        buffers.forget(&a);
+        assert!(buffers.messages(&a).is_empty());
+        assert_eq!(timetokens(&buffers.messages(&b)), vec![4]);
+    }
+}
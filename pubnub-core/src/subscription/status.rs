@@ -0,0 +1,192 @@
+//! Subscribe loop status events.
+
+use crate::data::pubsub::SubscribeTo;
+use futures_core::stream::Stream;
+use futures_core::task::{Context, Poll};
+use log::debug;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::Waker;
+
+/// # Subscribe loop status event
+///
+/// Reports the changes in the connectivity of the subscribe loop, see
+/// [`PubNub::status_stream`].
+///
+/// [`PubNub::status_stream`]: crate::pubnub::PubNub::status_stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StatusEvent {
+    /// The subscribe loop got its first successful response.
+    Connected,
+    /// The subscribe loop got a successful response after a failure.
+    Reconnected,
+    /// The subscribe loop stopped, since nothing is subscribed anymore.
+    Disconnected,
+    /// The subscribe request failed, the loop will retry it.
+    Error(String),
+    /// The access to the given destinations was denied, so the loop dropped
+    /// them: their subscription streams end, and the loop keeps going for
+    /// the rest. See [`TransportError::denied_destinations`].
+    ///
+    /// [`TransportError::denied_destinations`]: crate::TransportError::denied_destinations
+    AccessDenied {
+        /// The dropped destinations.
+        destinations: Vec<SubscribeTo>,
+        /// The error the access was denied with.
+        message: String,
+    },
+    /// The transport switched to the given origin, since the previous one
+    /// kept failing. See [`Transport::current_origin`].
+    ///
+    /// [`Transport::current_origin`]: crate::Transport::current_origin
+    Failover(String),
+    /// No subscribe request completed within the watchdog timeout, so the
+    /// loop dropped it and reconnects. See
+    /// [`Builder::watchdog_timeout`](crate::Builder::watchdog_timeout).
+    Stalled,
+    /// The subscribe loop panicked, and is restarted with the same
+    /// subscriptions after a backoff. The restarted loop reconnects, as
+    /// after an [`Error`](Self::Error).
+    Crashed,
+    /// The subscribe loop kept panicking, and was stopped instead of being
+    /// restarted again. The subscription streams end.
+    Abandoned,
+}
+
+/// The number of the status events a status stream buffers before the
+/// oldest ones are dropped for it, so a stream nobody reads doesn't grow
+/// with the errors of a failing loop, and still gets the latest state.
+pub(crate) const STATUS_BUFFER: usize = 64;
+
+/// Delivers the status events to all the status streams.
+///
+/// Clones share the set of the streams.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StatusBroadcaster {
+    listeners: Arc<Mutex<Vec<Listener>>>,
+}
+
+impl StatusBroadcaster {
+    /// Create a new status stream.
+    pub fn listen(&self) -> StatusStream {
+        let queue = Arc::new(Mutex::new(Queue::default()));
+        self.listeners
+            .lock()
+            .expect("status listeners lock is poisoned")
+            .push(Listener(Arc::clone(&queue)));
+        StatusStream { queue }
+    }
+
+    /// Send the event to every status stream, forgetting the dropped ones.
+    ///
+    /// The streams that are full drop their oldest event to make room.
+    pub fn broadcast(&self, event: &StatusEvent) {
+        let mut listeners = self
+            .listeners
+            .lock()
+            .expect("status listeners lock is poisoned");
+        // Only the listener holds the queue of a dropped stream.
+        listeners.retain(|Listener(queue)| Arc::strong_count(queue) > 1);
+        for Listener(queue) in listeners.iter() {
+            let mut queue = queue.lock().expect("status queue lock is poisoned");
+            if queue.events.len() == STATUS_BUFFER {
+                let dropped = queue.events.pop_front();
+                debug!("Status stream is full, dropping {:?}", dropped);
+            }
+            queue.events.push_back(event.clone());
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// The events of a status stream, yet to be read.
+#[derive(Debug, Default)]
+struct Queue {
+    events: VecDeque<StatusEvent>,
+    /// Wakes the stream waiting for the events.
+    waker: Option<Waker>,
+    /// Set once no more events can come.
+    closed: bool,
+}
+
+/// The broadcaster's end of a status stream. Ends the stream when all the
+/// broadcasters are gone.
+#[derive(Debug)]
+struct Listener(Arc<Mutex<Queue>>);
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        let mut queue = self.0.lock().expect("status queue lock is poisoned");
+        queue.closed = true;
+        if let Some(waker) = queue.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// A stream of the status events, see [`StatusBroadcaster::listen`].
+#[derive(Debug)]
+pub(crate) struct StatusStream {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl Stream for StatusStream {
+    type Item = StatusEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut queue = self.queue.lock().expect("status queue lock is poisoned");
+        if let Some(event) = queue.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatusBroadcaster, StatusEvent, STATUS_BUFFER};
+    use futures_executor::block_on_stream;
+
+    #[test]
+    fn unread_status_stream_is_bounded() {
+        let broadcaster = StatusBroadcaster::default();
+        let status_stream = broadcaster.listen();
+
+        for _ in 0..STATUS_BUFFER * 10 {
+            broadcaster.broadcast(&StatusEvent::Error("connection refused".to_owned()));
+        }
+        broadcaster.broadcast(&StatusEvent::Disconnected);
+        drop(broadcaster);
+
+        // The oldest events are dropped, the latest state is kept.
+        let events: Vec<_> = block_on_stream(status_stream).collect();
+        assert_eq!(events.len(), STATUS_BUFFER);
+        assert!(events[..STATUS_BUFFER - 1]
+            .iter()
+            .all(|event| *event == StatusEvent::Error("connection refused".to_owned())));
+        assert_eq!(events.last(), Some(&StatusEvent::Disconnected));
+    }
+
+    #[test]
+    fn dropped_status_streams_are_forgotten() {
+        let broadcaster = StatusBroadcaster::default();
+        let status_stream = broadcaster.listen();
+        let other_status_stream = broadcaster.listen();
+
+        drop(status_stream);
+        broadcaster.broadcast(&StatusEvent::Connected);
+        assert_eq!(broadcaster.listeners.lock().unwrap().len(), 1);
+
+        drop(broadcaster);
+        let events: Vec<_> = block_on_stream(other_status_stream).collect();
+        assert_eq!(events, vec![StatusEvent::Connected]);
+    }
+}
@@ -1,20 +1,43 @@
-use super::message_destinations::MessageDestinations;
-use super::registry::Registry as GenericRegistry;
+use super::cursors::CurrentCursors;
+use super::message_destinations::{matching_wildcards, MessageDestinations};
+use super::redelivery::LastTimetokens;
+use super::registry::{RegistrationEffect, Registry as GenericRegistry, UnregistrationEffect};
+use super::replay::ReplayBuffers;
+use super::status::{StatusBroadcaster, StatusEvent};
+use crate::data::cursor::Cursor;
 use crate::data::message::Message;
-use crate::data::timetoken::Timetoken;
-use crate::data::{pubsub, request, response};
-use crate::transport::Service;
+use crate::data::{filter::FilterExpression, pubsub, request};
+use crate::runtime::{JoinError, Runtime};
+use crate::transport::{Error as TransportError, Service, Transport};
 use futures_channel::{mpsc, oneshot};
 use futures_util::future::{select, Either, FutureExt};
+use futures_util::lock::Mutex;
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
 use log::{debug, error};
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
 
-pub(crate) use super::channel::{Rx as ChannelRx, Tx as ChannelTx};
+#[cfg(feature = "cipher")]
+use crate::data::message;
+#[cfg(feature = "cipher")]
+use json::JsonValue;
+#[cfg(feature = "cipher")]
+use log::warn;
+#[cfg(feature = "cipher")]
+use pubnub_util::cipher::Cipher;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Counters;
+
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+pub(crate) use super::channel::{Listener, Rx as ChannelRx};
 pub(crate) use super::registry::ID as SubscriptionID;
 
-pub(crate) type Registry = GenericRegistry<pubsub::SubscribeTo, ChannelTx>;
+pub(crate) type Registry = GenericRegistry<pubsub::SubscribeTo, Listener>;
 
 pub(crate) type ReadyTx = oneshot::Sender<()>;
 
@@ -25,18 +48,58 @@ pub(crate) type ControlRx = mpsc::Receiver<ControlCommand>;
 
 pub(crate) type SubscriptionIdTx = oneshot::Sender<SubscriptionID>;
 
+pub(crate) type ShutdownTx = oneshot::Sender<Vec<pubsub::SubscribeTo>>;
+
+pub(crate) type LeaveTx = oneshot::Sender<pubsub::SubscribeTo>;
+
+pub(crate) type DestinationsTx = oneshot::Sender<Vec<pubsub::SubscribeTo>>;
+
+/// The number of the times in a row the loop is restarted after panicking,
+/// before it's stopped.
+const MAX_RESTARTS: u32 = 5;
+
+/// The delay before restarting the loop after a panic, doubled for every
+/// next restart in a row.
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+
 /// Commands we pass via the control pipe.
 #[derive(Debug)]
 pub(crate) enum ControlCommand {
     /// A stream for a channel or channel group is being dropped.
     ///
+    /// If the stream was the last listener of the destination, the
+    /// destination is sent back via the `LeaveTx`, if any, so that it can be
+    /// left.
+    ///
     /// Only sent from `Subscription` to `SubscribeLoop`.
-    Drop(SubscriptionID, pubsub::SubscribeTo),
+    Drop(SubscriptionID, pubsub::SubscribeTo, Option<LeaveTx>),
 
-    /// A stream for a channel or channel group is being created.
+    /// All streams for a channel or channel group are being dropped.
     ///
     /// Only sent from `PubNub` to `SubscribeLoop`.
-    Add(pubsub::SubscribeTo, ChannelTx, SubscriptionIdTx),
+    DropAll(pubsub::SubscribeTo),
+
+    /// A stream for a channel or channel group is being created, starting
+    /// from the cursor.
+    ///
+    /// If the destination is new to the loop and the cursor is behind it, the
+    /// loop rewinds to the cursor, so the stream misses nothing. The
+    /// de-duplication skips the messages the other streams got already.
+    ///
+    /// Only sent from `PubNub` to `SubscribeLoop`.
+    Add(pubsub::SubscribeTo, Cursor, Listener, SubscriptionIdTx),
+
+    /// The loop is being shut down. All the streams are dropped, and the
+    /// destinations the loop was subscribed to are sent back once the loop
+    /// stops.
+    ///
+    /// Only sent from `PubNub` to `SubscribeLoop`.
+    Shutdown(ShutdownTx),
+
+    /// The destinations the loop is subscribed to are being requested.
+    ///
+    /// Only sent from `PubNub` to `SubscribeLoop`.
+    Destinations(DestinationsTx),
 }
 
 #[derive(Debug)]
@@ -44,82 +107,373 @@ pub(crate) struct SubscribeLoopParams<TTransport> {
     pub control_rx: ControlRx,
     pub ready_tx: Option<ReadyTx>,
     pub exit_tx: Option<ExitTx>,
+    pub message_deduplication: bool,
+    pub filter_expr: Option<FilterExpression>,
+    pub watchdog_timeout: Option<Duration>,
+    pub replay_buffer: usize,
+    pub status_broadcaster: StatusBroadcaster,
+    pub current_cursors: CurrentCursors,
+    #[cfg(feature = "cipher")]
+    pub cipher: Option<Cipher>,
+    #[cfg(feature = "metrics")]
+    pub metrics: Arc<Counters>,
 
     pub transport: TTransport,
 
     pub to: Registry,
+
+    /// The cursor to start from. It's kept across the changes of the
+    /// destinations set, so that adding or removing a destination doesn't
+    /// replay the messages to the existing listeners.
+    pub cursor: Cursor,
+}
+
+/// The parts of the loop that don't change over its lifetime.
+#[derive(Debug)]
+struct LoopConfig<TTransport, TRuntime> {
+    filter_expr: Option<FilterExpression>,
+    watchdog_timeout: Option<Duration>,
+    status_broadcaster: StatusBroadcaster,
+    current_cursors: CurrentCursors,
+    transport: TTransport,
+    runtime: TRuntime,
+}
+
+/// The state of the loop, kept across the restarts of the loop after a
+/// panic.
+#[derive(Debug)]
+struct LoopState {
+    control_rx: ControlRx,
+    ready_tx: Option<ReadyTx>,
+    exit_tx: Option<ExitTx>,
+    state_data: StateData,
+    cursor: Cursor,
+
+    /// The destinations the current cursor was last recorded for.
+    recorded_to: Vec<pubsub::SubscribeTo>,
+
+    /// Whether any request succeeded.
+    ever_connected: bool,
+
+    /// The number of the times the loop was restarted after panicking,
+    /// since a request last succeeded.
+    restarts: u32,
 }
 
 #[derive(Debug)]
 struct StateData {
     pub to: Registry,
+
+    /// Timetokens of the last delivered messages per channel, tracked only
+    /// when the de-duplication is enabled.
+    pub last_timetokens: Option<LastTimetokens>,
+
+    /// The last messages of each destination, kept only when the replay to
+    /// the late listeners is enabled.
+    pub replay: Option<ReplayBuffers>,
+
+    /// Cipher to decrypt the message payloads with.
+    #[cfg(feature = "cipher")]
+    pub cipher: Option<Cipher>,
+
+    /// The metrics counters.
+    #[cfg(feature = "metrics")]
+    pub metrics: Arc<Counters>,
+
+    /// Set when the shutdown was requested, along with the destinations to
+    /// report back.
+    pub shutdown: Option<(ShutdownTx, Vec<pubsub::SubscribeTo>)>,
 }
 
 /// Implements the subscribe loop, which efficiently polls for new messages.
-pub(crate) async fn subscribe_loop<TTransport>(params: SubscribeLoopParams<TTransport>)
-where
-    TTransport: Service<request::Subscribe, Response = response::Subscribe> + Clone,
-    <TTransport as Service<request::Subscribe>>::Error: Debug + 'static,
+///
+/// The loop runs as a task of its own on the `runtime`, and is restarted if
+/// it panics. The restarted loop keeps the subscriptions and the cursor.
+pub(crate) async fn subscribe_loop<TTransport, TRuntime>(
+    runtime: TRuntime,
+    params: SubscribeLoopParams<TTransport>,
+) where
+    TTransport: Transport + 'static,
+    TRuntime: Runtime + 'static,
+    <TTransport as Service<request::Subscribe>>::Error: TransportError + 'static,
 {
-    debug!("Starting subscribe loop");
-
     #[allow(clippy::unneeded_field_pattern)]
     let SubscribeLoopParams {
-        mut control_rx,
-        mut ready_tx,
-        mut exit_tx,
+        control_rx,
+        ready_tx,
+        exit_tx,
+        message_deduplication,
+        filter_expr,
+        watchdog_timeout,
+        replay_buffer,
+        status_broadcaster,
+        current_cursors,
+        #[cfg(feature = "cipher")]
+        cipher,
+        #[cfg(feature = "metrics")]
+        metrics,
 
         transport,
 
         to,
+        cursor,
     } = params;
 
-    let mut state_data = StateData { to };
+    let config = Arc::new(LoopConfig {
+        filter_expr,
+        watchdog_timeout,
+        status_broadcaster,
+        current_cursors,
+        transport,
+        runtime,
+    });
+    let state = Arc::new(Mutex::new(LoopState {
+        control_rx,
+        ready_tx,
+        exit_tx,
+        state_data: StateData {
+            to,
+            last_timetokens: if message_deduplication {
+                Some(LastTimetokens::default())
+            } else {
+                None
+            },
+            replay: if replay_buffer > 0 {
+                Some(ReplayBuffers::new(replay_buffer))
+            } else {
+                None
+            },
+            #[cfg(feature = "cipher")]
+            cipher,
+            #[cfg(feature = "metrics")]
+            metrics,
+            shutdown: None,
+        },
+        cursor,
+        recorded_to: Vec::new(),
+        ever_connected: false,
+        restarts: 0,
+    }));
+
+    loop {
+        let run = run_subscribe_loop(Arc::clone(&config), Arc::clone(&state));
+        // The loop lives for long, so it gets a single root span of its own
+        // rather than a span per poll.
+        #[cfg(feature = "tracing")]
+        let run = run.instrument(tracing::debug_span!(parent: None, "subscribe_loop"));
+        match config.runtime.spawn(run).await {
+            Err(JoinError::Panicked) => {
+                // The state is unlocked as the panicked loop is dropped, so
+                // the restarted one picks it up.
+                let mut state = state.lock().await;
+                state.restarts += 1;
+                if state.restarts > MAX_RESTARTS {
+                    error!("Subscribe loop keeps panicking, stopping");
+                    stop(&config, &mut state, &StatusEvent::Abandoned).await;
+                    break;
+                }
+                let backoff = RESTART_BACKOFF * 2_u32.pow(state.restarts - 1);
+                drop(state);
+
+                error!("Subscribe loop panicked, restarting in {:?}", backoff);
+                config.status_broadcaster.broadcast(&StatusEvent::Crashed);
+                config.runtime.delay(backoff).await;
+            }
+            // The loop stopped, or the executor is gone.
+            Ok(()) | Err(JoinError::Cancelled) => break,
+        }
+    }
+}
+
+/// Clean up after the loop, and report that it stopped with the `event`.
+async fn stop<TTransport, TRuntime>(
+    config: &LoopConfig<TTransport, TRuntime>,
+    state: &mut LoopState,
+    event: &StatusEvent,
+) {
+    debug!("Stopping subscribe loop");
+
+    config
+        .current_cursors
+        .remove(config.filter_expr.as_ref(), &state.recorded_to);
+
+    config.status_broadcaster.broadcast(event);
+
+    if let Some(ref mut exit_tx) = state.exit_tx {
+        exit_tx.send(()).await.expect("Unable to send exit message");
+    }
 
-    let mut timetoken = Timetoken::default();
+    if let Some((shutdown_tx, destinations)) = state.state_data.shutdown.take() {
+        // The requester might not wait for the result.
+        let _ = shutdown_tx.send(destinations);
+    }
+}
+
+/// Runs the subscribe loop until it stops or panics.
+// The loop reads best as a whole, it's the feature-gated fields that push it
+// over the limit.
+#[allow(clippy::too_many_lines)]
+async fn run_subscribe_loop<TTransport, TRuntime>(
+    config: Arc<LoopConfig<TTransport, TRuntime>>,
+    state: Arc<Mutex<LoopState>>,
+) where
+    TTransport: Transport,
+    TRuntime: Runtime,
+    <TTransport as Service<request::Subscribe>>::Error: TransportError + 'static,
+{
+    debug!("Starting subscribe loop");
+
+    let LoopConfig {
+        filter_expr,
+        watchdog_timeout,
+        status_broadcaster,
+        current_cursors,
+        transport,
+        runtime,
+    } = &*config;
+    let mut state = state.lock().await;
+    let LoopState {
+        control_rx,
+        ready_tx,
+        state_data,
+        cursor,
+        recorded_to,
+        ever_connected,
+        restarts,
+        ..
+    } = &mut *state;
+
+    // Whether the last request succeeded. The restarted loop reconnects.
+    let mut connected = false;
+    // The origin the transport used last time we checked.
+    let mut origin = transport.current_origin();
 
     loop {
         // TODO: re-add cache.
         let to: Vec<pubsub::SubscribeTo> = state_data.to.keys().cloned().collect();
 
         let request = request::Subscribe {
-            to,
-            timetoken,
+            to: to.clone(),
+            cursor: *cursor,
             heartbeat: None,
+            filter_expr: filter_expr.clone(),
         };
         let response = transport.call(request);
+        // Resolves to `None` if the request is stuck.
+        let stall = watchdog_timeout.map(|timeout| runtime.delay(timeout));
+        let response = async {
+            match stall {
+                Some(stall) => match select(response, stall).await {
+                    Either::Left((res, _)) => Some(res),
+                    Either::Right(_) => None,
+                },
+                None => Some(response.await),
+            }
+        };
 
         let response = response.fuse();
         futures_util::pin_mut!(response);
 
-        let control_rx_recv = control_rx.next();
-        futures_util::pin_mut!(control_rx_recv);
+        // Handle the control commands while the request is in flight, until
+        // we get the response.
+        let result = loop {
+            let control_rx_recv = control_rx.next();
+            futures_util::pin_mut!(control_rx_recv);
+
+            match select(control_rx_recv, response.as_mut()).await {
+                Either::Left((msg, _)) => {
+                    match handle_control_command(state_data, cursor, msg).await {
+                        // The in-flight request is still valid, keep waiting
+                        // for it.
+                        ControlOutcome::CanContinue => {}
+                        outcome => break Err(outcome),
+                    }
+                }
+                Either::Right((res, _)) => break Ok(res),
+            }
+        };
 
-        let (messages, next_timetoken) = match select(control_rx_recv, response).await {
-            Either::Left((msg, _)) => {
-                let outcome = handle_control_command(&mut state_data, msg).await;
-                if let ControlOutcome::Terminate = outcome {
-                    // Termination requested, break the loop.
-                    break;
+        // Report the failovers, whichever requests of the transport they
+        // were caused by.
+        if result.is_ok() {
+            let current_origin = transport.current_origin();
+            if current_origin != origin {
+                if let Some(ref current_origin) = current_origin {
+                    debug!("Transport failed over to {}", current_origin);
+                    status_broadcaster.broadcast(&StatusEvent::Failover(current_origin.clone()));
                 }
+                origin = current_origin;
+            }
+        }
 
-                // Control signalled we can continue with the polling, however
-                // we literally need to `continue` here in order to force rerun
-                // the loop from the beginning.
+        let (messages, next_cursor) = match result {
+            Err(ControlOutcome::Terminate) => {
+                // Termination requested, break the loop.
+                break;
+            }
+            Err(_) => {
+                // The set of destinations changed, so we need to `continue`
+                // here in order to force rerun the loop from the beginning
+                // and issue a request with the new destinations. The
+                // cursor is preserved, so no messages are replayed.
                 // We rely on the in-flight request to be properly cleaned up,
                 // since their futures are being dropped here.
                 continue;
             }
-            Either::Right((res, _)) => {
-                match res {
-                    Ok(v) => v,
-                    Err(err) => {
-                        // TODO: add some kind of circut breaker.
-                        // Report error and retry - maybe it'd work this time.
-                        error!("Transport error while polling: {:?}", err);
+            Ok(None) => {
+                // The request is dropped along with its connection, the
+                // next one starts afresh.
+                error!("Subscribe request stalled, reconnecting");
+                status_broadcaster.broadcast(&StatusEvent::Stalled);
+                connected = false;
+                continue;
+            }
+            Ok(Some(Ok(v))) => v,
+            Ok(Some(Err(err))) if err.is_fatal() => {
+                error!("Fatal transport error while polling: {:?}", err);
+
+                // Drop the destinations the access was denied to, and keep
+                // going for the rest.
+                let denied: Vec<_> = err
+                    .denied_destinations()
+                    .into_iter()
+                    .filter(|destination| state_data.to.unregister_all(destination).is_some())
+                    .collect();
+                if denied.is_empty() {
+                    // Retrying won't help, stop the loop to let the user
+                    // know.
+                    status_broadcaster.broadcast(&StatusEvent::Error(format!("{:?}", err)));
+                } else {
+                    for destination in &denied {
+                        forget(
+                            &mut state_data.last_timetokens,
+                            &mut state_data.replay,
+                            destination,
+                        );
+                    }
+                    status_broadcaster.broadcast(&StatusEvent::AccessDenied {
+                        destinations: denied,
+                        message: err.to_string(),
+                    });
+                    if !state_data.to.is_empty() {
                         continue;
                     }
                 }
+
+                // Unblock the pending subscription, its stream will end
+                // right away.
+                if let Some(ready_tx) = ready_tx.take() {
+                    let _ = ready_tx.send(());
+                }
+                break;
+            }
+            Ok(Some(Err(err))) => {
+                // TODO: add some kind of circut breaker.
+                // Report error and retry - maybe it'd work this time.
+                error!("Transport error while polling: {:?}", err);
+                status_broadcaster.broadcast(&StatusEvent::Error(format!("{:?}", err)));
+                connected = false;
+                continue;
             }
         };
 
@@ -129,50 +483,77 @@ where
         // the setup. It is invoked after the `Ok` result from the request
         // future, guaranteing that Transport was able to perform successfully
         // at least once.
+        // The subscriber might have stopped waiting already, see
+        // `Builder::ready_timeout`.
         if let Some(ready_tx) = ready_tx.take() {
-            if let Err(err) = ready_tx.send(()) {
-                error!("Error sending ready message: {:?}", err);
-                break;
-            }
+            let _ = ready_tx.send(());
+        }
+        *restarts = 0;
+
+        if !connected {
+            let event = if *ever_connected {
+                #[cfg(feature = "metrics")]
+                state_data.metrics.subscribe_reconnected();
+                StatusEvent::Reconnected
+            } else {
+                StatusEvent::Connected
+            };
+            status_broadcaster.broadcast(&event);
+            connected = true;
+            *ever_connected = true;
         }
 
-        // Save Timetoken for next request
-        timetoken = next_timetoken;
+        // Save Cursor for next request
+        *cursor = next_cursor;
+        current_cursors.update(filter_expr.as_ref(), recorded_to, &to, *cursor);
+        *recorded_to = to;
 
         debug!("messages: {:?}", messages);
-        debug!("timetoken: {:?}", timetoken);
+        debug!("cursor: {:?}", cursor);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(cursor = %cursor, messages = messages.len(), "Polled");
 
         // Distribute messages to each listener.
-        dispatch_messages(&mut state_data, messages).await;
+        dispatch_messages(state_data, messages);
     }
 
-    debug!("Stopping subscribe loop");
-
-    if let Some(ref mut exit_tx) = exit_tx {
-        exit_tx.send(()).await.expect("Unable to send exit message");
-    }
+    stop(&config, &mut state, &StatusEvent::Disconnected).await;
 }
 
 /// Encodes action to be taken in response to control command.
 #[derive(Debug)]
 enum ControlOutcome {
+    /// No destinations are left, the loop has to stop.
     Terminate,
+    /// The set of destinations changed, the in-flight request has to be
+    /// restarted.
+    Restart,
+    /// The set of destinations is the same, the in-flight request is still
+    /// valid.
     CanContinue,
 }
 
 /// Handle a control command.
 async fn handle_control_command(
     state_data: &mut StateData,
+    cursor: &mut Cursor,
     msg: Option<ControlCommand>,
 ) -> ControlOutcome {
     debug!("Got request: {:?}", msg);
     let request = match msg {
         Some(v) => v,
-        None => return ControlOutcome::CanContinue,
+        // All the control handles are gone, so nobody can be listening.
+        None => return ControlOutcome::Terminate,
     };
-    let StateData { to } = state_data;
-    match request {
-        ControlCommand::Drop(id, destination) => {
+    let StateData {
+        to,
+        last_timetokens,
+        replay,
+        shutdown,
+        ..
+    } = state_data;
+    let destinations_changed = match request {
+        ControlCommand::Drop(id, destination, leave_tx) => {
             // Log the event.
             debug!(
                 "Unregistering the listener at subscribe loop: {:?} {:?}",
@@ -180,37 +561,153 @@ async fn handle_control_command(
             );
 
             // Unregister specified listener from the registry.
-            let (_, _effect) = to
-                .unregister(&destination, id)
-                .expect("Unable to unregister destination from a subscribe loop");
-
-            // TODO: avoid terminating loop here to avoid special casing.
-            if to.is_empty() {
-                ControlOutcome::Terminate
-            } else {
-                ControlOutcome::CanContinue
+            // The listener might be already gone if the destination was
+            // unsubscribed from explicitly.
+            match to.unregister(&destination, id) {
+                Some((_, UnregistrationEffect::NameErased)) => {
+                    forget(last_timetokens, replay, &destination);
+                    if let Some(leave_tx) = leave_tx {
+                        // The requester might not wait for the result.
+                        let _ = leave_tx.send(destination);
+                    }
+                    true
+                }
+                Some((_, UnregistrationEffect::NamePreserved)) => false,
+                None => {
+                    debug!(
+                        "Listener is already unregistered: {:?} {:?}",
+                        destination, id
+                    );
+                    false
+                }
             }
         }
-        ControlCommand::Add(destination, channel_tx, id_tx) => {
+        ControlCommand::DropAll(destination) => {
+            // Log the event.
+            debug!(
+                "Unregistering all listeners at subscribe loop: {:?}",
+                destination
+            );
+
+            forget(last_timetokens, replay, &destination);
+
+            // Unregister all the listeners, this closes their streams.
+            to.unregister_all(&destination).is_some()
+        }
+        ControlCommand::Add(destination, from, mut listener, id_tx) => {
             // Log the event.
             debug!("Registering listener at subscribe loop: {:?}", destination);
 
+            // Replay before handling anything else, so that the live
+            // messages follow the replayed ones without gaps. The channel is
+            // new and sized to fit the replay, so none of them is dropped.
+            if let Some(ref replay) = replay {
+                for message in replay.messages(&destination) {
+                    listener.deliver(message);
+                }
+            }
+
             // Register the destination listener with the registry.
-            let (id, _effect) = to.register(destination, channel_tx);
+            let (id, effect) = to.register(destination.clone(), listener);
 
             // Send Subscription ID.
             id_tx.send(id).expect("Unable to send subscription id");
 
-            ControlOutcome::CanContinue
+            match effect {
+                RegistrationEffect::NewName => {
+                    // The default cursor is the current position, never
+                    // behind the loop.
+                    if from != Cursor::default()
+                        && (*cursor == Cursor::default() || from.timetoken < cursor.timetoken)
+                    {
+                        debug!("Rewinding the subscribe loop from {} to {}", cursor, from);
+                        *cursor = from;
+                    }
+                    true
+                }
+                RegistrationEffect::ExistingName => false,
+            }
         }
+        ControlCommand::Shutdown(shutdown_tx) => {
+            // Log the event.
+            debug!("Shutting down subscribe loop");
+
+            // Unregister all the listeners, this closes their streams and
+            // terminates the loop.
+            let destinations: Vec<_> = to.keys().cloned().collect();
+            for destination in &destinations {
+                to.unregister_all(destination);
+            }
+            for destination in &destinations {
+                forget(last_timetokens, replay, destination);
+            }
+            *shutdown = Some((shutdown_tx, destinations));
+            true
+        }
+        ControlCommand::Destinations(destinations_tx) => {
+            // The requester might not wait for the result.
+            let _ = destinations_tx.send(to.keys().cloned().collect());
+            false
+        }
+    };
+
+    if to.is_empty() {
+        ControlOutcome::Terminate
+    } else if destinations_changed {
+        ControlOutcome::Restart
+    } else {
+        ControlOutcome::CanContinue
+    }
+}
+
+/// Drop what's kept for a `destination` that has no listeners left.
+fn forget(
+    last_timetokens: &mut Option<LastTimetokens>,
+    replay: &mut Option<ReplayBuffers>,
+    destination: &pubsub::SubscribeTo,
+) {
+    if let Some(ref mut last_timetokens) = last_timetokens {
+        last_timetokens.forget(destination);
+    }
+    if let Some(ref mut replay) = replay {
+        replay.forget(destination);
     }
 }
 
 /// Dispatch messages to interested listeners.
-async fn dispatch_messages(state_data: &mut StateData, messages: Vec<Message>) {
+///
+/// Never waits for the listeners, see [`Listener`].
+fn dispatch_messages(state_data: &mut StateData, messages: Vec<Message>) {
     // Distribute messages to each listener.
     for message in messages {
-        let destinations = MessageDestinations::new(&message);
+        // The undecodable messages might lack the timetoken.
+        if let (Some(ref mut last_timetokens), None) =
+            (&mut state_data.last_timetokens, &message.decode_error)
+        {
+            if last_timetokens.is_redelivery(&message) {
+                debug!("Skipping redelivered message: {:?}", message.timetoken);
+                continue;
+            }
+        }
+
+        #[cfg(feature = "cipher")]
+        let message = match state_data.cipher {
+            Some(ref cipher) => decrypt_message(cipher, message),
+            None => message,
+        };
+
+        #[cfg(feature = "metrics")]
+        state_data.metrics.message_received();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            channel = %message.channel,
+            timetoken = %message.timetoken,
+            "Dispatching message"
+        );
+
+        let message = Arc::new(message);
+        let wildcards = matching_wildcards(&message, state_data.to.keys());
+        let destinations = MessageDestinations::new(&message).chain(wildcards);
         for destination in destinations {
             let listeners = state_data.to.get_iter_mut(&destination);
             let listeners = match listeners {
@@ -220,16 +717,48 @@ async fn dispatch_messages(state_data: &mut StateData, messages: Vec<Message>) {
                 }
                 Some(v) => v,
             };
+            if let Some(ref mut replay) = state_data.replay {
+                replay.record(&destination, &message);
+            }
             debug!(
                 "Delivering to {:?} listeners for {:?}...",
                 listeners.size_hint(),
                 destination
             );
-            for channel_tx in listeners {
-                if let Err(error) = channel_tx.send(message.clone()).await {
-                    error!("Delivery error: {:?}", error);
-                }
+            for listener in listeners {
+                listener.deliver(Arc::clone(&message));
             }
         }
     }
 }
+
+/// Decrypt the payload of the message.
+///
+/// If the payload can't be decrypted, the message is left as-is, so that a
+/// single unencrypted message doesn't break the subscription.
+#[cfg(feature = "cipher")]
+pub(crate) fn decrypt_message(cipher: &Cipher, mut message: Message) -> Message {
+    // Only the published messages are encrypted.
+    if message.message_type != message::Type::Publish || message.decode_error.is_some() {
+        return message;
+    }
+
+    if let Some(json) = decrypt_payload(cipher, &message.json) {
+        message.json = json;
+        message.decrypted = true;
+    } else {
+        warn!(
+            "Unable to decrypt the message, delivering as-is: {:?}",
+            message.timetoken
+        );
+    }
+    message
+}
+
+/// Decrypt the encrypted JSON payload.
+#[cfg(feature = "cipher")]
+fn decrypt_payload(cipher: &Cipher, payload: &JsonValue) -> Option<JsonValue> {
+    let decrypted = cipher.decrypt(payload.as_str()?).ok()?;
+    let decrypted = String::from_utf8(decrypted).ok()?;
+    json::parse(&decrypted).ok()
+}
@@ -1,21 +1,38 @@
+use super::channel::{self, DroppedCounter, Listener};
+use super::cursors::CurrentCursors;
 use super::registry::Registry;
+use super::status::StatusBroadcaster;
 use super::subscribe_loop::{
     subscribe_loop, ControlCommand, ControlTx, ExitTx, SubscribeLoopParams,
 };
 use super::subscription::Subscription;
-use crate::data::pubsub;
+use crate::data::{cursor::Cursor, filter::FilterExpression, pubsub};
 use crate::runtime::Runtime;
 use crate::transport::Transport;
 use crate::PubNub;
 use futures_channel::{mpsc, oneshot};
+use futures_util::future::{select, Either};
 use futures_util::sink::SinkExt;
-use log::debug;
+use log::{debug, error};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(feature = "cipher")]
+use pubnub_util::cipher::Cipher;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Counters;
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
 
 /// SubscribeLoopSupervisor is responsible for the lifecycle of the subscribe
-/// loop.
-/// It owns the subscribe loop control handle and provides a high-level
+/// loops.
+/// It owns the subscribe loop control handles and provides a high-level
 /// interface to the control operations.
-/// It will intelligently spawn and respawn the subscribe loop on a need basis.
+/// It will intelligently spawn and respawn the subscribe loops on a need basis.
+///
+/// The PubNub network applies the filter expression to the whole subscribe
+/// request, so there's a separate loop for every filter in use.
 ///
 /// Deliberately doesn't implement `Clone` to avoid issues with improper
 /// duplication of control handles.
@@ -24,8 +41,14 @@ pub(crate) struct SubscribeLoopSupervisor {
     /// Configuration params.
     params: SubscribeLoopSupervisorParams,
 
-    /// Control handle to the subscribe loop.
-    control_tx: Option<ControlTx>,
+    /// Control handles to the subscribe loops, by the filter they apply.
+    control_txs: HashMap<Option<FilterExpression>, ControlTx>,
+
+    /// The number of the explicit unsubscribes from each destination.
+    unsubscribes: HashMap<pubsub::SubscribeTo, usize>,
+
+    /// The number of the shutdowns.
+    shutdowns: usize,
 }
 
 /// SubscribeLoopSupervisorParams configuration params.
@@ -33,15 +56,65 @@ pub(crate) struct SubscribeLoopSupervisor {
 pub(crate) struct SubscribeLoopSupervisorParams {
     /// If set, gets a signal when subscribe loop exits.
     pub exit_tx: Option<ExitTx>,
+
+    /// Whether to skip the redelivered messages.
+    pub message_deduplication: bool,
+
+    /// The expression to filter the messages with, unless another one is
+    /// requested for the subscription.
+    pub filter_expr: Option<FilterExpression>,
+
+    /// The number of messages buffered for each subscription stream.
+    pub subscription_buffer: usize,
+
+    /// How long a subscribe request may take before the loop drops it and
+    /// reconnects, if at all.
+    pub watchdog_timeout: Option<Duration>,
+
+    /// How long to wait for a new subscribe loop to connect before
+    /// returning the subscription anyway, if at all.
+    pub ready_timeout: Option<Duration>,
+
+    /// The number of the recent messages replayed to the new listeners of
+    /// each destination.
+    pub replay_buffer: usize,
+
+    /// Delivers the status events.
+    pub status_broadcaster: StatusBroadcaster,
+
+    /// The latest cursors of the loops.
+    pub current_cursors: CurrentCursors,
+
+    /// Cipher to decrypt the received payloads with.
+    #[cfg(feature = "cipher")]
+    pub cipher: Option<Cipher>,
+
+    /// The metrics counters.
+    #[cfg(feature = "metrics")]
+    pub metrics: Arc<Counters>,
 }
 
 impl SubscribeLoopSupervisor {
     pub fn new(params: SubscribeLoopSupervisorParams) -> Self {
         Self {
             params,
-            control_tx: None,
+            control_txs: HashMap::new(),
+            unsubscribes: HashMap::new(),
+            shutdowns: 0,
         }
     }
+
+    /// The filter expression applied to the subscriptions by default.
+    pub fn filter_expr(&self) -> Option<&FilterExpression> {
+        self.params.filter_expr.as_ref()
+    }
+
+    /// The number of the times the subscriptions to the destination `to`
+    /// were ended on request, by an unsubscribe or a shutdown, to tell them
+    /// from the subscribe loop ending on its own.
+    pub fn explicit_ends(&self, to: &pubsub::SubscribeTo) -> usize {
+        self.unsubscribes.get(to).copied().unwrap_or_default() + self.shutdowns
+    }
 }
 
 impl SubscribeLoopSupervisor {
@@ -49,16 +122,23 @@ impl SubscribeLoopSupervisor {
         &mut self,
         pubnub: &'a mut PubNub<TTransport, TRuntime>,
         to: pubsub::SubscribeTo,
+        cursor: Cursor,
+        filter_expr: Option<FilterExpression>,
     ) -> Subscription<TRuntime>
     where
         TTransport: Transport + 'static,
         TRuntime: Runtime + 'static,
     {
         // Since recursion is troublesome with async fns, we use the loop trick.
+        let dropped = DroppedCounter::default();
         let (id, control_tx, channel_rx) = loop {
-            let (channel_tx, channel_rx) = mpsc::channel(10);
+            // Make room for the replayed messages on top of the live ones,
+            // so none of them is dropped.
+            let (channel_tx, channel_rx) =
+                channel::channel(self.params.subscription_buffer + self.params.replay_buffer);
+            let listener = Listener::new(channel_tx, dropped.clone());
 
-            let id_or_retry = if let Some(ref mut control_tx) = self.control_tx {
+            let id_or_retry = if let Some(control_tx) = self.control_txs.get_mut(&filter_expr) {
                 // Send a command to add the channel to the running
                 // subscribe loop.
 
@@ -67,7 +147,7 @@ impl SubscribeLoopSupervisor {
                 let (id_tx, id_rx) = oneshot::channel();
 
                 let control_comm_result = control_tx
-                    .send(ControlCommand::Add(to.clone(), channel_tx, id_tx))
+                    .send(ControlCommand::Add(to.clone(), cursor, listener, id_tx))
                     .await;
 
                 if control_comm_result.is_err() {
@@ -80,7 +160,7 @@ impl SubscribeLoopSupervisor {
                     // The successive subscribtion attempt will result in
                     // starting off of a new subscription loop and properly
                     // registering the channel there.
-                    self.control_tx = None;
+                    self.control_txs.remove(&filter_expr);
 
                     debug!("Restarting the subscription loop");
 
@@ -101,7 +181,7 @@ impl SubscribeLoopSupervisor {
                 // one.
 
                 let mut registry = Registry::new();
-                let (id, _) = registry.register(to.clone(), channel_tx);
+                let (id, _) = registry.register(to.clone(), listener);
 
                 let (control_tx, control_rx) = mpsc::channel(10);
                 let (ready_tx, ready_rx) = oneshot::channel();
@@ -111,26 +191,54 @@ impl SubscribeLoopSupervisor {
                     control_rx,
                     ready_tx: Some(ready_tx),
                     exit_tx: self.params.exit_tx.clone(),
+                    message_deduplication: self.params.message_deduplication,
+                    filter_expr: filter_expr.clone(),
+                    watchdog_timeout: self.params.watchdog_timeout,
+                    replay_buffer: self.params.replay_buffer,
+                    status_broadcaster: self.params.status_broadcaster.clone(),
+                    current_cursors: self.params.current_cursors.clone(),
+                    #[cfg(feature = "cipher")]
+                    cipher: self.params.cipher,
+                    #[cfg(feature = "metrics")]
+                    metrics: Arc::clone(&self.params.metrics),
 
                     transport: pubnub.transport.clone(),
 
                     to: registry,
+                    cursor,
                 };
 
-                // Spawn the subscribe loop onto the runtime
-                pubnub.runtime.spawn(subscribe_loop(subscribe_loop_params));
+                // Spawn the subscribe loop onto the runtime.
+                pubnub.runtime.spawn(subscribe_loop(
+                    pubnub.runtime.clone(),
+                    subscribe_loop_params,
+                ));
 
                 // Waiting for subscription loop to communicate that it's
                 // ready.
-                // Will deadlock if the signal is never received, which will
-                // only happen if the subscription loop is stuck somehow.
-                // If subscription loop fails and goes out of scope we'll
-                // get an error properly communicating that.
+                // The loop signals once its first request succeeds, or
+                // fails in a way it can't recover from. It keeps retrying
+                // the other errors, so give up waiting after the timeout,
+                // if any.
                 debug!("Waiting for subscription loop ready...");
-                ready_rx.await.expect("Unable to receive ready message");
+                let ready = match self.params.ready_timeout {
+                    Some(timeout) => match select(ready_rx, pubnub.runtime.delay(timeout)).await {
+                        Either::Left((ready, _)) => ready,
+                        Either::Right(_) => {
+                            error!("Subscription loop isn't ready after {:?}", timeout);
+                            Ok(())
+                        }
+                    },
+                    None => ready_rx.await,
+                };
+                if ready.is_err() {
+                    // The stream of the subscription ends right away.
+                    debug!("Subscription loop exited before getting ready");
+                }
 
                 // Keep the control tx for later.
-                self.control_tx = Some(control_tx.clone());
+                self.control_txs
+                    .insert(filter_expr.clone(), control_tx.clone());
 
                 // Return the values from the loop.
                 Some((id, control_tx))
@@ -142,12 +250,99 @@ impl SubscribeLoopSupervisor {
             }
         };
 
+        #[cfg(feature = "metrics")]
+        self.params.metrics.subscription_opened();
+
         Subscription {
             runtime: pubnub.runtime.clone(),
             destination: to,
             id,
             control_tx,
             channel_rx,
+            dropped,
+            leave_tx: None,
+            #[cfg(feature = "metrics")]
+            metrics: Arc::clone(&self.params.metrics),
+        }
+    }
+
+    /// Stop all the subscribe loops, and return the destinations they were
+    /// subscribed to.
+    pub async fn shutdown(&mut self) -> Vec<pubsub::SubscribeTo> {
+        self.shutdowns += 1;
+        let mut destinations = Vec::new();
+        for (_, mut control_tx) in self.control_txs.drain() {
+            debug!("Shutting down the subscribe loop");
+
+            let (shutdown_tx, shutdown_rx) = oneshot::channel();
+            let control_comm_result = control_tx.send(ControlCommand::Shutdown(shutdown_tx)).await;
+            if control_comm_result.is_err() {
+                // The subscribe loop has completed already.
+                continue;
+            }
+
+            // Wait for the loop to stop. The same destination may be
+            // subscribed to with different filters, so merge them.
+            if let Ok(to) = shutdown_rx.await {
+                for to in to {
+                    if !destinations.contains(&to) {
+                        destinations.push(to);
+                    }
+                }
+            }
+        }
+        destinations
+    }
+
+    /// The destinations the running subscribe loops are subscribed to.
+    pub async fn destinations(&mut self) -> Vec<pubsub::SubscribeTo> {
+        let mut destinations = Vec::new();
+        let mut completed = Vec::new();
+        for (filter_expr, control_tx) in &mut self.control_txs {
+            let (destinations_tx, destinations_rx) = oneshot::channel();
+            let control_comm_result = control_tx
+                .send(ControlCommand::Destinations(destinations_tx))
+                .await;
+            if control_comm_result.is_err() {
+                // The subscribe loop has completed already.
+                completed.push(filter_expr.clone());
+                continue;
+            }
+
+            // The same destination may be subscribed to with different
+            // filters, so merge them.
+            if let Ok(to) = destinations_rx.await {
+                for to in to {
+                    if !destinations.contains(&to) {
+                        destinations.push(to);
+                    }
+                }
+            }
+        }
+        for filter_expr in completed {
+            self.control_txs.remove(&filter_expr);
+        }
+        destinations
+    }
+
+    pub async fn unsubscribe(&mut self, to: pubsub::SubscribeTo) {
+        *self.unsubscribes.entry(to.clone()).or_default() += 1;
+
+        // The destination may be subscribed to with different filters, so
+        // remove it from every running loop.
+        let mut completed = Vec::new();
+        for (filter_expr, control_tx) in &mut self.control_txs {
+            debug!("Removing destination {:?} from the running loop", to);
+
+            let control_comm_result = control_tx.send(ControlCommand::DropAll(to.clone())).await;
+            if control_comm_result.is_err() {
+                // The subscribe loop has completed already, so there's
+                // nothing to unsubscribe from.
+                completed.push(filter_expr.clone());
+            }
+        }
+        for filter_expr in completed {
+            self.control_txs.remove(&filter_expr);
         }
     }
 }
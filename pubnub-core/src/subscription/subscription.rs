@@ -1,19 +1,29 @@
-use super::subscribe_loop::{ChannelRx, ControlCommand, ControlTx, SubscriptionID};
+use super::channel::DroppedCounter;
+use super::subscribe_loop::{ChannelRx, ControlCommand, ControlTx, LeaveTx, SubscriptionID};
 use crate::data::{message::Message, pubsub};
 use crate::runtime::Runtime;
 use futures_channel::mpsc;
 use futures_util::sink::SinkExt;
-use futures_util::stream::Stream;
+use futures_util::stream::{FusedStream, Stream};
 use futures_util::task::{Context, Poll};
 use log::debug;
 use std::pin::Pin;
+use std::sync::Arc;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Counters;
 
 /// # Inbound PubNub message stream
 ///
 /// This is the message stream returned by [`PubNub::subscribe`]. The stream yields [`Message`]
-/// items until it is dropped.
+/// items until it is dropped. The messages that couldn't be decoded are skipped, see
+/// [`PubNub::try_subscribe`] to get them reported instead.
+///
+/// The messages are shared with the other streams of the channel via [`Arc`], rather than cloned
+/// for each of them. [`Arc`] dereferences to the [`Message`], so the fields are accessed as usual.
 ///
 /// [`PubNub::subscribe`]: crate::pubnub::PubNub::subscribe
+/// [`PubNub::try_subscribe`]: crate::pubnub::PubNub::try_subscribe
 #[derive(Debug)]
 pub struct Subscription<TRuntime: Runtime> {
     pub(crate) runtime: TRuntime, // Runtime to use for managing resources
@@ -21,14 +31,29 @@ pub struct Subscription<TRuntime: Runtime> {
     pub(crate) id: SubscriptionID, // Unique identifier for the listener
     pub(crate) control_tx: ControlTx, // For cleaning up resources at the subscribe loop when dropped
     pub(crate) channel_rx: ChannelRx, // Stream that produces messages
+    pub(crate) dropped: DroppedCounter, // The messages dropped once the stream fell behind
+    pub(crate) leave_tx: Option<LeaveTx>, // For reporting the destination to leave when dropped
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Arc<Counters>, // For tracking the active subscriptions
 }
 
 /// `Subscription` is a stream.
+///
+/// Polls the receiving half of the channel the subscribe loop delivers to, via its public
+/// `Stream` implementation. The task is woken when a message arrives, so there's no busy-polling.
 impl<TRuntime: Runtime> Stream for Subscription<TRuntime> {
-    type Item = Message;
+    type Item = Arc<Message>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        Stream::poll_next(Pin::new(&mut self.get_mut().channel_rx), cx)
+        let this = self.get_mut();
+        loop {
+            match Stream::poll_next(Pin::new(&mut this.channel_rx), cx) {
+                Poll::Ready(Some(message)) if message.decode_error.is_some() => {
+                    debug!("Skipping undecodable message: {:?}", message.decode_error);
+                }
+                poll => return poll,
+            }
+        }
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -36,18 +61,71 @@ impl<TRuntime: Runtime> Stream for Subscription<TRuntime> {
     }
 }
 
+/// The stream is terminated once the subscribe loop stopped delivering to it, i.e. after an
+/// unsubscribe or the loop exit, and all the delivered messages were consumed.
+impl<TRuntime: Runtime> FusedStream for Subscription<TRuntime> {
+    fn is_terminated(&self) -> bool {
+        self.channel_rx.is_terminated()
+    }
+}
+
 impl<TRuntime: Runtime> Subscription<TRuntime> {
+    /// The name of the channel, or the channel group, the stream is subscribed to.
+    ///
+    /// Use with the [`listener_kind`](Self::listener_kind) to tell the channels and the groups of
+    /// the same name apart.
+    #[must_use]
+    pub fn channel_name(&self) -> &str {
+        self.destination.name()
+    }
+
+    /// The destination the stream is subscribed to.
+    #[must_use]
+    pub fn destination(&self) -> &pubsub::SubscribeTo {
+        &self.destination
+    }
+
+    /// Whether the stream is subscribed to a channel, a wildcard or a channel group.
+    #[must_use]
+    pub fn listener_kind(&self) -> pubsub::ListenerKind {
+        self.destination.kind()
+    }
+
+    /// The total number of the messages the stream missed so far.
+    ///
+    /// The subscribe loop doesn't wait for a stream that falls behind, once
+    /// its buffer is full the messages for it are dropped instead, see
+    /// [`Builder::subscription_buffer`](crate::Builder::subscription_buffer).
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped.get()
+    }
+
+    /// A handle to the number of the missed messages, to keep an eye on it
+    /// while the stream is consumed elsewhere.
+    #[must_use]
+    pub fn dropped_counter(&self) -> DroppedCounter {
+        self.dropped.clone()
+    }
+
     /// Prepare drop command.
-    fn drop_command(&self) -> ControlCommand {
-        ControlCommand::Drop(self.id, self.destination.clone())
+    fn drop_command(&mut self) -> ControlCommand {
+        ControlCommand::Drop(self.id, self.destination.clone(), self.leave_tx.take())
     }
 }
 
 /// Remove listener from the associated `SubscribeLoop` when the `Subscription` is dropped.
+///
+/// The drop never blocks: the removal is sent from a task spawned on the
+/// runtime, so it's safe to drop the subscriptions from within the runtime,
+/// including many at once.
 impl<TRuntime: Runtime> Drop for Subscription<TRuntime> {
     fn drop(&mut self) {
         debug!("Dropping Subscription: {:?}", self.destination);
 
+        #[cfg(feature = "metrics")]
+        self.metrics.subscription_closed();
+
         let command = self.drop_command();
         let mut control_tx = self.control_tx.clone();
 
@@ -0,0 +1,69 @@
+//! Subscriptions reporting the undecodable messages.
+
+use super::subscription::Subscription;
+use crate::data::message::{DecodeError, Message};
+use crate::runtime::Runtime;
+use futures_util::stream::{FusedStream, Stream};
+use futures_util::task::{Context, Poll};
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// # Fallible PubNub message stream
+///
+/// This is the message stream returned by [`PubNub::try_subscribe`]. It
+/// works like the [`Subscription`], but rather than skipping the messages
+/// that couldn't be decoded, it yields them as the [`DecodeError`]s. The
+/// stream goes on after an error.
+///
+/// [`PubNub::try_subscribe`]: crate::pubnub::PubNub::try_subscribe
+#[derive(Debug)]
+pub struct TrySubscription<TRuntime: Runtime> {
+    subscription: Subscription<TRuntime>,
+}
+
+impl<TRuntime: Runtime> TrySubscription<TRuntime> {
+    pub(crate) fn new(subscription: Subscription<TRuntime>) -> Self {
+        Self { subscription }
+    }
+
+    /// The underlying subscription.
+    #[must_use]
+    pub fn subscription(&self) -> &Subscription<TRuntime> {
+        &self.subscription
+    }
+
+    /// Return the underlying subscription, skipping the undecodable
+    /// messages from now on.
+    #[must_use]
+    pub fn into_inner(self) -> Subscription<TRuntime> {
+        self.subscription
+    }
+}
+
+impl<TRuntime: Runtime> Stream for TrySubscription<TRuntime> {
+    type Item = Result<Arc<Message>, DecodeError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let channel_rx = &mut self.get_mut().subscription.channel_rx;
+        Stream::poll_next(Pin::new(channel_rx), cx).map(|item| {
+            item.map(|message| match message.decode_error {
+                Some(ref reason) => Err(DecodeError {
+                    channel: message.channel.clone(),
+                    json: message.json.clone(),
+                    reason: reason.clone(),
+                }),
+                None => Ok(message),
+            })
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        Stream::size_hint(&self.subscription.channel_rx)
+    }
+}
+
+impl<TRuntime: Runtime> FusedStream for TrySubscription<TRuntime> {
+    fn is_terminated(&self) -> bool {
+        self.subscription.is_terminated()
+    }
+}
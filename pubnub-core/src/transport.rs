@@ -1,8 +1,51 @@
-use crate::data::{presence, request, response};
+use crate::data::{channel, presence, pubsub, request, response, uuid::UUID};
 use async_trait::async_trait;
+use bitflags::bitflags;
+use log::warn;
+use std::fmt;
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Traffic;
 
 /// Transport abstracts away the underlying mechanism through which the PubNub
 /// client communicates with the PubNub network.
+///
+/// # Cancellation
+///
+/// The futures returned by the [`Service::call`] are cancelled by dropping
+/// them. The subscribe loop relies on this to restart the long-poll request
+/// when the set of subscribed channels changes: the in-flight request future
+/// is simply dropped, and a new one is issued.
+///
+/// Implementations must make sure that dropping an in-flight request future
+/// aborts the request and releases all the resources associated with it
+/// (i.e. closes the underlying connection instead of keeping it around),
+/// otherwise rapid subscribe/unsubscribe churn will leak connections.
+///
+/// # Subscribe over other protocols
+///
+/// The subscribe loop only relies on the [`request::Subscribe`] contract: a
+/// call returns the messages newer than the passed timetoken, along with the
+/// timetoken to continue from. How the messages get to the client is up to
+/// the transport, so a transport streaming them over a persistent
+/// connection plugs in without any changes to the application code.
+///
+/// The PubNub REST API serves subscribe over HTTP long-polling only, so
+/// there's no WebSocket mode in the bundled transports. Keep-alive makes the
+/// long-poll requests share a single connection, which keeps the
+/// per-message overhead to a request line and headers per batch.
+///
+/// # Optional endpoints
+///
+/// The trait only requires the services the subscribe loop and the
+/// publishes rely on: publish, subscribe, leave and time. The rest of the
+/// endpoints are grouped into the [`PresenceTransport`],
+/// [`AccessManagerTransport`], [`HistoryTransport`], [`AppContextTransport`],
+/// [`PushTransport`] and [`FilesTransport`] traits, implemented for all the
+/// transports with the services of the group. A partial transport leaves out
+/// the services of the groups it doesn't support, and the client methods of
+/// those groups aren't available with it.
 pub trait Transport:
     Clone
     + Send
@@ -11,34 +54,341 @@ pub trait Transport:
     + Service<request::Publish, Response = response::Publish, Error = <Self as Transport>::Error>
     // Subscribe.
     + Service<request::Subscribe, Response = response::Subscribe, Error = <Self as Transport>::Error>
-    // Set state.
-    + Service<request::SetState, Response = response::SetState, Error = <Self as Transport>::Error>
-    // Get state.
-    + Service<request::GetState, Response = response::GetState, Error = <Self as Transport>::Error>
-    // Here now.
-    + Service<request::HereNow<presence::respond_with::OccupancyOnly>, Response = response::HereNow<presence::respond_with::OccupancyOnly>, Error = <Self as Transport>::Error>
-    + Service<request::HereNow<presence::respond_with::OccupancyAndUUIDs>, Response = response::HereNow<presence::respond_with::OccupancyAndUUIDs>, Error = <Self as Transport>::Error>
-    + Service<request::HereNow<presence::respond_with::Full>, Response = response::HereNow<presence::respond_with::Full>, Error = <Self as Transport>::Error>
-    // Global Here now.
-    + Service<request::GlobalHereNow<presence::respond_with::OccupancyOnly>, Response = response::GlobalHereNow<presence::respond_with::OccupancyOnly>, Error = <Self as Transport>::Error>
-    + Service<request::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>, Response = response::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>, Error = <Self as Transport>::Error>
-    + Service<request::GlobalHereNow<presence::respond_with::Full>, Response = response::GlobalHereNow<presence::respond_with::Full>, Error = <Self as Transport>::Error>
-    // Where now.
-    + Service<request::WhereNow, Response = response::WhereNow, Error = <Self as Transport>::Error>
-    // Heartbeat.
-    + Service<request::Heartbeat, Response = response::Heartbeat, Error = <Self as Transport>::Error>
-    // PAMv3.
-    + Service<request::Grant, Response = response::Grant, Error = <Self as Transport>::Error>
-    // History.
-    + Service<request::GetHistory, Response = response::GetHistory, Error = <Self as Transport>::Error>
-    + Service<request::DeleteHistory, Response = response::DeleteHistory, Error = <Self as Transport>::Error>
-    + Service<request::MessageCountsWithTimetoken, Response = response::MessageCountsWithTimetoken, Error = <Self as Transport>::Error>
-    + Service<request::MessageCountsWithChannelTimetokens, Response = response::MessageCountsWithChannelTimetokens, Error = <Self as Transport>::Error>
+    // Leave.
+    + Service<request::Leave, Response = response::Leave, Error = <Self as Transport>::Error>
+    // Time.
+    + Service<request::Time, Response = response::Time, Error = <Self as Transport>::Error>
 {
     /// Transport-specific error type this transport can generate.
-    type Error: std::error::Error + Send + Sync;
+    type Error: Error;
+
+    /// The network traffic of the transport so far.
+    ///
+    /// Reported as part of the [`PubNub::metrics`](crate::PubNub::metrics).
+    /// Transports that don't track the traffic report zeroes.
+    #[cfg(feature = "metrics")]
+    fn traffic(&self) -> Traffic {
+        Traffic::default()
+    }
+
+    /// The optional endpoints the transport supports.
+    ///
+    /// The client checks the capabilities before calling the optional
+    /// endpoints, and fails with the [`Unsupported`] error, without calling
+    /// the transport, for the ones the transport lacks, i.e. the ones it
+    /// implements the services of, but can't serve with its current
+    /// configuration. The client skips the
+    /// leave requests of the subscribe loop without the
+    /// [`PRESENCE`](Capabilities::PRESENCE).
+    ///
+    /// Defaults to all the endpoints.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::all()
+    }
+
+    /// The origin the requests are currently sent to, for the transports
+    /// that fail over between several origins.
+    ///
+    /// The subscribe loop reports the changes of the origin with
+    /// [`StatusEvent::Failover`](crate::StatusEvent::Failover). Defaults to
+    /// `None`, i.e. no failover.
+    fn current_origin(&self) -> Option<String> {
+        None
+    }
+
+    /// The UUID the transport identifies the client with, i.e. in the
+    /// subscribe and the leave requests.
+    ///
+    /// The client announces the presence on its own with the same UUID, see
+    /// [`PubNub::presence_only`](crate::PubNub::presence_only). Defaults to
+    /// `None`, for the transports that don't identify the client.
+    fn uuid(&self) -> Option<UUID> {
+        None
+    }
+
+    /// Check the channel or the channel group `name`, allowing the trailing
+    /// `.*` of the wildcards with `wildcard`, see
+    /// [`Name::check`](channel::Name::check).
+    ///
+    /// The client checks the names before publishing, and before adding
+    /// them to the subscribe loop, so an invalid name fails only the call it
+    /// was passed to, rather than the subscribe requests of all the
+    /// channels. Defaults to accepting all the names.
+    ///
+    /// # Errors
+    ///
+    /// Returns the transport-specific error for the invalid names.
+    fn check_name(
+        &self,
+        _name: &channel::Name,
+        _wildcard: bool,
+    ) -> Result<(), <Self as Transport>::Error> {
+        Ok(())
+    }
+
+    /// Attach the PAMv3 access `token` to the subsequent requests, or stop
+    /// attaching it if `None`, see [`PubNub::set_token`](crate::PubNub::set_token).
+    ///
+    /// The clones of the transport share the token, so it applies to the
+    /// running subscribe loop too. Takes precedence over the static auth
+    /// key the transport may be configured with.
+    ///
+    /// Defaults to dropping the token with a warning, for the transports
+    /// that don't attach any auth to the requests.
+    fn set_auth_token(&self, token: Option<String>) {
+        if token.is_some() {
+            warn!("The transport doesn't support the access tokens, dropping the token");
+        }
+    }
+}
+
+/// Declare an optional endpoint group trait, implemented for all the
+/// transports with the services of the group.
+macro_rules! endpoint_group {
+    (
+        $(#[$meta:meta])*
+        pub trait $name:ident {
+            $($request:ty => $response:ty,)+
+        }
+    ) => {
+        $(#[$meta])*
+        pub trait $name:
+            Transport
+            $(+ Service<$request, Response = $response, Error = <Self as Transport>::Error>)+
+        {
+        }
+
+        impl<T> $name for T
+        where
+            T: Transport
+                $(+ Service<$request, Response = $response, Error = <T as Transport>::Error>)+,
+        {
+        }
+    };
+}
+
+endpoint_group! {
+    /// A [`Transport`] with the presence endpoints, see
+    /// [`Endpoint::Presence`].
+    pub trait PresenceTransport {
+        // Set state.
+        request::SetState => response::SetState,
+        // Get state.
+        request::GetState => response::GetState,
+        // Here now.
+        request::HereNow<presence::respond_with::OccupancyOnly> => response::HereNow<presence::respond_with::OccupancyOnly>,
+        request::HereNow<presence::respond_with::OccupancyAndUUIDs> => response::HereNow<presence::respond_with::OccupancyAndUUIDs>,
+        request::HereNow<presence::respond_with::Full> => response::HereNow<presence::respond_with::Full>,
+        // Global Here now.
+        request::GlobalHereNow<presence::respond_with::OccupancyOnly> => response::GlobalHereNow<presence::respond_with::OccupancyOnly>,
+        request::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs> => response::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>,
+        request::GlobalHereNow<presence::respond_with::Full> => response::GlobalHereNow<presence::respond_with::Full>,
+        // Where now.
+        request::WhereNow => response::WhereNow,
+        // Heartbeat.
+        request::Heartbeat => response::Heartbeat,
+    }
+}
+
+endpoint_group! {
+    /// A [`Transport`] with the PAMv3 endpoints, see
+    /// [`Endpoint::AccessManager`].
+    pub trait AccessManagerTransport {
+        request::Grant => response::Grant,
+        request::RevokeToken => response::RevokeToken,
+    }
+}
+
+endpoint_group! {
+    /// A [`Transport`] with the history endpoints, see
+    /// [`Endpoint::History`].
+    pub trait HistoryTransport {
+        request::GetHistory => response::GetHistory,
+        request::DeleteHistory => response::DeleteHistory,
+        request::MessageCountsWithTimetoken => response::MessageCountsWithTimetoken,
+        request::MessageCountsWithChannelTimetokens => response::MessageCountsWithChannelTimetokens,
+    }
+}
+
+endpoint_group! {
+    /// A [`Transport`] with the App Context endpoints, see
+    /// [`Endpoint::AppContext`].
+    pub trait AppContextTransport {
+        request::GetUuidMetadata => response::GetUuidMetadata,
+        request::SetUuidMetadata => response::SetUuidMetadata,
+        request::RemoveUuidMetadata => response::RemoveUuidMetadata,
+        request::GetChannelMetadata => response::GetChannelMetadata,
+        request::GetAllChannelMetadata => response::GetAllChannelMetadata,
+        request::SetChannelMetadata => response::SetChannelMetadata,
+        request::RemoveChannelMetadata => response::RemoveChannelMetadata,
+        request::GetMemberships => response::GetMemberships,
+        request::SetMemberships => response::SetMemberships,
+        request::GetChannelMembers => response::GetChannelMembers,
+        request::SetChannelMembers => response::SetChannelMembers,
+    }
+}
+
+endpoint_group! {
+    /// A [`Transport`] with the mobile push endpoints, see
+    /// [`Endpoint::Push`].
+    pub trait PushTransport {
+        request::AddPushChannels => response::AddPushChannels,
+        request::RemovePushChannels => response::RemovePushChannels,
+        request::ListPushChannels => response::ListPushChannels,
+    }
 }
 
+endpoint_group! {
+    /// A [`Transport`] with the File Sharing endpoints, see
+    /// [`Endpoint::Files`].
+    pub trait FilesTransport {
+        request::GenerateFileUploadUrl => response::GenerateFileUploadUrl,
+        request::UploadFile => response::UploadFile,
+        request::PublishFileMessage => response::PublishFileMessage,
+        request::ListFiles => response::ListFiles,
+        request::GetFileUrl => response::GetFileUrl,
+        request::DownloadFile => response::DownloadFile<<Self as Transport>::Error>,
+    }
+}
+
+/// A group of the optional PubNub API endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// Presence: here now, where now, state, heartbeat and leave.
+    Presence,
+    /// Access Manager grants.
+    AccessManager,
+    /// Message history and counts.
+    History,
+    /// App Context, i.e. the UUID and channel metadata and memberships.
+    AppContext,
+    /// Mobile push notifications.
+    Push,
+    /// File Sharing.
+    Files,
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Endpoint::Presence => "presence",
+            Endpoint::AccessManager => "access manager",
+            Endpoint::History => "history",
+            Endpoint::AppContext => "app context",
+            Endpoint::Push => "mobile push",
+            Endpoint::Files => "file sharing",
+        };
+        f.write_str(name)
+    }
+}
+
+bitflags! {
+    /// The set of the optional endpoints supported by a [`Transport`], one
+    /// flag per [`Endpoint`]. Publish and subscribe are always supported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use pubnub_core::{Capabilities, Endpoint};
+    ///
+    /// let capabilities = Capabilities::all() - Capabilities::PUSH;
+    /// assert!(capabilities.contains(Capabilities::HISTORY));
+    /// assert!(!capabilities.contains(Endpoint::Push.into()));
+    /// ```
+    pub struct Capabilities: u8 {
+        /// See [`Endpoint::Presence`].
+        const PRESENCE = 0b0000_0001;
+        /// See [`Endpoint::AccessManager`].
+        const ACCESS_MANAGER = 0b0000_0010;
+        /// See [`Endpoint::History`].
+        const HISTORY = 0b0000_0100;
+        /// See [`Endpoint::AppContext`].
+        const APP_CONTEXT = 0b0000_1000;
+        /// See [`Endpoint::Push`].
+        const PUSH = 0b0001_0000;
+        /// See [`Endpoint::Files`].
+        const FILES = 0b0010_0000;
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl From<Endpoint> for Capabilities {
+    fn from(endpoint: Endpoint) -> Self {
+        match endpoint {
+            Endpoint::Presence => Self::PRESENCE,
+            Endpoint::AccessManager => Self::ACCESS_MANAGER,
+            Endpoint::History => Self::HISTORY,
+            Endpoint::AppContext => Self::APP_CONTEXT,
+            Endpoint::Push => Self::PUSH,
+            Endpoint::Files => Self::FILES,
+        }
+    }
+}
+
+/// Error produced by a [`Transport`].
+///
+/// Converts from the [`Unsupported`] error, which the client fails the
+/// calls to the unsupported endpoints with.
+pub trait Error: std::error::Error + From<Unsupported> + Send + Sync {
+    /// Whether retrying the request can't succeed, i.e. because the access
+    /// was denied.
+    ///
+    /// The subscribe loop stops on fatal errors instead of retrying, or drops
+    /// just the [`denied_destinations`](Self::denied_destinations) if the
+    /// error names them.
+    fn is_fatal(&self) -> bool {
+        false
+    }
+
+    /// The destinations the access was denied to, if the error names them.
+    ///
+    /// On a fatal error naming only some of the subscribed destinations, the
+    /// subscribe loop drops those and keeps going for the rest.
+    fn denied_destinations(&self) -> Vec<pubsub::SubscribeTo> {
+        Vec::new()
+    }
+
+    /// Whether the request was rejected for exceeding the rate limit of the
+    /// PubNub network, i.e. with the `429 Too Many Requests` status.
+    ///
+    /// The client backs off and retries the publishes on these errors, see
+    /// [`Builder::publish_rate_limit`](crate::Builder::publish_rate_limit),
+    /// so the transport should return them rather than retry on its own.
+    fn is_rate_limited(&self) -> bool {
+        false
+    }
+
+    /// How long to wait before retrying the rate limited request, as asked
+    /// for by the PubNub network, if it did.
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Whether the request failed in a way a retry may fix, i.e. for a
+    /// network error or a server-side (`5xx`) error.
+    ///
+    /// The client retries the publishes failed with these errors, see
+    /// [`Builder::publish_retry`](crate::Builder::publish_retry). Defaults
+    /// to `false`, i.e. no retries.
+    fn is_transient(&self) -> bool {
+        false
+    }
+}
+
+/// A call to an optional [`Endpoint`] the transport doesn't support, see
+/// [`Transport::capabilities`].
+///
+/// The client fails such calls without making them, with this error
+/// converted to the [`Error`] of the transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("The {0} endpoint is not supported by the transport")]
+pub struct Unsupported(pub Endpoint);
+
 /// Service respresents a single unit of an async request/response based API.
 #[async_trait]
 pub trait Service<Request>: Send {
@@ -48,5 +398,7 @@ pub trait Service<Request>: Send {
     type Error;
 
     /// Process the request and return the response asynchronously.
+    ///
+    /// Dropping the returned future cancels the request.
     async fn call(&self, req: Request) -> Result<Self::Response, Self::Error>;
 }
@@ -0,0 +1,117 @@
+//! Measures the subscribe calls returning big batches of messages, which is
+//! dominated by parsing the responses.
+//!
+//! Run with `cargo bench -p pubnub-hyper --bench subscribe`.
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::data::{cursor::Cursor, pubsub::SubscribeTo, request};
+use pubnub_hyper::core::json::{self, object, JsonValue};
+use pubnub_hyper::core::TransportService;
+use pubnub_hyper::transport::hyper::Hyper;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+
+const MESSAGES_PER_BATCH: usize = 100;
+const ITEMS_PER_MESSAGE: usize = 20;
+const WARMUP: usize = 10;
+const BATCHES: usize = 200;
+
+/// A subscribe response with a batch of chat-like messages.
+fn batch() -> String {
+    let messages: Vec<JsonValue> = (0..MESSAGES_PER_BATCH)
+        .map(|i| {
+            let items: Vec<JsonValue> = (0..ITEMS_PER_MESSAGE)
+                .map(|j| {
+                    object! {
+                        "id" => j,
+                        "name" => format!("item \"{}\" of message {}", j, i),
+                        "price" => 1.25 * j as f64,
+                        "tags" => json::array!["new", "sale", JsonValue::Null],
+                    }
+                })
+                .collect();
+            object! {
+                "a" => "3",
+                "f" => 0,
+                "i" => format!("client-{}", i),
+                "p" => object! { "t" => format!("1585055981566{:04}", i), "r" => 12 },
+                "k" => "demo",
+                "c" => "bench",
+                "d" => object! {
+                    "text" => "Lorem ipsum dolor sit amet, consectetur adipiscing elit.\n",
+                    "sender" => object! { "id" => i, "name" => "Joe Bob", "online" => true },
+                    "position" => json::array![52.520_008, 13.404_954],
+                    "items" => items,
+                },
+                "u" => object! { "seqn" => i },
+            }
+        })
+        .collect();
+    object! {
+        "t" => object! { "t" => "15850559815683819", "r" => 12 },
+        "m" => messages,
+    }
+    .dump()
+}
+
+/// Make the subscribe calls to a local server answering with the `body`, and
+/// return the time they took.
+async fn run(body: String) -> Duration {
+    let make_service = make_service_fn(move |_| {
+        let body = body.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                let body = body.clone();
+                async move { Ok::<_, Infallible>(Response::new(Body::from(body))) }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+
+    let transport = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin)
+        .secure(false)
+        .build()
+        .unwrap();
+    let subscribe = || {
+        transport.call(request::Subscribe {
+            to: vec![SubscribeTo::Channel("bench".parse().unwrap())],
+            cursor: Cursor::default(),
+            heartbeat: None,
+            filter_expr: None,
+        })
+    };
+
+    for _ in 0..WARMUP {
+        subscribe().await.unwrap();
+    }
+    let start = Instant::now();
+    for _ in 0..BATCHES {
+        let (messages, _) = subscribe().await.unwrap();
+        assert_eq!(messages.len(), MESSAGES_PER_BATCH);
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let body = batch();
+    let mut rt = tokio::runtime::Builder::new()
+        .enable_all()
+        .basic_scheduler()
+        .build()
+        .unwrap();
+    let elapsed = rt.block_on(run(body.clone()));
+
+    println!(
+        "{} batches of {} messages ({} KiB): {:?} per batch",
+        BATCHES,
+        MESSAGES_PER_BATCH,
+        body.len() / 1024,
+        elapsed / BATCHES as u32
+    );
+}
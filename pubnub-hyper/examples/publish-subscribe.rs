@@ -23,7 +23,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "content" => "Hello, world!",
     };
 
-    let mut stream = pubnub.subscribe("my-channel".parse().unwrap()).await;
+    let mut stream = pubnub.subscribe("my-channel".parse().unwrap()).await?;
     let timetoken = pubnub
         .publish("my-channel".parse().unwrap(), message)
         .await?;
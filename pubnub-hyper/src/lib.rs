@@ -6,6 +6,14 @@
 //!
 //! Uses [`pubnub-core`](pubnub-core) under the hood.
 //!
+//! # TLS backends
+//!
+//! The TLS implementation is selected with mutually exclusive features:
+//!
+//! - `tls` (default) - uses the platform native TLS via `native-tls`;
+//! - `rustls` - uses `rustls` with the native root certificates, handy for
+//!   static builds.
+//!
 //! # Example
 //!
 //! ```no_run
@@ -30,7 +38,7 @@
 //! };
 //!
 //! let channel_name: channel::Name = "my-channel".parse().unwrap();
-//! let mut stream = pubnub.subscribe(channel_name.clone()).await;
+//! let mut stream = pubnub.subscribe(channel_name.clone()).await?;
 //! let timetoken = pubnub.publish(channel_name, message.clone()).await?;
 //!
 //! let received = stream.next().await;
@@ -2,3 +2,18 @@
 
 pub mod tokio;
 pub mod tokio_global;
+
+use crate::core::{JoinError, JoinHandle};
+
+/// Wrap the join `handle` of a tokio task.
+fn join_handle(handle: ::tokio::task::JoinHandle<()>) -> JoinHandle {
+    JoinHandle::new(async move {
+        handle.await.map_err(|err| {
+            if err.is_panic() {
+                JoinError::Panicked
+            } else {
+                JoinError::Cancelled
+            }
+        })
+    })
+}
@@ -1,8 +1,12 @@
 //! Tokio runtime.
 
-use crate::core::Runtime;
+use super::join_handle;
+use crate::core::{JoinHandle, Runtime};
 use std::future::Future;
+use std::io;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime as TokioRuntime;
 
 /// Spawns tasks on the specified tokio runtime.
@@ -11,6 +15,18 @@ pub struct Tokio {
     runtime: Arc<TokioRuntime>,
 }
 
+impl Tokio {
+    /// Create a new tokio runtime, reporting the initialization failure
+    /// instead of panicking.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the underlying runtime can't be created.
+    pub fn try_new() -> io::Result<Self> {
+        TokioRuntime::new().map(Self::from)
+    }
+}
+
 impl From<TokioRuntime> for Tokio {
     #[must_use]
     fn from(rt: TokioRuntime) -> Self {
@@ -21,18 +37,23 @@ impl From<TokioRuntime> for Tokio {
 }
 
 impl Runtime for Tokio {
-    fn spawn<F>(&self, future: F)
+    fn spawn<F>(&self, future: F) -> JoinHandle
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        self.runtime.spawn(future);
+        join_handle(self.runtime.spawn(future))
+    }
+
+    fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+        // The timer has to be registered with this runtime, wherever the
+        // delay is polled.
+        Box::pin(self.runtime.enter(|| tokio::time::delay_for(duration)))
     }
 }
 
 impl Default for Tokio {
     #[must_use]
     fn default() -> Self {
-        let runtime = TokioRuntime::new().expect("unable to initialize tokio runtime");
-        Self::from(runtime)
+        Self::try_new().expect("unable to initialize tokio runtime")
     }
 }
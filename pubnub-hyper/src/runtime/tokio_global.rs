@@ -1,18 +1,25 @@
 //! Tokio global executor runtime.
 
-use crate::core::Runtime;
+use super::join_handle;
+use crate::core::{JoinHandle, Runtime};
 use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
 
 /// Spawns tasks on global tokio executor.
 #[derive(Debug, Clone, Copy)]
 pub struct TokioGlobal;
 
 impl Runtime for TokioGlobal {
-    fn spawn<F>(&self, future: F)
+    fn spawn<F>(&self, future: F) -> JoinHandle
     where
         F: Future<Output = ()> + Send + 'static,
     {
-        tokio::spawn(future);
+        join_handle(tokio::spawn(future))
+    }
+
+    fn delay(&self, duration: Duration) -> Pin<Box<dyn Future<Output = ()> + Send + 'static>> {
+        Box::pin(tokio::time::delay_for(duration))
     }
 }
 
@@ -0,0 +1,883 @@
+//! App Context (Objects).
+
+use super::error;
+use super::service::{HyperService, RequestContext};
+use super::util::{access_denied_error, build_request, build_uri, handle_json_response};
+use crate::core::data::app_context::{
+    ChannelMember, ChannelMembership, ChannelMetadata, MembershipChanges, MembershipInclude, Page,
+    PageCursor, PageDirection, UuidMetadata,
+};
+use crate::core::data::{request, response};
+use crate::core::json::{self, JsonValue};
+use async_trait::async_trait;
+use hyper::header::IF_MATCH;
+use hyper::{Body, Method, Response, StatusCode};
+use pubnub_util::uritemplate::{IfEmpty, UriTemplate};
+
+#[async_trait]
+impl HyperService<request::GetUuidMetadata> for RequestContext<'_> {
+    type Response = response::GetUuidMetadata;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::GetUuidMetadata) -> Result<Self::Response, Self::Error> {
+        let request::GetUuidMetadata { uuid } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new("/v2/objects/{sub_key}/uuids/{uuid}?include=custom")
+            .set_scalar("sub_key", self.subscribe_key.clone())
+            .set_scalar("uuid", uuid)
+            .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_uuid_metadata(&data_json["data"])
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::SetUuidMetadata> for RequestContext<'_> {
+    type Response = response::SetUuidMetadata;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::SetUuidMetadata) -> Result<Self::Response, Self::Error> {
+        let request::SetUuidMetadata { metadata, if_match } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new("/v2/objects/{sub_key}/uuids/{uuid}?include=custom")
+            .set_scalar("sub_key", self.subscribe_key.clone())
+            .set_scalar("uuid", metadata.id.clone())
+            .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Prepare the request.
+        let mut req =
+            build_request(&self, Method::PATCH, url).header("content-type", "application/json");
+        if let Some(if_match) = if_match {
+            req = req.header(IF_MATCH, if_match);
+        }
+        let req = req.body(Body::from(prepare_uuid_metadata_body(&metadata)))?;
+
+        // Send network request.
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_uuid_metadata(&data_json["data"])
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::RemoveUuidMetadata> for RequestContext<'_> {
+    type Response = response::RemoveUuidMetadata;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::RemoveUuidMetadata,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::RemoveUuidMetadata { uuid } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new("/v2/objects/{sub_key}/uuids/{uuid}")
+            .set_scalar("sub_key", self.subscribe_key.clone())
+            .set_scalar("uuid", uuid)
+            .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::DELETE, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let _ = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::GetChannelMetadata> for RequestContext<'_> {
+    type Response = response::GetChannelMetadata;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::GetChannelMetadata,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::GetChannelMetadata { channel } = request;
+
+        // Prepare the URL.
+        let path_and_query =
+            UriTemplate::new("/v2/objects/{sub_key}/channels/{channel}?include=custom")
+                .set_scalar("sub_key", self.subscribe_key.clone())
+                .set_scalar("channel", channel)
+                .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_channel_metadata(&data_json["data"])
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::GetAllChannelMetadata> for RequestContext<'_> {
+    type Response = response::GetAllChannelMetadata;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::GetAllChannelMetadata,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::GetAllChannelMetadata {
+            limit,
+            page,
+            include_total_count,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new(
+            "/v2/objects/{sub_key}/channels?include=custom{&limit,start,end,count}",
+        )
+        .set_scalar("sub_key", self.subscribe_key.clone())
+        .set_optional_scalar("limit", limit.map(|val| val.to_string()))
+        .set_optional_scalar("start", page_token(page.as_ref(), PageDirection::Next))
+        .set_optional_scalar("end", page_token(page.as_ref(), PageDirection::Prev))
+        .set_optional_scalar(
+            "count",
+            if include_total_count {
+                Some("true")
+            } else {
+                None
+            },
+        )
+        .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_page(&data_json, parse_channel_metadata)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::SetChannelMetadata> for RequestContext<'_> {
+    type Response = response::SetChannelMetadata;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::SetChannelMetadata,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::SetChannelMetadata { metadata, if_match } = request;
+
+        // Prepare the URL.
+        let path_and_query =
+            UriTemplate::new("/v2/objects/{sub_key}/channels/{channel}?include=custom")
+                .set_scalar("sub_key", self.subscribe_key.clone())
+                .set_scalar("channel", metadata.id.clone())
+                .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Prepare the request.
+        let mut req =
+            build_request(&self, Method::PATCH, url).header("content-type", "application/json");
+        if let Some(if_match) = if_match {
+            req = req.header(IF_MATCH, if_match);
+        }
+        let req = req.body(Body::from(prepare_channel_metadata_body(&metadata)))?;
+
+        // Send network request.
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_channel_metadata(&data_json["data"])
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::RemoveChannelMetadata> for RequestContext<'_> {
+    type Response = response::RemoveChannelMetadata;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::RemoveChannelMetadata,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::RemoveChannelMetadata { channel } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new("/v2/objects/{sub_key}/channels/{channel}")
+            .set_scalar("sub_key", self.subscribe_key.clone())
+            .set_scalar("channel", channel)
+            .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::DELETE, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let _ = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::GetMemberships> for RequestContext<'_> {
+    type Response = response::GetMemberships;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::GetMemberships) -> Result<Self::Response, Self::Error> {
+        let request::GetMemberships {
+            uuid,
+            include,
+            limit,
+            page,
+            include_total_count,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new(
+            "/v2/objects/{sub_key}/uuids/{uuid}/channels{?include,limit,start,end,count}",
+        )
+        .set_scalar("sub_key", self.subscribe_key.clone())
+        .set_scalar("uuid", uuid)
+        .set_list_with_if_empty(
+            "include",
+            membership_include(include, "channel"),
+            IfEmpty::Skip,
+        )
+        .set_optional_scalar("limit", limit.map(|val| val.to_string()))
+        .set_optional_scalar("start", page_token(page.as_ref(), PageDirection::Next))
+        .set_optional_scalar("end", page_token(page.as_ref(), PageDirection::Prev))
+        .set_optional_scalar(
+            "count",
+            if include_total_count {
+                Some("true")
+            } else {
+                None
+            },
+        )
+        .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_page(&data_json, parse_channel_membership)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::SetMemberships> for RequestContext<'_> {
+    type Response = response::SetMemberships;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::SetMemberships) -> Result<Self::Response, Self::Error> {
+        let request::SetMemberships {
+            uuid,
+            changes,
+            include,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query =
+            UriTemplate::new("/v2/objects/{sub_key}/uuids/{uuid}/channels{?include}")
+                .set_scalar("sub_key", self.subscribe_key.clone())
+                .set_scalar("uuid", uuid)
+                .set_list_with_if_empty(
+                    "include",
+                    membership_include(include, "channel"),
+                    IfEmpty::Skip,
+                )
+                .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Prepare the request.
+        let body = prepare_membership_changes_body(&changes, "channel");
+        let req = build_request(&self, Method::PATCH, url)
+            .header("content-type", "application/json")
+            .body(Body::from(body))?;
+
+        // Send network request.
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_page(&data_json, parse_channel_membership)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::GetChannelMembers> for RequestContext<'_> {
+    type Response = response::GetChannelMembers;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::GetChannelMembers,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::GetChannelMembers {
+            channel,
+            include,
+            limit,
+            page,
+            include_total_count,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new(
+            "/v2/objects/{sub_key}/channels/{channel}/uuids{?include,limit,start,end,count}",
+        )
+        .set_scalar("sub_key", self.subscribe_key.clone())
+        .set_scalar("channel", channel)
+        .set_list_with_if_empty(
+            "include",
+            membership_include(include, "uuid"),
+            IfEmpty::Skip,
+        )
+        .set_optional_scalar("limit", limit.map(|val| val.to_string()))
+        .set_optional_scalar("start", page_token(page.as_ref(), PageDirection::Next))
+        .set_optional_scalar("end", page_token(page.as_ref(), PageDirection::Prev))
+        .set_optional_scalar(
+            "count",
+            if include_total_count {
+                Some("true")
+            } else {
+                None
+            },
+        )
+        .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_page(&data_json, parse_channel_member)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::SetChannelMembers> for RequestContext<'_> {
+    type Response = response::SetChannelMembers;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::SetChannelMembers,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::SetChannelMembers {
+            channel,
+            changes,
+            include,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query =
+            UriTemplate::new("/v2/objects/{sub_key}/channels/{channel}/uuids{?include}")
+                .set_scalar("sub_key", self.subscribe_key.clone())
+                .set_scalar("channel", channel)
+                .set_list_with_if_empty(
+                    "include",
+                    membership_include(include, "uuid"),
+                    IfEmpty::Skip,
+                )
+                .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Prepare the request.
+        let body = prepare_membership_changes_body(&changes, "uuid");
+        let req = build_request(&self, Method::PATCH, url)
+            .header("content-type", "application/json")
+            .body(Body::from(body))?;
+
+        // Send network request.
+        let response = self.send(req).await?;
+        let data_json = handle_app_context_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_page(&data_json, parse_channel_member)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+async fn handle_app_context_response(
+    response: Response<Body>,
+    limit: usize,
+) -> Result<JsonValue, error::Error> {
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(error::Error::HttpStatus(status));
+    }
+    let data_json = handle_json_response(response, limit).await?;
+
+    match status {
+        status if status.is_success() => Ok(data_json),
+        StatusCode::FORBIDDEN => Err(access_denied_error(&data_json)),
+        StatusCode::PRECONDITION_FAILED => Err(error::Error::PreconditionFailed),
+        status => Err(data_json["error"]["message"]
+            .as_str()
+            .map_or(error::Error::HttpStatus(status), |error_message| {
+                error::Error::Server(error_message.to_owned())
+            })),
+    }
+}
+
+/// Prepare the body of the set UUID metadata request.
+///
+/// Only the fields that are set are included, so that the rest are left
+/// as-is by the server.
+fn prepare_uuid_metadata_body(metadata: &UuidMetadata) -> String {
+    let mut data = JsonValue::new_object();
+    let fields = [
+        ("name", &metadata.name),
+        ("email", &metadata.email),
+        ("externalId", &metadata.external_id),
+        ("profileUrl", &metadata.profile_url),
+    ];
+    for (key, value) in &fields {
+        if let Some(value) = value {
+            data[*key] = value.as_str().into();
+        }
+    }
+    if let Some(ref custom) = metadata.custom {
+        data["custom"] = custom.clone();
+    }
+    json::stringify(data)
+}
+
+/// Prepare the body of the set channel metadata request.
+fn prepare_channel_metadata_body(metadata: &ChannelMetadata) -> String {
+    let mut data = JsonValue::new_object();
+    let fields = [
+        ("name", &metadata.name),
+        ("description", &metadata.description),
+    ];
+    for (key, value) in &fields {
+        if let Some(value) = value {
+            data[*key] = value.as_str().into();
+        }
+    }
+    if let Some(ref custom) = metadata.custom {
+        data["custom"] = custom.clone();
+    }
+    json::stringify(data)
+}
+
+/// Prepare the body of the membership changes request.
+///
+/// Both the memberships to set and to remove are sent in the same body, the
+/// `kind` is the key of the other side of the memberships, i.e. `channel`.
+/// The new and the updated memberships both go to `set`, the server tells
+/// them apart.
+fn prepare_membership_changes_body<T: ToString>(
+    changes: &MembershipChanges<T>,
+    kind: &str,
+) -> String {
+    let reference = |id: &T| {
+        let mut data = JsonValue::new_object();
+        data["id"] = id.to_string().into();
+        data
+    };
+
+    let mut set = JsonValue::new_array();
+    for entry in &changes.set {
+        let mut data = JsonValue::new_object();
+        data[kind] = reference(&entry.id);
+        if let Some(ref custom) = entry.custom {
+            data["custom"] = custom.clone();
+        }
+        set.push(data).expect("pushing to an array never fails");
+    }
+
+    let mut delete = JsonValue::new_array();
+    for id in &changes.remove {
+        let mut data = JsonValue::new_object();
+        data[kind] = reference(id);
+        delete.push(data).expect("pushing to an array never fails");
+    }
+
+    let mut data = JsonValue::new_object();
+    data["set"] = set;
+    data["delete"] = delete;
+    json::stringify(data)
+}
+
+/// The values of the `include` parameter of the membership requests, the
+/// `kind` is the key of the other side of the memberships, i.e. `channel`.
+fn membership_include(include: MembershipInclude, kind: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    if include.custom {
+        values.push("custom".to_owned());
+    }
+    if include.metadata {
+        values.push(kind.to_owned());
+        if include.metadata_custom {
+            values.push(format!("{}.custom", kind));
+        }
+    }
+    values
+}
+
+/// The token of the `page` cursor, if it points in the `direction`.
+fn page_token(page: Option<&PageCursor>, direction: PageDirection) -> Option<String> {
+    page.filter(|page| page.direction() == direction)
+        .map(|page| page.token().to_owned())
+}
+
+fn parse_page<T>(
+    data_json: &JsonValue,
+    parse_item: impl Fn(&JsonValue) -> Option<T>,
+) -> Option<Page<T>> {
+    if !data_json["data"].is_array() {
+        return None;
+    }
+    let items: Option<_> = data_json["data"].members().map(parse_item).collect();
+    let cursor = |key: &str, direction| {
+        data_json[key]
+            .as_str()
+            .map(|token| PageCursor::new(direction, token))
+    };
+    Some(Page {
+        items: items?,
+        total_count: data_json["totalCount"].as_usize(),
+        next: cursor("next", PageDirection::Next),
+        prev: cursor("prev", PageDirection::Prev),
+    })
+}
+
+fn parse_uuid_metadata(data_json: &JsonValue) -> Option<UuidMetadata> {
+    let string = |key: &str| data_json[key].as_str().map(str::to_owned);
+    Some(UuidMetadata {
+        id: data_json["id"].as_str()?.into(),
+        name: string("name"),
+        email: string("email"),
+        external_id: string("externalId"),
+        profile_url: string("profileUrl"),
+        custom: parse_custom(&data_json["custom"]),
+        updated: string("updated"),
+        etag: string("eTag"),
+    })
+}
+
+fn parse_channel_metadata(data_json: &JsonValue) -> Option<ChannelMetadata> {
+    let string = |key: &str| data_json[key].as_str().map(str::to_owned);
+    Some(ChannelMetadata {
+        id: data_json["id"].as_str()?.parse().ok()?,
+        name: string("name"),
+        description: string("description"),
+        custom: parse_custom(&data_json["custom"]),
+        updated: string("updated"),
+        etag: string("eTag"),
+    })
+}
+
+fn parse_channel_membership(data_json: &JsonValue) -> Option<ChannelMembership> {
+    let string = |key: &str| data_json[key].as_str().map(str::to_owned);
+    Some(ChannelMembership {
+        channel: parse_channel_metadata(&data_json["channel"])?,
+        custom: parse_custom(&data_json["custom"]),
+        updated: string("updated"),
+        etag: string("eTag"),
+    })
+}
+
+fn parse_channel_member(data_json: &JsonValue) -> Option<ChannelMember> {
+    let string = |key: &str| data_json[key].as_str().map(str::to_owned);
+    Some(ChannelMember {
+        uuid: parse_uuid_metadata(&data_json["uuid"])?,
+        custom: parse_custom(&data_json["custom"]),
+        updated: string("updated"),
+        etag: string("eTag"),
+    })
+}
+
+fn parse_custom(custom: &JsonValue) -> Option<JsonValue> {
+    if custom.is_null() {
+        None
+    } else {
+        Some(custom.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        membership_include, page_token, parse_channel_member, parse_channel_metadata, parse_page,
+        parse_uuid_metadata, prepare_membership_changes_body, prepare_uuid_metadata_body,
+    };
+    use crate::core::data::app_context::{
+        ChannelMetadata, MembershipChanges, MembershipEntry, MembershipInclude, Page, PageCursor,
+        PageDirection, UuidMetadata,
+    };
+    use crate::core::data::channel;
+    use crate::core::json;
+
+    #[test]
+    fn test_parse_uuid_metadata() {
+        let data_json = json::parse(
+            r#"{
+                "id": "my-uuid",
+                "name": "John Doe",
+                "externalId": null,
+                "profileUrl": "https://example.com/john.png",
+                "email": "john@example.com",
+                "custom": {"visits": 10, "vip": true},
+                "updated": "2020-06-17T16:28:14.060718Z",
+                "eTag": "AY39mJKK//C0VA"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            parse_uuid_metadata(&data_json),
+            Some(UuidMetadata {
+                id: "my-uuid".into(),
+                name: Some("John Doe".to_owned()),
+                email: Some("john@example.com".to_owned()),
+                external_id: None,
+                profile_url: Some("https://example.com/john.png".to_owned()),
+                custom: Some(json::object! {
+                    "visits" => 10,
+                    "vip" => true,
+                }),
+                updated: Some("2020-06-17T16:28:14.060718Z".to_owned()),
+                etag: Some("AY39mJKK//C0VA".to_owned()),
+            })
+        );
+
+        assert_eq!(parse_uuid_metadata(&json::object! {}), None);
+    }
+
+    #[test]
+    fn test_prepare_uuid_metadata_body() {
+        let mut metadata = UuidMetadata::new("my-uuid");
+        metadata.name = Some("John Doe".to_owned());
+        metadata.custom = Some(json::object! {
+            "visits" => 10,
+        });
+        metadata.etag = Some("AY39mJKK//C0VA".to_owned());
+
+        assert_eq!(
+            json::parse(&prepare_uuid_metadata_body(&metadata)).unwrap(),
+            json::object! {
+                "name" => "John Doe",
+                "custom" => json::object! {
+                    "visits" => 10,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_channel_metadata_page() {
+        let data_json = json::parse(
+            r#"{
+                "status": 200,
+                "data": [
+                    {
+                        "id": "my-channel",
+                        "name": "My Channel",
+                        "description": null,
+                        "custom": {"topic": "news"},
+                        "updated": "2020-06-17T16:28:14.060718Z",
+                        "eTag": "AY39mJKK//C0VA"
+                    }
+                ],
+                "totalCount": 9,
+                "next": "MUIwQTAwMUItQkRBRC00NDkyLTgyMEMtODg2OUU1N0REMTNBCg=="
+            }"#,
+        )
+        .unwrap();
+
+        let page = parse_page(&data_json, parse_channel_metadata).unwrap();
+        assert_eq!(
+            page,
+            Page {
+                items: vec![ChannelMetadata {
+                    id: "my-channel".parse().unwrap(),
+                    name: Some("My Channel".to_owned()),
+                    description: None,
+                    custom: Some(json::object! {
+                        "topic" => "news",
+                    }),
+                    updated: Some("2020-06-17T16:28:14.060718Z".to_owned()),
+                    etag: Some("AY39mJKK//C0VA".to_owned()),
+                }],
+                total_count: Some(9),
+                next: Some(PageCursor::new(
+                    PageDirection::Next,
+                    "MUIwQTAwMUItQkRBRC00NDkyLTgyMEMtODg2OUU1N0REMTNBCg=="
+                )),
+                prev: None,
+            }
+        );
+
+        // The cursor is passed back as the matching query parameter.
+        assert_eq!(
+            page_token(page.next.as_ref(), PageDirection::Next),
+            Some("MUIwQTAwMUItQkRBRC00NDkyLTgyMEMtODg2OUU1N0REMTNBCg==".to_owned())
+        );
+        assert_eq!(page_token(page.next.as_ref(), PageDirection::Prev), None);
+    }
+
+    #[test]
+    fn test_prepare_membership_changes_body() {
+        let changes: MembershipChanges<channel::Name> = MembershipChanges {
+            set: vec![
+                MembershipEntry {
+                    id: "channel_a".parse().unwrap(),
+                    custom: Some(json::object! {
+                        "role" => "admin",
+                    }),
+                },
+                MembershipEntry {
+                    id: "channel_b".parse().unwrap(),
+                    custom: None,
+                },
+            ],
+            remove: vec!["channel_c".parse().unwrap()],
+        };
+
+        assert_eq!(
+            json::parse(&prepare_membership_changes_body(&changes, "channel")).unwrap(),
+            json::object! {
+                "set" => json::array![
+                    json::object! {
+                        "channel" => json::object! { "id" => "channel_a" },
+                        "custom" => json::object! { "role" => "admin" },
+                    },
+                    json::object! {
+                        "channel" => json::object! { "id" => "channel_b" },
+                    },
+                ],
+                "delete" => json::array![
+                    json::object! {
+                        "channel" => json::object! { "id" => "channel_c" },
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_prepare_membership_changes_body_adds_and_replaces_with_set() {
+        // Adding `channel_a` and replacing the custom data of the existing
+        // `channel_b` is the same operation, with the custom data in full.
+        let changes: MembershipChanges<channel::Name> = MembershipChanges {
+            set: vec![
+                MembershipEntry {
+                    id: "channel_a".parse().unwrap(),
+                    custom: None,
+                },
+                MembershipEntry {
+                    id: "channel_b".parse().unwrap(),
+                    custom: Some(json::object! {
+                        "role" => "admin",
+                        "muted" => false,
+                    }),
+                },
+            ],
+            ..MembershipChanges::default()
+        };
+
+        assert_eq!(
+            json::parse(&prepare_membership_changes_body(&changes, "channel")).unwrap(),
+            json::object! {
+                "set" => json::array![
+                    json::object! {
+                        "channel" => json::object! { "id" => "channel_a" },
+                    },
+                    json::object! {
+                        "channel" => json::object! { "id" => "channel_b" },
+                        "custom" => json::object! { "role" => "admin", "muted" => false },
+                    },
+                ],
+                "delete" => json::array![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_membership_include() {
+        assert_eq!(
+            membership_include(MembershipInclude::all(), "uuid"),
+            vec!["custom", "uuid", "uuid.custom"]
+        );
+        assert_eq!(
+            membership_include(
+                MembershipInclude {
+                    metadata_custom: true,
+                    ..MembershipInclude::default()
+                },
+                "uuid"
+            ),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_parse_channel_members_page() {
+        let data_json = json::parse(
+            r#"{
+                "status": 200,
+                "data": [
+                    {
+                        "uuid": {"id": "uuid_a", "name": "John Doe"},
+                        "custom": {"role": "admin"},
+                        "updated": "2020-06-17T16:28:14.060718Z",
+                        "eTag": "AY39mJKK//C0VA"
+                    },
+                    {
+                        "uuid": {"id": "uuid_b"},
+                        "updated": "2020-06-17T16:28:14.060718Z",
+                        "eTag": "AZO/t53al7m8fw"
+                    }
+                ],
+                "prev": "Mg"
+            }"#,
+        )
+        .unwrap();
+
+        let page = parse_page(&data_json, parse_channel_member).unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].uuid.name, Some("John Doe".to_owned()));
+        assert_eq!(
+            page.items[0].custom,
+            Some(json::object! {
+                "role" => "admin",
+            })
+        );
+        assert_eq!(page.items[1].uuid, UuidMetadata::new("uuid_b"));
+        assert_eq!(page.items[1].custom, None);
+        assert_eq!(page.total_count, None);
+        assert_eq!(page.next, None);
+        assert_eq!(page.prev, Some(PageCursor::new(PageDirection::Prev, "Mg")));
+    }
+}
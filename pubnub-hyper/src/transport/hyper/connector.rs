@@ -0,0 +1,139 @@
+//! Type-erased connector, allowing custom connectors to be used with the
+//! [`Hyper`](super::Hyper) transport.
+
+use futures_util::future::{poll_fn, BoxFuture};
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::Uri;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+type ConnectFn = dyn Fn(Uri) -> BoxFuture<'static, Result<BoxedConnection, BoxError>> + Send + Sync;
+
+/// A connector that wraps an arbitrary `hyper` connector.
+///
+/// Allows plugging custom connectors (i.e. with a different TLS
+/// implementation or a custom certificate store) into the
+/// [`Hyper`](super::Hyper) transport without changing its type:
+///
+/// ```
+/// use hyper::{client::HttpConnector, Client};
+/// use pubnub_hyper::transport::hyper::{BoxedConnector, Hyper};
+///
+/// let connector = BoxedConnector::new(HttpConnector::new());
+/// let http_client = Client::builder()
+///     .pool_max_idle_per_host(16)
+///     .build(connector);
+///
+/// let transport = Hyper::new()
+///     .publish_key("demo")
+///     .subscribe_key("demo")
+///     .http_client(http_client)
+///     .build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct BoxedConnector {
+    connect: Arc<ConnectFn>,
+}
+
+impl BoxedConnector {
+    /// Wrap the `connector`.
+    pub fn new<C>(connector: C) -> Self
+    where
+        C: Service<Uri> + Clone + Send + Sync + 'static,
+        C::Response: AsyncRead + AsyncWrite + Connection + Unpin + Send + 'static,
+        C::Error: Into<BoxError>,
+        C::Future: Send + 'static,
+    {
+        let connect = move |dst: Uri| {
+            let mut connector = connector.clone();
+            let fut: BoxFuture<'static, _> = Box::pin(async move {
+                poll_fn(|cx| connector.poll_ready(cx))
+                    .await
+                    .map_err(Into::into)?;
+                let conn = connector.call(dst).await.map_err(Into::into)?;
+                Ok(BoxedConnection(Box::new(conn)))
+            });
+            fut
+        };
+        Self {
+            connect: Arc::new(connect),
+        }
+    }
+}
+
+impl fmt::Debug for BoxedConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedConnector").finish()
+    }
+}
+
+impl Service<Uri> for BoxedConnector {
+    type Response = BoxedConnection;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness of the wrapped connector is checked on every call.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        (self.connect)(dst)
+    }
+}
+
+trait Io: AsyncRead + AsyncWrite + Connection + Unpin + Send {}
+
+impl<T> Io for T where T: AsyncRead + AsyncWrite + Connection + Unpin + Send {}
+
+/// A connection established by the [`BoxedConnector`].
+pub struct BoxedConnection(Box<dyn Io>);
+
+impl fmt::Debug for BoxedConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedConnection").finish()
+    }
+}
+
+impl Connection for BoxedConnection {
+    fn connected(&self) -> Connected {
+        self.0.connected()
+    }
+}
+
+impl AsyncRead for BoxedConnection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for BoxedConnection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
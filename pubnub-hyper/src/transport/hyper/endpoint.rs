@@ -0,0 +1,236 @@
+//! Paths and queries of the PubNub REST API endpoints.
+//!
+//! The endpoints are built from the request data and the transport
+//! configuration, with the values percent-encoded by the [`UriTemplate`].
+//! The request ID is appended later, by the
+//! [`build_uri`](super::util::build_uri).
+
+use super::Hyper;
+use crate::core::data::pubsub::{self, PublishMethod};
+use crate::core::data::{cursor::Cursor, request};
+use crate::core::json;
+use pubnub_util::uritemplate::{IfEmpty, UriTemplate};
+
+/// The path and query of the publish request.
+pub(super) fn publish(hyper: &Hyper, request: &request::Publish) -> String {
+    let request::Publish {
+        channel,
+        payload,
+        meta,
+        timetoken_override,
+        replicate,
+        method,
+    } = request;
+
+    // With `POST` the message is sent in the body instead.
+    let (template, message) = match method {
+        PublishMethod::Get => (
+            "/publish/{pub_key}/{sub_key}/0/{channel}/0/{message}{?uuid,meta,ptto,norep}",
+            Some(payload.dump()),
+        ),
+        PublishMethod::Post => (
+            "/publish/{pub_key}/{sub_key}/0/{channel}/0{?uuid,meta,ptto,norep}",
+            None,
+        ),
+    };
+    UriTemplate::new(template)
+        .set_scalar("pub_key", hyper.publish_key.clone())
+        .set_scalar("sub_key", hyper.subscribe_key.clone())
+        .set_scalar("channel", channel.clone())
+        .set_optional_scalar("message", message)
+        .set_scalar("uuid", hyper.uuid.clone())
+        .set_optional_scalar("meta", meta.as_ref().map(json::JsonValue::dump))
+        .set_optional_scalar("ptto", timetoken_override.map(|val| val.to_string()))
+        .set_optional_scalar("norep", if *replicate { None } else { Some("true") })
+        .build()
+}
+
+/// The path and query of the subscribe request.
+pub(super) fn subscribe(hyper: &Hyper, request: &request::Subscribe) -> String {
+    let request::Subscribe {
+        to,
+        cursor,
+        heartbeat,
+        filter_expr,
+    } = request;
+
+    // TODO: add caching of repeating params to avoid reencoding.
+    UriTemplate::new(
+        "/v2/subscribe/{sub_key}/{channel}/0{?channel-group,tt,tr,uuid,heartbeat,filter-expr}",
+    )
+    .set_scalar("sub_key", hyper.subscribe_key.clone())
+    .tap(|val| inject_subscribe_to(val, to))
+    .set_scalar("tt", cursor.timetoken.to_string())
+    .set_optional_scalar("tr", region_hint(*cursor))
+    .set_scalar("uuid", hyper.uuid.clone())
+    .set_optional_scalar("heartbeat", heartbeat.map(|e| e.to_string()))
+    .set_optional_scalar("filter-expr", filter_expr.as_ref())
+    .build()
+}
+
+/// Set the `channel` and `channel-group` variables of the template from the
+/// subscribe destinations.
+pub(super) fn inject_subscribe_to(template: &mut UriTemplate, to: &[pubsub::SubscribeTo]) {
+    let channels = to.iter().filter_map(|to| {
+        to.as_channel()
+            .map(AsRef::<str>::as_ref)
+            .or_else(|| to.as_channel_wildcard().map(AsRef::<str>::as_ref))
+    });
+    template.set_list_with_if_empty("channel", channels, IfEmpty::Comma);
+
+    let channel_groups = to
+        .iter()
+        .filter_map(|to| to.as_channel_group().map(AsRef::<str>::as_ref));
+    template.set_list_with_if_empty("channel-group", channel_groups, IfEmpty::Skip);
+}
+
+/// The region is `0` when it's not known (i.e. for the cursors made of the
+/// timetokens returned from publish), in which case we let the server pick
+/// it.
+fn region_hint(cursor: Cursor) -> Option<String> {
+    if cursor.region == 0 {
+        None
+    } else {
+        Some(cursor.region.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{publish, region_hint, subscribe};
+    use crate::core::data::pubsub::{PublishMethod, SubscribeTo};
+    use crate::core::data::{channel, cursor::Cursor, request};
+    use crate::transport::hyper::Hyper;
+
+    fn hyper() -> Hyper {
+        Hyper::new()
+            .publish_key("pub-demo")
+            .subscribe_key("sub-demo")
+            .uuid("my uuid")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_publish() {
+        let request = request::Publish {
+            channel: "my-channel".parse().unwrap(),
+            payload: json::object! { "text" => "Hello, world!" },
+            meta: None,
+            timetoken_override: None,
+            replicate: true,
+            method: PublishMethod::Get,
+        };
+        assert_eq!(
+            publish(&hyper(), &request),
+            "/publish/pub-demo/sub-demo/0/my-channel/0/%7B%22text%22%3A%22Hello%2C%20world%21%22%7D?uuid=my%20uuid"
+        );
+
+        let request = request::Publish {
+            meta: Some(json::object! { "k" => 1 }),
+            timetoken_override: Some(15_000_000_000_000_000),
+            replicate: false,
+            ..request
+        };
+        assert_eq!(
+            publish(&hyper(), &request),
+            "/publish/pub-demo/sub-demo/0/my-channel/0/%7B%22text%22%3A%22Hello%2C%20world%21%22%7D?uuid=my%20uuid&meta=%7B%22k%22%3A1%7D&ptto=15000000000000000&norep=true"
+        );
+    }
+
+    #[test]
+    fn test_publish_via_post() {
+        let request = request::Publish {
+            channel: "my-channel".parse().unwrap(),
+            payload: json::object! { "text" => "Hello, world!" },
+            meta: Some(json::object! { "k" => 1 }),
+            timetoken_override: None,
+            replicate: true,
+            method: PublishMethod::Post,
+        };
+        assert_eq!(
+            publish(&hyper(), &request),
+            "/publish/pub-demo/sub-demo/0/my-channel/0?uuid=my%20uuid&meta=%7B%22k%22%3A1%7D"
+        );
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let request = request::Subscribe {
+            to: vec![
+                SubscribeTo::Channel("ch1".parse().unwrap()),
+                SubscribeTo::ChannelWildcard("ch2.*".parse().unwrap()),
+                SubscribeTo::ChannelGroup("group".parse().unwrap()),
+            ],
+            cursor: Cursor::default(),
+            heartbeat: None,
+            filter_expr: None,
+        };
+        assert_eq!(
+            subscribe(&hyper(), &request),
+            "/v2/subscribe/sub-demo/ch1,ch2.%2A/0?channel-group=group&tt=0&uuid=my%20uuid"
+        );
+
+        let request = request::Subscribe {
+            to: vec![SubscribeTo::ChannelGroup("group".parse().unwrap())],
+            cursor: Cursor::new(15_850_559_815_683_819, 12),
+            heartbeat: Some(300),
+            filter_expr: Some("uuid != 'me'".parse().unwrap()),
+        };
+        assert_eq!(
+            subscribe(&hyper(), &request),
+            "/v2/subscribe/sub-demo/%2C/0?channel-group=group&tt=15850559815683819&tr=12&uuid=my%20uuid&heartbeat=300&filter-expr=uuid%20%21%3D%20%27me%27"
+        );
+    }
+
+    #[test]
+    fn test_channel_names_are_encoded() {
+        let publish_path = |channel: &str| {
+            let request = request::Publish {
+                channel: channel::Name::from_string_unchecked(channel.to_owned()),
+                payload: json::JsonValue::Null,
+                meta: None,
+                timetoken_override: None,
+                replicate: true,
+                method: PublishMethod::Get,
+            };
+            publish(&hyper(), &request)
+        };
+        assert_eq!(
+            publish_path("a/b"),
+            "/publish/pub-demo/sub-demo/0/a%2Fb/0/null?uuid=my%20uuid"
+        );
+        assert_eq!(
+            publish_path("a,b"),
+            "/publish/pub-demo/sub-demo/0/a%2Cb/0/null?uuid=my%20uuid"
+        );
+        assert_eq!(
+            publish_path("чат"),
+            "/publish/pub-demo/sub-demo/0/%D1%87%D0%B0%D1%82/0/null?uuid=my%20uuid"
+        );
+
+        // The separators between the channels stay literal, so the encoded
+        // names can't be split.
+        let request = request::Subscribe {
+            to: vec![
+                SubscribeTo::Channel(channel::Name::from_string_unchecked("a,b".to_owned())),
+                SubscribeTo::Channel("a/b".parse().unwrap()),
+                SubscribeTo::Channel("чат".parse().unwrap()),
+                SubscribeTo::ChannelGroup("g/1".parse().unwrap()),
+            ],
+            cursor: Cursor::default(),
+            heartbeat: None,
+            filter_expr: None,
+        };
+        assert_eq!(
+            subscribe(&hyper(), &request),
+            "/v2/subscribe/sub-demo/a%2Cb,a%2Fb,%D1%87%D0%B0%D1%82/0?channel-group=g%2F1&tt=0&uuid=my%20uuid"
+        );
+    }
+
+    #[test]
+    fn test_region_hint() {
+        assert_eq!(region_hint(Cursor::new(1, 0)), None);
+        assert_eq!(region_hint(Cursor::new(1, 12)), Some("12".to_owned()));
+    }
+}
@@ -1,7 +1,9 @@
 //! Hyper transport related errors.
 
-use crate::core::json;
+use crate::core::data::{channel, pubsub};
+use crate::core::{json, Endpoint, TransportError, Unsupported};
 use error_iter::ErrorIter;
+use std::time::Duration;
 use thiserror::Error;
 
 /// # Error variants
@@ -31,9 +33,215 @@ pub enum Error {
     #[error("Server responded with error")]
     Server(String),
 
+    /// Access was denied by the PubNub Access Manager.
+    #[error("Access denied: {message}")]
+    AccessDenied {
+        /// The channels the access was denied to.
+        channels: Vec<String>,
+        /// The channel groups the access was denied to.
+        channel_groups: Vec<String>,
+        /// The error message returned by the server.
+        message: String,
+    },
+
+    /// The entity tag passed with the `If-Match` header didn't match the
+    /// stored one, i.e. the resource was modified since it was fetched.
+    #[error("Precondition failed, the resource was modified")]
+    PreconditionFailed,
+
+    /// The publish was rejected, i.e. the server responded with something
+    /// other than `[1, "Sent", ...]`.
+    #[error("Publish failed with HTTP status {status}: {message}")]
+    Publish {
+        /// The HTTP status code of the response.
+        status: http::StatusCode,
+        /// The status message returned by the server.
+        message: String,
+    },
+
+    /// The file upload was rejected by the storage, see
+    /// [`PubNub::send_file`](crate::core::PubNub::send_file).
+    #[error("File upload failed with HTTP status {status}: {message}")]
+    FileUpload {
+        /// The HTTP status code of the response.
+        status: http::StatusCode,
+        /// The response body, explaining the failure.
+        message: String,
+    },
+
+    /// The URL of the request is too long for the server, i.e. the message
+    /// is too big to be published with `GET`.
+    ///
+    /// Publish the message with
+    /// [`publish_via_post`](crate::core::PubNub::publish_via_post) instead.
+    #[error("Request URI too long, publish the message with POST instead")]
+    UriTooLong,
+
+    /// The request was rejected for exceeding the rate limit of the PubNub
+    /// network, i.e. with the `429 Too Many Requests` status.
+    ///
+    /// The client holds back the publishes for the delay the server asks
+    /// for, and retries them, see
+    /// [`Builder::publish_rate_limit`](crate::core::Builder::publish_rate_limit).
+    #[error("Rate limit exceeded")]
+    RateLimited {
+        /// How long to wait before retrying, as asked for by the server with
+        /// the `Retry-After` header, if it did.
+        retry_after: Option<Duration>,
+    },
+
+    /// The subscribe request was rejected with a client error (`4xx`)
+    /// status, e.g. for an invalid subscribe key.
+    ///
+    /// Retrying won't help, so the subscribe loop stops on it.
+    #[error("Subscribe rejected with HTTP status {status}: {message}")]
+    SubscribeRejected {
+        /// The HTTP status code of the response.
+        status: http::StatusCode,
+        /// The error message returned by the server.
+        message: String,
+    },
+
+    /// Server responded with an error HTTP status code.
+    #[error("Server responded with HTTP status {0}")]
+    HttpStatus(http::StatusCode),
+
+    /// The channel or the channel group name would be rejected by the
+    /// PubNub network, see
+    /// [`Name::check`](crate::core::data::channel::Name::check).
+    ///
+    /// Reported without sending the request.
+    #[error("Invalid channel name {name:?}: {reason}")]
+    InvalidChannelName {
+        /// The rejected name.
+        name: String,
+        /// Why the name is rejected.
+        reason: &'static str,
+    },
+
+    /// The endpoint is not supported by the transport.
+    #[error("The {0} endpoint is not supported by the transport")]
+    Unsupported(Endpoint),
+
+    /// The response body exceeds the size limit, in bytes.
+    ///
+    /// See [`HyperBuilder::max_response_bytes`](super::HyperBuilder::max_response_bytes)
+    /// and [`HyperBuilder::max_subscribe_response_bytes`](super::HyperBuilder::max_subscribe_response_bytes).
+    #[error("Response body exceeds the limit of {0} bytes")]
+    ResponseTooLarge(usize),
+
     /// Unexpected response schema.
     #[error("Unexpected response schema")]
     UnexpectedResponseSchema(json::JsonValue),
+
+    /// An error of a request, along with the unique ID the request was sent
+    /// with.
+    ///
+    /// Every error returned by the transport calls is wrapped into this
+    /// variant. Quote the request ID when contacting the PubNub support.
+    #[error("{source} (request ID: {request_id})")]
+    Request {
+        /// The ID of the failed request.
+        request_id: String,
+        /// The underlying error.
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Whether the error is transient, i.e. caused by a network failure or
+    /// by a server-side (`5xx`) error, so the request can be retried.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self.inner() {
+            Self::Hyper(_) => true,
+            Self::HttpStatus(status) => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    /// The ID of the failed request, if the error is associated with one.
+    #[must_use]
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Self::Request { request_id, .. } => Some(request_id),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, with the request ID stripped.
+    #[must_use]
+    pub fn inner(&self) -> &Self {
+        match self {
+            Self::Request { source, .. } => source.inner(),
+            error => error,
+        }
+    }
+}
+
+impl TransportError for Error {
+    #[allow(clippy::match_like_matches_macro)] // `matches!` requires Rust 1.42
+    fn is_fatal(&self) -> bool {
+        match self.inner() {
+            Self::AccessDenied { .. } | Self::SubscribeRejected { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// The channels ending with `.*` are reported as the wildcards.
+    fn denied_destinations(&self) -> Vec<pubsub::SubscribeTo> {
+        match self.inner() {
+            Self::AccessDenied {
+                channels,
+                channel_groups,
+                ..
+            } => channels
+                .iter()
+                .map(|channel| {
+                    if channel.ends_with(".*") {
+                        pubsub::SubscribeTo::ChannelWildcard(
+                            channel::WildcardSpec::from_string_unchecked(channel.clone()),
+                        )
+                    } else {
+                        pubsub::SubscribeTo::Channel(channel::Name::from_string_unchecked(
+                            channel.clone(),
+                        ))
+                    }
+                })
+                .chain(channel_groups.iter().map(|group| {
+                    pubsub::SubscribeTo::ChannelGroup(channel::Name::from_string_unchecked(
+                        group.clone(),
+                    ))
+                }))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn is_rate_limited(&self) -> bool {
+        match self.inner() {
+            Self::RateLimited { .. } => true,
+            Self::Publish { status, .. } => *status == http::StatusCode::TOO_MANY_REQUESTS,
+            _ => false,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self.inner() {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+
+    fn is_transient(&self) -> bool {
+        Error::is_transient(self)
+    }
+}
+
+impl From<Unsupported> for Error {
+    fn from(Unsupported(endpoint): Unsupported) -> Self {
+        Self::Unsupported(endpoint)
+    }
 }
 
 impl ErrorIter for Error {}
@@ -46,3 +254,84 @@ pub enum Configuration {
     #[error("Secret key is unavailable")]
     SecretKeyUnavailable,
 }
+
+/// Misconfigurations reported by
+/// [`HyperBuilder::try_build`](super::HyperBuilder::try_build).
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum BuilderError {
+    /// The subscribe key is set to an empty string.
+    #[error("Subscribe key is empty")]
+    EmptySubscribeKey,
+
+    /// The publish key is set to an empty string.
+    #[error("Publish key is empty")]
+    EmptyPublishKey,
+
+    /// The secret key is set to an empty string. The calls signed with the
+    /// secret key, such as the PAM grants, would be rejected by the server.
+    #[error("Secret key is empty")]
+    EmptySecretKey,
+
+    /// The [`access_manager`](super::HyperBuilder::access_manager) is
+    /// enabled, but there's no secret key to sign its calls with.
+    #[error("Access Manager requires a secret key")]
+    SecretKeyRequired,
+
+    /// The origin is not a valid URL authority, i.e. it includes a scheme
+    /// or a path.
+    #[error("Invalid origin: {0:?}")]
+    InvalidOrigin(String),
+
+    /// The name of an extra header is invalid.
+    #[error("Invalid header name: {0:?}")]
+    InvalidHeaderName(String),
+
+    /// The value of an extra header is invalid.
+    #[error("Invalid value for header {name:?}: {value:?}")]
+    InvalidHeaderValue {
+        /// The name of the header.
+        name: String,
+        /// The invalid value.
+        value: String,
+    },
+
+    /// Any other error, such as a missing required field, an invalid proxy
+    /// URL or a failure to initialize TLS.
+    #[error("{0}")]
+    Build(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use crate::core::data::pubsub;
+    use crate::core::TransportError;
+
+    #[test]
+    fn test_request_error() {
+        let error = Error::Request {
+            request_id: "my-request".to_owned(),
+            source: Box::new(Error::AccessDenied {
+                channels: vec!["ch1".to_owned(), "ch2.*".to_owned()],
+                channel_groups: vec!["cg1".to_owned()],
+                message: "Forbidden".to_owned(),
+            }),
+        };
+        assert_eq!(error.request_id(), Some("my-request"));
+        assert!(error.to_string().ends_with("(request ID: my-request)"));
+        match error.inner() {
+            Error::AccessDenied { message, .. } => assert_eq!(message, "Forbidden"),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(error.is_fatal());
+        assert!(!error.is_transient());
+        assert_eq!(
+            error.denied_destinations(),
+            vec![
+                pubsub::SubscribeTo::Channel("ch1".parse().unwrap()),
+                pubsub::SubscribeTo::ChannelWildcard("ch2.*".parse().unwrap()),
+                pubsub::SubscribeTo::ChannelGroup("cg1".parse().unwrap()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Failover between the origins.
+
+use std::sync::Mutex;
+
+/// The number of the consecutive failures after which the transport moves
+/// on to the next origin.
+const FAILOVER_AFTER: usize = 3;
+
+/// Tracks the origin the requests are sent to.
+///
+/// The requests stick to the current origin for as long as it works, and
+/// move on to the next one after a few failures in a row, wrapping around
+/// after the last one. Shared by the clones of the transport.
+#[derive(Debug, Default)]
+pub(super) struct Failover {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The index of the current origin.
+    current: usize,
+    /// The number of the consecutive failures of the current origin.
+    failures: usize,
+}
+
+impl Failover {
+    /// The index of the origin to send the requests to.
+    pub fn current(&self) -> usize {
+        self.lock().current
+    }
+
+    /// Record a successful request to the origin at `index`.
+    pub fn succeeded(&self, index: usize) {
+        let mut state = self.lock();
+        if state.current == index {
+            state.failures = 0;
+        }
+    }
+
+    /// Record a failed request to the origin at `index`, out of `count`
+    /// origins. Returns the index of the next origin if it's time to fail
+    /// over.
+    ///
+    /// The failures of the requests sent before the last failover don't
+    /// count.
+    pub fn failed(&self, index: usize, count: usize) -> Option<usize> {
+        let mut state = self.lock();
+        if state.current != index || count < 2 {
+            return None;
+        }
+
+        state.failures += 1;
+        if state.failures < FAILOVER_AFTER {
+            return None;
+        }
+        state.current = (index + 1) % count;
+        state.failures = 0;
+        Some(state.current)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("failover lock is poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Failover, FAILOVER_AFTER};
+
+    #[test]
+    fn test_failover_after_consecutive_failures() {
+        let failover = Failover::default();
+
+        // A success in between resets the count.
+        for _ in 1..FAILOVER_AFTER {
+            failover.failed(0, 2);
+        }
+        failover.succeeded(0);
+        for _ in 1..FAILOVER_AFTER {
+            failover.failed(0, 2);
+        }
+        assert_eq!(failover.current(), 0);
+
+        assert_eq!(failover.failed(0, 2), Some(1));
+        assert_eq!(failover.current(), 1);
+
+        // The late failures of the previous origin don't count.
+        for _ in 0..FAILOVER_AFTER {
+            failover.failed(0, 2);
+        }
+        assert_eq!(failover.current(), 1);
+
+        // Wraps around after the last origin.
+        for _ in 0..FAILOVER_AFTER {
+            failover.failed(1, 2);
+        }
+        assert_eq!(failover.current(), 0);
+    }
+
+    #[test]
+    fn test_single_origin_never_fails_over() {
+        let failover = Failover::default();
+
+        for _ in 0..FAILOVER_AFTER * 2 {
+            assert_eq!(failover.failed(0, 1), None);
+        }
+        assert_eq!(failover.current(), 0);
+    }
+}
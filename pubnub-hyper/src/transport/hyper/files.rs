@@ -0,0 +1,489 @@
+//! File Sharing.
+
+use super::error;
+use super::pubsub::{check_name, publish_response};
+use super::service::{HyperService, RequestContext};
+use super::util::{
+    access_denied_error, build_request, build_uri, handle_json_response, parse_json_body,
+    receive_body, stream_body,
+};
+use super::Hyper;
+use crate::core::data::file::{File, FileInfo, FilesPage, UploadForm};
+use crate::core::data::{channel, request, response, uuid::UUID};
+use crate::core::json::{self, JsonValue};
+use async_trait::async_trait;
+use futures_util::stream::TryStreamExt;
+use hyper::header::{CONTENT_TYPE, LOCATION, USER_AGENT};
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
+use log::debug;
+use pubnub_util::uritemplate::UriTemplate;
+
+/// The most redirects to follow when downloading a file.
+const MAX_REDIRECTS: usize = 3;
+
+#[async_trait]
+impl HyperService<request::GenerateFileUploadUrl> for RequestContext<'_> {
+    type Response = response::GenerateFileUploadUrl;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::GenerateFileUploadUrl,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::GenerateFileUploadUrl { channel, file_name } = request;
+        check_name(&channel, false)?;
+
+        // Prepare the URL.
+        let path_and_query =
+            UriTemplate::new("/v1/files/{sub_key}/channels/{channel}/generate-upload-url{?uuid}")
+                .set_scalar("sub_key", self.subscribe_key.clone())
+                .set_scalar("channel", channel)
+                .set_scalar("uuid", self.uuid.clone())
+                .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Prepare the request.
+        let body = json::object! { "name" => file_name }.dump();
+        let req = build_request(&self, Method::POST, url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))?;
+
+        // Send network request.
+        let response = self.send(req).await?;
+        let data_json = handle_files_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_upload_form(&data_json)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::UploadFile> for RequestContext<'_> {
+    type Response = response::UploadFile;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::UploadFile) -> Result<Self::Response, Self::Error> {
+        let request::UploadFile { form, data } = request;
+
+        // The form goes to the storage rather than to the PubNub network, so
+        // it's sent without the custom headers, and it's presigned already.
+        let url: Uri = form.url.parse().map_err(http::Error::from)?;
+        let method = Method::from_bytes(form.method.as_bytes()).map_err(http::Error::from)?;
+        debug!("Uploading {} bytes to {}", data.len(), url);
+
+        let boundary = format!("pubnub-{}", UUID::random());
+        let body = multipart_body(&boundary, &form.fields, &form.file.name, &data);
+        let req = Request::builder()
+            .method(method)
+            .uri(url)
+            .header(USER_AGENT, self.agent.as_str())
+            .header(
+                CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(body))?;
+
+        // Send network request.
+        let response = self.send(req).await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let body = receive_body(response, self.max_response_bytes).await?;
+        Err(error::Error::FileUpload {
+            status,
+            message: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+#[async_trait]
+impl HyperService<request::PublishFileMessage> for RequestContext<'_> {
+    type Response = response::PublishFileMessage;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::PublishFileMessage,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::PublishFileMessage {
+            channel,
+            file,
+            message,
+        } = request;
+        check_name(&channel, false)?;
+
+        // Prepare the URL.
+        let message = json::object! {
+            "message" => message.unwrap_or(JsonValue::Null),
+            "file" => json::object! { "id" => file.id, "name" => file.name },
+        };
+        let path_and_query = UriTemplate::new(
+            "/v1/files/publish-file/{pub_key}/{sub_key}/0/{channel}/0/{message}{?uuid}",
+        )
+        .set_scalar("pub_key", self.publish_key.clone())
+        .set_scalar("sub_key", self.subscribe_key.clone())
+        .set_scalar("channel", channel)
+        .set_scalar("message", message.dump())
+        .set_scalar("uuid", self.uuid.clone())
+        .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let status = response.status();
+        if status.is_server_error() {
+            return Err(error::Error::HttpStatus(status));
+        }
+        let body = receive_body(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        publish_response(status, &body).map(|response| response.timetoken)
+    }
+}
+
+#[async_trait]
+impl HyperService<request::ListFiles> for RequestContext<'_> {
+    type Response = response::ListFiles;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::ListFiles) -> Result<Self::Response, Self::Error> {
+        let request::ListFiles { channel, next } = request;
+        check_name(&channel, false)?;
+
+        // Prepare the URL.
+        let path_and_query =
+            UriTemplate::new("/v1/files/{sub_key}/channels/{channel}/files{?uuid,next}")
+                .set_scalar("sub_key", self.subscribe_key.clone())
+                .set_scalar("channel", channel)
+                .set_scalar("uuid", self.uuid.clone())
+                .set_optional_scalar("next", next)
+                .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_files_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_files_page(&data_json)
+            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+#[async_trait]
+impl HyperService<request::GetFileUrl> for RequestContext<'_> {
+    type Response = response::GetFileUrl;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::GetFileUrl) -> Result<Self::Response, Self::Error> {
+        let request::GetFileUrl { channel, file } = request;
+        check_name(&channel, false)?;
+
+        let url = build_uri(&self, &file_path(self, channel, file))?;
+        Ok(url.to_string())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::DownloadFile> for RequestContext<'_> {
+    type Response = response::DownloadFile<error::Error>;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::DownloadFile) -> Result<Self::Response, Self::Error> {
+        let request::DownloadFile { channel, file } = request;
+        check_name(&channel, false)?;
+
+        // Send network request. The PubNub network redirects to the storage.
+        let mut url = build_uri(&self, &file_path(self, channel, file))?;
+        let mut req = build_request(&self, Method::GET, url.clone()).body(Body::empty())?;
+        let mut redirects = 0;
+        let response = loop {
+            let response = self.send(req).await?;
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|location| location.to_str().ok());
+            match location {
+                Some(location)
+                    if response.status().is_redirection() && redirects < MAX_REDIRECTS =>
+                {
+                    url = redirect_target(&url, location)?;
+                    // The storage URL is presigned, so only its host is
+                    // logged.
+                    debug!(
+                        "Following the redirect to {}",
+                        url.host().unwrap_or_default()
+                    );
+                }
+                _ => break response,
+            }
+            // The storage URL is presigned, so it goes without the custom
+            // headers.
+            req = Request::builder()
+                .uri(url.clone())
+                .header(USER_AGENT, self.agent.as_str())
+                .body(Body::empty())?;
+            redirects += 1;
+        };
+
+        // The body is streamed a chunk at a time, and the download fails as
+        // soon as it exceeds the limit.
+        let status = response.status();
+        if status.is_success() {
+            let body = stream_body(response, self.max_response_bytes)?;
+            // The errors midway come after the request is processed, so
+            // they're identified here.
+            let request_id = self.request_id.to_string();
+            let body = body.map_err(move |source| error::Error::Request {
+                request_id: request_id.clone(),
+                source: Box::new(source),
+            });
+            return Ok(Box::pin(body));
+        }
+        if status.is_server_error() {
+            return Err(error::Error::HttpStatus(status));
+        }
+        let body = receive_body(response, self.max_response_bytes).await?;
+        Err(match parse_json_body(&body) {
+            Ok(data_json) => files_error(status, &data_json),
+            // The storage explains the errors in XML.
+            Err(_) => error::Error::HttpStatus(status),
+        })
+    }
+}
+
+/// The path and query of the file download.
+fn file_path(hyper: &Hyper, channel: channel::Name, file: File) -> String {
+    UriTemplate::new("/v1/files/{sub_key}/channels/{channel}/files/{id}/{name}{?uuid}")
+        .set_scalar("sub_key", hyper.subscribe_key.clone())
+        .set_scalar("channel", channel)
+        .set_scalar("id", file.id)
+        .set_scalar("name", file.name)
+        .set_scalar("uuid", hyper.uuid.clone())
+        .build()
+}
+
+/// Resolve the `location` of a redirect from the `url`.
+fn redirect_target(url: &Uri, location: &str) -> Result<Uri, http::Error> {
+    let location: Uri = location.parse()?;
+    if location.authority().is_some() {
+        return Ok(location);
+    }
+
+    let mut parts = location.into_parts();
+    parts.scheme = url.scheme().cloned();
+    parts.authority = url.authority().cloned();
+    Ok(Uri::from_parts(parts)?)
+}
+
+/// Receive the JSON body of a File Sharing response, or the error it
+/// reports.
+async fn handle_files_response(
+    response: Response<Body>,
+    limit: usize,
+) -> Result<JsonValue, error::Error> {
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(error::Error::HttpStatus(status));
+    }
+    let data_json = handle_json_response(response, limit).await?;
+    if status.is_success() {
+        return Ok(data_json);
+    }
+    Err(files_error(status, &data_json))
+}
+
+/// The error reported by a File Sharing response.
+fn files_error(status: StatusCode, data_json: &JsonValue) -> error::Error {
+    if status == StatusCode::FORBIDDEN {
+        return access_denied_error(data_json);
+    }
+    data_json["error"]["message"]
+        .as_str()
+        .map_or(error::Error::HttpStatus(status), |message| {
+            error::Error::Server(message.to_owned())
+        })
+}
+
+/// Parse a list files response.
+fn parse_files_page(data_json: &JsonValue) -> Option<FilesPage> {
+    let files = data_json["data"]
+        .members()
+        .map(|file| {
+            Some(FileInfo {
+                id: file["id"].as_str()?.to_owned(),
+                name: file["name"].as_str()?.to_owned(),
+                size: file["size"].as_u64()?,
+                created: file["created"].as_str()?.to_owned(),
+            })
+        })
+        .collect::<Option<_>>()?;
+    Some(FilesPage {
+        files,
+        next: data_json["next"].as_str().map(str::to_owned),
+    })
+}
+
+/// Parse the form of a generate upload URL response.
+fn parse_upload_form(data_json: &JsonValue) -> Option<UploadForm> {
+    let upload_request = &data_json["file_upload_request"];
+    let fields = upload_request["form_fields"]
+        .members()
+        .map(|field| {
+            Some((
+                field["key"].as_str()?.to_owned(),
+                field["value"].as_str()?.to_owned(),
+            ))
+        })
+        .collect::<Option<_>>()?;
+    Some(UploadForm {
+        file: File {
+            id: data_json["data"]["id"].as_str()?.to_owned(),
+            name: data_json["data"]["name"].as_str()?.to_owned(),
+        },
+        url: upload_request["url"].as_str()?.to_owned(),
+        method: upload_request["method"]
+            .as_str()
+            .unwrap_or("POST")
+            .to_owned(),
+        fields,
+    })
+}
+
+/// Build a `multipart/form-data` body of the `fields` followed by the file,
+/// as the storage expects the file to come last.
+fn multipart_body(
+    boundary: &str,
+    fields: &[(String, String)],
+    file_name: &str,
+    data: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len() + 1024);
+    for (name, value) in fields {
+        body.extend(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary,
+                quote(name),
+                value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n",
+            boundary,
+            quote(file_name)
+        )
+        .as_bytes(),
+    );
+    body.extend(data);
+    body.extend(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// Escape the quoted parameter of the `Content-Disposition` the way the
+/// browsers do.
+fn quote(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{multipart_body, parse_files_page, parse_upload_form, redirect_target};
+    use crate::core::data::file::{File, FileInfo, FilesPage, UploadForm};
+
+    #[test]
+    fn test_parse_upload_form() {
+        let data_json = json::parse(
+            r#"{"status":200,"data":{"id":"0-1","name":"cat.jpg"},"file_upload_request":{"url":"https://files.example.com/","method":"POST","expiration_date":"2020-04-03T22:44:47Z","form_fields":[{"key":"tagging","value":"<Tagging></Tagging>"},{"key":"key","value":"sub-c/0-1/cat.jpg"}]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            parse_upload_form(&data_json),
+            Some(UploadForm {
+                file: File {
+                    id: "0-1".to_owned(),
+                    name: "cat.jpg".to_owned(),
+                },
+                url: "https://files.example.com/".to_owned(),
+                method: "POST".to_owned(),
+                fields: vec![
+                    ("tagging".to_owned(), "<Tagging></Tagging>".to_owned()),
+                    ("key".to_owned(), "sub-c/0-1/cat.jpg".to_owned()),
+                ],
+            })
+        );
+
+        assert_eq!(parse_upload_form(&json::parse("{}").unwrap()), None);
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let body = multipart_body(
+            "b",
+            &[("key".to_owned(), "a/b".to_owned())],
+            "my \"cat\".jpg",
+            b"meow",
+        );
+        assert_eq!(
+            String::from_utf8(body).unwrap(),
+            "--b\r\nContent-Disposition: form-data; name=\"key\"\r\n\r\na/b\r\n\
+             --b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"my %22cat%22.jpg\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\nmeow\r\n--b--\r\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_files_page() {
+        let data_json = json::parse(
+            r#"{"status":200,"data":[{"name":"cat.jpg","id":"0-1","size":25778,"created":"2020-07-26T13:42:06Z"}],"next":"token","count":1}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parse_files_page(&data_json),
+            Some(FilesPage {
+                files: vec![FileInfo {
+                    id: "0-1".to_owned(),
+                    name: "cat.jpg".to_owned(),
+                    size: 25778,
+                    created: "2020-07-26T13:42:06Z".to_owned(),
+                }],
+                next: Some("token".to_owned()),
+            })
+        );
+
+        let data_json = json::parse(r#"{"status":200,"data":[],"count":0}"#).unwrap();
+        assert_eq!(
+            parse_files_page(&data_json),
+            Some(FilesPage {
+                files: vec![],
+                next: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_redirect_target() {
+        let url = "https://ps.pndsn.com/v1/files/demo/channels/ch/files/0-1/cat.jpg"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            redirect_target(&url, "https://files.example.com/0-1/cat.jpg?X-Amz=1").unwrap(),
+            "https://files.example.com/0-1/cat.jpg?X-Amz=1"
+        );
+        assert_eq!(
+            redirect_target(&url, "/elsewhere").unwrap(),
+            "https://ps.pndsn.com/elsewhere"
+        );
+    }
+}
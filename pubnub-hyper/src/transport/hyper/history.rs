@@ -1,21 +1,24 @@
 //! History.
 
-use super::util::{build_uri, handle_json_response, json_as_array, json_as_object};
-use super::{error, Hyper};
+use super::error;
+use super::service::{HyperService, RequestContext};
+use super::util::{
+    build_request, build_uri, handle_json_response, json_as_array, json_as_object,
+    with_extra_params,
+};
 use crate::core::data::{request, response};
 use crate::core::json;
-use crate::core::TransportService;
 use async_trait::async_trait;
-use http::{Method, Request};
-use hyper::{Body, Response};
+use hyper::{Body, Method, Response};
 use pubnub_core::data::{channel, history};
 use pubnub_util::uritemplate::UriTemplate;
 use std::collections::HashMap;
 
 async fn handle_history_response(
     response: Response<Body>,
+    limit: usize,
 ) -> Result<json::JsonValue, error::Error> {
-    let history_data = handle_json_response(response).await?;
+    let history_data = handle_json_response(response, limit).await?;
 
     if history_data["error"] == true {
         let error_message = history_data["message"].to_string();
@@ -26,7 +29,7 @@ async fn handle_history_response(
 }
 
 #[async_trait]
-impl TransportService<request::GetHistory> for Hyper {
+impl HyperService<request::GetHistory> for RequestContext<'_> {
     type Response = response::GetHistory;
     type Error = error::Error;
 
@@ -52,11 +55,13 @@ impl TransportService<request::GetHistory> for Hyper {
         .set_optional_scalar("end", end)
         .set_optional_scalar("include_meta", include_metadata)
         .build();
+        let path_and_query = with_extra_params(self, path_and_query);
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_history_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_history_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let channels = parse_get_history(&data_json)
@@ -66,7 +71,7 @@ impl TransportService<request::GetHistory> for Hyper {
 }
 
 #[async_trait]
-impl TransportService<request::DeleteHistory> for Hyper {
+impl HyperService<request::DeleteHistory> for RequestContext<'_> {
     type Response = response::DeleteHistory;
     type Error = error::Error;
 
@@ -88,21 +93,18 @@ impl TransportService<request::DeleteHistory> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Prepare the request.
-        let req = Request::builder()
-            .method(Method::DELETE)
-            .uri(url)
-            .body(Body::empty())?;
+        let req = build_request(&self, Method::DELETE, url).body(Body::empty())?;
 
         // Send network request.
-        let response = self.http_client.request(req).await?;
-        let _data_json = handle_history_response(response).await?;
+        let response = self.send(req).await?;
+        let _data_json = handle_history_response(response, self.max_response_bytes).await?;
 
         Ok(())
     }
 }
 
 #[async_trait]
-impl TransportService<request::MessageCountsWithTimetoken> for Hyper {
+impl HyperService<request::MessageCountsWithTimetoken> for RequestContext<'_> {
     type Response = response::MessageCountsWithTimetoken;
     type Error = error::Error;
 
@@ -125,8 +127,9 @@ impl TransportService<request::MessageCountsWithTimetoken> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_history_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_history_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let channels = parse_message_counts(&data_json)
@@ -136,7 +139,7 @@ impl TransportService<request::MessageCountsWithTimetoken> for Hyper {
 }
 
 #[async_trait]
-impl TransportService<request::MessageCountsWithChannelTimetokens> for Hyper {
+impl HyperService<request::MessageCountsWithChannelTimetokens> for RequestContext<'_> {
     type Response = response::MessageCountsWithChannelTimetokens;
     type Error = error::Error;
 
@@ -160,8 +163,9 @@ impl TransportService<request::MessageCountsWithChannelTimetokens> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_history_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_history_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let channels = parse_message_counts(&data_json)
@@ -0,0 +1,45 @@
+//! Request and response middleware of the [`Hyper`](super::Hyper)
+//! transport.
+
+use hyper::{Body, Request, Response};
+
+/// Processes the HTTP requests of the [`Hyper`](super::Hyper) transport
+/// before they're sent, and inspects the responses before they're parsed.
+///
+/// Added with [`HyperBuilder::with_middleware`](super::HyperBuilder::with_middleware),
+/// and run for the requests of every endpoint alike, including the retries
+/// and the redirects, i.e. to add the tracing headers, to log or count the
+/// calls, or to fake the responses in tests.
+///
+/// The closures taking and returning the request are middleware too.
+pub trait Middleware: Send + Sync {
+    /// Process the `request` before it's sent.
+    ///
+    /// Returns the request to send, or the response to reply with right
+    /// away, without sending the request. The short-circuited responses are
+    /// inspected and parsed like the real ones.
+    fn on_request(&self, request: Request<Body>) -> Outcome {
+        Outcome::Send(request)
+    }
+
+    /// Inspect the `response` before it's parsed.
+    fn on_response(&self, _response: &Response<Body>) {}
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(Request<Body>) -> Request<Body> + Send + Sync,
+{
+    fn on_request(&self, request: Request<Body>) -> Outcome {
+        Outcome::Send(self(request))
+    }
+}
+
+/// What to do with a request, as decided by [`Middleware::on_request`].
+#[derive(Debug)]
+pub enum Outcome {
+    /// Send the request, through the rest of the middleware.
+    Send(Request<Body>),
+    /// Reply with the response, without sending the request.
+    Respond(Response<Body>),
+}
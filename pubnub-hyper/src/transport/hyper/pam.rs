@@ -1,57 +1,98 @@
 //! PAMv3.
 
-use super::util::{build_uri, handle_json_response, json_as_object};
-use super::{error, Hyper};
+use super::error;
+use super::service::{HyperService, RequestContext};
+use super::util::{build_request, build_uri, encoded_auth, handle_json_response, json_as_object};
 use crate::core::data::{pam, request, response};
 use crate::core::json;
-use crate::core::TransportService;
 use async_trait::async_trait;
-use hyper::{Body, Method, Request, Response, StatusCode};
+use hyper::{Body, Method, Response, StatusCode};
 use pubnub_util::pam_signature;
 use pubnub_util::uritemplate::UriTemplate;
 use std::collections::HashMap;
 
 #[async_trait]
-impl TransportService<request::Grant> for Hyper {
+impl HyperService<request::Grant> for RequestContext<'_> {
     type Response = response::Grant;
     type Error = error::Error;
 
     async fn call(&self, request: request::Grant) -> Result<Self::Response, Self::Error> {
+        let path = UriTemplate::new("/v3/pam/{sub_key}/grant")
+            .set_scalar("sub_key", self.subscribe_key.clone())
+            .build();
+        let body = prepare_grant_body(request);
+        let data_json = self.signed_call(Method::POST, &path, body).await?;
+
+        let err_fn = || error::Error::UnexpectedResponseSchema(data_json.clone());
+        let data = json_as_object(&data_json["data"]).ok_or_else(err_fn)?;
+        let token = data["token"].as_str().ok_or_else(err_fn)?;
+        Ok(token.to_owned())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::RevokeToken> for RequestContext<'_> {
+    type Response = response::RevokeToken;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::RevokeToken) -> Result<Self::Response, Self::Error> {
+        let path = UriTemplate::new("/v3/pam/{sub_key}/grant/{token}")
+            .set_scalar("sub_key", self.subscribe_key.clone())
+            .set_scalar("token", request.token)
+            .build();
+        self.signed_call(Method::DELETE, &path, String::new())
+            .await?;
+        Ok(())
+    }
+}
+
+impl RequestContext<'_> {
+    /// Send a PAMv3 request to the `path`, signed with the secret key, and
+    /// return the JSON response.
+    async fn signed_call(
+        &self,
+        method: Method,
+        path: &str,
+        body: String,
+    ) -> Result<json::JsonValue, error::Error> {
         // Abort if we don't have a secret key.
         let secret_key = self
             .secret_key
             .as_ref()
-            .ok_or_else(|| error::Configuration::SecretKeyUnavailable)?;
+            .ok_or(error::Configuration::SecretKeyUnavailable)?;
 
-        // Prepare the request body and the signature.
-        let body = prepare_grant_body(request);
+        // Prepare the signature. The signed query has to match the one
+        // sent, including the parameters appended by `build_uri`.
         let timestamp = get_unix_time();
-        let signature = prepare_signature(
+        let mut query = format!("requestid={}&timestamp={}", self.request_id, timestamp);
+        if let Some(auth) = encoded_auth(self) {
+            query = format!("auth={}&{}", auth, query);
+        }
+        let signature = pam_signature::sign(
             secret_key,
-            &self.subscribe_key,
-            &self.publish_key,
-            timestamp,
-            body.as_str(),
+            pam_signature::Request {
+                publish_key: &self.publish_key,
+                method: method.as_str(),
+                path,
+                query: &query,
+                body: &body,
+            },
         );
 
-        // Prepare the URL.
-        let path_and_query = UriTemplate::new("/v3/pam/{sub_key}/grant{?signature,timestamp}")
-            .set_scalar("sub_key", self.subscribe_key.clone())
-            .set_scalar("signature", signature)
-            .set_scalar("timestamp", timestamp.to_string())
-            .build();
-        let url = build_uri(&self, &path_and_query)?;
+        // Prepare the URL. The signature is URL-safe as-is.
+        let path_and_query = format!("{}?signature={}&timestamp={}", path, signature, timestamp);
+        let url = build_uri(self, &path_and_query)?;
 
         // Prepare the request.
-        let req = Request::builder()
-            .method(Method::POST)
-            .uri(url)
-            .header("content-type", "application/json")
-            .body(Body::from(body))?;
+        let mut req = build_request(self, method, url);
+        if !body.is_empty() {
+            req = req.header("content-type", "application/json");
+        }
+        let req = req.body(Body::from(body))?;
 
         // Send network request.
-        let response = self.http_client.request(req).await?;
-        handle_grant_response(response).await
+        let response = self.send(req).await?;
+        handle_pam_response(response, self.max_response_bytes).await
     }
 }
 
@@ -68,6 +109,7 @@ fn prepare_grant_body(input: pam::GrantBody) -> String {
         let mut data = json::JsonValue::new_object();
         data["channels"] = map(&input.channels);
         data["groups"] = map(&input.groups);
+        data["uuids"] = map(&input.uuids);
         data["users"] = map(&input.users);
         data["spaces"] = map(&input.spaces);
         data
@@ -77,6 +119,7 @@ fn prepare_grant_body(input: pam::GrantBody) -> String {
         let mut data = json::JsonValue::new_object();
         data["channels"] = map(&input.channels);
         data["groups"] = map(&input.groups);
+        data["uuids"] = map(&input.uuids);
         data["users"] = map(&input.users);
         data["spaces"] = map(&input.spaces);
         data
@@ -106,40 +149,14 @@ fn get_unix_time() -> u64 {
     since_the_epoch.as_secs()
 }
 
-/// Prepare the signature.
-fn prepare_signature(
-    secret_key: &str,
-    subscribe_key: &str,
-    publish_key: &str,
-    timestamp: u64,
-    body: &str,
-) -> String {
-    pam_signature::sign(
-        secret_key,
-        pam_signature::Request {
-            publish_key,
-            method: "POST",
-            path: &format!("/v3/pam/{}/grant", subscribe_key),
-            query: &format!("timestamp={}", timestamp),
-            body,
-        },
-    )
-}
-
-async fn handle_grant_response(response: Response<Body>) -> Result<response::Grant, error::Error> {
+async fn handle_pam_response(
+    response: Response<Body>,
+    limit: usize,
+) -> Result<json::JsonValue, error::Error> {
     match response.status() {
-        StatusCode::OK => {
-            let data_json = handle_json_response(response).await?;
-            let err_fn = || error::Error::UnexpectedResponseSchema(data_json.clone());
-            let token = {
-                let data = json_as_object(&data_json["data"]).ok_or_else(err_fn)?;
-                let token = data["token"].as_str().ok_or_else(err_fn)?;
-                token.to_owned()
-            };
-            Ok(token)
-        }
+        StatusCode::OK => handle_json_response(response, limit).await,
         StatusCode::BAD_REQUEST | StatusCode::FORBIDDEN => {
-            let data = handle_json_response(response).await?;
+            let data = handle_json_response(response, limit).await?;
             let error_message: String = format!("{}", data["error"]["message"]);
             Err(error::Error::Server(error_message))
         }
@@ -177,6 +194,12 @@ mod tests {
                         map.insert("groups_b".into(), pam::BitMask::empty());
                         map
                     },
+                    uuids: {
+                        let mut map = HashMap::new();
+                        map.insert("uuids_a".into(), pam::BitMask::GET | pam::BitMask::UPDATE);
+                        map.insert("uuids_b".into(), pam::BitMask::empty());
+                        map
+                    },
                     users: {
                         let mut map = HashMap::new();
                         map.insert("users_a".into(), pam::BitMask::MANAGE);
@@ -203,6 +226,12 @@ mod tests {
                         map.insert("groups_d".into(), pam::BitMask::empty());
                         map
                     },
+                    uuids: {
+                        let mut map = HashMap::new();
+                        map.insert("uuids_c".into(), pam::BitMask::GET | pam::BitMask::UPDATE);
+                        map.insert("uuids_d".into(), pam::BitMask::empty());
+                        map
+                    },
                     users: {
                         let mut map = HashMap::new();
                         map.insert("users_c".into(), pam::BitMask::MANAGE);
@@ -238,6 +267,10 @@ mod tests {
                     "groups_a": 4,
                     "groups_b": 0
                   },
+                  "uuids": {
+                    "uuids_a": 96,
+                    "uuids_b": 0
+                  },
                   "users": {
                     "users_a": 4,
                     "users_b": 0
@@ -256,6 +289,10 @@ mod tests {
                     "groups_c": 4,
                     "groups_d": 0
                   },
+                  "uuids": {
+                    "uuids_c": 96,
+                    "uuids_d": 0
+                  },
                   "users": {
                     "users_c": 4,
                     "users_d": 0
@@ -0,0 +1,129 @@
+//! Connecting to the pinned addresses of the hosts, bypassing the DNS.
+
+use futures_util::future::{poll_fn, BoxFuture};
+use hyper::service::Service;
+use hyper::Uri;
+use log::debug;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// A connector connecting to the addresses pinned with
+/// [`HyperBuilder::pin_addr`](super::HyperBuilder::pin_addr), and to the
+/// other hosts as usual.
+///
+/// Only the TCP connection is redirected: the TLS connector wrapping it
+/// still gets the original URI, so the hostname is used for the SNI and
+/// for validating the certificate.
+#[derive(Debug, Clone)]
+pub(super) struct PinnedConnector<C> {
+    inner: C,
+    pins: Arc<Vec<(String, SocketAddr)>>,
+}
+
+impl<C> PinnedConnector<C> {
+    pub fn new(inner: C, pins: Vec<(String, SocketAddr)>) -> Self {
+        Self {
+            inner,
+            pins: Arc::new(pins),
+        }
+    }
+
+    /// The addresses pinned for the host of the `dst`, in order.
+    fn pinned(&self, dst: &Uri) -> Vec<SocketAddr> {
+        let host = match dst.host() {
+            Some(host) => host,
+            None => return Vec::new(),
+        };
+        self.pins
+            .iter()
+            .filter(|(pinned, _)| pinned.eq_ignore_ascii_case(host))
+            .map(|(_, addr)| *addr)
+            .collect()
+    }
+}
+
+impl<C> Service<Uri> for PinnedConnector<C>
+where
+    C: Service<Uri> + Clone + Send + 'static,
+    C::Response: Send + 'static,
+    C::Error: Send + 'static,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = BoxFuture<'static, Result<C::Response, C::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let addrs = self.pinned(&dst);
+        if addrs.is_empty() {
+            return Box::pin(self.inner.call(dst));
+        }
+
+        let mut inner = self.inner.clone();
+        Box::pin(async move {
+            let mut last_error = None;
+            for addr in addrs {
+                poll_fn(|cx| inner.poll_ready(cx)).await?;
+                match inner.call(pinned_uri(&dst, addr)).await {
+                    Ok(connection) => return Ok(connection),
+                    Err(error) => {
+                        debug!("Unable to connect to the pinned address {}", addr);
+                        last_error = Some(error);
+                    }
+                }
+            }
+            Err(last_error.expect("no addresses are pinned"))
+        })
+    }
+}
+
+/// The `dst` with the authority replaced by the `addr`.
+fn pinned_uri(dst: &Uri, addr: SocketAddr) -> Uri {
+    // The scope of the IPv6 addresses can't be expressed in a URI.
+    let addr = SocketAddr::new(addr.ip(), addr.port());
+    let scheme = dst.scheme_str().unwrap_or("https");
+    format!("{}://{}/", scheme, addr)
+        .parse()
+        .expect("socket address is a valid authority")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pinned_uri, PinnedConnector};
+    use std::net::SocketAddr;
+
+    #[test]
+    fn test_pinned_uri() {
+        let dst = "https://ps.pndsn.com/time/0".parse().unwrap();
+
+        let addr: SocketAddr = "203.0.113.7:8443".parse().unwrap();
+        assert_eq!(pinned_uri(&dst, addr), "https://203.0.113.7:8443/");
+
+        let addr: SocketAddr = "[2001:db8::7%3]:443".parse().unwrap();
+        assert_eq!(pinned_uri(&dst, addr), "https://[2001:db8::7]:443/");
+    }
+
+    #[test]
+    fn test_pinned() {
+        let v4: SocketAddr = "203.0.113.7:443".parse().unwrap();
+        let v6: SocketAddr = "[2001:db8::7]:443".parse().unwrap();
+        let connector = PinnedConnector::new(
+            (),
+            vec![
+                ("ps.pndsn.com".to_owned(), v6),
+                ("other.test".to_owned(), v4),
+                ("PS.pndsn.com".to_owned(), v4),
+            ],
+        );
+
+        let dst = "https://ps.pndsn.com/time/0".parse().unwrap();
+        assert_eq!(connector.pinned(&dst), vec![v6, v4]);
+        let dst = "https://pubsub.pubnub.com/time/0".parse().unwrap();
+        assert!(connector.pinned(&dst).is_empty());
+    }
+}
@@ -1,20 +1,21 @@
 //! Presence.
 
-use super::pubsub::inject_subscribe_to;
-use super::util::{build_uri, handle_json_response, json_as_array, json_as_object};
-use super::{error, Hyper};
+use super::endpoint::inject_subscribe_to;
+use super::error;
+use super::service::{HyperService, RequestContext};
+use super::util::{build_request, build_uri, handle_json_response, json_as_array, json_as_object};
 use crate::core::data::{presence, request, response};
 use crate::core::json;
-use crate::core::TransportService;
 use async_trait::async_trait;
-use hyper::{Body, Response};
+use hyper::{Body, Method, Response};
 use pubnub_util::uritemplate::{IfEmpty, UriTemplate};
 use std::collections::HashMap;
 
 async fn handle_presence_response(
     response: Response<Body>,
+    limit: usize,
 ) -> Result<json::JsonValue, error::Error> {
-    let presence_data = handle_json_response(response).await?;
+    let presence_data = handle_json_response(response, limit).await?;
 
     if presence_data["error"] == true {
         let error_message = presence_data["message"].to_string();
@@ -35,15 +36,19 @@ trait HereNowParse<T: presence::respond_with::RespondWith> {
         let total_channels = payload["total_channels"].as_u64()?;
         let total_occupancy = payload["total_occupancy"].as_u64()?;
 
-        let channels = {
-            let channels = json_as_object(&payload["channels"])?;
-            let mut values = HashMap::new();
-            for (k, v) in channels.iter() {
-                let channel_info = self.parse(v)?;
-                values.insert(k.parse().ok()?, channel_info);
+        // Keyed by the channel name, unlike the per-channel here now. With no
+        // channels occupied, it may come as an empty array or not at all.
+        let mut channels = HashMap::new();
+        match &payload["channels"] {
+            json::JsonValue::Null => {}
+            json::JsonValue::Array(values) if values.is_empty() => {}
+            values => {
+                for (k, v) in json_as_object(values)?.iter() {
+                    let channel_info = self.parse(v)?;
+                    channels.insert(k.parse().ok()?, channel_info);
+                }
             }
-            values
-        };
+        }
 
         Some(presence::GlobalInfo {
             total_channels,
@@ -122,7 +127,7 @@ impl HereNowParse<presence::respond_with::Full> for () {
 }
 
 #[async_trait]
-impl TransportService<request::SetState> for Hyper {
+impl HyperService<request::SetState> for RequestContext<'_> {
     type Response = response::SetState;
     type Error = error::Error;
 
@@ -146,15 +151,16 @@ impl TransportService<request::SetState> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let _ = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let _ = handle_presence_response(response, self.max_response_bytes).await?;
 
         Ok(())
     }
 }
 
 #[async_trait]
-impl TransportService<request::GetState> for Hyper {
+impl HyperService<request::GetState> for RequestContext<'_> {
     type Response = response::GetState;
     type Error = error::Error;
 
@@ -177,8 +183,9 @@ impl TransportService<request::GetState> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let mut data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let mut data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         Ok(data_json.remove("payload"))
@@ -186,7 +193,7 @@ impl TransportService<request::GetState> for Hyper {
 }
 
 #[async_trait]
-impl TransportService<request::HereNow<presence::respond_with::OccupancyOnly>> for Hyper {
+impl HyperService<request::HereNow<presence::respond_with::OccupancyOnly>> for RequestContext<'_> {
     type Response = response::HereNow<presence::respond_with::OccupancyOnly>;
     type Error = error::Error;
 
@@ -211,8 +218,9 @@ impl TransportService<request::HereNow<presence::respond_with::OccupancyOnly>> f
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let value = HereNowParse::<presence::respond_with::OccupancyOnly>::parse(&(), &data_json)
@@ -222,7 +230,9 @@ impl TransportService<request::HereNow<presence::respond_with::OccupancyOnly>> f
 }
 
 #[async_trait]
-impl TransportService<request::HereNow<presence::respond_with::OccupancyAndUUIDs>> for Hyper {
+impl HyperService<request::HereNow<presence::respond_with::OccupancyAndUUIDs>>
+    for RequestContext<'_>
+{
     type Response = response::HereNow<presence::respond_with::OccupancyAndUUIDs>;
     type Error = error::Error;
 
@@ -247,8 +257,9 @@ impl TransportService<request::HereNow<presence::respond_with::OccupancyAndUUIDs
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let value =
@@ -259,7 +270,7 @@ impl TransportService<request::HereNow<presence::respond_with::OccupancyAndUUIDs
 }
 
 #[async_trait]
-impl TransportService<request::HereNow<presence::respond_with::Full>> for Hyper {
+impl HyperService<request::HereNow<presence::respond_with::Full>> for RequestContext<'_> {
     type Response = response::HereNow<presence::respond_with::Full>;
     type Error = error::Error;
 
@@ -284,8 +295,9 @@ impl TransportService<request::HereNow<presence::respond_with::Full>> for Hyper
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let value = HereNowParse::<presence::respond_with::Full>::parse(&(), &data_json)
@@ -295,7 +307,9 @@ impl TransportService<request::HereNow<presence::respond_with::Full>> for Hyper
 }
 
 #[async_trait]
-impl TransportService<request::GlobalHereNow<presence::respond_with::OccupancyOnly>> for Hyper {
+impl HyperService<request::GlobalHereNow<presence::respond_with::OccupancyOnly>>
+    for RequestContext<'_>
+{
     type Response = response::GlobalHereNow<presence::respond_with::OccupancyOnly>;
     type Error = error::Error;
 
@@ -313,8 +327,9 @@ impl TransportService<request::GlobalHereNow<presence::respond_with::OccupancyOn
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let value =
@@ -325,7 +340,9 @@ impl TransportService<request::GlobalHereNow<presence::respond_with::OccupancyOn
 }
 
 #[async_trait]
-impl TransportService<request::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>> for Hyper {
+impl HyperService<request::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>>
+    for RequestContext<'_>
+{
     type Response = response::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>;
     type Error = error::Error;
 
@@ -343,8 +360,9 @@ impl TransportService<request::GlobalHereNow<presence::respond_with::OccupancyAn
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let value = HereNowParse::<presence::respond_with::OccupancyAndUUIDs>::parse_global(
@@ -357,7 +375,7 @@ impl TransportService<request::GlobalHereNow<presence::respond_with::OccupancyAn
 }
 
 #[async_trait]
-impl TransportService<request::GlobalHereNow<presence::respond_with::Full>> for Hyper {
+impl HyperService<request::GlobalHereNow<presence::respond_with::Full>> for RequestContext<'_> {
     type Response = response::GlobalHereNow<presence::respond_with::Full>;
     type Error = error::Error;
 
@@ -375,8 +393,9 @@ impl TransportService<request::GlobalHereNow<presence::respond_with::Full>> for
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
 
         // Parse response.
         let value = HereNowParse::<presence::respond_with::Full>::parse_global(&(), &data_json)
@@ -386,7 +405,7 @@ impl TransportService<request::GlobalHereNow<presence::respond_with::Full>> for
 }
 
 #[async_trait]
-impl TransportService<request::WhereNow> for Hyper {
+impl HyperService<request::WhereNow> for RequestContext<'_> {
     type Response = response::WhereNow;
     type Error = error::Error;
 
@@ -401,8 +420,9 @@ impl TransportService<request::WhereNow> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let data_json = handle_presence_response(response, self.max_response_bytes).await?;
         let err_fn = || error::Error::UnexpectedResponseSchema(data_json.clone());
 
         // Parse response.
@@ -420,7 +440,7 @@ impl TransportService<request::WhereNow> for Hyper {
 }
 
 #[async_trait]
-impl TransportService<request::Heartbeat> for Hyper {
+impl HyperService<request::Heartbeat> for RequestContext<'_> {
     type Response = response::Heartbeat;
     type Error = error::Error;
 
@@ -444,9 +464,173 @@ impl TransportService<request::Heartbeat> for Hyper {
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let _ = handle_presence_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let _ = handle_presence_response(response, self.max_response_bytes).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::Leave> for RequestContext<'_> {
+    type Response = response::Leave;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::Leave) -> Result<Self::Response, Self::Error> {
+        let request::Leave { to, uuid } = request;
+
+        // Prepare the URL.
+        let path_and_query = UriTemplate::new(
+            "/v2/presence/sub-key/{sub_key}/channel/{channel}/leave{?channel-group,uuid}",
+        )
+        .set_scalar("sub_key", self.subscribe_key.clone())
+        .tap(|val| inject_subscribe_to(val, &to))
+        .set_scalar("uuid", uuid.unwrap_or_else(|| self.uuid.clone()))
+        .build();
+        let url = build_uri(&self, &path_and_query)?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let _ = handle_presence_response(response, self.max_response_bytes).await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{json, presence, HereNowParse};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_global() {
+        let sample = json::parse(
+            r#"{
+                "status": 200,
+                "message": "OK",
+                "payload": {
+                    "channels": {
+                        "lobby": {
+                            "occupancy": 2,
+                            "uuids": [
+                                { "uuid": "alice", "state": { "mood": "happy" } },
+                                { "uuid": "bob" }
+                            ]
+                        },
+                        "room-1": {
+                            "occupancy": 1,
+                            "uuids": [{ "uuid": "alice" }]
+                        }
+                    },
+                    "total_channels": 2,
+                    "total_occupancy": 3
+                },
+                "service": "Presence"
+            }"#,
+        )
+        .unwrap();
+
+        let info =
+            HereNowParse::<presence::respond_with::Full>::parse_global(&(), &sample).unwrap();
+        assert_eq!(info.total_channels, 2);
+        assert_eq!(info.total_occupancy, 3);
+        assert_eq!(info.channels.len(), 2);
+
+        let lobby = &info.channels[&"lobby".parse().unwrap()];
+        assert_eq!(lobby.occupancy, 2);
+        assert_eq!(
+            lobby.occupants,
+            vec![
+                presence::ChannelOccupantFullDetails {
+                    uuid: "alice".into(),
+                    state: json::object! { "mood": "happy" },
+                },
+                presence::ChannelOccupantFullDetails {
+                    uuid: "bob".into(),
+                    state: json::JsonValue::Null,
+                },
+            ]
+        );
+
+        let room = &info.channels[&"room-1".parse().unwrap()];
+        assert_eq!(room.occupancy, 1);
+        assert_eq!(room.occupants.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_global_occupancy_only() {
+        let sample = json::object! {
+            "status": 200,
+            "payload": {
+                "channels": {
+                    "lobby": { "occupancy": 2 },
+                    "room-1": { "occupancy": 1 }
+                },
+                "total_channels": 2,
+                "total_occupancy": 3
+            }
+        };
+
+        let info =
+            HereNowParse::<presence::respond_with::OccupancyOnly>::parse_global(&(), &sample)
+                .unwrap();
+        let mut expected = HashMap::new();
+        expected.insert(
+            "lobby".parse().unwrap(),
+            presence::ChannelInfo { occupancy: 2 },
+        );
+        expected.insert(
+            "room-1".parse().unwrap(),
+            presence::ChannelInfo { occupancy: 1 },
+        );
+        assert_eq!(
+            info,
+            presence::GlobalInfo {
+                total_channels: 2,
+                total_occupancy: 3,
+                channels: expected,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_global_empty() {
+        let empty = presence::GlobalInfo::<presence::respond_with::OccupancyAndUUIDs> {
+            total_channels: 0,
+            total_occupancy: 0,
+            channels: HashMap::new(),
+        };
+
+        for channels in &[Some(json::object! {}), Some(json::array![]), None] {
+            let mut payload = json::object! {
+                "total_channels": 0,
+                "total_occupancy": 0
+            };
+            if let Some(channels) = channels {
+                payload["channels"] = channels.clone();
+            }
+            let sample = json::object! { "status": 200, "payload": payload };
+
+            let info = HereNowParse::<presence::respond_with::OccupancyAndUUIDs>::parse_global(
+                &(),
+                &sample,
+            );
+            assert_eq!(info, Some(empty.clone()));
+        }
+
+        // Anything else is still rejected.
+        let sample = json::object! {
+            "status": 200,
+            "payload": {
+                "channels": ["lobby"],
+                "total_channels": 1,
+                "total_occupancy": 1
+            }
+        };
+        let info =
+            HereNowParse::<presence::respond_with::OccupancyOnly>::parse_global(&(), &sample);
+        assert!(info.is_none());
+    }
+}
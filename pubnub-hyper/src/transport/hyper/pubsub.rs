@@ -1,164 +1,582 @@
 //! Publish / subscribe.
 
-use super::util::json_as_object;
-use super::util::{build_uri, handle_json_response};
-use super::{error, shared_parsers::parse_message, Hyper};
-use crate::core::data::{message::Message, pubsub, request, response, timetoken::Timetoken};
+use super::service::{HyperService, RequestContext};
+use super::shared_parsers::{parse_message, undecodable_message};
+use super::util::{access_denied_error, build_request, build_uri, parse_json_body, receive_body};
+use super::util::{json_as_object, rate_limited_error, with_extra_params};
+use super::{endpoint, error};
+use crate::core::data::{
+    channel,
+    cursor::Cursor,
+    message::RawPayload,
+    pubsub::{PublishMethod, PublishResponse, SubscribeTo},
+    request, response,
+    timetoken::Timetoken,
+};
 use crate::core::json;
-use crate::core::TransportService;
 use async_trait::async_trait;
-use pubnub_util::uritemplate::{IfEmpty, UriTemplate};
+use hyper::{Body, Method, StatusCode};
+use log::error;
+use std::ops::Range;
+use std::sync::Arc;
 
 #[async_trait]
-impl TransportService<request::Publish> for Hyper {
+impl HyperService<request::Publish> for RequestContext<'_> {
     type Response = response::Publish;
     type Error = error::Error;
 
     async fn call(&self, request: request::Publish) -> Result<Self::Response, Self::Error> {
-        let request::Publish {
-            channel,
-            payload,
-            meta,
-        } = request;
+        check_name(&request.channel, false)?;
+        publish_request(self, &request).await
+    }
+}
 
-        // Prepare the URL.
-        let path_and_query =
-            UriTemplate::new("/publish/{pub_key}/{sub_key}/0/{channel}/0/{message}{?uuid,meta}")
-                .set_scalar("pub_key", self.publish_key.clone())
-                .set_scalar("sub_key", self.subscribe_key.clone())
-                .set_scalar("channel", channel)
-                .set_scalar("message", json::stringify(payload))
-                .set_scalar("uuid", self.uuid.clone())
-                .set_optional_scalar("meta", meta.map(json::stringify))
-                .build();
-        let url = build_uri(&self, &path_and_query)?;
+/// Check the channel or the channel group `name` before sending the request,
+/// allowing the trailing `.*` with `wildcard`.
+pub(super) fn check_name(name: &channel::Name, wildcard: bool) -> Result<(), error::Error> {
+    name.check(wildcard)
+        .map_err(|reason| error::Error::InvalidChannelName {
+            name: name.to_string(),
+            reason,
+        })
+}
 
-        // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_json_response(response).await?;
+async fn publish_request(
+    hyper: &RequestContext<'_>,
+    request: &request::Publish,
+) -> Result<response::Publish, error::Error> {
+    // Prepare the URL.
+    let path_and_query = with_extra_params(hyper, endpoint::publish(hyper, request));
+    let url = build_uri(hyper, &path_and_query)?;
 
-        // Parse timetoken.
-        let timetoken = Timetoken {
-            t: data_json[2].as_str().unwrap().parse().unwrap(),
-            r: 0, // TODO
-        };
+    // Prepare the request. With `POST` the message goes in the body.
+    let body = match request.method {
+        PublishMethod::Get => String::new(),
+        PublishMethod::Post => request.payload.dump(),
+    };
+    #[cfg(feature = "metrics")]
+    let sent = path_and_query.len() + body.len();
+    let req = match request.method {
+        PublishMethod::Get => build_request(hyper, Method::GET, url).body(Body::empty())?,
+        PublishMethod::Post => build_request(hyper, Method::POST, url)
+            .header("content-type", "application/json")
+            .body(Body::from(body))?,
+    };
 
-        Ok(timetoken)
+    // Send network request.
+    let response = hyper.send(req).await?;
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(error::Error::HttpStatus(status));
+    }
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(rate_limited_error(&response));
+    }
+    if status == StatusCode::URI_TOO_LONG {
+        return Err(error::Error::UriTooLong);
     }
+    let body = receive_body(response, hyper.max_response_bytes).await?;
+    #[cfg(feature = "metrics")]
+    hyper.traffic.record(sent, body.len());
+    publish_response(status, &body)
+}
+
+/// Parse the received `body` of a publish response, or the error it
+/// reports.
+pub(super) fn publish_response(
+    status: StatusCode,
+    body: &[u8],
+) -> Result<PublishResponse, error::Error> {
+    let data_json = parse_json_body(body)?;
+
+    if status == StatusCode::FORBIDDEN {
+        return Err(access_denied_error(&data_json));
+    }
+
+    // Anything but `[1, "Sent", ...]` is a failure, and the server explains
+    // it in the status message.
+    if !status.is_success() || data_json[0].as_u8() != Some(1) {
+        return Err(data_json[1]
+            .as_str()
+            .map_or(error::Error::HttpStatus(status), |message| {
+                error::Error::Publish {
+                    status,
+                    message: message.to_owned(),
+                }
+            }));
+    }
+
+    // Parse timetoken.
+    parse_publish(status, &data_json)
+        .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
 }
 
 #[async_trait]
-impl TransportService<request::Subscribe> for Hyper {
+impl HyperService<request::Subscribe> for RequestContext<'_> {
     type Response = response::Subscribe;
     type Error = error::Error;
 
     async fn call(&self, request: request::Subscribe) -> Result<Self::Response, Self::Error> {
-        let request::Subscribe {
-            to,
-            timetoken,
-            heartbeat,
-        } = request;
-
-        // TODO: add caching of repeating params to avoid reencoding.
+        for to in &request.to {
+            match to {
+                SubscribeTo::Channel(name) => check_name(name, true)?,
+                SubscribeTo::ChannelGroup(name) => check_name(name, false)?,
+                // Checked by the type already.
+                SubscribeTo::ChannelWildcard(_) => {}
+            }
+        }
 
         // Prepare the URL.
-        let path_and_query = UriTemplate::new(
-            "/v2/subscribe/{sub_key}/{channel}/0{?channel-group,tt,tr,uuid,heartbeat}",
-        )
-        .set_scalar("sub_key", self.subscribe_key.clone())
-        .tap(|val| inject_subscribe_to(val, &to))
-        .set_scalar("tt", timetoken.t.to_string())
-        .set_scalar("tr", timetoken.r.to_string())
-        .set_scalar("uuid", self.uuid.clone())
-        .set_optional_scalar("heartbeat", heartbeat.map(|e| e.to_string()))
-        .build();
+        let path_and_query = with_extra_params(self, endpoint::subscribe(self, &request));
         let url = build_uri(&self, &path_and_query)?;
 
         // Send network request.
-        let response = self.http_client.get(url).await?;
-        let data_json = handle_json_response(response).await?;
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let status = response.status();
+        let body = receive_body(response, self.max_subscribe_response_bytes).await?;
+        #[cfg(feature = "metrics")]
+        self.traffic.record(path_and_query.len(), body.len());
+        if !status.is_success() {
+            let data_json = parse_json_body(&body)?;
+            if status == StatusCode::FORBIDDEN {
+                return Err(access_denied_error(&data_json));
+            }
+            if status.is_client_error()
+                && status != StatusCode::REQUEST_TIMEOUT
+                && status != StatusCode::TOO_MANY_REQUESTS
+            {
+                let message = data_json["message"].as_str().unwrap_or("").to_owned();
+                return Err(error::Error::SubscribeRejected { status, message });
+            }
+            return Err(error::Error::UnexpectedResponseSchema(data_json));
+        }
 
         // Parse response.
-        let (messages, timetoken) = parse_subscribe(&data_json)
-            .ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))?;
-        Ok((messages, timetoken))
+        parse_subscribe(body)
     }
 }
 
-pub(super) fn inject_subscribe_to(template: &mut UriTemplate, to: &[pubsub::SubscribeTo]) {
-    let channels = to.iter().filter_map(|to| {
-        to.as_channel()
-            .map(AsRef::<str>::as_ref)
-            .or_else(|| to.as_channel_wildcard().map(AsRef::<str>::as_ref))
-    });
-    template.set_list_with_if_empty("channel", channels, IfEmpty::Comma);
+fn parse_publish(status: StatusCode, data_json: &json::JsonValue) -> Option<PublishResponse> {
+    Some(PublishResponse::new(
+        status.as_u16(),
+        data_json[1].as_str()?.to_owned(),
+        // Publish responses carry no region.
+        Timetoken::from_parts(data_json[2].as_str()?.parse().ok()?, 0),
+    ))
+}
 
-    let channel_groups = to
-        .iter()
-        .filter_map(|to| to.as_channel_group().map(AsRef::<str>::as_ref));
-    template.set_list_with_if_empty("channel-group", channel_groups, IfEmpty::Skip);
+/// Parse the subscribe cursor, i.e. the `t` object of the response.
+fn parse_cursor(data_json: &json::JsonValue) -> Option<Cursor> {
+    Some(Cursor::new(
+        data_json["t"].as_str()?.parse().ok()?,
+        data_json["r"].as_u32().unwrap_or(0),
+    ))
 }
 
-fn parse_subscribe(data_json: &json::JsonValue) -> Option<(Vec<Message>, Timetoken)> {
-    // Parse timetoken.
-    let timetoken = Timetoken {
-        t: data_json["t"]["t"].as_str().unwrap().parse().unwrap(),
-        r: data_json["t"]["r"].as_u32().unwrap_or(0),
+/// Parse the subscribe response `body`.
+///
+/// A message that fails to parse doesn't fail the rest of the body. It's
+/// delivered to the subscribers of its channel as undecodable, or dropped if
+/// its channel can't be told.
+fn parse_subscribe(body: Vec<u8>) -> Result<response::Subscribe, error::Error> {
+    let data_json = parse_json_body(&body)?;
+    let cursor = match parse_cursor(&data_json["t"]) {
+        Some(cursor) => cursor,
+        None => return Err(error::Error::UnexpectedResponseSchema(data_json)),
     };
 
-    // Parse messages.
-    let messages = {
-        let result: Option<Vec<_>> = data_json["m"]
-            .members()
-            .map(|message| match json_as_object(message) {
-                Some(message) => parse_message(message).ok(),
-                None => None,
-            })
-            .collect();
-        result?
-    };
+    // The body parsed fine, so the payloads are expected to be found.
+    let payloads = payload_ranges(&body).unwrap_or_default();
+    let buffer: Arc<[u8]> = body.into();
+    let messages = data_json["m"]
+        .members()
+        .enumerate()
+        .filter_map(|(index, data_json)| {
+            let parsed = match json_as_object(data_json) {
+                Some(object) => parse_message(object).map_err(|err| err.reason()),
+                None => Err("not an object"),
+            };
+            match parsed {
+                Ok(mut message) => {
+                    if let Some(range) = payloads.get(index).cloned().flatten() {
+                        message.raw_payload = Some(RawPayload::new(Arc::clone(&buffer), range));
+                    }
+                    Some(message)
+                }
+                Err(reason) => {
+                    let undecodable = undecodable_message(data_json, reason);
+                    if undecodable.is_none() {
+                        error!("Dropping a message of an unknown channel: {}", reason);
+                    }
+                    undecodable
+                }
+            }
+        })
+        .collect();
+    Ok((messages, cursor))
+}
+
+/// Find the positions of the message payloads (`m[].d`) in the subscribe
+/// response body, if any.
+///
+/// The `json` crate doesn't keep the positions of the values, so this scans
+/// the body once more, skipping everything but the payloads.
+fn payload_ranges(body: &[u8]) -> Option<Vec<Option<Range<usize>>>> {
+    let mut ranges = Vec::new();
+    let mut pos = 0;
+    scan_object(body, &mut pos, |body, pos, key| {
+        if key != b"m" {
+            return skip_value(body, pos);
+        }
+        scan_array(body, pos, |body, pos| {
+            let mut payload = None;
+            if peek(body, pos)? == b'{' {
+                scan_object(body, pos, |body, pos, key| {
+                    let start = *pos;
+                    skip_value(body, pos)?;
+                    if key == b"d" {
+                        payload = Some(start..*pos);
+                    }
+                    Some(())
+                })?;
+            } else {
+                skip_value(body, pos)?;
+            }
+            ranges.push(payload);
+            Some(())
+        })
+    })?;
+    Some(ranges)
+}
+
+/// Scan a JSON object at `pos`, calling `on_member` with `pos` at each
+/// member value, which it has to skip.
+fn scan_object(
+    body: &[u8],
+    pos: &mut usize,
+    mut on_member: impl FnMut(&[u8], &mut usize, &[u8]) -> Option<()>,
+) -> Option<()> {
+    expect(body, pos, b'{')?;
+    if peek(body, pos)? == b'}' {
+        *pos += 1;
+        return Some(());
+    }
+    loop {
+        skip_whitespace(body, pos);
+        let key_start = *pos + 1;
+        skip_string(body, pos)?;
+        // The keys of interest have no escapes.
+        let key = &body[key_start..*pos - 1];
+        expect(body, pos, b':')?;
+        skip_whitespace(body, pos);
+        on_member(body, pos, key)?;
+        if !scan_separator(body, pos, b'}')? {
+            return Some(());
+        }
+    }
+}
+
+/// Scan a JSON array at `pos`, calling `on_element` with `pos` at each
+/// element, which it has to skip.
+fn scan_array(
+    body: &[u8],
+    pos: &mut usize,
+    mut on_element: impl FnMut(&[u8], &mut usize) -> Option<()>,
+) -> Option<()> {
+    expect(body, pos, b'[')?;
+    if peek(body, pos)? == b']' {
+        *pos += 1;
+        return Some(());
+    }
+    loop {
+        skip_whitespace(body, pos);
+        on_element(body, pos)?;
+        if !scan_separator(body, pos, b']')? {
+            return Some(());
+        }
+    }
+}
+
+/// Consume a `,`, returning `true`, or the closing bracket, returning
+/// `false`.
+fn scan_separator(body: &[u8], pos: &mut usize, close: u8) -> Option<bool> {
+    match peek(body, pos)? {
+        b',' => {
+            *pos += 1;
+            Some(true)
+        }
+        c if c == close => {
+            *pos += 1;
+            Some(false)
+        }
+        _ => None,
+    }
+}
+
+/// Skip the JSON value at `pos`.
+///
+/// Only the bodies the `json` crate parsed are scanned, so the values are
+/// nested no deeper than it allows.
+fn skip_value(body: &[u8], pos: &mut usize) -> Option<()> {
+    match *body.get(*pos)? {
+        b'{' => scan_object(body, pos, |body, pos, _| skip_value(body, pos)),
+        b'[' => scan_array(body, pos, skip_value),
+        b'"' => skip_string(body, pos),
+        _ => {
+            // A number or a literal.
+            let len = body[*pos..]
+                .iter()
+                .position(|c| b",]} \t\r\n".contains(c))
+                .unwrap_or(body.len() - *pos);
+            *pos += len;
+            Some(())
+        }
+    }
+}
+
+/// Skip the JSON string at `pos`, including the quotes.
+fn skip_string(body: &[u8], pos: &mut usize) -> Option<()> {
+    if *body.get(*pos)? != b'"' {
+        return None;
+    }
+    let mut escaped = false;
+    for (offset, &c) in body[*pos + 1..].iter().enumerate() {
+        if !escaped && c == b'"' {
+            *pos += offset + 2;
+            return Some(());
+        }
+        escaped = !escaped && c == b'\\';
+    }
+    None
+}
+
+/// Skip the whitespace, and consume the expected character.
+fn expect(body: &[u8], pos: &mut usize, expected: u8) -> Option<()> {
+    if peek(body, pos)? == expected {
+        *pos += 1;
+        Some(())
+    } else {
+        None
+    }
+}
+
+/// Skip the whitespace, and return the next character.
+fn peek(body: &[u8], pos: &mut usize) -> Option<u8> {
+    skip_whitespace(body, pos);
+    body.get(*pos).copied()
+}
 
-    Some((messages, timetoken))
+fn skip_whitespace(body: &[u8], pos: &mut usize) {
+    while let Some(c) = body.get(*pos) {
+        if !c.is_ascii_whitespace() {
+            break;
+        }
+        *pos += 1;
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_subscribe;
+    use super::{parse_publish, parse_subscribe, payload_ranges};
     use crate::core::data::{
+        cursor::Cursor,
         message::{self, Message, Route},
+        pubsub::PublishResponse,
+        response,
         timetoken::Timetoken,
     };
+    use crate::transport::hyper::error::Error;
+    use hyper::StatusCode;
+
+    fn parse_subscribe_str(body: &str) -> Result<response::Subscribe, Error> {
+        parse_subscribe(body.as_bytes().to_vec())
+    }
 
     #[test]
     fn test_parse_subscribe() {
         let string_sample = r#"{"t":{"t":"15850559815683819","r":12},"m":[{"a":"3","f":514,"i":"31257c03-3722-4409-a0ea-e7b072540115","p":{"t":"15850559815660696","r":12},"k":"demo","c":"demo2","d":"Hello, world!","b":"demo2"}]}"#;
-        let json_sample = json::parse(string_sample).unwrap();
-
-        let actual_response = parse_subscribe(&json_sample).unwrap();
-
-        let expected_message = Message {
-            message_type: message::Type::Publish,
-            route: Some(Route::ChannelWildcard("demo2".parse().unwrap())),
-            channel: "demo2".parse().unwrap(),
-            json: json::from("Hello, world!"),
-            metadata: json::Null,
-            timetoken: Timetoken {
-                t: 15_850_559_815_660_696,
-                r: 12,
-            },
-            client: Some("31257c03-3722-4409-a0ea-e7b072540115".to_owned()),
-            subscribe_key: "demo".to_owned(),
-            flags: 514,
-        };
+
+        let mut expected_message = Message::new(
+            "demo2".parse().unwrap(),
+            json::from("Hello, world!"),
+            Timetoken::from_parts(15_850_559_815_660_696, 12),
+        );
+        expected_message.route = Some(Route::ChannelWildcard("demo2".parse().unwrap()));
+        expected_message.client = Some("31257c03-3722-4409-a0ea-e7b072540115".to_owned());
+        expected_message.subscribe_key = "demo".to_owned();
+        expected_message.flags = 514;
 
         let expected_response = (
             vec![expected_message],
-            Timetoken {
-                t: 15_850_559_815_683_819,
-                r: 12,
-            },
+            Cursor::new(15_850_559_815_683_819, 12),
         );
 
+        let actual_response = parse_subscribe_str(string_sample).unwrap();
+        assert_eq!(actual_response.0[0].raw(), Some(&br#""Hello, world!""#[..]));
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[test]
+    fn test_parse_subscribe_channel_names() {
+        // The server returns the channel names as-is, which are the exact
+        // names the client encoded in the request.
+        for channel in &["a/b", "чат", "chan nel", "a%2Cb"] {
+            let mut message =
+                json::parse(r#"{"f":0,"p":{"t":"15850559815660696","r":12},"k":"demo","d":null}"#)
+                    .unwrap();
+            message["c"] = (*channel).into();
+            let body = json::object! {
+                "t" => json::object! { "t" => "15850559815683819", "r" => 12 },
+                "m" => json::array![message],
+            };
+
+            let (messages, _) = parse_subscribe_str(&body.dump()).unwrap();
+            assert_eq!(messages[0].channel.as_ref() as &str, *channel);
+        }
+    }
+
+    #[test]
+    fn test_parse_subscribe_presence_events() {
+        let body = r#"{"t":{"t":"15850559815683819","r":12},"m":[
+            {"f":0,"p":{"t":"15850559815660696","r":12},"k":"demo","c":"demo-pnpres","d":{"action":"join"},"b":"demo.*"},
+            {"f":0,"e":1,"p":{"t":"15850559815660697","r":12},"k":"demo","c":"demo","d":null}
+        ]}"#;
+
+        let (messages, _) = parse_subscribe_str(body).unwrap();
+        assert_eq!(messages[0].message_type, message::Type::Presence);
+        assert_eq!(messages[0].raw_type_code(), 0);
+        assert_eq!(messages[1].message_type, message::Type::Signal);
+    }
+
+    #[test]
+    fn test_parse_subscribe_raw_payloads() {
+        let body = r#"{ "t": {"t":"15850559815683819","r":12},
+            "m": [
+                {"f":0,"p":{"t":"1","r":1},"k":"demo","c":"a","d": {"text": "a \"}]\" b", "list": [1, {"x": null}]} },
+                {"f":0,"p":{"t":"2","r":1},"k":"demo","c":"a","d":-1.5e3},
+                {"f":0,"p":{"t":"3","r":1},"k":"demo","c":"a","d":"Hello, world!"}
+            ] }
+        "#;
+        let (messages, _) = parse_subscribe_str(body).unwrap();
+        let payloads: Vec<_> = messages
+            .iter()
+            .map(|message| std::str::from_utf8(message.raw().unwrap()).unwrap())
+            .collect();
+        assert_eq!(
+            payloads,
+            vec![
+                r#"{"text": "a \"}]\" b", "list": [1, {"x": null}]}"#,
+                "-1.5e3",
+                r#""Hello, world!""#,
+            ]
+        );
+
+        // The payloads share the body.
+        let first = messages[0].raw().unwrap().as_ptr() as usize;
+        let last = messages[2].raw().unwrap().as_ptr() as usize;
+        assert_eq!(
+            last - first,
+            body.find(r#""Hello"#).unwrap() - body.find(r#"{"text"#).unwrap()
+        );
+
+        let (messages, cursor) = parse_subscribe_str(r#"{"m":[],"t":{"t":"1"}}"#).unwrap();
+        assert!(messages.is_empty());
+        assert_eq!(cursor, Cursor::new(1, 0));
+    }
+
+    #[test]
+    fn test_payload_ranges() {
+        assert_eq!(payload_ranges(br#"{"t":{},"m":[]}"#), Some(vec![]));
+        assert_eq!(
+            payload_ranges(br#"{"m":[{"f":0},1,{"d":2}]}"#),
+            Some(vec![None, None, Some(21..22)])
+        );
+        assert_eq!(payload_ranges(br#"{"m":[{"d":"#), None);
+    }
+
+    #[test]
+    fn test_parse_subscribe_rejects_deep_nesting() {
+        let depth = 16 * 1024;
+        let body = format!(
+            r#"{{"t":{{"t":"1"}},"m":[{{"f":0,"p":{{"t":"1"}},"k":"demo","c":"a","d":{}{}}}]}}"#,
+            "[".repeat(depth),
+            "]".repeat(depth)
+        );
+        match parse_subscribe_str(&body) {
+            Err(Error::Json(json::Error::ExceededDepthLimit)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_subscribe_malformed() {
+        let message = r#"{"f":0,"p":{"t":"15850559815660696","r":12},"k":"demo","c":"a","d":1}"#;
+        let complete = format!(r#"{{"t":{{"t":"1","r":1}},"m":[{}]}}"#, message);
+        assert!(parse_subscribe_str(&complete).is_ok());
+
+        // Truncated in the middle of a message, or after one.
+        for end in &[complete.len() - 10, complete.len() - 2, complete.len() - 1] {
+            match parse_subscribe_str(&complete[..*end]) {
+                Err(Error::Json(json::Error::UnexpectedEndOfJson)) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        // Garbage in the body.
+        for body in &[
+            format!(r#"{{"t":{{"t":"1","r":1}},"m":[{},{{"d":1 x}}]}}"#, message),
+            format!("{}]", complete),
+            format!(r#"{{"t":{{"t":"1","r":1}},"m":[{}}}"#, message),
+        ] {
+            match parse_subscribe_str(body) {
+                Err(Error::Json(json::Error::UnexpectedCharacter { .. })) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_subscribe_undecodable_messages() {
+        let message = r#"{"f":0,"p":{"t":"15850559815660696","r":12},"k":"demo","c":"a","d":1}"#;
+        let body = format!(
+            r#"{{"t":{{"t":"1","r":1}},"m":[{{"d":1}},{},{{"p":{{"t":"2"}},"c":"a","b":"b.*","d":2}},{}]}}"#,
+            message, message
+        );
+
+        // The bad messages don't fail the good ones. The one without the
+        // channel can't be delivered anywhere, so it's dropped.
+        let (messages, cursor) = parse_subscribe_str(&body).unwrap();
+        assert_eq!(cursor, Cursor::new(1, 1));
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].decode_error.is_none());
+        assert!(messages[2].decode_error.is_none());
+
+        let undecodable = &messages[1];
+        assert_eq!(
+            undecodable.decode_error,
+            Some("missing subscribe key".to_owned())
+        );
+        assert_eq!(undecodable.channel, "a".parse().unwrap());
+        assert_eq!(
+            undecodable.route,
+            Some(Route::ChannelWildcard("b.*".parse().unwrap()))
+        );
+        assert_eq!(undecodable.timetoken, Timetoken::from_parts(2, 0));
+        assert_eq!(undecodable.json["d"], 2);
+    }
+
+    #[test]
+    fn test_parse_publish() {
+        let json_sample = json::parse(r#"[1,"Sent","15850559815660696"]"#).unwrap();
+
+        let actual_response = parse_publish(StatusCode::OK, &json_sample).unwrap();
+
+        let expected_response = PublishResponse::new(
+            200,
+            "Sent".to_owned(),
+            Timetoken::from_parts(15_850_559_815_660_696, 0),
+        );
         assert_eq!(expected_response, actual_response);
     }
 }
@@ -0,0 +1,230 @@
+//! Mobile push notifications.
+
+use super::error;
+use super::service::{HyperService, RequestContext};
+use super::util::{access_denied_error, build_request, build_uri, handle_json_response};
+use crate::core::data::{channel, push::PushType, request, response};
+use crate::core::json::JsonValue;
+use async_trait::async_trait;
+use hyper::{Body, Method, StatusCode};
+use pubnub_util::uritemplate::UriTemplate;
+
+#[async_trait]
+impl HyperService<request::AddPushChannels> for RequestContext<'_> {
+    type Response = response::AddPushChannels;
+    type Error = error::Error;
+
+    async fn call(&self, request: request::AddPushChannels) -> Result<Self::Response, Self::Error> {
+        let request::AddPushChannels {
+            device_token,
+            push_type,
+            channels,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query = prepare_push_path(
+            &self.subscribe_key,
+            device_token,
+            push_type,
+            PushAction::Add(channels),
+        );
+
+        let _ = push_request(&self, &path_and_query).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::RemovePushChannels> for RequestContext<'_> {
+    type Response = response::RemovePushChannels;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::RemovePushChannels,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::RemovePushChannels {
+            device_token,
+            push_type,
+            channels,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query = prepare_push_path(
+            &self.subscribe_key,
+            device_token,
+            push_type,
+            PushAction::Remove(channels),
+        );
+
+        let _ = push_request(&self, &path_and_query).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HyperService<request::ListPushChannels> for RequestContext<'_> {
+    type Response = response::ListPushChannels;
+    type Error = error::Error;
+
+    async fn call(
+        &self,
+        request: request::ListPushChannels,
+    ) -> Result<Self::Response, Self::Error> {
+        let request::ListPushChannels {
+            device_token,
+            push_type,
+        } = request;
+
+        // Prepare the URL.
+        let path_and_query = prepare_push_path(
+            &self.subscribe_key,
+            device_token,
+            push_type,
+            PushAction::List,
+        );
+
+        let data_json = push_request(&self, &path_and_query).await?;
+
+        // Parse response.
+        if !data_json.is_array() {
+            return Err(error::Error::UnexpectedResponseSchema(data_json));
+        }
+        let channels: Option<_> = data_json
+            .members()
+            .map(|val| val.as_str().and_then(|s| s.parse().ok()))
+            .collect();
+        channels.ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+/// What to do with the device registrations.
+#[derive(Debug)]
+enum PushAction {
+    Add(Vec<channel::Name>),
+    Remove(Vec<channel::Name>),
+    List,
+}
+
+/// Prepare the path and query of a push request.
+///
+/// APNs2 registrations are managed via a separate API version, which
+/// requires the environment and the topic to be passed along.
+fn prepare_push_path(
+    subscribe_key: &str,
+    device_token: String,
+    push_type: PushType,
+    action: PushAction,
+) -> String {
+    let legacy_template = |push_type: &str| {
+        let mut template =
+            UriTemplate::new("/v1/push/sub-key/{sub_key}/devices/{device_token}{?add,remove,type}");
+        template.set_scalar("type", push_type);
+        template
+    };
+
+    let mut template = match push_type {
+        PushType::Apns2 { environment, topic } => {
+            let mut template = UriTemplate::new(
+                "/v2/push/sub-key/{sub_key}/devices-apns2/{device_token}{?add,remove,environment,topic}",
+            );
+            template
+                .set_scalar("environment", environment.as_str())
+                .set_scalar("topic", topic);
+            template
+        }
+        PushType::Apns => legacy_template("apns"),
+        PushType::Gcm => legacy_template("gcm"),
+        PushType::Mpns => legacy_template("mpns"),
+    };
+
+    template
+        .set_scalar("sub_key", subscribe_key)
+        .set_scalar("device_token", device_token);
+    match action {
+        PushAction::Add(channels) => template.set_list("add", channels),
+        PushAction::Remove(channels) => template.set_list("remove", channels),
+        PushAction::List => &mut template,
+    }
+    .build()
+}
+
+/// Send a push request, and handle the response.
+async fn push_request(
+    hyper: &RequestContext<'_>,
+    path_and_query: &str,
+) -> Result<JsonValue, error::Error> {
+    let url = build_uri(hyper, path_and_query)?;
+
+    // Send network request.
+    let req = build_request(hyper, Method::GET, url).body(Body::empty())?;
+    let response = hyper.send(req).await?;
+    let status = response.status();
+    if status.is_server_error() {
+        return Err(error::Error::HttpStatus(status));
+    }
+    let data_json = handle_json_response(response, hyper.max_response_bytes).await?;
+
+    match status {
+        status if status.is_success() => Ok(data_json),
+        StatusCode::FORBIDDEN => Err(access_denied_error(&data_json)),
+        status => Err(data_json["message"]
+            .as_str()
+            .map_or(error::Error::HttpStatus(status), |error_message| {
+                error::Error::Server(error_message.to_owned())
+            })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare_push_path, PushAction};
+    use crate::core::data::push::{ApnsEnvironment, PushType};
+
+    #[test]
+    fn test_prepare_push_path() {
+        assert_eq!(
+            prepare_push_path(
+                "demo",
+                "token".to_owned(),
+                PushType::Gcm,
+                PushAction::Add(vec!["ch1".parse().unwrap(), "ch2".parse().unwrap()]),
+            ),
+            "/v1/push/sub-key/demo/devices/token?add=ch1,ch2&type=gcm"
+        );
+        assert_eq!(
+            prepare_push_path(
+                "demo",
+                "token".to_owned(),
+                PushType::Apns,
+                PushAction::Remove(vec!["ch1".parse().unwrap()]),
+            ),
+            "/v1/push/sub-key/demo/devices/token?remove=ch1&type=apns"
+        );
+        assert_eq!(
+            prepare_push_path("demo", "token".to_owned(), PushType::Mpns, PushAction::List),
+            "/v1/push/sub-key/demo/devices/token?type=mpns"
+        );
+    }
+
+    #[test]
+    fn test_prepare_apns2_push_path() {
+        let push_type = PushType::Apns2 {
+            environment: ApnsEnvironment::Development,
+            topic: "com.example.app".to_owned(),
+        };
+        assert_eq!(
+            prepare_push_path(
+                "demo",
+                "token".to_owned(),
+                push_type.clone(),
+                PushAction::Add(vec!["ch1".parse().unwrap()]),
+            ),
+            "/v2/push/sub-key/demo/devices-apns2/token?add=ch1&environment=development&topic=com.example.app"
+        );
+        assert_eq!(
+            prepare_push_path("demo", "token".to_owned(), push_type, PushAction::List),
+            "/v2/push/sub-key/demo/devices-apns2/token?environment=development&topic=com.example.app"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Type-erased DNS resolver, allowing custom resolvers to be used with the
+//! default HTTP client of the [`Hyper`](super::Hyper) transport.
+
+use futures_util::future::{poll_fn, BoxFuture};
+use hyper::client::connect::dns::Name;
+use hyper::service::Service;
+use std::error::Error as StdError;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::vec;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+type ResolveFn = dyn Fn(Name) -> BoxFuture<'static, Result<Vec<IpAddr>, BoxError>> + Send + Sync;
+
+/// A resolver that wraps an arbitrary `hyper` DNS resolver, i.e. a
+/// [`Service`] resolving the host [`Name`]s to the IP addresses.
+///
+/// Set with [`HyperBuilder::resolver`](super::HyperBuilder::resolver):
+///
+/// ```
+/// use futures_util::future::{ready, Ready};
+/// use hyper::client::connect::dns::Name;
+/// use hyper::service::Service;
+/// use pubnub_hyper::transport::hyper::{BoxedResolver, Hyper};
+/// use std::io;
+/// use std::net::{IpAddr, Ipv4Addr};
+/// use std::task::{Context, Poll};
+/// use std::vec;
+///
+/// /// Resolves every host to the same address.
+/// #[derive(Clone)]
+/// struct Pinned(IpAddr);
+///
+/// impl Service<Name> for Pinned {
+///     type Response = vec::IntoIter<IpAddr>;
+///     type Error = io::Error;
+///     type Future = Ready<io::Result<Self::Response>>;
+///
+///     fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+///         Poll::Ready(Ok(()))
+///     }
+///
+///     fn call(&mut self, _name: Name) -> Self::Future {
+///         ready(Ok(vec![self.0].into_iter()))
+///     }
+/// }
+///
+/// let transport = Hyper::new()
+///     .publish_key("demo")
+///     .subscribe_key("demo")
+///     .resolver(BoxedResolver::new(Pinned(IpAddr::V4(Ipv4Addr::LOCALHOST))))
+///     .build()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct BoxedResolver {
+    resolve: Arc<ResolveFn>,
+}
+
+impl BoxedResolver {
+    /// Wrap the `resolver`.
+    pub fn new<R>(resolver: R) -> Self
+    where
+        R: Service<Name> + Clone + Send + Sync + 'static,
+        R::Response: Iterator<Item = IpAddr>,
+        R::Error: Into<BoxError>,
+        R::Future: Send + 'static,
+    {
+        let resolve = move |name: Name| {
+            let mut resolver = resolver.clone();
+            let fut: BoxFuture<'static, _> = Box::pin(async move {
+                poll_fn(|cx| resolver.poll_ready(cx))
+                    .await
+                    .map_err(Into::into)?;
+                let addrs = resolver.call(name).await.map_err(Into::into)?;
+                Ok(addrs.collect())
+            });
+            fut
+        };
+        Self {
+            resolve: Arc::new(resolve),
+        }
+    }
+}
+
+impl fmt::Debug for BoxedResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedResolver").finish()
+    }
+}
+
+impl Service<Name> for BoxedResolver {
+    type Response = vec::IntoIter<IpAddr>;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // Readiness of the wrapped resolver is checked on every call.
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let fut = (self.resolve)(name);
+        Box::pin(async move { fut.await.map(Vec::into_iter) })
+    }
+}
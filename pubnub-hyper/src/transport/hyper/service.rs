@@ -0,0 +1,216 @@
+//! Request identification.
+
+use super::middleware::Outcome;
+use super::{error, Hyper};
+use crate::core::data::uuid::UUID;
+use crate::core::data::{presence, request, response};
+use crate::core::TransportService;
+use async_trait::async_trait;
+use hyper::{Body, Request, Response};
+use log::{debug, warn};
+use std::ops::Deref;
+
+/// A request being made with the [`Hyper`] transport.
+///
+/// Dereferences to the transport, and carries the unique ID of the request.
+/// The ID is sent along with the request, and attached to its errors.
+#[derive(Debug)]
+pub(super) struct RequestContext<'a> {
+    hyper: &'a Hyper,
+    pub request_id: UUID,
+    /// The origin to send the request to.
+    pub origin: &'a str,
+    origin_index: usize,
+}
+
+impl Deref for RequestContext<'_> {
+    type Target = Hyper;
+
+    fn deref(&self) -> &Hyper {
+        self.hyper
+    }
+}
+
+/// The implementation of a PubNub API call, in the context of a single
+/// request.
+#[async_trait]
+pub(super) trait HyperService<TRequest> {
+    /// Response given by the service.
+    type Response;
+    /// Error produced by the service.
+    type Error;
+
+    /// Process the request and return the response asynchronously.
+    async fn call(&self, request: TRequest) -> Result<Self::Response, Self::Error>;
+}
+
+impl<'a> RequestContext<'a> {
+    /// Make a new request with the `hyper` transport.
+    pub(super) fn new(hyper: &'a Hyper) -> Self {
+        let origin_index = hyper.failover.current();
+        Self {
+            hyper,
+            request_id: UUID::random(),
+            origin: hyper.origin_at(origin_index),
+            origin_index,
+        }
+    }
+
+    /// Send the HTTP `request` through the [`middleware`](super::Middleware)
+    /// and the HTTP client.
+    pub(super) async fn send(
+        &self,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, hyper::Error> {
+        let mut request = request;
+        let mut sent = 0;
+        let response = loop {
+            let middleware = match self.middleware.get(sent) {
+                Some(middleware) => middleware,
+                None => break self.http_client.request(request).await?,
+            };
+            match middleware.on_request(request) {
+                Outcome::Send(next) => request = next,
+                Outcome::Respond(response) => {
+                    debug!("Request {} short-circuited by middleware", self.request_id);
+                    break response;
+                }
+            }
+            sent += 1;
+        };
+        // Only the middleware that saw the request sees the response.
+        let seen = (sent + 1).min(self.middleware.len());
+        for middleware in self.middleware[..seen].iter().rev() {
+            middleware.on_response(&response);
+        }
+        Ok(response)
+    }
+
+    /// Process the request, attaching the request ID to the error, and
+    /// keeping track of the failures of the origin.
+    async fn process<TRequest>(
+        &self,
+        request: TRequest,
+    ) -> Result<<Self as HyperService<TRequest>>::Response, error::Error>
+    where
+        Self: HyperService<TRequest, Error = error::Error>,
+    {
+        let err = match HyperService::call(self, request).await {
+            Ok(response) => {
+                self.failover.succeeded(self.origin_index);
+                return Ok(response);
+            }
+            Err(err) => err,
+        };
+
+        debug!("Request {} failed: {}", self.request_id, err);
+        if err.is_transient() {
+            let count = self.origin_count();
+            if let Some(next) = self.failover.failed(self.origin_index, count) {
+                warn!(
+                    "Origin {} keeps failing, failing over to {}",
+                    self.origin,
+                    self.origin_at(next)
+                );
+            }
+        }
+        Err(error::Error::Request {
+            request_id: self.request_id.to_string(),
+            source: Box::new(err),
+        })
+    }
+}
+
+macro_rules! impl_transport_service {
+    ($req:ty, $res:ty) => {
+        #[async_trait]
+        impl TransportService<$req> for Hyper {
+            type Response = $res;
+            type Error = error::Error;
+
+            async fn call(&self, request: $req) -> Result<Self::Response, Self::Error> {
+                RequestContext::new(self).process(request).await
+            }
+        }
+    };
+}
+
+impl_transport_service![request::Publish, response::Publish];
+impl_transport_service![request::Subscribe, response::Subscribe];
+
+impl_transport_service![request::SetState, response::SetState];
+impl_transport_service![request::GetState, response::GetState];
+impl_transport_service![
+    request::HereNow<presence::respond_with::OccupancyOnly>,
+    response::HereNow<presence::respond_with::OccupancyOnly>
+];
+impl_transport_service![
+    request::HereNow<presence::respond_with::OccupancyAndUUIDs>,
+    response::HereNow<presence::respond_with::OccupancyAndUUIDs>
+];
+impl_transport_service![
+    request::HereNow<presence::respond_with::Full>,
+    response::HereNow<presence::respond_with::Full>
+];
+impl_transport_service![
+    request::GlobalHereNow<presence::respond_with::OccupancyOnly>,
+    response::GlobalHereNow<presence::respond_with::OccupancyOnly>
+];
+impl_transport_service![
+    request::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>,
+    response::GlobalHereNow<presence::respond_with::OccupancyAndUUIDs>
+];
+impl_transport_service![
+    request::GlobalHereNow<presence::respond_with::Full>,
+    response::GlobalHereNow<presence::respond_with::Full>
+];
+impl_transport_service![request::WhereNow, response::WhereNow];
+impl_transport_service![request::Heartbeat, response::Heartbeat];
+impl_transport_service![request::Leave, response::Leave];
+impl_transport_service![request::Grant, response::Grant];
+impl_transport_service![request::RevokeToken, response::RevokeToken];
+
+impl_transport_service![request::GetHistory, response::GetHistory];
+impl_transport_service![request::DeleteHistory, response::DeleteHistory];
+impl_transport_service![
+    request::MessageCountsWithTimetoken,
+    response::MessageCountsWithTimetoken
+];
+impl_transport_service![
+    request::MessageCountsWithChannelTimetokens,
+    response::MessageCountsWithChannelTimetokens
+];
+
+impl_transport_service![request::GetUuidMetadata, response::GetUuidMetadata];
+impl_transport_service![request::SetUuidMetadata, response::SetUuidMetadata];
+impl_transport_service![request::RemoveUuidMetadata, response::RemoveUuidMetadata];
+impl_transport_service![request::GetChannelMetadata, response::GetChannelMetadata];
+impl_transport_service![
+    request::GetAllChannelMetadata,
+    response::GetAllChannelMetadata
+];
+impl_transport_service![request::SetChannelMetadata, response::SetChannelMetadata];
+impl_transport_service![
+    request::RemoveChannelMetadata,
+    response::RemoveChannelMetadata
+];
+impl_transport_service![request::GetMemberships, response::GetMemberships];
+impl_transport_service![request::SetMemberships, response::SetMemberships];
+impl_transport_service![request::GetChannelMembers, response::GetChannelMembers];
+impl_transport_service![request::SetChannelMembers, response::SetChannelMembers];
+
+impl_transport_service![request::AddPushChannels, response::AddPushChannels];
+impl_transport_service![request::RemovePushChannels, response::RemovePushChannels];
+impl_transport_service![request::ListPushChannels, response::ListPushChannels];
+
+impl_transport_service![request::Time, response::Time];
+
+impl_transport_service![
+    request::GenerateFileUploadUrl,
+    response::GenerateFileUploadUrl
+];
+impl_transport_service![request::UploadFile, response::UploadFile];
+impl_transport_service![request::PublishFileMessage, response::PublishFileMessage];
+impl_transport_service![request::ListFiles, response::ListFiles];
+impl_transport_service![request::GetFileUrl, response::GetFileUrl];
+impl_transport_service![request::DownloadFile, response::DownloadFile<error::Error>];
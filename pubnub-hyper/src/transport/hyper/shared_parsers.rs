@@ -20,15 +20,12 @@ fn parse_message_route(route: &json::JsonValue) -> Result<Option<message::Route>
     Err(())
 }
 
+/// The suffix of the presence channel names.
+const PRESENCE_CHANNEL_SUFFIX: &str = "-pnpres";
+
 fn parse_message_type(i: &json::JsonValue) -> Option<message::Type> {
     let i = if i.is_null() { 0 } else { i.as_u32()? };
-    Some(match i {
-        0 => message::Type::Publish,
-        1 => message::Type::Signal,
-        2 => message::Type::Objects,
-        3 => message::Type::Action,
-        i => message::Type::Unknown(i),
-    })
+    Some(message::Type::from_code(i))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -40,32 +37,80 @@ pub enum ParseMessageError {
     SubscribeKey,
 }
 
+impl ParseMessageError {
+    /// Explain what's wrong with the message.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Type => "invalid message type",
+            Self::Route => "invalid route",
+            Self::Channel => "missing or invalid channel",
+            Self::Timetoken => "missing or invalid timetoken",
+            Self::SubscribeKey => "missing subscribe key",
+        }
+    }
+}
+
 /// Parse message from a json object.
 pub fn parse_message(message: &json::object::Object) -> Result<Message, ParseMessageError> {
-    let message = Message {
-        message_type: parse_message_type(&message["e"]).ok_or(ParseMessageError::Type)?,
-        route: parse_message_route(&message["b"]).map_err(|_| ParseMessageError::Route)?,
-        channel: message["c"]
+    let channel = message["c"]
+        .as_str()
+        .ok_or(ParseMessageError::Channel)?
+        .parse()
+        .map_err(|_| ParseMessageError::Channel)?;
+    let timetoken = Timetoken::from_parts(
+        message["p"]["t"]
             .as_str()
-            .ok_or(ParseMessageError::Channel)?
+            .ok_or(ParseMessageError::Timetoken)?
             .parse()
-            .map_err(|_| ParseMessageError::Channel)?,
-        json: message["d"].clone(),
-        metadata: message["u"].clone(),
-        timetoken: Timetoken {
-            t: message["p"]["t"]
-                .as_str()
-                .ok_or(ParseMessageError::Timetoken)?
-                .parse()
-                .map_err(|_| ParseMessageError::Timetoken)?,
-            r: message["p"]["r"].as_u32().unwrap_or(0),
-        },
-        client: message["i"].as_str().map(std::borrow::ToOwned::to_owned),
-        subscribe_key: message["k"]
-            .as_str()
-            .ok_or(ParseMessageError::SubscribeKey)?
-            .to_owned(),
-        flags: message["f"].as_u32().unwrap_or(0),
-    };
-    Ok(message)
+            .map_err(|_| ParseMessageError::Timetoken)?,
+        message["p"]["r"].as_u32().unwrap_or(0),
+    );
+
+    // Not with `Message::new`, the transport keeps the payload as received.
+    let mut parsed = Message::default();
+    parsed.channel = channel;
+    parsed.json = message["d"].clone();
+    parsed.timetoken = timetoken;
+    parsed.message_type = parse_message_type(&message["e"]).ok_or(ParseMessageError::Type)?;
+    // The presence events are sent as the regular messages, to the presence
+    // channels.
+    if parsed.message_type == message::Type::Publish
+        && AsRef::<str>::as_ref(&parsed.channel).ends_with(PRESENCE_CHANNEL_SUFFIX)
+    {
+        parsed.message_type = message::Type::Presence;
+    }
+    parsed.route = parse_message_route(&message["b"]).map_err(|_| ParseMessageError::Route)?;
+    parsed.metadata = message["u"].clone();
+    parsed.client = message["i"].as_str().map(std::borrow::ToOwned::to_owned);
+    parsed.subscribe_key = message["k"]
+        .as_str()
+        .map(std::borrow::ToOwned::to_owned)
+        .ok_or(ParseMessageError::SubscribeKey)?;
+    parsed.flags = message["f"].as_u32().unwrap_or(0);
+    Ok(parsed)
+}
+
+/// Wrap a message that failed to parse, so that it's delivered as an error
+/// to the subscribers of its channel.
+///
+/// Returns `None` if the channel can't be told, i.e. there's no one to
+/// deliver the message to.
+pub fn undecodable_message(message: &json::JsonValue, reason: &str) -> Option<Message> {
+    let channel = message["c"].as_str()?.parse().ok()?;
+    let timetoken = message["p"]["t"]
+        .as_str()
+        .and_then(|t| t.parse().ok())
+        .map_or_else(Timetoken::default, |t| {
+            Timetoken::from_parts(t, message["p"]["r"].as_u32().unwrap_or(0))
+        });
+
+    let mut undecodable = Message::default();
+    undecodable.message_type = message::Type::Publish;
+    undecodable.channel = channel;
+    undecodable.json = message.clone();
+    undecodable.timetoken = timetoken;
+    // Route it like a well-formed message would be, as far as possible.
+    undecodable.route = parse_message_route(&message["b"]).unwrap_or(None);
+    undecodable.decode_error = Some(reason.to_owned());
+    Some(undecodable)
 }
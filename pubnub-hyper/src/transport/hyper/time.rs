@@ -0,0 +1,55 @@
+//! Time.
+
+use super::error;
+use super::service::{HyperService, RequestContext};
+use super::util::{build_request, build_uri, handle_json_response, json_as_array};
+use crate::core::data::{request, response, timetoken::Timetoken};
+use crate::core::json::JsonValue;
+use async_trait::async_trait;
+use hyper::{Body, Method};
+
+#[async_trait]
+impl HyperService<request::Time> for RequestContext<'_> {
+    type Response = response::Time;
+    type Error = error::Error;
+
+    async fn call(&self, _request: request::Time) -> Result<Self::Response, Self::Error> {
+        let url = build_uri(&self, "/time/0")?;
+
+        // Send network request.
+        let req = build_request(&self, Method::GET, url).body(Body::empty())?;
+        let response = self.send(req).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(error::Error::HttpStatus(status));
+        }
+        let data_json = handle_json_response(response, self.max_response_bytes).await?;
+
+        // Parse response.
+        parse_time(&data_json).ok_or_else(|| error::Error::UnexpectedResponseSchema(data_json))
+    }
+}
+
+/// Parse the `[15850559815660696]` time response.
+fn parse_time(data_json: &JsonValue) -> Option<Timetoken> {
+    let t = json_as_array(data_json)?.first()?.as_u64()?;
+    Some(Timetoken::from_parts(t, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_time;
+    use crate::core::data::timetoken::Timetoken;
+
+    #[test]
+    fn test_parse_time() {
+        let data_json = json::parse("[15850559815660696]").unwrap();
+        assert_eq!(
+            parse_time(&data_json),
+            Some(Timetoken::from_parts(15_850_559_815_660_696, 0))
+        );
+
+        assert_eq!(parse_time(&json::parse(r#"["1"]"#).unwrap()), None);
+        assert_eq!(parse_time(&json::parse("{}").unwrap()), None);
+    }
+}
@@ -0,0 +1,30 @@
+//! Network traffic accounting.
+
+use crate::core::metrics::Traffic;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Counts the bytes of the publish and subscribe calls: the request targets
+/// sent, and the response bodies received.
+///
+/// Shared by the clones of the transport.
+#[derive(Debug, Default)]
+pub(super) struct TrafficCounters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl TrafficCounters {
+    /// Record a round trip.
+    pub fn record(&self, sent: usize, received: usize) {
+        self.bytes_sent.fetch_add(sent as u64, Ordering::Relaxed);
+        self.bytes_received
+            .fetch_add(received as u64, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Traffic {
+        Traffic::new(
+            self.bytes_sent.load(Ordering::Relaxed),
+            self.bytes_received.load(Ordering::Relaxed),
+        )
+    }
+}
@@ -2,36 +2,237 @@
 
 use super::error;
 use crate::core::json;
-use futures_util::stream::StreamExt;
-use hyper::{Body, Response, Uri};
+use futures_util::stream::{self, Stream, StreamExt};
+use hyper::header::{CONTENT_LENGTH, RETRY_AFTER, USER_AGENT};
+use hyper::{Body, Method, Request, Response, Uri};
 use json::{object::Object as JsonObject, JsonValue};
 use log::{debug, trace};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::time::{Duration, SystemTime};
 
+use super::service::RequestContext;
 use super::Hyper;
 
-pub(super) fn build_uri(hyper: &Hyper, path_and_query: &str) -> Result<Uri, http::Error> {
+/// Build the URL of the request, identifying it with the request ID.
+pub(super) fn build_uri(
+    context: &RequestContext<'_>,
+    path_and_query: &str,
+) -> Result<Uri, http::Error> {
+    let separator = if path_and_query.contains('?') {
+        '&'
+    } else {
+        '?'
+    };
+    let mut path_and_query = format!(
+        "{}{}requestid={}",
+        path_and_query, separator, context.request_id
+    );
+    if let Some(auth) = encoded_auth(context) {
+        path_and_query.push_str("&auth=");
+        path_and_query.push_str(&auth);
+    }
     let url = Uri::builder()
-        .scheme("https")
-        .authority(hyper.origin.as_str())
-        .path_and_query(path_and_query)
+        .scheme(if context.secure { "https" } else { "http" })
+        .authority(context.origin)
+        .path_and_query(path_and_query.as_str())
         .build()?;
-    debug!("URL: {}", url);
+    debug!("URL: {}", redact_uri(&url, &context.publish_key));
+    if !context.redact_logs {
+        trace!("Unredacted URL: {}", url);
+    }
     Ok(url)
 }
 
+/// The `auth` parameter value of the requests, percent-encoded, if any.
+pub(super) fn encoded_auth(hyper: &Hyper) -> Option<String> {
+    hyper
+        .auth()
+        .map(|auth| utf8_percent_encode(&auth, QUERY_ENCODE_SET).to_string())
+}
+
+/// The query parameters holding the secrets.
+const SECRET_PARAMS: [&str; 2] = ["signature", "auth"];
+
+/// The query parameters managed by the transport, which the
+/// [`extra_params`](super::HyperBuilder::extra_params) can't overwrite.
+const MANAGED_PARAMS: [&str; 6] = [
+    "signature",
+    "auth",
+    "timestamp",
+    "uuid",
+    "requestid",
+    "pnsdk",
+];
+
+/// The characters to percent-encode in the extra query parameters, all but
+/// the unreserved ones.
+const QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Append the [`extra_params`](super::HyperBuilder::extra_params) to the
+/// `path_and_query`, skipping the ones it already has and the ones managed
+/// by the transport.
+pub(super) fn with_extra_params(hyper: &Hyper, mut path_and_query: String) -> String {
+    let mut present: Vec<String> = match path_and_query.find('?') {
+        Some(pos) => path_and_query[pos + 1..]
+            .split('&')
+            .map(|pair| pair.split('=').next().unwrap_or_default().to_owned())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    for (name, value) in &hyper.extra_params {
+        let name = utf8_percent_encode(name, QUERY_ENCODE_SET).to_string();
+        if MANAGED_PARAMS.contains(&name.as_str()) || present.contains(&name) {
+            debug!("Skipping the extra query parameter {:?}", name);
+            continue;
+        }
+        path_and_query.push(if path_and_query.contains('?') {
+            '&'
+        } else {
+            '?'
+        });
+        path_and_query.push_str(&name);
+        path_and_query.push('=');
+        path_and_query.extend(utf8_percent_encode(value, QUERY_ENCODE_SET));
+        present.push(name);
+    }
+    path_and_query
+}
+
+/// Format the `url` for the logs, with the `publish_key` and the secret
+/// query parameters masked.
+fn redact_uri(url: &Uri, publish_key: &str) -> String {
+    let mut redacted = format!(
+        "{}://{}",
+        url.scheme_str().unwrap_or_default(),
+        url.authority().map_or("", |authority| authority.as_str())
+    );
+
+    for segment in url.path().split('/').skip(1) {
+        redacted.push('/');
+        redacted.push_str(if !publish_key.is_empty() && segment == publish_key {
+            "***"
+        } else {
+            segment
+        });
+    }
+
+    if let Some(query) = url.query() {
+        for (index, pair) in query.split('&').enumerate() {
+            redacted.push(if index == 0 { '?' } else { '&' });
+            match pair.find('=') {
+                Some(pos) if SECRET_PARAMS.contains(&&pair[..pos]) => {
+                    redacted.push_str(&pair[..=pos]);
+                    redacted.push_str("***");
+                }
+                _ => redacted.push_str(pair),
+            }
+        }
+    }
+    redacted
+}
+
+/// Prepare a request builder with the parts common to every request, such as
+/// the user agent and the custom headers, already applied.
+pub(super) fn build_request(
+    hyper: &RequestContext<'_>,
+    method: Method,
+    url: Uri,
+) -> http::request::Builder {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri(url)
+        .header(USER_AGENT, hyper.agent.as_str());
+    for (name, value) in &hyper.headers {
+        builder = builder.header(name.as_str(), value.as_str());
+    }
+    builder
+}
+
 pub(super) async fn handle_json_response(
     response: Response<Body>,
+    limit: usize,
 ) -> Result<json::JsonValue, error::Error> {
+    let bytes = receive_body(response, limit).await?;
+    parse_json_body(&bytes)
+}
+
+/// Receive the whole response body, failing as soon as it exceeds the
+/// `limit` in bytes.
+pub(super) async fn receive_body(
+    response: Response<Body>,
+    limit: usize,
+) -> Result<Vec<u8>, error::Error> {
+    check_content_length(&response, limit)?;
     let mut body = response.into_body();
     let mut bytes = Vec::new();
 
     // Receive the response as a byte stream
     while let Some(chunk) = body.next().await {
-        bytes.extend(chunk?);
+        let chunk = chunk?;
+        check_body_size(bytes.len() + chunk.len(), limit)?;
+        bytes.extend(chunk);
     }
 
+    Ok(bytes)
+}
+
+/// Stream the response body a chunk at a time, failing as soon as it exceeds
+/// the `limit` in bytes. The stream ends after the first error.
+pub(super) fn stream_body(
+    response: Response<Body>,
+    limit: usize,
+) -> Result<impl Stream<Item = Result<Vec<u8>, error::Error>>, error::Error> {
+    check_content_length(&response, limit)?;
+    let body = response.into_body();
+
+    Ok(stream::unfold(Some((body, 0)), move |state| async move {
+        let (mut body, size) = state?;
+        let received = body.next().await?.map_err(error::Error::from);
+        let chunk = received.and_then(|chunk| {
+            let size = size + chunk.len();
+            check_body_size(size, limit)?;
+            Ok((chunk.to_vec(), size))
+        });
+        match chunk {
+            Ok((chunk, size)) => Some((Ok(chunk), Some((body, size)))),
+            Err(err) => Some((Err(err), None)),
+        }
+    }))
+}
+
+/// Fail early if the response announces a body over the `limit` in bytes.
+pub(super) fn check_content_length(
+    response: &Response<Body>,
+    limit: usize,
+) -> Result<(), error::Error> {
+    let length = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    match length {
+        Some(length) if length > limit as u64 => Err(error::Error::ResponseTooLarge(limit)),
+        _ => Ok(()),
+    }
+}
+
+/// Fail if the `size` of the body received so far exceeds the `limit`.
+pub(super) fn check_body_size(size: usize, limit: usize) -> Result<(), error::Error> {
+    if size > limit {
+        return Err(error::Error::ResponseTooLarge(limit));
+    }
+    Ok(())
+}
+
+/// Parse the received response body as JSON.
+pub(super) fn parse_json_body(bytes: &[u8]) -> Result<json::JsonValue, error::Error> {
     // Convert the resolved byte stream to JSON.
-    let data = std::str::from_utf8(&bytes)?;
+    let data = std::str::from_utf8(bytes)?;
     let data_json = json::parse(data)?;
 
     trace!("Response JSON: {}", data_json);
@@ -39,6 +240,51 @@ pub(super) async fn handle_json_response(
     Ok(data_json)
 }
 
+/// Build an error for a `429 Too Many Requests` response, with the delay the
+/// server asks for in the `Retry-After` header.
+pub(super) fn rate_limited_error(response: &Response<Body>) -> error::Error {
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, SystemTime::now()));
+    error::Error::RateLimited { retry_after }
+}
+
+/// Parse the `Retry-After` header `value`, either the number of seconds or
+/// the HTTP date to retry after. The dates in the past mean no delay.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or_default())
+}
+
+/// Build an error for a `403 Forbidden` response from the PubNub Access
+/// Manager.
+pub(super) fn access_denied_error(data_json: &JsonValue) -> error::Error {
+    let names = |key: &str| {
+        data_json["payload"][key]
+            .members()
+            .filter_map(JsonValue::as_str)
+            .map(str::to_owned)
+            .collect()
+    };
+    let channels = names("channels");
+    let channel_groups = names("channel-groups");
+    let message = data_json["message"]
+        .as_str()
+        .unwrap_or("Forbidden")
+        .to_owned();
+    error::Error::AccessDenied {
+        channels,
+        channel_groups,
+        message,
+    }
+}
+
 pub(super) fn json_as_array(val: &JsonValue) -> Option<&Vec<JsonValue>> {
     match val {
         JsonValue::Array(val) => Some(val),
@@ -52,3 +298,151 @@ pub(super) fn json_as_object(val: &JsonValue) -> Option<&JsonObject> {
         _ => None,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{access_denied_error, build_uri, parse_retry_after, redact_uri, with_extra_params};
+    use crate::core::Transport;
+    use crate::transport::hyper::error::Error;
+    use crate::transport::hyper::service::RequestContext;
+    use crate::transport::hyper::Hyper;
+    use std::time::Duration;
+
+    #[test]
+    fn test_access_denied_error() {
+        let data_json = json::parse(
+            r#"{"message":"Forbidden","payload":{"channels":["ch1","ch2"],"channel-groups":["cg1"]},"error":true,"service":"Access Manager","status":403}"#,
+        )
+        .unwrap();
+
+        match access_denied_error(&data_json) {
+            Error::AccessDenied {
+                channels,
+                channel_groups,
+                message,
+            } => {
+                assert_eq!(channels, vec!["ch1".to_owned(), "ch2".to_owned()]);
+                assert_eq!(channel_groups, vec!["cg1".to_owned()]);
+                assert_eq!(message, "Forbidden");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_build_uri_appends_request_id() {
+        let hyper = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .build()
+            .unwrap();
+        let context = RequestContext::new(&hyper);
+        let request_id = context.request_id.to_string();
+
+        let url = build_uri(&context, "/time/0").unwrap();
+        assert_eq!(
+            url.query(),
+            Some(format!("requestid={}", request_id).as_str())
+        );
+
+        let url = build_uri(&context, "/time/0?uuid=me").unwrap();
+        assert_eq!(
+            url.query(),
+            Some(format!("uuid=me&requestid={}", request_id).as_str())
+        );
+    }
+
+    #[test]
+    fn test_build_uri_appends_auth() {
+        let hyper = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .auth_key("my-auth-key")
+            .build()
+            .unwrap();
+        let auth = |hyper: &Hyper| {
+            let url = build_uri(&RequestContext::new(hyper), "/time/0").unwrap();
+            url.query()
+                .unwrap()
+                .split("&auth=")
+                .nth(1)
+                .map(str::to_owned)
+        };
+        assert_eq!(auth(&hyper), Some("my-auth-key".to_owned()));
+
+        // The token takes precedence, for all the clones.
+        hyper.clone().set_auth_token(Some("p0+/=".to_owned()));
+        assert_eq!(auth(&hyper), Some("p0%2B%2F%3D".to_owned()));
+
+        hyper.set_auth_token(None);
+        assert_eq!(auth(&hyper), Some("my-auth-key".to_owned()));
+    }
+
+    #[test]
+    fn test_redact_uri() {
+        let url = "https://ps.pndsn.com/publish/pub-c-1/sub-c-1/0/pub-c-1x/0/1?signature=v2.abc&timestamp=1&auth=key&uuid=me"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            redact_uri(&url, "pub-c-1"),
+            "https://ps.pndsn.com/publish/***/sub-c-1/0/pub-c-1x/0/1?signature=***&timestamp=1&auth=***&uuid=me"
+        );
+
+        let url = "http://localhost:8080/time/0".parse().unwrap();
+        assert_eq!(redact_uri(&url, ""), "http://localhost:8080/time/0");
+    }
+
+    #[test]
+    fn test_build_uri_scheme() {
+        let mut builder = Hyper::new();
+        builder.publish_key("demo").subscribe_key("demo");
+
+        let hyper = builder.build().unwrap();
+        let url = build_uri(&RequestContext::new(&hyper), "/time/0").unwrap();
+        assert_eq!(url.scheme_str(), Some("https"));
+
+        let hyper = builder.secure(false).build().unwrap();
+        let url = build_uri(&RequestContext::new(&hyper), "/time/0").unwrap();
+        assert_eq!(url.scheme_str(), Some("http"));
+    }
+
+    #[test]
+    fn test_with_extra_params() {
+        let hyper = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .extra_param("beta", "a b&c=d")
+            .extra_param("auth", "overwritten")
+            .extra_param("uuid", "overwritten")
+            .extra_param("tt", "overwritten")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            with_extra_params(&hyper, "/v2/subscribe/demo/ch/0?tt=0&uuid=me".to_owned()),
+            "/v2/subscribe/demo/ch/0?tt=0&uuid=me&beta=a%20b%26c%3Dd"
+        );
+        assert_eq!(
+            with_extra_params(&hyper, "/time/0".to_owned()),
+            "/time/0?beta=a%20b%26c%3Dd&tt=overwritten"
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = std::time::UNIX_EPOCH + Duration::from_secs(1_445_411_600);
+
+        assert_eq!(parse_retry_after("90", now), Some(Duration::from_secs(90)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:30:45 GMT", now),
+            Some(Duration::from_secs(1_045))
+        );
+        // The dates in the past.
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("-5", now), None);
+    }
+}
@@ -0,0 +1,132 @@
+use pubnub_hyper::core::data::app_context::{
+    ChannelMetadata, MembershipChanges, MembershipEntry, UuidMetadata,
+};
+use pubnub_hyper::core::data::channel;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+
+mod common;
+
+const SAMPLE_UUID: &str = "903145ee-7c15-4579-aa5d-38a900717512";
+
+#[test]
+fn set_get_remove_uuid_metadata() {
+    common::init();
+    common::current_thread_block_on(async {
+        let transport = Hyper::new()
+            .agent("Rust-Agent-Test")
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .build()
+            .unwrap();
+
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let mut metadata = UuidMetadata::new(SAMPLE_UUID);
+        metadata.name = Some("Rust Test".to_owned());
+        metadata.custom = Some(json::object! {
+            "visits" => 10,
+        });
+
+        let updated = pubnub.set_uuid_metadata(metadata.clone()).await.unwrap();
+        assert_eq!(updated.name, metadata.name);
+        assert_eq!(updated.custom, metadata.custom);
+        assert!(updated.etag.is_some());
+
+        let fetched = pubnub.get_uuid_metadata(SAMPLE_UUID.into()).await.unwrap();
+        assert_eq!(fetched.custom, metadata.custom);
+        assert_eq!(fetched.etag, updated.etag);
+
+        pubnub
+            .remove_uuid_metadata(SAMPLE_UUID.into())
+            .await
+            .unwrap();
+    });
+}
+
+#[test]
+fn set_list_remove_channel_metadata() {
+    common::init();
+    common::current_thread_block_on(async {
+        let transport = Hyper::new()
+            .agent("Rust-Agent-Test")
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .build()
+            .unwrap();
+
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let channel: channel::Name = "rust-test-channel".parse().unwrap();
+        let mut metadata = ChannelMetadata::new(channel.clone());
+        metadata.description = Some("Rust Test".to_owned());
+
+        let updated = pubnub.set_channel_metadata(metadata.clone()).await.unwrap();
+        assert_eq!(updated.description, metadata.description);
+
+        let page = pubnub
+            .get_all_channel_metadata(Some(1), None)
+            .await
+            .unwrap();
+        assert!(page.items.len() <= 1);
+        if let Some(next) = page.next {
+            let next_page = pubnub
+                .get_all_channel_metadata(Some(1), Some(next))
+                .await
+                .unwrap();
+            assert_ne!(next_page.items, page.items);
+        }
+
+        pubnub.remove_channel_metadata(channel).await.unwrap();
+    });
+}
+
+#[test]
+fn set_get_memberships() {
+    common::init();
+    common::current_thread_block_on(async {
+        let transport = Hyper::new()
+            .agent("Rust-Agent-Test")
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .build()
+            .unwrap();
+
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let channel: channel::Name = "rust-test-channel".parse().unwrap();
+        let changes = MembershipChanges {
+            set: vec![MembershipEntry {
+                id: channel.clone(),
+                custom: Some(json::object! {
+                    "role" => "admin",
+                }),
+            }],
+            remove: vec![],
+        };
+
+        pubnub
+            .set_memberships(SAMPLE_UUID.into(), changes)
+            .await
+            .unwrap();
+
+        let members = pubnub
+            .get_channel_members(channel.clone(), None)
+            .await
+            .unwrap();
+        assert!(members
+            .items
+            .iter()
+            .any(|member| *member.uuid.id == SAMPLE_UUID));
+
+        let changes = MembershipChanges {
+            set: vec![],
+            remove: vec![channel],
+        };
+        pubnub
+            .set_memberships(SAMPLE_UUID.into(), changes)
+            .await
+            .unwrap();
+    });
+}
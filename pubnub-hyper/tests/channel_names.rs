@@ -0,0 +1,114 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::data::{channel, cursor::Cursor, pubsub, request};
+use pubnub_hyper::core::TransportService;
+use pubnub_hyper::transport::hyper::{error::Error, Hyper};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+mod common;
+
+/// A transport of a local server counting the requests, and answering all
+/// of them with a publish response.
+fn transport() -> (Hyper, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server_requests = Arc::clone(&requests);
+    let make_service = make_service_fn(move |_| {
+        let requests = Arc::clone(&server_requests);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                async {
+                    Ok::<_, Infallible>(Response::new(Body::from(
+                        r#"[1,"Sent","15000000000000000"]"#,
+                    )))
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+
+    let transport = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin)
+        .secure(false)
+        .build()
+        .unwrap();
+    (transport, requests)
+}
+
+fn publish(channel: &str) -> request::Publish {
+    request::Publish {
+        channel: channel::Name::from_string_unchecked(channel.to_owned()),
+        payload: json::JsonValue::from(42),
+        meta: None,
+        timetoken_override: None,
+        replicate: true,
+        method: pubsub::PublishMethod::Get,
+    }
+}
+
+fn subscribe(to: pubsub::SubscribeTo) -> request::Subscribe {
+    request::Subscribe {
+        to: vec![to],
+        cursor: Cursor::default(),
+        heartbeat: None,
+        filter_expr: None,
+    }
+}
+
+fn assert_invalid(error: &Error, expected_name: &str) {
+    match error.inner() {
+        Error::InvalidChannelName { name, .. } => assert_eq!(name, expected_name),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_channel_names_are_rejected_before_sending() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (transport, requests) = transport();
+
+        for name in &["", "a/b", "a:b", "a\\b", "a*", "a.*", "a\tb"] {
+            let error = transport.call(publish(name)).await.unwrap_err();
+            assert_invalid(&error, name);
+        }
+
+        let group = channel::Name::from_string_unchecked("group:a".to_owned());
+        let error = transport
+            .call(subscribe(pubsub::SubscribeTo::ChannelGroup(group)))
+            .await
+            .unwrap_err();
+        assert_invalid(&error, "group:a");
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // Valid names go through.
+        transport.call(publish("a.b")).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[test]
+fn wildcard_names_are_only_allowed_in_subscribe() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (transport, requests) = transport();
+
+        let error = transport.call(publish("a.*")).await.unwrap_err();
+        assert_invalid(&error, "a.*");
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // The server doesn't answer like a subscribe, but the request is
+        // sent.
+        let wildcard = channel::Name::from_string_unchecked("a.*".to_owned());
+        let _ = transport
+            .call(subscribe(pubsub::SubscribeTo::Channel(wildcard)))
+            .await;
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    });
+}
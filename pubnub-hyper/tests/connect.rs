@@ -0,0 +1,132 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::data::request;
+use pubnub_hyper::core::json::object;
+use pubnub_hyper::core::TransportService;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn connect_warms_up_the_connection() {
+    common::init();
+    common::current_thread_block_on(async {
+        // A local server counting the connections it accepts, and recording
+        // the paths of the requests.
+        let connections = Arc::new(AtomicUsize::new(0));
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let server_connections = Arc::clone(&connections);
+        let server_paths = Arc::clone(&paths);
+        let make_service = make_service_fn(move |_| {
+            server_connections.fetch_add(1, Ordering::SeqCst);
+            let paths = Arc::clone(&server_paths);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let path = request.uri().path().to_owned();
+                    let body = if path.starts_with("/time/") {
+                        "[15850559815660696]"
+                    } else {
+                        r#"[1,"Sent","15850559815660697"]"#
+                    };
+                    paths.lock().unwrap().push(path);
+                    async move { Ok::<_, Infallible>(Response::new(Body::from(body))) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        pubnub.connect().await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // Idempotent, and the requests that follow reuse the connection.
+        pubnub.connect().await.unwrap();
+        pubnub
+            .publish("my-channel".parse().unwrap(), object! { "ready" => true })
+            .await
+            .unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec![
+                "/time/0".to_owned(),
+                "/time/0".to_owned(),
+                "/publish/demo/demo/0/my-channel/0/%7B%22ready%22%3Atrue%7D".to_owned(),
+            ]
+        );
+    });
+}
+
+/// A local server answering the time requests, and counting the connections
+/// it accepts.
+fn counting_server() -> (String, Arc<AtomicUsize>) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    let server_connections = Arc::clone(&connections);
+    let make_service = make_service_fn(move |_| {
+        server_connections.fetch_add(1, Ordering::SeqCst);
+        async {
+            Ok::<_, Infallible>(service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("[15850559815660696]")))
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+    (origin, connections)
+}
+
+#[test]
+fn idle_connections_are_evicted() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (origin, connections) = counting_server();
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin.clone())
+            .secure(false)
+            .idle_timeout(Some(Duration::from_millis(100)))
+            .build()
+            .unwrap();
+
+        transport.call(request::Time).await.unwrap();
+        transport.call(request::Time).await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // The idle connection expires.
+        tokio::time::delay_for(Duration::from_millis(300)).await;
+        transport.call(request::Time).await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+        // No idle connections are kept at all.
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .max_idle_connections(0)
+            .build()
+            .unwrap();
+
+        transport.call(request::Time).await.unwrap();
+        transport.call(request::Time).await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+    });
+}
@@ -0,0 +1,61 @@
+use futures_channel::oneshot;
+use futures_util::future::pending;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::{json::JsonValue, Aborted};
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn timed_out_publish_aborts_request() {
+    common::init();
+    common::current_thread_block_on(async {
+        // A server that never responds, and notices when the request is
+        // given up on.
+        let (dropped_tx, dropped_rx) = oneshot::channel::<()>();
+        let dropped_tx = Arc::new(Mutex::new(Some(dropped_tx)));
+        let make_service = make_service_fn(move |_| {
+            let dropped_tx = Arc::clone(&dropped_tx);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                    let dropped_tx = dropped_tx.lock().unwrap().take();
+                    async move {
+                        let _dropped_tx = dropped_tx;
+                        pending::<()>().await;
+                        Ok::<_, Infallible>(Response::new(Body::empty()))
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let publish = pubnub.publish("my-channel".parse().unwrap(), JsonValue::from(42));
+        let result = pubnub
+            .with_timeout(Duration::from_millis(100), publish)
+            .await;
+        assert_eq!(result.unwrap_err(), Aborted::DeadlineExceeded);
+
+        // The connection is closed, so the server drops the request too.
+        let dropped = tokio::time::timeout(Duration::from_secs(5), dropped_rx)
+            .await
+            .expect("request wasn't aborted");
+        assert!(dropped.is_err());
+    });
+}
@@ -0,0 +1,76 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+mod common;
+
+/// A local server answering all the requests with the given status, and
+/// counting them.
+fn server(status: StatusCode) -> (String, Arc<AtomicUsize>) {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let server_hits = Arc::clone(&hits);
+    let make_service = make_service_fn(move |_| {
+        let hits = Arc::clone(&server_hits);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                hits.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let mut response =
+                        Response::new(Body::from(r#"[1,"Sent","15000000000000000"]"#));
+                    *response.status_mut() = status;
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+    (origin, hits)
+}
+
+#[test]
+fn failing_origin_is_failed_over() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (failing_origin, failing_hits) = server(StatusCode::SERVICE_UNAVAILABLE);
+        let (working_origin, working_hits) = server(StatusCode::OK);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origins(&[&failing_origin, &working_origin])
+            .secure(false)
+            .build()
+            .unwrap();
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+        let publish = || pubnub.publish("my-channel".parse().unwrap(), json::JsonValue::from(42));
+
+        for _ in 0..3 {
+            publish().await.unwrap_err();
+        }
+        assert_eq!(failing_hits.load(Ordering::SeqCst), 3);
+
+        // The requests stick to the working origin.
+        for _ in 0..3 {
+            publish().await.unwrap();
+        }
+        assert_eq!(failing_hits.load(Ordering::SeqCst), 3);
+        assert_eq!(working_hits.load(Ordering::SeqCst), 3);
+    });
+}
+
+#[test]
+fn invalid_fallback_origin_is_rejected() {
+    let result = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origins(&["ps.pndsn.com", "not an origin"])
+        .build();
+    assert!(result.is_err());
+}
@@ -0,0 +1,313 @@
+use futures_util::stream::{self, StreamExt};
+use hyper::header::LOCATION;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use pubnub_hyper::core::data::file::File;
+use pubnub_hyper::core::json::object;
+use pubnub_hyper::core::SendFileError;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::{error::Error, Hyper};
+use pubnub_hyper::{Builder, PubNub};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+/// Where the local server serves the uploaded files from.
+const FILES_PATH: &str = "/v1/files/demo/channels/my-channel/files/";
+
+/// The requests received by the local server: the method, the path and
+/// query, and the body.
+type Received = Arc<Mutex<Vec<(String, String, String)>>>;
+
+/// A client of a local server serving the File Sharing API, with the
+/// storage at `/upload` and `/storage` on the same server. The uploads fail
+/// if `reject_uploads` is set.
+fn pubnub(reject_uploads: bool) -> (PubNub, Received) {
+    let received: Received = Arc::default();
+    let origin = Arc::new(Mutex::new(String::new()));
+    let server_received = Arc::clone(&received);
+    let server_origin = Arc::clone(&origin);
+    let make_service = make_service_fn(move |_| {
+        let received = Arc::clone(&server_received);
+        let origin = Arc::clone(&server_origin);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let received = Arc::clone(&received);
+                let origin = origin.lock().unwrap().clone();
+                async move {
+                    let method = request.method().to_string();
+                    let path = request.uri().path().to_owned();
+                    let path_and_query = request.uri().to_string();
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    let body = String::from_utf8_lossy(&body).into_owned();
+                    received
+                        .lock()
+                        .unwrap()
+                        .push((method, path_and_query, body));
+
+                    let response = if path.ends_with("/generate-upload-url") {
+                        Response::new(Body::from(format!(
+                            r#"{{"status":200,"data":{{"id":"0-1","name":"cat.jpg"}},"file_upload_request":{{"url":"http://{}/upload","method":"POST","form_fields":[{{"key":"key","value":"sub/0-1/cat.jpg"}}]}}}}"#,
+                            origin
+                        )))
+                    } else if path == "/upload" && reject_uploads {
+                        let mut response = Response::new(Body::from("<Error>AccessDenied</Error>"));
+                        *response.status_mut() = StatusCode::FORBIDDEN;
+                        response
+                    } else if path == "/upload" {
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = StatusCode::NO_CONTENT;
+                        response
+                    } else if path.ends_with("/files") {
+                        Response::new(Body::from(
+                            r#"{"status":200,"data":[{"name":"cat.jpg","id":"0-1","size":4,"created":"2020-07-26T13:42:06Z"}],"count":1}"#,
+                        ))
+                    } else if path.starts_with(FILES_PATH) {
+                        let file = &path[FILES_PATH.len()..];
+                        let mut response = Response::new(Body::empty());
+                        *response.status_mut() = StatusCode::TEMPORARY_REDIRECT;
+                        response.headers_mut().insert(
+                            LOCATION,
+                            format!("http://{}/storage/{}", origin, file)
+                                .parse()
+                                .unwrap(),
+                        );
+                        response
+                    } else if path.ends_with("/big.bin") {
+                        Response::new(Body::from(vec![0; 64 * 1024]))
+                    } else if path.ends_with("/chunked.bin") {
+                        // No `Content-Length`, so the size is only known as
+                        // the chunks come.
+                        let chunks = (0..16).map(|_| Ok::<_, Infallible>(vec![0; 4 * 1024]));
+                        Response::new(Body::wrap_stream(stream::iter(chunks)))
+                    } else if path.starts_with("/storage/") {
+                        Response::new(Body::from("meow"))
+                    } else {
+                        Response::new(Body::from(r#"[1,"Sent","15850559815660697"]"#))
+                    };
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    *origin.lock().unwrap() = server.local_addr().to_string();
+    let transport = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin.lock().unwrap().clone())
+        .secure(false)
+        .uuid("me")
+        .max_response_bytes(16 * 1024)
+        .build()
+        .unwrap();
+    tokio::spawn(server);
+
+    (
+        Builder::with_components(transport, TokioGlobal).build(),
+        received,
+    )
+}
+
+#[test]
+fn send_file_uploads_then_publishes() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = pubnub(false);
+
+        let sent = pubnub
+            .send_file(
+                "my-channel".parse().unwrap(),
+                "cat.jpg".to_owned(),
+                b"meow".to_vec(),
+                Some(object! { "caption" => "Hi" }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            sent.file,
+            File {
+                id: "0-1".to_owned(),
+                name: "cat.jpg".to_owned(),
+            }
+        );
+        assert_eq!(sent.timetoken.t, 15_850_559_815_660_697);
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+
+        let (method, path_and_query, body) = &received[0];
+        assert_eq!(method, "POST");
+        assert!(
+            path_and_query.starts_with(
+                "/v1/files/demo/channels/my-channel/generate-upload-url?uuid=me&requestid="
+            ),
+            "{}",
+            path_and_query
+        );
+        assert_eq!(body, r#"{"name":"cat.jpg"}"#);
+
+        // The form fields come first, and the file last.
+        let (method, path_and_query, body) = &received[1];
+        assert_eq!(method, "POST");
+        assert_eq!(path_and_query, "/upload");
+        let key = body.find("sub/0-1/cat.jpg").unwrap();
+        let file = body.find("filename=\"cat.jpg\"").unwrap();
+        assert!(key < file, "{}", body);
+        assert!(body.contains("\r\n\r\nmeow\r\n"), "{}", body);
+
+        let (method, path_and_query, _) = &received[2];
+        assert_eq!(method, "GET");
+        assert!(
+            path_and_query.starts_with(
+                "/v1/files/publish-file/demo/demo/0/my-channel/0/%7B%22message%22%3A%7B%22caption%22%3A%22Hi%22%7D%2C%22file%22%3A%7B%22id%22%3A%220-1%22%2C%22name%22%3A%22cat.jpg%22%7D%7D?uuid=me&requestid="
+            ),
+            "{}",
+            path_and_query
+        );
+    });
+}
+
+#[test]
+fn send_file_reports_the_rejected_upload() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = pubnub(true);
+
+        let error = pubnub
+            .send_file(
+                "my-channel".parse().unwrap(),
+                "cat.jpg".to_owned(),
+                b"meow".to_vec(),
+                None,
+            )
+            .await
+            .unwrap_err();
+        match error {
+            SendFileError::Upload(error) => match error.inner() {
+                Error::FileUpload { status, message } => {
+                    assert_eq!(*status, StatusCode::FORBIDDEN);
+                    assert_eq!(message, "<Error>AccessDenied</Error>");
+                }
+                other => panic!("unexpected error: {:?}", other),
+            },
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // Nothing is published.
+        assert_eq!(received.lock().unwrap().len(), 2);
+    });
+}
+
+#[test]
+fn list_then_download_files() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = pubnub(false);
+        let channel = "my-channel".parse().unwrap();
+
+        let files = pubnub.list_files(&channel).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].size, 4);
+
+        let file = files.into_iter().next().unwrap();
+        let url = pubnub
+            .get_file_url(channel.clone(), file.id.clone(), file.name.clone())
+            .await
+            .unwrap();
+        assert!(
+            url.contains("/v1/files/demo/channels/my-channel/files/0-1/cat.jpg?uuid=me"),
+            "{}",
+            url
+        );
+
+        // Follows the redirect to the storage.
+        let data = pubnub
+            .download_file(channel, file.id, file.name)
+            .await
+            .unwrap();
+        assert_eq!(data, b"meow");
+        let paths: Vec<_> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, path_and_query, _)| path_and_query.clone())
+            .collect();
+        assert_eq!(paths.last().unwrap(), "/storage/0-1/cat.jpg");
+    });
+}
+
+#[test]
+fn download_file_stops_at_the_limit() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, _) = pubnub(false);
+
+        let error = pubnub
+            .download_file(
+                "my-channel".parse().unwrap(),
+                "0-2".to_owned(),
+                "big.bin".to_owned(),
+            )
+            .await
+            .unwrap_err();
+        match error.inner() {
+            Error::ResponseTooLarge(limit) => assert_eq!(*limit, 16 * 1024),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    });
+}
+
+#[test]
+fn download_file_stream_yields_the_chunks() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, _) = pubnub(false);
+
+        let stream = pubnub
+            .download_file_stream(
+                "my-channel".parse().unwrap(),
+                "0-1".to_owned(),
+                "cat.jpg".to_owned(),
+            )
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+
+        let data: Vec<u8> = chunks
+            .into_iter()
+            .flat_map(|chunk| chunk.unwrap())
+            .collect();
+        assert_eq!(data, b"meow");
+    });
+}
+
+#[test]
+fn download_file_stream_stops_at_the_limit() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, _) = pubnub(false);
+
+        let stream = pubnub
+            .download_file_stream(
+                "my-channel".parse().unwrap(),
+                "0-3".to_owned(),
+                "chunked.bin".to_owned(),
+            )
+            .await
+            .unwrap();
+        let mut chunks: Vec<_> = stream.collect().await;
+
+        // The chunks up to the limit are yielded, then the error ends the
+        // stream.
+        let error = chunks.pop().unwrap().unwrap_err();
+        match error.inner() {
+            Error::ResponseTooLarge(limit) => assert_eq!(*limit, 16 * 1024),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert!(error.request_id().is_some());
+        let received: usize = chunks.into_iter().map(|chunk| chunk.unwrap().len()).sum();
+        assert!(received <= 16 * 1024, "{}", received);
+    });
+}
@@ -1,5 +1,5 @@
 use log::info;
-use pubnub_hyper::core::data::{channel, request};
+use pubnub_hyper::core::data::{channel, pubsub, request};
 use pubnub_hyper::runtime::tokio_global::TokioGlobal;
 use pubnub_hyper::transport::hyper::Hyper;
 use pubnub_hyper::Builder;
@@ -54,6 +54,9 @@ fn get_history() {
                     channel: test_channel.clone(),
                     payload: test_payload.clone(),
                     meta: Some(test_metadata.clone()),
+                    timetoken_override: None,
+                    replicate: true,
+                    method: pubsub::PublishMethod::Get,
                 })
                 .await
                 .unwrap();
@@ -136,6 +139,9 @@ fn delete_history() {
                     channel: test_channel.clone(),
                     payload: test_payload.clone(),
                     meta: None,
+                    timetoken_override: None,
+                    replicate: true,
+                    method: pubsub::PublishMethod::Get,
                 })
                 .await
                 .unwrap();
@@ -220,6 +226,9 @@ fn message_counts() {
                     channel: test_channel.clone(),
                     payload: test_payload.clone(),
                     meta: None,
+                    timetoken_override: None,
+                    replicate: true,
+                    method: pubsub::PublishMethod::Get,
                 })
                 .await
                 .unwrap();
@@ -0,0 +1,43 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+
+mod common;
+
+#[test]
+fn publish_over_plain_http() {
+    common::init();
+    common::current_thread_block_on(async {
+        // A local mock server, speaking plain HTTP.
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|request: Request<Body>| async move {
+                assert!(request.uri().path().starts_with("/publish/demo/demo/"));
+                Ok::<_, Infallible>(Response::new(Body::from(
+                    r#"[1,"Sent","15000000000000000"]"#,
+                )))
+            }))
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        // The default HTTP client is used.
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let timetoken = pubnub
+            .publish("my-channel".parse().unwrap(), json::JsonValue::from(42))
+            .await
+            .unwrap();
+        assert_eq!(timetoken.t, 15_000_000_000_000_000);
+    });
+}
@@ -0,0 +1,128 @@
+use hyper::header::HeaderValue;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::data::{pubsub::PublishMethod, request};
+use pubnub_hyper::core::json::object;
+use pubnub_hyper::core::TransportService;
+use pubnub_hyper::transport::hyper::{Hyper, Middleware, Outcome};
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+/// Counts the responses, and fakes the time ones.
+#[derive(Default)]
+struct FakeTime {
+    responses: AtomicUsize,
+}
+
+impl Middleware for FakeTime {
+    fn on_request(&self, request: Request<Body>) -> Outcome {
+        if request.uri().path().starts_with("/time/") {
+            return Outcome::Respond(Response::new(Body::from("[15850559815660696]")));
+        }
+        Outcome::Send(request)
+    }
+
+    fn on_response(&self, _response: &Response<Body>) {
+        self.responses.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn middleware_runs_for_every_endpoint() {
+    common::init();
+    common::current_thread_block_on(async {
+        // A local server recording the tracing header of the requests.
+        let traces = Arc::new(Mutex::new(Vec::new()));
+        let server_traces = Arc::clone(&traces);
+        let make_service = make_service_fn(move |_| {
+            let traces = Arc::clone(&server_traces);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let path = request.uri().path().to_owned();
+                    let body = if path.starts_with("/time/") {
+                        "[15850559815660696]"
+                    } else {
+                        r#"[1,"Sent","15850559815660697"]"#
+                    };
+                    let trace = request.headers().get("traceparent").cloned();
+                    traces.lock().unwrap().push(trace);
+                    async move { Ok::<_, Infallible>(Response::new(Body::from(body))) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .with_middleware(|mut request: Request<Body>| {
+                request
+                    .headers_mut()
+                    .insert("traceparent", HeaderValue::from_static("00-abc-def-01"));
+                request
+            })
+            .build()
+            .unwrap();
+
+        transport.call(request::Time).await.unwrap();
+        transport
+            .call(request::Publish {
+                channel: "test_channel".parse().unwrap(),
+                payload: object! { "text" => "hi" },
+                meta: None,
+                timetoken_override: None,
+                replicate: true,
+                method: PublishMethod::Get,
+            })
+            .await
+            .unwrap();
+
+        let trace = Some(HeaderValue::from_static("00-abc-def-01"));
+        assert_eq!(*traces.lock().unwrap(), vec![trace.clone(), trace]);
+    });
+}
+
+#[test]
+fn middleware_short_circuits_with_synthetic_response() {
+    common::init();
+    common::current_thread_block_on(async {
+        let fake = Arc::new(FakeTime::default());
+        let forwarded = Arc::clone(&fake);
+        // Never reached, the origin doesn't resolve.
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin("pubnub.invalid")
+            .secure(false)
+            .with_middleware(ForwardTo(forwarded))
+            .with_middleware(|_: Request<Body>| -> Request<Body> {
+                panic!("the middleware after the short-circuit is skipped")
+            })
+            .build()
+            .unwrap();
+
+        let timetoken = transport.call(request::Time).await.unwrap();
+        assert_eq!(timetoken.t, 15_850_559_815_660_696);
+        assert_eq!(fake.responses.load(Ordering::SeqCst), 1);
+    });
+}
+
+/// Shares the middleware with the test.
+struct ForwardTo(Arc<FakeTime>);
+
+impl Middleware for ForwardTo {
+    fn on_request(&self, request: Request<Body>) -> Outcome {
+        self.0.on_request(request)
+    }
+
+    fn on_response(&self, response: &Response<Body>) {
+        self.0.on_response(response);
+    }
+}
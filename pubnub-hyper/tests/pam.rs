@@ -1,8 +1,14 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
 use pubnub_hyper::core::data::{pam, request};
+use pubnub_hyper::core::Endpoint;
 use pubnub_hyper::runtime::tokio_global::TokioGlobal;
-use pubnub_hyper::transport::hyper::Hyper;
-use pubnub_hyper::Builder;
+use pubnub_hyper::transport::hyper::{error::Error, Hyper};
+use pubnub_hyper::{Builder, PubNub};
+use pubnub_util::pam_signature;
 use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
 
 mod common;
 
@@ -43,6 +49,15 @@ fn grant() {
                                 map.insert("groups_b".into(), pam::BitMask::READ);
                                 map
                             },
+                            uuids: {
+                                let mut map = HashMap::new();
+                                map.insert(
+                                    "uuids_a".into(),
+                                    pam::BitMask::GET | pam::BitMask::UPDATE,
+                                );
+                                map.insert("uuids_b".into(), pam::BitMask::GET);
+                                map
+                            },
                             users: {
                                 let mut map = HashMap::new();
                                 map.insert("users_a".into(), pam::BitMask::MANAGE);
@@ -69,6 +84,15 @@ fn grant() {
                                 map.insert("groups_d".into(), pam::BitMask::READ);
                                 map
                             },
+                            uuids: {
+                                let mut map = HashMap::new();
+                                map.insert(
+                                    "uuids_c".into(),
+                                    pam::BitMask::GET | pam::BitMask::UPDATE,
+                                );
+                                map.insert("uuids_d".into(), pam::BitMask::GET);
+                                map
+                            },
                             users: {
                                 let mut map = HashMap::new();
                                 map.insert("users_c".into(), pam::BitMask::MANAGE);
@@ -92,3 +116,202 @@ fn grant() {
         }
     });
 }
+
+/// The requests received by the local server: the method, the path, the
+/// query and the body.
+type Received = Arc<Mutex<Vec<(String, String, String, String)>>>;
+
+/// A client of a local server answering the PAMv3 requests, signing them
+/// with the `my-secret` key. The requests with the `expired-token` are
+/// denied.
+fn local_pubnub(secret_key: Option<&str>) -> (PubNub, Received) {
+    let received: Received = Arc::default();
+    let server_received = Arc::clone(&received);
+    let make_service = make_service_fn(move |_| {
+        let received = Arc::clone(&server_received);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let received = Arc::clone(&received);
+                async move {
+                    let method = request.method().to_string();
+                    let path = request.uri().path().to_owned();
+                    let query = request.uri().query().unwrap_or_default().to_owned();
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    let body = String::from_utf8(body.to_vec()).unwrap();
+                    let (status, response) = if query.contains("auth=expired-token") {
+                        (
+                            StatusCode::FORBIDDEN,
+                            r#"{"message":"Token is expired.","error":true,"service":"Access Manager","status":403}"#,
+                        )
+                    } else if method == "POST" {
+                        (
+                            StatusCode::OK,
+                            r#"{"status":200,"data":{"message":"Success","token":"p0+/="},"service":"Access Manager"}"#,
+                        )
+                    } else {
+                        (
+                            StatusCode::OK,
+                            r#"{"status":200,"data":{},"service":"Access Manager"}"#,
+                        )
+                    };
+                    received.lock().unwrap().push((method, path, query, body));
+                    let mut response = Response::new(Body::from(response));
+                    *response.status_mut() = status;
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let mut builder = Hyper::new();
+    builder
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .auth_key("my-auth-key")
+        .origin(server.local_addr().to_string())
+        .secure(false);
+    if let Some(secret_key) = secret_key {
+        builder.secret_key(secret_key);
+    }
+    let transport = builder.build().unwrap();
+    tokio::spawn(server);
+
+    (
+        Builder::with_components(transport, TokioGlobal).build(),
+        received,
+    )
+}
+
+/// Assert the request carries the signature of its method, path, body and
+/// the rest of the query, including the auth key.
+fn assert_signed(method: &str, path: &str, query: &str, body: &str) {
+    let param = |name: &str| {
+        query
+            .split('&')
+            .find(|pair| pair.starts_with(&format!("{}=", name)))
+            .map(|pair| &pair[name.len() + 1..])
+            .unwrap_or_else(|| panic!("no {} in {}", name, query))
+            .to_owned()
+    };
+    let expected = pam_signature::sign(
+        "my-secret",
+        pam_signature::Request {
+            publish_key: "demo",
+            method,
+            path,
+            query: &format!(
+                "auth={}&requestid={}&timestamp={}",
+                param("auth"),
+                param("requestid"),
+                param("timestamp")
+            ),
+            body,
+        },
+    );
+    assert_eq!(param("signature"), expected);
+}
+
+#[test]
+fn grant_and_revoke_token_are_signed() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = local_pubnub(Some("my-secret"));
+
+        let mut resources = pam::Resources::default();
+        resources
+            .channels
+            .insert("my-channel".to_owned(), pam::BitMask::READ);
+        let mut patterns = pam::Patterns::default();
+        patterns
+            .uuids
+            .insert("^user-.*$".to_owned(), pam::BitMask::GET);
+        let token = pubnub
+            .grant_token(request::Grant {
+                ttl: 15,
+                permissions: pam::Permissions {
+                    resources,
+                    patterns,
+                    meta: json::object! {},
+                },
+            })
+            .await
+            .unwrap();
+        assert_eq!(token, "p0+/=");
+
+        pubnub.revoke_token(&token).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+
+        let (method, path, query, body) = &received[0];
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/v3/pam/demo/grant");
+        let body_json = json::parse(body).unwrap();
+        assert_eq!(body_json["ttl"], 15);
+        assert_eq!(
+            body_json["permissions"]["resources"]["channels"],
+            json::object! { "my-channel" => 1 }
+        );
+        assert_eq!(
+            body_json["permissions"]["patterns"]["uuids"],
+            json::object! { "^user-.*$" => 32 }
+        );
+        assert_signed(method, path, query, body);
+
+        let (method, path, query, body) = &received[1];
+        assert_eq!(method, "DELETE");
+        assert_eq!(path, "/v3/pam/demo/grant/p0%2B%2F%3D");
+        assert_eq!(body, "");
+        assert_signed(method, path, query, body);
+    });
+}
+
+#[test]
+fn grant_token_without_secret_key_is_unsupported() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = local_pubnub(None);
+
+        let error = pubnub
+            .grant_token(request::Grant {
+                ttl: 15,
+                permissions: pam::Permissions {
+                    resources: pam::Resources::default(),
+                    patterns: pam::Patterns::default(),
+                    meta: json::object! {},
+                },
+            })
+            .await
+            .unwrap_err();
+        match error.inner() {
+            Error::Unsupported(Endpoint::AccessManager) => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        assert!(received.lock().unwrap().is_empty());
+    });
+}
+
+#[test]
+fn token_takes_precedence_over_auth_key() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (mut pubnub, received) = local_pubnub(Some("my-secret"));
+        let channel = "my-channel".parse().unwrap();
+
+        pubnub.set_token("expired-token");
+        let error = pubnub
+            .publish(channel, json::object! { "text" => "Hi" })
+            .await
+            .unwrap_err();
+        match error.inner() {
+            Error::AccessDenied { message, .. } => assert_eq!(message, "Token is expired."),
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        let received = received.lock().unwrap();
+        let (_, _, query, _) = &received[0];
+        assert!(query.contains("auth=expired-token"), "{}", query);
+        assert!(!query.contains("my-auth-key"), "{}", query);
+    });
+}
@@ -1,8 +1,6 @@
 use json::JsonValue;
 use log::info;
-use pubnub_hyper::core::data::{
-    channel, presence, pubsub, request, response, timetoken::Timetoken,
-};
+use pubnub_hyper::core::data::{channel, cursor::Cursor, presence, pubsub, request, response};
 use pubnub_hyper::runtime::tokio_global::TokioGlobal;
 use pubnub_hyper::transport::hyper::Hyper;
 use pubnub_hyper::Builder;
@@ -95,8 +93,9 @@ fn here_now_single_channel() {
             let val = pubnub
                 .call(request::Subscribe {
                     to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                    timetoken: Timetoken::default(),
+                    cursor: Cursor::default(),
                     heartbeat: None,
+                    filter_expr: None,
                 })
                 .await;
             assert!(val.is_ok());
@@ -184,8 +183,9 @@ fn global_here_now() {
             let val = pubnub
                 .call(request::Subscribe {
                     to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                    timetoken: Timetoken::default(),
+                    cursor: Cursor::default(),
                     heartbeat: None,
+                    filter_expr: None,
                 })
                 .await;
             assert!(val.is_ok());
@@ -282,8 +282,9 @@ fn where_now() {
             let val = other_pubnub
                 .call(request::Subscribe {
                     to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                    timetoken: Timetoken::default(),
+                    cursor: Cursor::default(),
                     heartbeat: None,
+                    filter_expr: None,
                 })
                 .await;
             assert!(val.is_ok());
@@ -325,8 +326,9 @@ fn heartbeat() {
             let val = pubnub
                 .call(request::Subscribe {
                     to: vec![pubsub::SubscribeTo::Channel(test_channel.clone())],
-                    timetoken: Timetoken::default(),
+                    cursor: Cursor::default(),
                     heartbeat: None,
+                    filter_expr: None,
                 })
                 .await;
             assert!(val.is_ok());
@@ -0,0 +1,106 @@
+use futures_util::future;
+use hyper::header::PROXY_AUTHORIZATION;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server};
+use pubnub_hyper::core::json::object;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpStream;
+
+mod common;
+
+/// The `CONNECT` requests received by the proxy: the target, and the
+/// `Proxy-Authorization` header.
+type Received = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// A local server answering the publishes.
+fn origin() -> String {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|_request: Request<Body>| async {
+            Ok::<_, Infallible>(Response::new(Body::from(
+                r#"[1,"Sent","15850559815660697"]"#,
+            )))
+        }))
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+    origin
+}
+
+/// A local HTTP proxy tunneling the `CONNECT` requests to their target.
+fn proxy() -> (String, Received) {
+    let received: Received = Arc::default();
+    let server_received = Arc::clone(&received);
+    let make_service = make_service_fn(move |_| {
+        let received = Arc::clone(&server_received);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                assert_eq!(request.method(), Method::CONNECT);
+                let target = request.uri().to_string();
+                let authorization = request
+                    .headers()
+                    .get(PROXY_AUTHORIZATION)
+                    .map(|value| value.to_str().unwrap().to_owned());
+                received
+                    .lock()
+                    .unwrap()
+                    .push((target.clone(), authorization));
+
+                tokio::spawn(async move {
+                    let upgraded = request.into_body().on_upgrade().await.unwrap();
+                    let server = TcpStream::connect(target.parse::<SocketAddr>().unwrap())
+                        .await
+                        .unwrap();
+                    let (mut client_rx, mut client_tx) = tokio::io::split(upgraded);
+                    let (mut server_rx, mut server_tx) = tokio::io::split(server);
+                    let _ = future::join(
+                        tokio::io::copy(&mut client_rx, &mut server_tx),
+                        tokio::io::copy(&mut server_rx, &mut client_tx),
+                    )
+                    .await;
+                });
+                async { Ok::<_, Infallible>(Response::new(Body::empty())) }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let addr = server.local_addr().to_string();
+    tokio::spawn(server);
+    (addr, received)
+}
+
+#[test]
+fn requests_are_tunneled_through_the_proxy() {
+    common::init();
+    common::current_thread_block_on(async {
+        let origin = origin();
+        let (proxy, received) = proxy();
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin.clone())
+            .secure(false)
+            .proxy(format!("http://user:pa%40ss@{}", proxy))
+            .build()
+            .unwrap();
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let timetoken = pubnub
+            .publish("my-channel".parse().unwrap(), object! { "test" => "value" })
+            .await
+            .unwrap();
+        assert_eq!(timetoken.t, 15_850_559_815_660_697);
+
+        // The credentials are decoded from the URL, i.e. `user:pa@ss`.
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![(origin, Some("Basic dXNlcjpwYUBzcw==".to_owned()))]
+        );
+    });
+}
@@ -0,0 +1,113 @@
+use hyper::client::HttpConnector;
+use hyper::service::{make_service_fn, service_fn, Service};
+use hyper::{Body, Client, Request, Response, Server, Uri};
+use pubnub_hyper::core::data::channel;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::{BoxedConnector, Hyper};
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+mod common;
+
+/// A connector that counts the connections it opens.
+#[derive(Clone)]
+struct CountingConnector {
+    inner: HttpConnector,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Service<Uri> for CountingConnector {
+    type Response = <HttpConnector as Service<Uri>>::Response;
+    type Error = <HttpConnector as Service<Uri>>::Error;
+    type Future = <HttpConnector as Service<Uri>>::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        self.connections.fetch_add(1, Ordering::SeqCst);
+        self.inner.call(dst)
+    }
+}
+
+#[test]
+fn publish_many_uses_single_connection() {
+    common::init();
+    common::current_thread_block_on(async {
+        // A local server acknowledging the publishes, and recording the
+        // order they came in.
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let server_paths = Arc::clone(&paths);
+        let make_service = make_service_fn(move |_| {
+            let paths = Arc::clone(&server_paths);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let mut paths = paths.lock().unwrap();
+                    paths.push(request.uri().path().to_owned());
+                    let body = format!("[1,\"Sent\",\"{}\"]", 15_000_000 + paths.len());
+                    async move { Ok::<_, Infallible>(Response::new(Body::from(body))) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let connections = Arc::new(AtomicUsize::new(0));
+        let connector = CountingConnector {
+            inner: HttpConnector::new(),
+            connections: Arc::clone(&connections),
+        };
+        let http_client = Client::builder().build(BoxedConnector::new(connector));
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .http_client(http_client)
+            .build()
+            .unwrap();
+        let pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let channel_a: channel::Name = "channel-a".parse().unwrap();
+        let channel_b: channel::Name = "channel-b".parse().unwrap();
+        let items: Vec<_> = (0..10)
+            .map(|n| {
+                let channel = if n % 2 == 0 { &channel_a } else { &channel_b };
+                (channel.clone(), json::JsonValue::from(n))
+            })
+            .collect();
+
+        let timetokens: Vec<_> = pubnub
+            .publish_many(items)
+            .await
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        // The timetokens follow the order of the items.
+        let mut sorted = timetokens.clone();
+        sorted.sort();
+        assert_eq!(timetokens, sorted);
+        let paths = paths.lock().unwrap();
+        for (n, path) in paths.iter().enumerate() {
+            assert!(path.ends_with(&format!("/0/{}", n)), "{}", path);
+        }
+
+        // The whole batch went over a single connection.
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        #[cfg(feature = "metrics")]
+        {
+            let metrics = pubnub.metrics();
+            assert_eq!(metrics.messages_published, 10);
+            assert!(metrics.traffic.bytes_sent > 0);
+            assert!(metrics.traffic.bytes_received > 0);
+        }
+    });
+}
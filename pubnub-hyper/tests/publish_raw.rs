@@ -0,0 +1,98 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::{error::Error, Hyper};
+use pubnub_hyper::{Builder, PubNub};
+use std::convert::Infallible;
+
+mod common;
+
+/// A client of a local server answering all the publishes with the given
+/// status and body.
+fn pubnub(status: StatusCode, body: &'static str) -> PubNub {
+    let make_service = make_service_fn(move |_| async move {
+        Ok::<_, Infallible>(service_fn(move |_: Request<Body>| async move {
+            let mut response = Response::new(Body::from(body));
+            *response.status_mut() = status;
+            Ok::<_, Infallible>(response)
+        }))
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+
+    let transport = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin)
+        .secure(false)
+        .build()
+        .unwrap();
+    Builder::with_components(transport, TokioGlobal).build()
+}
+
+#[test]
+fn publish_raw_returns_server_response() {
+    common::init();
+    common::current_thread_block_on(async {
+        let pubnub = pubnub(StatusCode::OK, r#"[1,"Sent","15000000000000000"]"#);
+
+        let response = pubnub
+            .publish_raw("my-channel".parse().unwrap(), json::JsonValue::from(42))
+            .await
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.message, "Sent");
+        assert_eq!(response.timetoken.t, 15_000_000_000_000_000);
+    });
+}
+
+#[test]
+fn publish_raw_rejection_keeps_status_and_message() {
+    common::init();
+    common::current_thread_block_on(async {
+        let pubnub = pubnub(
+            StatusCode::BAD_REQUEST,
+            r#"[0,"Invalid Key","15000000000000000"]"#,
+        );
+
+        let error = pubnub
+            .publish_raw("my-channel".parse().unwrap(), json::JsonValue::from(42))
+            .await
+            .unwrap_err();
+        match error.inner() {
+            Error::Publish { status, message } => {
+                assert_eq!(*status, StatusCode::BAD_REQUEST);
+                assert_eq!(message, "Invalid Key");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // The same goes for the plain publish.
+        let error = pubnub
+            .publish("my-channel".parse().unwrap(), json::JsonValue::from(42))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid Key"), "{}", error);
+    });
+}
+
+#[test]
+fn publish_raw_failure_code_is_an_error() {
+    common::init();
+    common::current_thread_block_on(async {
+        let pubnub = pubnub(StatusCode::OK, r#"[0,"Message Too Large","0"]"#);
+
+        let error = pubnub
+            .publish_raw("my-channel".parse().unwrap(), json::JsonValue::from(42))
+            .await
+            .unwrap_err();
+        match error.inner() {
+            Error::Publish { status, message } => {
+                assert_eq!(*status, StatusCode::OK);
+                assert_eq!(message, "Message Too Large");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    });
+}
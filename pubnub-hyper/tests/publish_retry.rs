@@ -0,0 +1,112 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use pubnub_hyper::core::json::object;
+use pubnub_hyper::core::RetryPolicy;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::error::Error;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// Start a local server answering the publishes with the `responses` in
+/// order, repeating the last one, and return its address along with the
+/// number of the requests received.
+fn serve(responses: Vec<(StatusCode, &'static str)>) -> (String, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server_requests = Arc::clone(&requests);
+    let make_service = make_service_fn(move |_| {
+        let requests = Arc::clone(&server_requests);
+        let responses = responses.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                let n = requests.fetch_add(1, Ordering::SeqCst);
+                let (status, body) = responses[n.min(responses.len() - 1)];
+                let response = Response::builder()
+                    .status(status)
+                    .body(Body::from(body))
+                    .unwrap();
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+    (origin, requests)
+}
+
+async fn publish(origin: String) -> Result<(), Error> {
+    let transport = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin)
+        .secure(false)
+        .build()
+        .unwrap();
+    let pubnub = Builder::with_components(transport, TokioGlobal)
+        .publish_retry(RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(10),
+        })
+        .build();
+
+    pubnub
+        .publish("test_channel".parse().unwrap(), object! { "text" => "hi" })
+        .await
+        .map(|_| ())
+}
+
+#[test]
+fn server_errors_are_retried() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (origin, requests) = serve(vec![
+            (StatusCode::SERVICE_UNAVAILABLE, ""),
+            (StatusCode::OK, r#"[1,"Sent","15850559815660697"]"#),
+        ]);
+
+        publish(origin).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    });
+}
+
+#[test]
+fn bad_requests_are_not_retried() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (origin, requests) = serve(vec![(StatusCode::BAD_REQUEST, r#"[0,"Invalid JSON"]"#)]);
+
+        let error = publish(origin).await.unwrap_err();
+        match error.inner() {
+            Error::Publish { status, .. } => assert_eq!(*status, StatusCode::BAD_REQUEST),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[test]
+fn access_denied_is_not_retried() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (origin, requests) = serve(vec![(
+            StatusCode::FORBIDDEN,
+            r#"{"message":"Forbidden","payload":{"channels":["test_channel"]},"error":true,"service":"Access Manager","status":403}"#,
+        )]);
+
+        let error = publish(origin).await.unwrap_err();
+        match error.inner() {
+            Error::AccessDenied { channels, .. } => {
+                assert_eq!(*channels, vec!["test_channel".to_owned()])
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    });
+}
@@ -0,0 +1,99 @@
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::{error::Error, Hyper};
+use pubnub_hyper::{Builder, PubNub};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+mod common;
+
+/// The parts of a request received by the local server.
+type Received = Arc<Mutex<Vec<(Method, String, Vec<u8>)>>>;
+
+/// A client of a local server recording the requests, and rejecting the
+/// URIs longer than `max_uri` the way a proxy would.
+fn pubnub(max_uri: usize) -> (PubNub, Received) {
+    let received = Received::default();
+    let server_received = Arc::clone(&received);
+    let make_service = make_service_fn(move |_| {
+        let received = Arc::clone(&server_received);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let received = Arc::clone(&received);
+                async move {
+                    let method = request.method().clone();
+                    let uri = request.uri().to_string();
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    let too_long = uri.len() > max_uri;
+                    received.lock().unwrap().push((method, uri, body.to_vec()));
+
+                    let mut response =
+                        Response::new(Body::from(r#"[1,"Sent","15000000000000000"]"#));
+                    if too_long {
+                        *response.body_mut() = Body::from("URI Too Long");
+                        *response.status_mut() = StatusCode::URI_TOO_LONG;
+                    }
+                    Ok::<_, Infallible>(response)
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+
+    let transport = Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin)
+        .secure(false)
+        .build()
+        .unwrap();
+    let pubnub = Builder::with_components(transport, TokioGlobal).build();
+    (pubnub, received)
+}
+
+#[test]
+fn publish_via_post_sends_message_in_body() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = pubnub(1024);
+        let message = json::object! { "text" => "x".repeat(4096) };
+
+        let timetoken = pubnub
+            .publish_via_post("my-channel".parse().unwrap(), message.clone())
+            .await
+            .unwrap();
+        assert_eq!(timetoken.t, 15_000_000_000_000_000);
+
+        let received = received.lock().unwrap();
+        let (method, uri, body) = &received[0];
+        assert_eq!(*method, Method::POST);
+        assert!(
+            uri.starts_with("/publish/demo/demo/0/my-channel/0?"),
+            "{}",
+            uri
+        );
+        assert_eq!(body, message.dump().as_bytes());
+    });
+}
+
+#[test]
+fn publish_too_long_for_get_is_detected() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (pubnub, received) = pubnub(1024);
+        let message = json::object! { "text" => "x".repeat(4096) };
+
+        let error = pubnub
+            .publish("my-channel".parse().unwrap(), message)
+            .await
+            .unwrap_err();
+        match error.inner() {
+            Error::UriTooLong => {}
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(received.lock().unwrap()[0].0, Method::GET);
+    });
+}
@@ -72,7 +72,7 @@ fn pubnub_subscribe_ok() {
 
         {
             // Create a subscription
-            let mut subscription = pubnub.subscribe(channel.clone()).await;
+            let mut subscription = pubnub.subscribe(channel.clone()).await.unwrap();
 
             // Send a message to it
             let message = JsonValue::String("Hello, world!".to_string());
@@ -127,18 +127,18 @@ fn pubnub_subscribeloop_drop() {
 
         {
             // Create a bunch of subscriptions
-            let _sub0 = pubnub.subscribe(channel.clone()).await;
-            let _sub1 = pubnub.subscribe(channel.clone()).await;
-            let _sub2 = pubnub.subscribe(channel.clone()).await;
-            let _sub3 = pubnub.subscribe(channel.clone()).await;
-            let _sub4 = pubnub.subscribe(channel.clone()).await;
-            let _sub5 = pubnub.subscribe(channel.clone()).await;
-            let _sub6 = pubnub.subscribe(channel.clone()).await;
-            let _sub7 = pubnub.subscribe(channel.clone()).await;
-            let _sub8 = pubnub.subscribe(channel.clone()).await;
-            let _sub9 = pubnub.subscribe(channel.clone()).await;
-            let _sub10 = pubnub.subscribe(channel.clone()).await;
-            let _sub11 = pubnub.subscribe(channel.clone()).await;
+            let _sub0 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub1 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub2 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub3 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub4 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub5 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub6 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub7 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub8 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub9 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub10 = pubnub.subscribe(channel.clone()).await.unwrap();
+            let _sub11 = pubnub.subscribe(channel.clone()).await.unwrap();
 
             // HA-HAAAA! Now we drop 12 at once and see if the `Drop` impl hangs!
         }
@@ -173,12 +173,12 @@ fn pubnub_subscribeloop_recreate() {
 
         // Create two subscribe loops, dropping each
         {
-            let _ = pubnub.subscribe(channel.clone()).await;
+            let _ = pubnub.subscribe(channel.clone()).await.unwrap();
         }
         assert!(subscribe_loop_exit_rx.next().await.is_some());
 
         {
-            let _ = pubnub.subscribe(channel).await;
+            let _ = pubnub.subscribe(channel).await.unwrap();
         }
         assert!(subscribe_loop_exit_rx.next().await.is_some());
     });
@@ -207,15 +207,35 @@ fn pubnub_subscribe_clone_ok() {
             .runtime(TokioGlobal)
             .subscribe_loop_exit_tx(subscribe_loop_exit_tx)
             .build();
-        streams.push(pubnub1.subscribe("channel1".parse().unwrap()).await);
+        streams.push(
+            pubnub1
+                .subscribe("channel1".parse().unwrap())
+                .await
+                .unwrap(),
+        );
 
         // Create a cloned client and immediate subscribe
         let mut pubnub2 = pubnub1.clone();
-        streams.push(pubnub2.subscribe("channel2".parse().unwrap()).await);
+        streams.push(
+            pubnub2
+                .subscribe("channel2".parse().unwrap())
+                .await
+                .unwrap(),
+        );
 
         // Subscribe to two more channels from each clone
-        streams.push(pubnub1.subscribe("channel3".parse().unwrap()).await);
-        streams.push(pubnub2.subscribe("channel4".parse().unwrap()).await);
+        streams.push(
+            pubnub1
+                .subscribe("channel3".parse().unwrap())
+                .await
+                .unwrap(),
+        );
+        streams.push(
+            pubnub2
+                .subscribe("channel4".parse().unwrap())
+                .await
+                .unwrap(),
+        );
 
         // Create a list of publish futures, mix-and-match clients
         let mut publishers = vec![
@@ -296,10 +316,16 @@ fn pubnub_subscribe_clones_share_loop() {
         let mut pubnub2 = pubnub1.clone();
 
         // Subscribe to spawn the subscribe loop on the original.
-        let sub1 = pubnub1.subscribe("channel1".parse().unwrap()).await;
+        let sub1 = pubnub1
+            .subscribe("channel1".parse().unwrap())
+            .await
+            .unwrap();
 
         // Subscribe to potentially spawn the subscribe loop on the clone.
-        let sub2 = pubnub2.subscribe("channel2".parse().unwrap()).await;
+        let sub2 = pubnub2
+            .subscribe("channel2".parse().unwrap())
+            .await
+            .unwrap();
 
         // Dropping `sub1` should not exit the loop if it's shared, but will
         // if the loop is not shared it would exit.
@@ -0,0 +1,143 @@
+use futures_util::future::{ready, Ready};
+use hyper::client::connect::dns::Name;
+use hyper::service::{make_service_fn, service_fn, Service};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::data::request;
+use pubnub_hyper::core::TransportService;
+use pubnub_hyper::transport::hyper::{BoxedResolver, Hyper};
+use std::convert::Infallible;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::vec;
+
+mod common;
+
+/// Resolves every host to the same address, recording the names.
+#[derive(Clone)]
+struct Pinned {
+    addr: IpAddr,
+    names: Arc<Mutex<Vec<String>>>,
+}
+
+impl Service<Name> for Pinned {
+    type Response = vec::IntoIter<IpAddr>;
+    type Error = io::Error;
+    type Future = Ready<io::Result<Self::Response>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        self.names.lock().unwrap().push(name.as_str().to_owned());
+        ready(Ok(vec![self.addr].into_iter()))
+    }
+}
+
+#[test]
+fn origin_is_resolved_with_custom_resolver() {
+    common::init();
+    common::current_thread_block_on(async {
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|_: Request<Body>| async {
+                Ok::<_, Infallible>(Response::new(Body::from("[15850559815660696]")))
+            }))
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let port = server.local_addr().port();
+        tokio::spawn(server);
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(format!("pubnub.test:{}", port))
+            .secure(false)
+            .resolver(BoxedResolver::new(Pinned {
+                addr: IpAddr::V4(Ipv4Addr::LOCALHOST),
+                names: Arc::clone(&names),
+            }))
+            .build()
+            .unwrap();
+
+        transport.call(request::Time).await.unwrap();
+        assert_eq!(*names.lock().unwrap(), vec!["pubnub.test".to_owned()]);
+    });
+}
+
+#[test]
+fn connect_timeout_bounds_unreachable_origin() {
+    common::init();
+    common::current_thread_block_on(async {
+        // TEST-NET-1, never routed: the connection either fails right away
+        // or hangs until the timeout.
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin("pubnub.test")
+            .secure(false)
+            .connect_timeout(Duration::from_millis(200))
+            .resolver(BoxedResolver::new(Pinned {
+                addr: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                names: Arc::default(),
+            }))
+            .build()
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(10), transport.call(request::Time))
+            .await
+            .expect("connection attempt is not bounded");
+        assert!(result.is_err());
+    });
+}
+
+#[test]
+fn pinned_addr_bypasses_resolution_and_keeps_host() {
+    common::init();
+    common::current_thread_block_on(async {
+        let hosts = Arc::new(Mutex::new(Vec::new()));
+        let server_hosts = Arc::clone(&hosts);
+        let make_service = make_service_fn(move |_| {
+            let hosts = Arc::clone(&server_hosts);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let host = request.headers()["host"].to_str().unwrap().to_owned();
+                    hosts.lock().unwrap().push(host);
+                    async { Ok::<_, Infallible>(Response::new(Body::from("[15850559815660696]"))) }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+
+        // A port nobody listens on, refusing the connection.
+        let refused = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let names = Arc::new(Mutex::new(Vec::new()));
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin("pubnub.test")
+            .secure(false)
+            .resolver(BoxedResolver::new(Pinned {
+                addr: IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                names: Arc::clone(&names),
+            }))
+            .pin_addr("pubnub.test", refused)
+            .pin_addr("pubnub.test", addr)
+            .build()
+            .unwrap();
+
+        // The pinned addresses are tried in order, without resolving.
+        transport.call(request::Time).await.unwrap();
+        assert!(names.lock().unwrap().is_empty());
+        assert_eq!(*hosts.lock().unwrap(), vec!["pubnub.test".to_owned()]);
+    });
+}
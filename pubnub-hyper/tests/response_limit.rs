@@ -0,0 +1,115 @@
+use hyper::body::Bytes;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::core::data::{channel, cursor::Cursor, request};
+use pubnub_hyper::core::TransportService;
+use pubnub_hyper::transport::hyper::{error::Error, Hyper};
+use std::convert::Infallible;
+use std::str::FromStr;
+
+mod common;
+
+/// A transport of a local server answering all the requests with the
+/// `prefix` followed by an endless stream of whitespace, or with the
+/// `prefix` alone if `endless` is false.
+fn transport(prefix: impl Into<Bytes>, endless: bool) -> Hyper {
+    let prefix = prefix.into();
+    let make_service = make_service_fn(move |_| {
+        let prefix = prefix.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                let prefix = prefix.clone();
+                async move { Ok::<_, Infallible>(respond(prefix, endless)) }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+
+    Hyper::new()
+        .publish_key("demo")
+        .subscribe_key("demo")
+        .origin(origin)
+        .secure(false)
+        .max_response_bytes(4096)
+        .max_subscribe_response_bytes(16 * 1024)
+        .build()
+        .unwrap()
+}
+
+fn respond(prefix: Bytes, endless: bool) -> Response<Body> {
+    if !endless {
+        return Response::new(Body::from(prefix));
+    }
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut chunk = prefix;
+        // Stops once the client drops the response.
+        while sender.send_data(chunk).await.is_ok() {
+            chunk = Bytes::from(vec![b' '; 1024]);
+        }
+    });
+    Response::new(body)
+}
+
+fn get_history() -> request::GetHistory {
+    request::GetHistory {
+        channels: vec!["my-channel".parse().unwrap()],
+        max: None,
+        reverse: None,
+        start: None,
+        end: None,
+        include_metadata: None,
+    }
+}
+
+fn subscribe() -> request::Subscribe {
+    request::Subscribe {
+        to: vec![channel::Name::from_str("my-channel").unwrap().into()],
+        cursor: Cursor::default(),
+        heartbeat: None,
+        filter_expr: None,
+    }
+}
+
+fn assert_too_large(error: &Error, limit: usize) {
+    match error.inner() {
+        Error::ResponseTooLarge(exceeded) => assert_eq!(*exceeded, limit),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn response_with_large_content_length_is_rejected() {
+    common::init();
+    common::current_thread_block_on(async {
+        let body = format!(r#"{{"channels":{{}},"x":"{}"}}"#, "x".repeat(8192));
+        let transport = transport(body, false);
+
+        let error = transport.call(get_history()).await.unwrap_err();
+        assert_too_large(&error, 4096);
+    });
+}
+
+#[test]
+fn endless_response_is_aborted() {
+    common::init();
+    common::current_thread_block_on(async {
+        let transport = transport(r#"{"channels":{}"#, true);
+
+        let error = transport.call(get_history()).await.unwrap_err();
+        assert_too_large(&error, 4096);
+    });
+}
+
+#[test]
+fn endless_subscribe_response_is_aborted_at_its_own_limit() {
+    common::init();
+    common::current_thread_block_on(async {
+        let transport = transport(r#"{"t":{"t":"1","r":1},"m":["#, true);
+
+        let error = transport.call(subscribe()).await.unwrap_err();
+        assert_too_large(&error, 16 * 1024);
+    });
+}
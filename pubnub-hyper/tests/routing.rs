@@ -0,0 +1,95 @@
+use futures_util::future;
+use futures_util::stream::StreamExt;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use percent_encoding::percent_decode_str;
+use pubnub_hyper::core::data::channel;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+
+mod common;
+
+/// Respond to the subscribe requests with a single message, sent to the
+/// channel from the request path.
+async fn subscribe_service(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let uri = request.uri();
+    let channel = uri.path().split('/').nth(4).unwrap();
+    let channel = percent_decode_str(channel).decode_utf8().unwrap();
+
+    let body = if uri.query().unwrap().contains("tt=0") {
+        r#"{"t":{"t":"15000000000000000","r":1},"m":[]}"#.to_owned()
+    } else if uri.query().unwrap().contains("tt=15000000000000000") {
+        let mut body = json::parse(
+            r#"{"t":{"t":"15000000000000001","r":1},"m":[{"f":0,"p":{"t":"15000000000000001","r":1},"k":"demo","d":"hello"}]}"#,
+        )
+        .unwrap();
+        body["m"][0]["c"] = channel.as_ref().into();
+        body.dump()
+    } else {
+        // Keep the long-poll hanging.
+        return future::pending().await;
+    };
+    Ok(Response::new(Body::from(body)))
+}
+
+#[test]
+fn subscribe_routes_messages_to_channels_needing_encoding() {
+    common::init();
+    common::current_thread_block_on(async {
+        let make_service =
+            make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(subscribe_service)) });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let mut pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        let channel: channel::Name = "chan nel".parse().unwrap();
+        let mut subscription = pubnub.subscribe(channel.clone()).await.unwrap();
+
+        let message = subscription.next().await.unwrap();
+        assert_eq!(message.channel, channel);
+        assert_eq!(message.json, "hello");
+        assert_eq!(message.raw(), Some(&b"\"hello\""[..]));
+    });
+}
+
+#[test]
+fn subscribe_from_spawned_task() {
+    common::init();
+    common::current_thread_block_on(async {
+        let make_service =
+            make_service_fn(|_| async { Ok::<_, Infallible>(service_fn(subscribe_service)) });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let mut pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        // The subscribe setup doesn't block the thread, so it works from
+        // within a task on the single-threaded runtime.
+        let message = tokio::spawn(async move {
+            let mut subscription = pubnub.subscribe("channel".parse().unwrap()).await.unwrap();
+            subscription.next().await.unwrap()
+        })
+        .await
+        .unwrap();
+        assert_eq!(message.json, "hello");
+    });
+}
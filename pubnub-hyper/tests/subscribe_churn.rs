@@ -0,0 +1,127 @@
+use futures_util::future;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+
+/// The connections the server has open, and the most it had at once.
+#[derive(Debug, Default)]
+struct Connections {
+    open: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// Held by the service of a connection, which is dropped once the
+/// connection is closed.
+struct ConnectionGuard(Arc<Connections>);
+
+impl ConnectionGuard {
+    fn new(connections: Arc<Connections>) -> Self {
+        let open = connections.open.fetch_add(1, Ordering::SeqCst) + 1;
+        // The test runs on a single thread, so there's no race here.
+        if open > connections.peak.load(Ordering::SeqCst) {
+            connections.peak.store(open, Ordering::SeqCst);
+        }
+        Self(connections)
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.open.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Start a local server answering the initial subscribe request, and
+/// keeping the following long-polls hanging. Returns its address along with
+/// its connections and the number of the requests received.
+fn serve() -> (String, Arc<Connections>, Arc<AtomicUsize>) {
+    let connections = Arc::new(Connections::default());
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server_connections = Arc::clone(&connections);
+    let server_requests = Arc::clone(&requests);
+    let make_service = make_service_fn(move |_| {
+        let guard = Arc::new(ConnectionGuard::new(Arc::clone(&server_connections)));
+        let requests = Arc::clone(&server_requests);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                let _guard = Arc::clone(&guard);
+                requests.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if request.uri().query().unwrap().contains("tt=0") {
+                        let body = r#"{"t":{"t":"15000000000000000","r":1},"m":[]}"#;
+                        Ok::<_, Infallible>(Response::new(Body::from(body)))
+                    } else {
+                        // Keep the long-poll hanging.
+                        future::pending().await
+                    }
+                }
+            }))
+        }
+    });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let origin = server.local_addr().to_string();
+    tokio::spawn(server);
+    (origin, connections, requests)
+}
+
+/// Wait for the `condition` to hold, for up to a few seconds.
+async fn wait_for(condition: impl Fn() -> bool) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !condition() {
+            tokio::time::delay_for(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .expect("the condition never held");
+}
+
+#[test]
+fn subscribe_churn_does_not_leak_connections() {
+    common::init();
+    common::current_thread_block_on(async {
+        let (origin, connections, requests) = serve();
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("demo")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let mut pubnub = Builder::with_components(transport, TokioGlobal).build();
+
+        // Keeps the subscribe loop running throughout.
+        let _subscription = pubnub.subscribe("base".parse().unwrap()).await.unwrap();
+        wait_for(|| requests.load(Ordering::SeqCst) == 2).await;
+
+        // Every add and drop restarts the loop, abandoning the long-poll in
+        // flight for a new one.
+        for i in 0..100 {
+            let seen = requests.load(Ordering::SeqCst);
+            let subscription = pubnub
+                .subscribe(format!("channel-{}", i).parse().unwrap())
+                .await
+                .unwrap();
+            wait_for(|| requests.load(Ordering::SeqCst) > seen).await;
+
+            let seen = requests.load(Ordering::SeqCst);
+            drop(subscription);
+            wait_for(|| requests.load(Ordering::SeqCst) > seen).await;
+        }
+
+        // The abandoned long-polls closed their connections, only the one of
+        // the long-poll in flight is left.
+        wait_for(|| connections.open.load(Ordering::SeqCst) == 1).await;
+        assert!(requests.load(Ordering::SeqCst) > 200);
+        let peak = connections.peak.load(Ordering::SeqCst);
+        assert!(peak <= 5, "up to {} connections were open at once", peak);
+    });
+}
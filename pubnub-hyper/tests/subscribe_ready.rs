@@ -0,0 +1,62 @@
+use futures_util::stream::StreamExt;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use pubnub_hyper::core::StatusEvent;
+use pubnub_hyper::runtime::tokio_global::TokioGlobal;
+use pubnub_hyper::transport::hyper::Hyper;
+use pubnub_hyper::Builder;
+use std::convert::Infallible;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn invalid_subscribe_key_ends_subscription() {
+    common::init();
+    common::current_thread_block_on(async {
+        // A server rejecting the subscribe key, like the PubNub network does.
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|_: Request<Body>| async {
+                let response = Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from(
+                        r#"{"message":"Invalid Subscribe Key","error":true,"service":"Access Manager","status":400}"#,
+                    ))
+                    .unwrap();
+                Ok::<_, Infallible>(response)
+            }))
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let origin = server.local_addr().to_string();
+        tokio::spawn(server);
+
+        let transport = Hyper::new()
+            .publish_key("demo")
+            .subscribe_key("invalid")
+            .origin(origin)
+            .secure(false)
+            .build()
+            .unwrap();
+        let mut pubnub = Builder::with_components(transport, TokioGlobal).build();
+        let mut status_stream = pubnub.status_stream();
+
+        // The subscribing doesn't hang, and the stream ends right away.
+        let mut subscription = tokio::time::timeout(
+            Duration::from_secs(5),
+            pubnub.subscribe("my-channel".parse().unwrap()),
+        )
+        .await
+        .expect("subscribe hangs")
+        .unwrap();
+        assert!(subscription.next().await.is_none());
+
+        match status_stream.next().await {
+            Some(StatusEvent::Error(error)) => assert!(
+                error.contains("Invalid Subscribe Key"),
+                "unexpected error: {}",
+                error
+            ),
+            other => panic!("unexpected status: {:?}", other),
+        }
+    });
+}
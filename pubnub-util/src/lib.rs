@@ -20,3 +20,9 @@ pub mod uritemplate;
 
 #[cfg(feature = "pam_signature")]
 pub mod pam_signature;
+
+#[cfg(feature = "pam_token")]
+pub mod pam_token;
+
+#[cfg(feature = "cipher")]
+pub mod cipher;
@@ -0,0 +1,316 @@
+//! PAM v3 token decoding.
+//!
+//! The tokens are base64-encoded CBOR, in either the standard or the
+//! URL-safe alphabet, with or without the padding. Only the subset of CBOR
+//! the tokens are made of is supported: no indefinite-length strings, and
+//! the floats are decoded but never produced by the PubNub network.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+/// The maximum nesting of the arrays and the maps, to keep the malformed
+/// tokens from exhausting the stack.
+const MAX_DEPTH: usize = 16;
+
+/// A decoded CBOR value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// An unsigned or a negative integer.
+    Integer(i128),
+    /// A byte string.
+    Bytes(Vec<u8>),
+    /// A text string.
+    Text(String),
+    /// An array.
+    Array(Vec<Value>),
+    /// A map, with the keys in order.
+    Map(BTreeMap<Key, Value>),
+    /// A boolean.
+    Bool(bool),
+    /// A floating-point number.
+    Float(f64),
+    /// The `null` or the `undefined` value.
+    Null,
+}
+
+/// A map key.
+///
+/// The tokens use both the text and the byte strings as the keys, so both
+/// are decoded as text, the byte strings lossily.
+pub type Key = String;
+
+impl Value {
+    /// The value of the map entry with the `key`, if the value is a map.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    /// The value as an integer, if it's one.
+    #[must_use]
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value as text, if it's a text or a UTF-8 byte string.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            Value::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// An error that can occur while decoding a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The token is not valid base64.
+    Base64,
+    /// The token ends in the middle of a value.
+    UnexpectedEnd,
+    /// The token has more data after the value.
+    TrailingData,
+    /// The token uses the CBOR features the decoder doesn't support.
+    Unsupported,
+    /// The token is nested too deep.
+    TooDeep,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Error::Base64 => "the token is not valid base64",
+            Error::UnexpectedEnd => "the token is truncated",
+            Error::TrailingData => "the token has unexpected trailing data",
+            Error::Unsupported => "the token uses unsupported CBOR features",
+            Error::TooDeep => "the token is nested too deep",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Decode the `token` into the CBOR value it holds.
+///
+/// # Errors
+///
+/// Returns an error if the token is not valid base64-encoded CBOR.
+pub fn decode(token: &str) -> Result<Value, Error> {
+    let token: String = token
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    let bytes =
+        base64::decode_config(&token, base64::STANDARD_NO_PAD).map_err(|_| Error::Base64)?;
+
+    let mut decoder = Decoder { bytes: &bytes };
+    let value = decoder.value(0)?;
+    if !decoder.bytes.is_empty() {
+        return Err(Error::TrailingData);
+    }
+    Ok(value)
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(Error::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// The argument of the item with the `info` bits, as an unsigned
+    /// integer.
+    fn argument(&mut self, info: u8) -> Result<u64, Error> {
+        let len = match info {
+            0..=23 => return Ok(u64::from(info)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return Err(Error::Unsupported),
+        };
+        Ok(self
+            .take(len)?
+            .iter()
+            .fold(0, |value, &byte| value << 8 | u64::from(byte)))
+    }
+
+    fn length(&mut self, info: u8) -> Result<usize, Error> {
+        let len = usize::try_from(self.argument(info)?).map_err(|_| Error::UnexpectedEnd)?;
+        // Every item takes at least a byte, so the longer ones can't fit.
+        if len > self.bytes.len() {
+            return Err(Error::UnexpectedEnd);
+        }
+        Ok(len)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::TooDeep);
+        }
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        Ok(match major {
+            0 => Value::Integer(i128::from(self.argument(info)?)),
+            1 => Value::Integer(-1 - i128::from(self.argument(info)?)),
+            2 => {
+                let len = self.length(info)?;
+                Value::Bytes(self.take(len)?.to_vec())
+            }
+            3 => {
+                let len = self.length(info)?;
+                Value::Text(String::from_utf8_lossy(self.take(len)?).into_owned())
+            }
+            4 => {
+                let len = self.length(info)?;
+                let mut values = Vec::with_capacity(len);
+                for _ in 0..len {
+                    values.push(self.value(depth + 1)?);
+                }
+                Value::Array(values)
+            }
+            5 => {
+                let len = self.length(info)?;
+                let mut map = BTreeMap::new();
+                for _ in 0..len {
+                    let key = match self.value(depth + 1)? {
+                        Value::Text(text) => text,
+                        Value::Bytes(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                        _ => return Err(Error::Unsupported),
+                    };
+                    let value = self.value(depth + 1)?;
+                    map.insert(key, value);
+                }
+                Value::Map(map)
+            }
+            // The tags only annotate the value that follows.
+            6 => {
+                self.argument(info)?;
+                self.value(depth + 1)?
+            }
+            _ => self.simple(info)?,
+        })
+    }
+
+    fn simple(&mut self, info: u8) -> Result<Value, Error> {
+        Ok(match info {
+            20 => Value::Bool(false),
+            21 => Value::Bool(true),
+            22 | 23 => Value::Null,
+            25 => {
+                let bits = self.argument(info)?;
+                Value::Float(half_to_f64(
+                    u16::try_from(bits).map_err(|_| Error::Unsupported)?,
+                ))
+            }
+            26 => {
+                let bits = self.argument(info)?;
+                Value::Float(f64::from(f32::from_bits(
+                    u32::try_from(bits).map_err(|_| Error::Unsupported)?,
+                )))
+            }
+            27 => Value::Float(f64::from_bits(self.argument(info)?)),
+            _ => return Err(Error::Unsupported),
+        })
+    }
+}
+
+/// Convert a half-precision float.
+fn half_to_f64(bits: u16) -> f64 {
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    let value = match exponent {
+        0 => mantissa * 2_f64.powi(-24),
+        31 if mantissa == 0.0 => std::f64::INFINITY,
+        31 => std::f64::NAN,
+        _ => (1024.0 + mantissa) * 2_f64.powi(exponent - 25),
+    };
+    if bits & 0x8000 == 0 {
+        value
+    } else {
+        -value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, Error, Value};
+
+    #[test]
+    fn test_decode() {
+        // {"v": 2, "t": -1, "s": h'0102', "a": [true, null, 1.5], "b": {"x": "y"}}
+        let token = base64::encode_config(
+            [
+                0xa5, 0x61, b'v', 0x02, 0x61, b't', 0x20, 0x61, b's', 0x42, 0x01, 0x02, 0x61, b'a',
+                0x83, 0xf5, 0xf6, 0xf9, 0x3e, 0x00, 0x41, b'b', 0xa1, 0x61, b'x', 0x61, b'y',
+            ],
+            base64::URL_SAFE_NO_PAD,
+        );
+        let value = decode(&token).unwrap();
+
+        assert_eq!(value.get("v").and_then(Value::as_integer), Some(2));
+        assert_eq!(value.get("t").and_then(Value::as_integer), Some(-1));
+        assert_eq!(value.get("s"), Some(&Value::Bytes(vec![1, 2])));
+        assert_eq!(
+            value.get("a"),
+            Some(&Value::Array(vec![
+                Value::Bool(true),
+                Value::Null,
+                Value::Float(1.5)
+            ]))
+        );
+        // The byte string keys are decoded as text.
+        assert_eq!(
+            value
+                .get("b")
+                .and_then(|b| b.get("x"))
+                .and_then(Value::as_str),
+            Some("y")
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("not base64!"), Err(Error::Base64));
+        // A map of two entries with a single one.
+        assert_eq!(
+            decode(&base64::encode([0xa2, 0x61, b'v', 0x02])),
+            Err(Error::UnexpectedEnd)
+        );
+        assert_eq!(
+            decode(&base64::encode([0x02, 0x02])),
+            Err(Error::TrailingData)
+        );
+        assert_eq!(
+            decode(&base64::encode(&[0x81; 64][..])),
+            Err(Error::TooDeep)
+        );
+        // An indefinite-length array.
+        assert_eq!(
+            decode(&base64::encode([0x9f, 0xff])),
+            Err(Error::Unsupported)
+        );
+    }
+}